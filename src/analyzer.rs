@@ -1,7 +1,81 @@
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use url::Url;
-use std::collections::HashSet;
-use std::path::Path;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{Result, anyhow};
+use globset::{Glob, GlobMatcher};
+use lazy_static::lazy_static;
+use log::warn;
+use parking_lot::Mutex;
+use regex::{Captures, Regex};
+use crate::caching::unfingerprinted;
+use crate::images::{kilobytes, ImageFile};
+use crate::macros::base_path;
+
+lazy_static! {
+    /// Tags that load subresources, and the attributes holding their URLs.
+    static ref RESOURCE_TAG_REGEX: Regex = Regex::new(
+        r"(?i)<(?:img|script|iframe|frame|link|source|video|audio|embed|object|track|input)\b[^>]*>"
+    ).unwrap();
+    static ref RESOURCE_ATTR_REGEX: Regex = Regex::new(
+        r#"(?i)(\s(?:src|srcset|href|poster|data)\s*=\s*["']?)([^"'>]*)"#
+    ).unwrap();
+    static ref HTTP_URL_REGEX: Regex = Regex::new(r"(?i)\bhttp://([^/\s,\x22'>]+)").unwrap();
+    static ref LINK_REL_REGEX: Regex = Regex::new(r#"(?i)\srel\s*=\s*["']?([^"'>]*)"#).unwrap();
+    static ref DISABLE_COMMENT_REGEX: Regex = Regex::new(r"(?s)<!--\s*ssg-disable(\s.*?)?-->").unwrap();
+}
+
+/// `rel` values of `<link>` tags that load something into the page, rather
+/// than point at another document like `canonical` or `alternate` do.
+const SUBRESOURCE_RELS: &[&str] = &["stylesheet", "preload", "modulepreload", "icon", "apple-touch-icon", "manifest"];
+
+/// Scripts that don't render anything and need no fallback content.
+const NON_VISUAL_SCRIPT_HOSTS: &[&str] = &[
+    "googletagmanager.com",
+    "google-analytics.com",
+    "plausible.io",
+    "static.cloudflareinsights.com",
+];
+
+/// Properties each schema.org type needs for rich results.
+const REQUIRED_PROPERTIES: &[(&str, &[&str])] = &[
+    ("Article", &["headline", "image", "datePublished"]),
+    ("BlogPosting", &["headline", "image", "datePublished"]),
+    ("NewsArticle", &["headline", "image", "datePublished"]),
+    ("TechArticle", &["headline", "image", "datePublished"]),
+    ("Organization", &["name", "url"]),
+    ("Person", &["name"]),
+    ("WebSite", &["name", "url"]),
+    ("BreadcrumbList", &["itemListElement"]),
+    ("ListItem", &["position"]),
+    ("FAQPage", &["mainEntity"]),
+    ("Question", &["name", "acceptedAnswer"]),
+    ("Answer", &["text"]),
+    ("Product", &["name"]),
+    ("Event", &["name", "startDate", "location"]),
+    ("HowTo", &["name", "step"]),
+    ("Recipe", &["name", "image"]),
+    ("SoftwareApplication", &["name"]),
+    ("ImageObject", &["url"]),
+];
+
+/// Properties holding ISO 8601 dates.
+const DATE_PROPERTIES: &[&str] = &["datePublished", "dateModified", "dateCreated", "startDate", "endDate", "uploadDate"];
+
+/// Properties holding URLs, which must be absolute.
+const URL_PROPERTIES: &[&str] = &["url", "image", "logo", "sameAs", "contentUrl", "thumbnailUrl", "mainEntityOfPage"];
+
+/// Title lengths search engines show without truncating or padding.
+const TITLE_LENGTH: (usize, usize) = (10, 60);
+
+/// Meta description lengths search engines show without truncating.
+const DESCRIPTION_LENGTH: (usize, usize) = (50, 160);
+
+/// Host used to resolve relative links against a page's location.
+const LOCAL_HOST: &str = "site.invalid";
 
 pub struct SecurityReport {
     pub mixed_content: Vec<String>,
@@ -13,15 +87,211 @@ pub struct SecurityReport {
 pub struct PerformanceReport {
     pub details: String,
     pub recommendations: Vec<String>,
+    /// Page size in bytes
+    pub page_size: usize,
+    /// Scripts without `async` or `defer`
+    pub blocking_scripts: usize,
+    /// Bytes of the local images found on disk
+    pub image_bytes: u64,
+    /// Bytes the image recommendations would save, roughly
+    pub image_savings: u64,
+    /// Score out of 100
+    pub score: u32,
+}
+
+pub struct NoJsReport {
+    /// Body has no visible text once scripts are stripped
+    pub script_only: bool,
+    /// Third-party widget scripts on a page without any `<noscript>` fallback
+    pub widgets_without_fallback: Vec<String>,
+}
+
+impl NoJsReport {
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.script_only {
+            issues.push("Page has no visible content without JavaScript. Render the content statically or add a <noscript> fallback.".to_string());
+        }
+        for src in &self.widgets_without_fallback {
+            issues.push(format!("Widget script {} has no <noscript> fallback.", src));
+        }
+        issues
+    }
+}
+
+pub struct StructuredDataReport {
+    /// One message per problem, prefixed with the offending `@type` and property
+    pub violations: Vec<String>,
+}
+
+/// Where a page loads each kind of resource from, for building a
+/// Content-Security-Policy. Origins are `'self'`, `data:`, or `scheme://host[:port]`.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceInventory {
+    pub script_origins: BTreeSet<String>,
+    pub style_origins: BTreeSet<String>,
+    pub image_origins: BTreeSet<String>,
+    pub font_origins: BTreeSet<String>,
+    pub frame_origins: BTreeSet<String>,
+    pub media_origins: BTreeSet<String>,
+    /// Text of each inline executable script, exactly as the browser hashes it
+    pub inline_scripts: Vec<String>,
+    /// `<style>` blocks or `style` attributes
+    pub inline_styles: bool,
+    /// `onclick` and other inline event handler attributes
+    pub inline_handlers: bool,
+}
+
+/// Metadata search engines show for a page.
+#[derive(Debug, Clone, Default)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub canonical: Option<String>,
+    /// Excluded from search results, so exempt from the audit
+    pub noindex: bool,
+}
+
+pub struct BrokenLink {
+    /// Page containing the link, relative to the output directory
+    pub page: PathBuf,
+    pub url: String,
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.page.display(), self.url, self.reason)
+    }
+}
+
+pub struct LinkReport {
+    pub pages_checked: usize,
+    pub links_checked: usize,
+    pub broken: Vec<BrokenLink>,
+}
+
+/// Analyzer passes a page can opt out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pass {
+    /// Mixed content and insecure links
+    Security,
+    /// Structured data and the metadata audit
+    Seo,
+    /// Performance report and budgets
+    Performance,
+    /// Fallback content for JavaScript widgets
+    NoJs,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 4] = [Pass::Security, Pass::Seo, Pass::Performance, Pass::NoJs];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pass::Security => "security",
+            Pass::Seo => "seo",
+            Pass::Performance => "performance",
+            Pass::NoJs => "no_js",
+        }
+    }
+
+    /// Passes named in a list; `all` names every pass.
+    pub fn parse_list<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<HashSet<Pass>, String> {
+        let mut passes = HashSet::new();
+        for name in names {
+            if name == "all" {
+                passes.extend(Pass::ALL);
+            } else {
+                passes.insert(name.parse()?);
+            }
+        }
+        Ok(passes)
+    }
+}
+
+impl FromStr for Pass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pass::ALL.into_iter()
+            .find(|pass| pass.as_str() == s)
+            .ok_or_else(|| format!("unknown analyzer pass '{}', expected one of security, seo, performance, no_js, all", s))
+    }
+}
+
+/// Passes skipped by sections matching a glob in `[analysis.exclude]`, such
+/// as embeds that load third-party iframes on purpose.
+#[derive(Debug, Default)]
+pub struct PassExclusions {
+    rules: Vec<(GlobMatcher, HashSet<Pass>)>,
+}
+
+impl PassExclusions {
+    pub fn from_config(exclude: &HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (pattern, names) in exclude {
+            // Patterns are relative to the input directory, like `[defaults]`
+            let matcher = Glob::new(pattern.trim_start_matches('/'))
+                .map_err(|e| anyhow!("Invalid analysis exclude pattern '{}': {}", pattern, e))?
+                .compile_matcher();
+            let passes = Pass::parse_list(names.iter().map(String::as_str))
+                .map_err(|e| anyhow!("In analysis exclude pattern '{}': {}", pattern, e))?;
+            rules.push((matcher, passes));
+        }
+        Ok(Self { rules })
+    }
+
+    /// Passes to skip for the page at `relative_path`, from the config and
+    /// the page's own `<!-- ssg-disable ... -->` comments.
+    pub fn disabled(&self, relative_path: &Path, html: &str) -> HashSet<Pass> {
+        let mut passes: HashSet<Pass> = self.rules.iter()
+            .filter(|(matcher, _)| matcher.is_match(relative_path))
+            .flat_map(|(_, passes)| passes.iter().copied())
+            .collect();
+        passes.extend(Analyzer::inline_disabled_passes(html, relative_path));
+        passes
+    }
 }
 
 pub struct Analyzer {
     base_url: Option<String>,
+    /// Directories the site root is served from, where images are looked up
+    image_dirs: Vec<PathBuf>,
+    /// Images measured so far, shared by every page that shows them
+    images: Mutex<HashMap<PathBuf, Option<ImageFile>>>,
 }
 
 impl Analyzer {
     pub fn new(base_url: Option<String>) -> Self {
-        Self { base_url }
+        Self { base_url, image_dirs: Vec::new(), images: Mutex::new(HashMap::new()) }
+    }
+
+    /// Measure the images pages show from `dirs`, the input and static
+    /// directories, first match wins.
+    pub fn with_image_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.image_dirs = dirs;
+        self
+    }
+
+    /// Passes a page turns off with `<!-- ssg-disable security, no_js -->`;
+    /// without names the comment turns off every pass.
+    pub fn inline_disabled_passes(html: &str, file_path: &Path) -> HashSet<Pass> {
+        let mut passes = HashSet::new();
+        for caps in DISABLE_COMMENT_REGEX.captures_iter(html) {
+            let names: Vec<&str> = caps.get(1).map(|m| m.as_str()).unwrap_or_default().split([',', ' ', '\n', '\t']).filter(|name| !name.is_empty()).collect();
+            if names.is_empty() {
+                passes.extend(Pass::ALL);
+                continue;
+            }
+            for name in names {
+                match Pass::parse_list([name]) {
+                    Ok(named) => passes.extend(named),
+                    Err(e) => warn!("Ignoring ssg-disable in {}: {}", file_path.display(), e),
+                }
+            }
+        }
+        passes
     }
 
     pub fn analyze_security(&self, html: &str, file_path: &Path) -> SecurityReport {
@@ -97,7 +367,10 @@ impl Analyzer {
         report
     }
 
-    pub fn analyze_performance(&self, content: &str, _file_path: &Path) -> PerformanceReport {
+    /// Page weight, images, and render-blocking resources of the page at
+    /// `out_relative` in the output. Local images are measured on disk when
+    /// the analyzer has image directories.
+    pub fn analyze_performance(&self, content: &str, out_relative: &Path) -> PerformanceReport {
         let document = Html::parse_document(content);
         let mut details = String::new();
         let mut recommendations = Vec::new();
@@ -111,9 +384,12 @@ impl Analyzer {
         }
 
         // Check image optimization
+        let mut image_bytes = 0;
+        let mut image_savings = 0;
         if let Ok(selector) = Selector::parse("img") {
             let mut img_count = 0;
             let mut unoptimized = 0;
+            let mut measured = HashSet::new();
             
             for img in document.select(&selector) {
                 img_count += 1;
@@ -122,6 +398,17 @@ impl Analyzer {
                 if img.value().attr("width").is_none() || img.value().attr("height").is_none() {
                     unoptimized += 1;
                 }
+
+                // Weigh the file it loads, once however often it is shown
+                let Some(image) = img.value().attr("src").and_then(|src| self.image_file(src, out_relative)) else { continue };
+                if !measured.insert(image.path.clone()) {
+                    continue;
+                }
+                let display_width = img.value().attr("width").and_then(|width| width.trim().parse().ok());
+                let advice = image.advise(display_width, serves_webp(img));
+                image_bytes += image.bytes;
+                image_savings += advice.savings;
+                recommendations.extend(advice.issues);
             }
 
             details.push_str(&format!("Images: {} ({}% optimized)\n", 
@@ -139,13 +426,18 @@ impl Analyzer {
                     unoptimized
                 ));
             }
+            if !measured.is_empty() {
+                details.push_str(&format!("Image weight: {} in {} local files, about {} could be saved\n",
+                    kilobytes(image_bytes as f64), measured.len(), kilobytes(image_savings as f64)));
+            }
         }
 
         // Check resource loading
+        let mut blocking_scripts = 0;
         if let Ok(selector) = Selector::parse("script:not([async]):not([defer])") {
-            let blocking_scripts: Vec<_> = document.select(&selector).collect();
-            if !blocking_scripts.is_empty() {
-                details.push_str(&format!("Blocking scripts: {}\n", blocking_scripts.len()));
+            blocking_scripts = document.select(&selector).count();
+            if blocking_scripts > 0 {
+                details.push_str(&format!("Blocking scripts: {}\n", blocking_scripts));
                 recommendations.push("Add async or defer to non-critical scripts.".to_string());
             }
         }
@@ -168,7 +460,231 @@ impl Analyzer {
             recommendations.push("Overall performance needs improvement. Consider implementing the above recommendations.".to_string());
         }
 
-        PerformanceReport { details, recommendations }
+        PerformanceReport {
+            details,
+            recommendations,
+            page_size: content_size,
+            blocking_scripts,
+            image_bytes,
+            image_savings,
+            score,
+        }
+    }
+
+    /// Check how the page degrades for old browsers and visitors without JavaScript.
+    pub fn analyze_no_js(&self, html: &str) -> NoJsReport {
+        let document = Html::parse_document(html);
+        let has_scripts = document.select(&Selector::parse("script").unwrap()).next().is_some();
+        let has_noscript = document.select(&Selector::parse("noscript").unwrap()).next().is_some();
+
+        // Visible text, ignoring anything that only exists for or through scripts
+        let body_text = document.select(&Selector::parse("body").unwrap()).next()
+            .map(|body| body.descendants()
+                .filter_map(|node| node.value().as_text().map(|text| (node, text)))
+                .filter(|(node, _)| !node.ancestors().any(|ancestor| ancestor.value().as_element()
+                    .is_some_and(|el| matches!(el.name(), "script" | "noscript" | "style" | "template"))))
+                .any(|(_, text)| !text.trim().is_empty()))
+            .unwrap_or(false);
+
+        let mut widgets_without_fallback = Vec::new();
+        if !has_noscript {
+            for script in document.select(&Selector::parse("script[src]").unwrap()) {
+                let src = script.value().attr("src").unwrap_or_default();
+                // Only third-party scripts count as injected widgets
+                let absolute = if src.starts_with("//") { format!("https:{}", src) } else { src.to_string() };
+                let Ok(url) = Url::parse(&absolute) else { continue };
+                let Some(host) = url.host_str() else { continue };
+                let own_host = self.base_url.as_deref()
+                    .and_then(|base| Url::parse(base).ok())
+                    .is_some_and(|base| base.host_str() == Some(host));
+                let non_visual = NON_VISUAL_SCRIPT_HOSTS.iter()
+                    .any(|h| host == *h || host.ends_with(&format!(".{}", h)));
+                if !own_host && !non_visual {
+                    widgets_without_fallback.push(src.to_string());
+                }
+            }
+        }
+
+        NoJsReport {
+            script_only: has_scripts && !body_text,
+            widgets_without_fallback,
+        }
+    }
+
+    /// Validate every JSON-LD block on the page against schema.org expectations:
+    /// required properties per `@type`, ISO 8601 dates, and absolute URLs.
+    pub fn analyze_structured_data(&self, html: &str) -> StructuredDataReport {
+        let document = Html::parse_document(html);
+        let selector = Selector::parse("script[type='application/ld+json']").unwrap();
+        let mut violations = Vec::new();
+
+        for script in document.select(&selector) {
+            violations.extend(self.validate_json_ld(&script.inner_html()));
+        }
+
+        StructuredDataReport { violations }
+    }
+
+    /// Validate a single JSON-LD document, such as a page's `<script>` block
+    /// or `structured_data` supplied in front matter.
+    pub fn validate_json_ld(&self, json: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+        match serde_json::from_str::<serde_json::Value>(json) {
+            Ok(data) => {
+                let items = match &data {
+                    serde_json::Value::Array(items) => items.iter().collect(),
+                    other => vec![other],
+                };
+                for item in items {
+                    if item.get("@context").and_then(|c| c.as_str()).is_none_or(|c| !c.contains("schema.org")) {
+                        violations.push("JSON-LD block is missing a schema.org @context".to_string());
+                    }
+                    match item.get("@graph").and_then(|g| g.as_array()) {
+                        Some(graph) => graph.iter().for_each(|node| validate_schema_node(node, &mut violations)),
+                        None => validate_schema_node(item, &mut violations),
+                    }
+                }
+            },
+            Err(e) => violations.push(format!("Invalid JSON-LD: {}", e)),
+        }
+        violations
+    }
+
+    /// Upgrade `http://` subresource URLs to `https://`, except for hosts in
+    /// `allowlist` (and their subdomains). Links to other pages are left alone.
+    /// Returns the rewritten HTML and the URLs that were upgraded.
+    pub fn fix_mixed_content(&self, html: &str, allowlist: &[String]) -> (String, Vec<String>) {
+        let mut upgraded = Vec::new();
+        let fixed = RESOURCE_TAG_REGEX.replace_all(html, |tag: &Captures| {
+            if !loads_subresource(&tag[0]) {
+                return tag[0].to_string();
+            }
+            RESOURCE_ATTR_REGEX.replace_all(&tag[0], |attr: &Captures| {
+                let value = HTTP_URL_REGEX.replace_all(&attr[2], |url: &Captures| {
+                    let host = url[1].split(':').next().unwrap_or_default().to_lowercase();
+                    let allowed = allowlist.iter()
+                        .any(|h| host == h.to_lowercase() || host.ends_with(&format!(".{}", h.to_lowercase())));
+                    if allowed {
+                        url[0].to_string()
+                    } else {
+                        upgraded.push(url[0].to_string());
+                        format!("https://{}", &url[1])
+                    }
+                });
+                format!("{}{}", &attr[1], value)
+            }).to_string()
+        });
+        (fixed.to_string(), upgraded)
+    }
+
+    /// Inventory of the origins each kind of resource on the page comes from.
+    pub fn resource_inventory(&self, html: &str) -> ResourceInventory {
+        let document = Html::parse_document(html);
+        let mut inventory = ResourceInventory::default();
+        let own_origin = self.base_url.as_deref()
+            .and_then(|base| Url::parse(base).ok())
+            .map(|base| base.origin().ascii_serialization());
+        let collect = |selector: &str, attr: &str, origins: &mut BTreeSet<String>| {
+            for element in document.select(&Selector::parse(selector).unwrap()) {
+                if let Some(origin) = element.value().attr(attr).and_then(resource_origin) {
+                    if own_origin.as_ref() == Some(&origin) {
+                        origins.insert("'self'".to_string());
+                    } else {
+                        origins.insert(origin);
+                    }
+                }
+            }
+        };
+
+        collect("script[src]", "src", &mut inventory.script_origins);
+        collect("link[rel~='stylesheet'][href]", "href", &mut inventory.style_origins);
+        collect("img[src]", "src", &mut inventory.image_origins);
+        collect("link[rel~='icon'][href]", "href", &mut inventory.image_origins);
+        collect("link[rel~='preload'][as='font'][href]", "href", &mut inventory.font_origins);
+        collect("iframe[src], frame[src]", "src", &mut inventory.frame_origins);
+        collect("video[src], audio[src], source[src]", "src", &mut inventory.media_origins);
+        collect("video[poster]", "poster", &mut inventory.image_origins);
+
+        for script in document.select(&Selector::parse("script:not([src])").unwrap()) {
+            let executable = script.value().attr("type")
+                .is_none_or(|t| t.is_empty() || t == "module" || t.contains("javascript"));
+            if executable {
+                inventory.inline_scripts.push(script.text().collect());
+            }
+        }
+
+        inventory.inline_styles = document.select(&Selector::parse("style, [style]").unwrap()).next().is_some();
+        inventory.inline_handlers = document.root_element().descendants()
+            .filter_map(|node| node.value().as_element())
+            .any(|el| el.attrs().any(|(name, _)| name.starts_with("on")));
+
+        inventory
+    }
+
+    /// Read the title, meta description, canonical URL, and robots directives.
+    pub fn extract_metadata(&self, html: &str) -> PageMetadata {
+        let document = Html::parse_document(html);
+        let first = |selector: &str, attr: Option<&str>| {
+            let selector = Selector::parse(selector).unwrap();
+            document.select(&selector).next()
+                .map(|el| match attr {
+                    Some(attr) => el.value().attr(attr).unwrap_or_default().to_string(),
+                    None => el.text().collect(),
+                })
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        PageMetadata {
+            title: first("head title", None),
+            description: first("meta[name='description']", Some("content")),
+            canonical: first("link[rel='canonical']", Some("href")),
+            noindex: first("meta[name='robots']", Some("content"))
+                .is_some_and(|robots| robots.to_lowercase().contains("noindex")),
+        }
+    }
+
+    /// Site-wide metadata audit: titles and descriptions shared between pages,
+    /// lengths outside what search results display, and missing descriptions or
+    /// canonical URLs. Returns one message per problem, keyed by page.
+    pub fn audit_metadata(&self, pages: &[(PathBuf, PageMetadata)]) -> Vec<(PathBuf, String)> {
+        let indexed: Vec<_> = pages.iter().filter(|(_, meta)| !meta.noindex).collect();
+        let mut issues = Vec::new();
+
+        for (page, meta) in &indexed {
+            let mut issue = |message: String| issues.push((page.clone(), message));
+            match &meta.title {
+                None => issue("Missing <title>".to_string()),
+                Some(title) => {
+                    let length = title.chars().count();
+                    if length < TITLE_LENGTH.0 {
+                        issue(format!("Title is {} characters; aim for at least {}", length, TITLE_LENGTH.0));
+                    } else if length > TITLE_LENGTH.1 {
+                        issue(format!("Title is {} characters; search results truncate after {}", length, TITLE_LENGTH.1));
+                    }
+                },
+            }
+            match &meta.description {
+                None => issue("Missing meta description".to_string()),
+                Some(description) => {
+                    let length = description.chars().count();
+                    if length < DESCRIPTION_LENGTH.0 {
+                        issue(format!("Meta description is {} characters; aim for at least {}", length, DESCRIPTION_LENGTH.0));
+                    } else if length > DESCRIPTION_LENGTH.1 {
+                        issue(format!("Meta description is {} characters; search results truncate after {}", length, DESCRIPTION_LENGTH.1));
+                    }
+                },
+            }
+            if meta.canonical.is_none() {
+                issue("Missing canonical URL".to_string());
+            }
+        }
+
+        duplicates(&indexed, "title", |meta| meta.title.as_deref(), &mut issues);
+        duplicates(&indexed, "meta description", |meta| meta.description.as_deref(), &mut issues);
+
+        issues.sort();
+        issues
     }
 
     fn calculate_performance_score(&self, document: &Html) -> u32 {
@@ -198,4 +714,399 @@ impl Analyzer {
 
         score
     }
-}
\ No newline at end of file
+
+    /// Resolve every internal `href`/`src` in the generated HTML against the
+    /// files in `output_dir` and `static_dirs`, including `#fragment` targets.
+    pub fn check_links(&self, output_dir: &Path, static_dirs: &[PathBuf]) -> Result<LinkReport> {
+        let mut files = HashSet::new();
+        let mut pages = Vec::new();
+        for entry in walkdir::WalkDir::new(output_dir).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(output_dir)?.to_string_lossy().replace('\\', "/");
+            if relative.ends_with(".html") {
+                pages.push(relative.clone());
+            }
+            files.insert(relative);
+        }
+        pages.sort();
+
+        // Anchor targets of every page
+        let id_selector = Selector::parse("[id], a[name]").unwrap();
+        let mut anchors: HashMap<&str, HashSet<String>> = HashMap::new();
+        let mut documents = Vec::new();
+        for page in &pages {
+            let document = Html::parse_document(&fs::read_to_string(output_dir.join(page))?);
+            let ids = document.select(&id_selector)
+                .filter_map(|el| el.value().attr("id").or_else(|| el.value().attr("name")))
+                .map(str::to_string)
+                .collect();
+            anchors.insert(page, ids);
+            documents.push((page, document));
+        }
+
+        let base_path = base_path(self.base_url.as_deref());
+        let link_selector = Selector::parse("a[href], link[href], area[href], img[src], script[src], source[src], iframe[src], video[src], audio[src]").unwrap();
+        let mut report = LinkReport {
+            pages_checked: pages.len(),
+            links_checked: 0,
+            broken: Vec::new(),
+        };
+
+        for (page, document) in &documents {
+            let page_url = Url::parse(&format!("http://{}/{}", LOCAL_HOST, page))?;
+            for element in document.select(&link_selector) {
+                let value = element.value();
+                let Some(href) = value.attr("href").or_else(|| value.attr("src")) else { continue };
+                // Canonical and alternate links point at the public site on purpose
+                if value.name() == "link" && matches!(value.attr("rel"), Some("canonical" | "alternate")) {
+                    continue;
+                }
+                let Some(target) = self.internal_url(href, &page_url) else { continue };
+                report.links_checked += 1;
+
+                let path = site_path(&target, &base_path);
+                let resolved = resolve_target(&path, &files)
+                    .or_else(|| static_dirs.iter().find(|dir| dir.join(&path).is_file()).map(|_| path.clone()));
+
+                let reason = match (resolved, target.fragment()) {
+                    (None, _) => Some("target not found".to_string()),
+                    (Some(file), Some(fragment)) if !fragment.is_empty() => anchors.get(file.as_str())
+                        .filter(|ids| !ids.contains(&percent_decode(fragment)))
+                        .map(|_| format!("no element with id '{}'", fragment)),
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    report.broken.push(BrokenLink {
+                        page: PathBuf::from(page),
+                        url: href.to_string(),
+                        reason,
+                    });
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// The measured file a local image `src` on the page at `out_relative`
+    /// loads, resolved the way a browser does against the page's URL and
+    /// found in the image directories under its own name or the one it had
+    /// before fingerprinting.
+    fn image_file(&self, src: &str, out_relative: &Path) -> Option<ImageFile> {
+        if src.trim_start().starts_with("data:") {
+            return None;
+        }
+        let page_url = Url::parse(&format!("http://{}/", LOCAL_HOST)).ok()?
+            .join(&out_relative.to_string_lossy().replace('\\', "/")).ok()?;
+        let path = site_path(&self.internal_url(src, &page_url)?, &base_path(self.base_url.as_deref()));
+        let candidates = [Some(path.clone()), unfingerprinted(&path)];
+        let file = self.image_dirs.iter()
+            .flat_map(|dir| candidates.iter().flatten().map(move |path| dir.join(path)))
+            .find(|file| file.is_file())?;
+
+        // Decoding happens outside the lock so pages are analyzed in parallel
+        if let Some(image) = self.images.lock().get(&file) {
+            return image.clone();
+        }
+        let image = ImageFile::inspect(&file);
+        self.images.lock().insert(file, image.clone());
+        image
+    }
+
+    /// The resolved URL when `href` points into this site.
+    fn internal_url(&self, href: &str, page_url: &Url) -> Option<Url> {
+        let href = href.trim();
+        // Absolute links to our own base URL are internal too
+        let href = match self.base_url.as_deref().map(|base| base.trim_end_matches('/')) {
+            Some(base) if !base.is_empty() && href.starts_with(base) => {
+                let rest = &href[base.len()..];
+                if rest.is_empty() { "/" } else { rest }
+            },
+            _ => href,
+        };
+        if href.is_empty() || href.starts_with("//") {
+            return None;
+        }
+        let url = page_url.join(href).ok()?;
+        (url.host_str() == Some(LOCAL_HOST)).then_some(url)
+    }
+}
+
+/// Path of a site URL relative to the site root, without the path the site
+/// is served under, which root-relative links include.
+fn site_path(target: &Url, base_path: &str) -> String {
+    let target_path = target.path();
+    let target_path = target_path.strip_prefix(base_path)
+        .filter(|rest| !base_path.is_empty() && (rest.is_empty() || rest.starts_with('/')))
+        .unwrap_or(target_path);
+    percent_decode(target_path.trim_start_matches('/'))
+}
+
+/// Whether `img` sits in a `<picture>` that offers a WebP source.
+fn serves_webp(img: ElementRef) -> bool {
+    let Some(picture) = img.ancestors().filter_map(ElementRef::wrap).find(|ancestor| ancestor.value().name() == "picture") else {
+        return false;
+    };
+    picture.children().filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "source")
+        .any(|source| source.value().attr("type") == Some("image/webp")
+            || source.value().attr("srcset").is_some_and(|srcset| srcset.contains(".webp")))
+}
+
+/// Whether a tag matched by `RESOURCE_TAG_REGEX` loads a subresource: any
+/// but a `<link>` whose `rel` isn't one of `SUBRESOURCE_RELS`.
+fn loads_subresource(tag: &str) -> bool {
+    if !tag.get(..5).is_some_and(|name| name.eq_ignore_ascii_case("<link")) {
+        return true;
+    }
+    LINK_REL_REGEX.captures(tag).is_some_and(|rel| {
+        rel[1].split_whitespace().any(|value| SUBRESOURCE_RELS.iter().any(|sub| value.eq_ignore_ascii_case(sub)))
+    })
+}
+
+/// CSP source for a resource URL: `'self'` for same-site paths, `data:` for
+/// inline data, or the scheme, host, and port of absolute URLs.
+fn resource_origin(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    if url.starts_with("data:") {
+        return Some("data:".to_string());
+    }
+    let absolute = if url.starts_with("//") { format!("https:{}", url) } else { url.to_string() };
+    if !absolute.starts_with("http://") && !absolute.starts_with("https://") {
+        return Some("'self'".to_string());
+    }
+    let parsed = Url::parse(&absolute).ok()?;
+    Some(parsed.origin().ascii_serialization())
+}
+
+/// Report every page whose `value` is shared with another page.
+fn duplicates<'a>(
+    pages: &[&'a (PathBuf, PageMetadata)],
+    label: &str,
+    value: impl Fn(&'a PageMetadata) -> Option<&'a str>,
+    issues: &mut Vec<(PathBuf, String)>,
+) {
+    let mut by_value: HashMap<&str, Vec<&PathBuf>> = HashMap::new();
+    for (page, meta) in pages {
+        if let Some(value) = value(meta) {
+            by_value.entry(value).or_default().push(page);
+        }
+    }
+    for (value, shared) in by_value.into_iter().filter(|(_, pages)| pages.len() > 1) {
+        for page in &shared {
+            let others: Vec<_> = shared.iter()
+                .filter(|other| *other != page)
+                .map(|other| other.display().to_string())
+                .collect();
+            issues.push(((*page).clone(), format!(
+                "Duplicate {} \"{}\" also used by {}", label, value, others.join(", ")
+            )));
+        }
+    }
+}
+
+/// Output file a link path refers to, allowing directory indexes and clean URLs.
+fn resolve_target(path: &str, files: &HashSet<String>) -> Option<String> {
+    let trimmed = path.trim_end_matches('/');
+    let candidates = [
+        path.to_string(),
+        format!("{}/index.html", trimmed).trim_start_matches('/').to_string(),
+        format!("{}.html", trimmed),
+    ];
+    candidates.into_iter().find(|candidate| files.contains(candidate))
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = ((bytes[i + 1] as char).to_digit(16), (bytes[i + 2] as char).to_digit(16));
+            if let (Some(high), Some(low)) = hex {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Check one schema.org object and the typed objects nested inside it.
+fn validate_schema_node(node: &serde_json::Value, violations: &mut Vec<String>) {
+    let Some(object) = node.as_object() else { return };
+    let type_name = match object.get("@type") {
+        Some(serde_json::Value::String(t)) => t.as_str(),
+        Some(serde_json::Value::Array(types)) => types.first().and_then(|t| t.as_str()).unwrap_or("Thing"),
+        _ => {
+            violations.push("JSON-LD object is missing @type".to_string());
+            "Thing"
+        },
+    };
+
+    let present = |key: &str| object.get(key).is_some_and(|value| match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::String(s) => !s.trim().is_empty(),
+        serde_json::Value::Array(items) => !items.is_empty(),
+        _ => true,
+    });
+
+    if let Some((_, required)) = REQUIRED_PROPERTIES.iter().find(|(t, _)| *t == type_name) {
+        for property in required.iter().filter(|p| !present(p)) {
+            violations.push(format!("{}.{}: missing required property", type_name, property));
+        }
+    }
+
+    for property in DATE_PROPERTIES {
+        if let Some(date) = object.get(*property).and_then(|d| d.as_str()) {
+            let valid = chrono::DateTime::parse_from_rfc3339(date).is_ok()
+                || chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+                || chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").is_ok();
+            if !valid {
+                violations.push(format!("{}.{}: '{}' is not an ISO 8601 date", type_name, property, date));
+            }
+        }
+    }
+
+    for property in URL_PROPERTIES {
+        let urls: Vec<&str> = match object.get(*property) {
+            Some(serde_json::Value::String(url)) => vec![url],
+            Some(serde_json::Value::Array(items)) => items.iter().filter_map(|item| item.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        for url in urls {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                violations.push(format!("{}.{}: '{}' must be an absolute URL", type_name, property, url));
+            }
+        }
+    }
+
+    // Nested typed objects, e.g. author, publisher, itemListElement
+    for (key, value) in object {
+        if key.starts_with('@') {
+            continue;
+        }
+        match value {
+            serde_json::Value::Object(_) if value.get("@type").is_some() => validate_schema_node(value, violations),
+            serde_json::Value::Array(items) => items.iter()
+                .filter(|item| item.get("@type").is_some())
+                .for_each(|item| validate_schema_node(item, violations)),
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pass_exclusions() {
+        let exclude = HashMap::from([
+            ("/embeds/**".to_string(), vec!["security".to_string()]),
+            ("legacy/*.html".to_string(), vec!["all".to_string()]),
+        ]);
+        let exclusions = PassExclusions::from_config(&exclude).unwrap();
+        assert_eq!(exclusions.disabled(Path::new("embeds/maps/a.html"), ""), HashSet::from([Pass::Security]));
+        assert_eq!(exclusions.disabled(Path::new("legacy/old.html"), "").len(), Pass::ALL.len());
+        assert!(exclusions.disabled(Path::new("about.html"), "").is_empty());
+
+        let html = "<p>x</p><!-- ssg-disable performance, no_js --><!-- ssg-disable-typo -->";
+        assert_eq!(exclusions.disabled(Path::new("about.html"), html), HashSet::from([Pass::Performance, Pass::NoJs]));
+        assert_eq!(Analyzer::inline_disabled_passes("<!-- ssg-disable -->", Path::new("a.html")).len(), Pass::ALL.len());
+
+        let bad = HashMap::from([("a/**".to_string(), vec!["speed".to_string()])]);
+        assert!(PassExclusions::from_config(&bad).is_err());
+    }
+
+    #[test]
+    fn test_mixed_content_fix_upgrades_subresources_only() {
+        let html = r#"<link rel="canonical" href="http://example.com/a/"><link rel="alternate" hreflang="fr" href="http://example.com/fr/a/">
+            <link rel="stylesheet" href="http://cdn.example.com/site.css"><LINK REL="Preload" as="font" href="http://cdn.example.com/f.woff2">
+            <link rel="shortcut icon" href="http://example.com/favicon.ico"><script src="http://legacy.example.net/a.js"></script>
+            <a href="http://example.com/b/">b</a><img src="http://img.example.com/x.png">"#;
+        let (fixed, upgraded) = Analyzer::new(None).fix_mixed_content(html, &["legacy.example.net".to_string()]);
+        assert!(fixed.contains(r#"<link rel="canonical" href="http://example.com/a/">"#));
+        assert!(fixed.contains(r#"hreflang="fr" href="http://example.com/fr/a/""#));
+        assert!(fixed.contains(r#"<a href="http://example.com/b/">"#));
+        assert!(fixed.contains(r#"src="http://legacy.example.net/a.js""#));
+        assert_eq!(upgraded, [
+            "http://cdn.example.com",
+            "http://cdn.example.com",
+            "http://example.com",
+            "http://img.example.com",
+        ]);
+        assert!(fixed.contains(r#"href="https://cdn.example.com/site.css""#));
+        assert!(fixed.contains(r#"href="https://example.com/favicon.ico""#));
+    }
+
+    #[test]
+    fn test_check_links_reports_missing_targets_and_fragments() {
+        let site = tempfile::tempdir().unwrap();
+        let out = site.path().join("output");
+        let static_dir = site.path().join("static");
+        fs::create_dir_all(out.join("blog/post")).unwrap();
+        fs::create_dir_all(static_dir.join("img")).unwrap();
+        fs::write(static_dir.join("img/logo.png"), "png").unwrap();
+        fs::write(out.join("about.html"), "<h2 id=\"team\">Team</h2>").unwrap();
+        fs::write(out.join("blog/post/index.html"), "<p>Post</p>").unwrap();
+        fs::write(out.join("index.html"), r##"<html><head><link rel="canonical" href="/nowhere"></head><body id="top">
+            <a href="/blog/post/">ok</a> <a href="about#team">ok</a> <a href="https://example.com/about">ok</a>
+            <img src="/img/logo.png"> <a href="#top">ok</a> <a href="https://other.example.org/gone">external</a>
+            <a href="/gone">dead</a> <a href="/about#people">dead fragment</a> <script src="/js/app.js"></script>
+            </body></html>"##).unwrap();
+
+        let report = Analyzer::new(Some("https://example.com/".to_string())).check_links(&out, &[static_dir]).unwrap();
+        assert_eq!(report.pages_checked, 3);
+        assert_eq!(report.links_checked, 8);
+        let broken: Vec<String> = report.broken.iter().map(ToString::to_string).collect();
+        assert_eq!(broken, [
+            "index.html: /gone (target not found)",
+            "index.html: /about#people (no element with id 'people')",
+            "index.html: /js/app.js (target not found)",
+        ]);
+    }
+
+    #[test]
+    fn test_images_are_weighed_from_disk() {
+        let site = tempfile::tempdir().unwrap();
+        let content = site.path().join("content");
+        let static_dir = site.path().join("static");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::create_dir_all(static_dir.join("img")).unwrap();
+        image::RgbImage::from_pixel(800, 400, image::Rgb([20, 40, 60])).save(static_dir.join("img/hero.jpg")).unwrap();
+        image::RgbImage::from_pixel(100, 100, image::Rgb([0, 0, 0])).save(content.join("blog/chart.png")).unwrap();
+        let hero = fs::metadata(static_dir.join("img/hero.jpg")).unwrap().len();
+        let chart = fs::metadata(content.join("blog/chart.png")).unwrap().len();
+
+        let analyzer = Analyzer::new(Some("https://example.com/docs/".to_string()))
+            .with_image_dirs(vec![static_dir, content.clone()]);
+        let html = r#"<img src="/docs/img/hero.3f2a9c1b.jpg" width="200" height="100">
+            <img src="https://example.com/docs/img/hero.jpg">
+            <picture><source srcset="../chart.webp" type="image/webp"><img src="../chart.png"></picture>
+            <img src="missing.png"><img src="data:image/png;base64,AA=="><img src="https://cdn.example.org/a.jpg">"#;
+        // Relative sources resolve against the page's URL, /docs/blog/post/
+        let report = analyzer.analyze_performance(html, Path::new("blog/post/index.html"));
+
+        assert_eq!(report.image_bytes, hero + chart);
+        let image_advice: Vec<&String> = report.recommendations.iter().filter(|rec| rec.contains("hero.jpg")).collect();
+        assert_eq!(image_advice.len(), 2);
+        assert!(image_advice[0].contains("800x400 but shown 200 pixels wide; resize it to 400x200"));
+        assert!(image_advice[1].contains("no WebP alternative"));
+        assert!(!report.recommendations.iter().any(|rec| rec.contains("chart.png")));
+        assert!(report.image_savings > 0 && report.image_savings < hero);
+        assert!(report.details.contains("in 2 local files"));
+
+        assert_eq!(Analyzer::new(None).analyze_performance(html, Path::new("blog/post/index.html")).image_bytes, 0);
+        let beside_source = r#"<img src="chart.png">"#;
+        assert_eq!(analyzer.analyze_performance(beside_source, Path::new("blog/post/index.html")).image_bytes, 0);
+    }
+}
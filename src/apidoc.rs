@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use log::info;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::layout::{load_layout, render_layout};
+use crate::themes::ComponentDirs;
+use crate::markdown::markdown_to_html;
+
+/// `[api_docs]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiDocsConfig {
+    /// Rustdoc JSON file, e.g. `target/doc/my_crate.json`
+    pub json: PathBuf,
+    /// Output subdirectory for the generated reference
+    #[serde(default = "default_api_path")]
+    pub path: String,
+    /// Optional layout containing `@{title}` and `@{yield}` placeholders
+    pub layout: Option<PathBuf>,
+}
+
+fn default_api_path() -> String {
+    "api".to_string()
+}
+
+/// Item kinds listed on module pages, in display order.
+const LISTED_KINDS: [(&str, &str, &str); 10] = [
+    ("module", "Modules", "Module"),
+    ("macro", "Macros", "Macro"),
+    ("struct", "Structs", "Struct"),
+    ("enum", "Enums", "Enum"),
+    ("union", "Unions", "Union"),
+    ("trait", "Traits", "Trait"),
+    ("function", "Functions", "Function"),
+    ("type_alias", "Type Aliases", "Type Alias"),
+    ("constant", "Constants", "Constant"),
+    ("static", "Statics", "Static"),
+];
+
+struct ApiItem<'a> {
+    name: &'a str,
+    kind: String,
+    docs: Option<&'a str>,
+    raw: &'a Value,
+}
+
+/// Generates a browsable API reference from `cargo doc` JSON output
+/// (`RUSTDOCFLAGS="-Z unstable-options --output-format json"`).
+struct ApiDocGenerator<'a> {
+    config: &'a ApiDocsConfig,
+    index: &'a serde_json::Map<String, Value>,
+    layout: String,
+    output_root: PathBuf,
+}
+
+impl<'a> ApiDocGenerator<'a> {
+    fn item(&self, id: &Value) -> Option<ApiItem<'a>> {
+        let key = match id {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let raw = self.index.get(&key)?;
+        let kind = item_kind(raw)?;
+        Some(ApiItem {
+            name: raw.get("name").and_then(Value::as_str).unwrap_or(""),
+            kind,
+            docs: raw.get("docs").and_then(Value::as_str),
+            raw,
+        })
+    }
+
+    fn render_page(&self, title: &str, body: &str, out_path: &Path) -> Result<PathBuf> {
+        let page = render_layout(&self.layout, title, body);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(out_path, page)?;
+        Ok(out_path.to_path_buf())
+    }
+
+    fn url_for(&self, module_path: &[&str], item: &ApiItem) -> String {
+        let mut url = format!("/{}/{}", self.config.path.trim_matches('/'), module_path.join("/"));
+        if item.kind == "module" {
+            url.push_str(&format!("/{}/", item.name));
+        } else {
+            url.push_str(&format!("/{}.{}.html", item.kind, item.name));
+        }
+        url
+    }
+
+    fn generate_module(&self, module: &ApiItem<'a>, module_path: &[&'a str], written: &mut Vec<PathBuf>) -> Result<()> {
+        let children = module_items(module.raw);
+        let mut grouped: BTreeMap<usize, Vec<ApiItem>> = BTreeMap::new();
+
+        for child in children.iter().filter_map(|id| self.item(id)) {
+            if !is_public(child.raw) || child.name.is_empty() {
+                continue;
+            }
+            if let Some(order) = LISTED_KINDS.iter().position(|(k, _, _)| *k == child.kind) {
+                grouped.entry(order).or_default().push(child);
+            }
+        }
+
+        let full_name = module_path.join("::");
+        let mut body = format!("<h1>Module <code>{}</code></h1>\n", html_escape::encode_text(&full_name));
+        body.push_str(&breadcrumbs(&self.config.path, module_path));
+        if let Some(docs) = module.docs {
+            body.push_str(&markdown_to_html(docs));
+        }
+
+        for (order, mut items) in grouped {
+            items.sort_by(|a, b| a.name.cmp(b.name));
+            body.push_str(&format!("\n<h2>{}</h2>\n<ul class=\"api-items\">", LISTED_KINDS[order].1));
+            for child in &items {
+                let summary = child.docs
+                    .and_then(|d| d.split("\n\n").next())
+                    .map(markdown_to_html)
+                    .unwrap_or_default();
+                body.push_str(&format!(
+                    "<li><a href=\"{}\"><code>{}</code></a>{}</li>",
+                    self.url_for(module_path, child),
+                    html_escape::encode_text(child.name),
+                    summary
+                ));
+            }
+            body.push_str("</ul>");
+
+            for child in &items {
+                if child.kind == "module" {
+                    let mut child_path = module_path.to_vec();
+                    child_path.push(child.name);
+                    self.generate_module(child, &child_path, written)?;
+                } else {
+                    written.push(self.generate_item(child, module_path)?);
+                }
+            }
+        }
+
+        let out_path = self.output_root.join(module_path.join("/")).join("index.html");
+        written.push(self.render_page(&format!("{} - API", full_name), &body, &out_path)?);
+        Ok(())
+    }
+
+    fn generate_item(&self, item: &ApiItem, module_path: &[&str]) -> Result<PathBuf> {
+        let full_name = format!("{}::{}", module_path.join("::"), item.name);
+        let mut body = format!(
+            "<h1>{} <code>{}</code></h1>\n",
+            kind_label(&item.kind),
+            html_escape::encode_text(&full_name)
+        );
+        body.push_str(&breadcrumbs(&self.config.path, module_path));
+        match item.docs {
+            Some(docs) => body.push_str(&markdown_to_html(docs)),
+            None => body.push_str("<p class=\"api-undocumented\">No documentation.</p>"),
+        }
+
+        let out_path = self.output_root
+            .join(module_path.join("/"))
+            .join(format!("{}.{}.html", item.kind, item.name));
+        self.render_page(&format!("{} - API", full_name), &body, &out_path)
+    }
+}
+
+/// Generate the API reference into `output_dir`, returning every page written.
+pub fn generate_api_docs(config: &ApiDocsConfig, output_dir: &Path, components: &ComponentDirs) -> Result<Vec<PathBuf>> {
+    let json = fs::read_to_string(&config.json)
+        .map_err(|e| anyhow!("Failed to read rustdoc JSON {}: {}", config.json.display(), e))?;
+    let krate: Value = serde_json::from_str(&json)?;
+
+    let index = krate.get("index")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("Rustdoc JSON has no item index"))?;
+    let root_id = krate.get("root")
+        .ok_or_else(|| anyhow!("Rustdoc JSON has no root module"))?;
+
+    let layout = load_layout(components, config.layout.as_deref());
+
+    let generator = ApiDocGenerator {
+        config,
+        index,
+        layout,
+        output_root: output_dir.join(config.path.trim_matches('/')),
+    };
+
+    let root = generator.item(root_id)
+        .ok_or_else(|| anyhow!("Root module missing from rustdoc index"))?;
+    let mut written = Vec::new();
+    generator.generate_module(&root, &[root.name], &mut written)?;
+
+    info!("Generated {} API reference pages", written.len());
+    Ok(written)
+}
+
+/// Item kind across rustdoc JSON format versions: newer versions tag the
+/// `inner` object, older ones carry a separate `kind` field.
+fn item_kind(item: &Value) -> Option<String> {
+    if let Some(kind) = item.get("kind").and_then(Value::as_str) {
+        return Some(kind.to_string());
+    }
+    match item.get("inner")? {
+        Value::Object(inner) => inner.keys().next().cloned(),
+        Value::String(kind) => Some(kind.clone()),
+        _ => None,
+    }
+}
+
+fn module_items(item: &Value) -> Vec<Value> {
+    let inner = match item.get("inner") {
+        Some(inner) => inner,
+        None => return Vec::new(),
+    };
+    inner.get("module")
+        .and_then(|m| m.get("items"))
+        .or_else(|| inner.get("items"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn is_public(item: &Value) -> bool {
+    matches!(item.get("visibility").and_then(Value::as_str), Some("public") | Some("default"))
+}
+
+fn kind_label(kind: &str) -> &str {
+    LISTED_KINDS.iter()
+        .find(|(k, _, _)| *k == kind)
+        .map(|(_, _, label)| *label)
+        .unwrap_or(kind)
+}
+
+fn breadcrumbs(api_path: &str, module_path: &[&str]) -> String {
+    let mut html = String::from("<nav class=\"api-breadcrumbs\">");
+    let mut url = format!("/{}", api_path.trim_matches('/'));
+    for (i, segment) in module_path.iter().enumerate() {
+        url.push('/');
+        url.push_str(segment);
+        if i > 0 {
+            html.push_str("::");
+        }
+        html.push_str(&format!("<a href=\"{}/\">{}</a>", url, html_escape::encode_text(segment)));
+    }
+    html.push_str("</nav>\n");
+    html
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use crate::slug::Slugs;
+
+/// Front matter for new content when the site has no archetype of its own.
+const DEFAULT_ARCHETYPE: &str = "---\ntitle: \"@{title}\"\ndate: \"@{date}\"\ntags: []\n---\n\n";
+
+/// Templates for new content in `archetypes/` next to `eldroid.toml`:
+/// `<section>.md` for one content section, `default.md` for the rest.
+/// `@{title}`, `@{slug}`, `@{date}`, and `@{section}` are filled in; in the
+/// front matter, values are escaped for a double-quoted YAML string.
+#[derive(Debug, Clone)]
+pub struct Archetypes {
+    dir: PathBuf,
+    slugs: Slugs,
+}
+
+impl Archetypes {
+    /// The archetypes of the site configured by `site_config`.
+    pub fn new(site_config: &Path) -> Self {
+        Self { dir: site_config.parent().unwrap_or(Path::new("")).join("archetypes"), slugs: Slugs::default() }
+    }
+
+    /// Name new files by slugging their titles this way.
+    pub fn with_slugs(mut self, slugs: Slugs) -> Self {
+        self.slugs = slugs;
+        self
+    }
+
+    /// The template for `section`.
+    pub fn template(&self, section: &str) -> Result<String> {
+        for name in [section, "default"] {
+            let path = self.dir.join(format!("{}.md", name));
+            if path.is_file() {
+                return fs::read_to_string(&path).with_context(|| format!("Failed to read archetype {}", path.display()));
+            }
+        }
+        Ok(DEFAULT_ARCHETYPE.to_string())
+    }
+
+    /// Write a new page titled `title` to `<input_dir>/<section>/<slug>.md`,
+    /// refusing to replace an existing file.
+    pub fn create(&self, input_dir: &Path, section: &str, title: &str, now: DateTime<Utc>) -> Result<PathBuf> {
+        let slug = self.slugs.slugify(title);
+        if slug.is_empty() {
+            return Err(anyhow!("Title '{}' has no characters to make a file name from", title));
+        }
+        let path = input_dir.join(section).join(format!("{}.md", slug));
+        if path.exists() {
+            return Err(anyhow!("{} already exists", path.display()));
+        }
+        let date = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let content = fill(&self.template(section)?, &[("title", title), ("slug", &slug), ("date", &date), ("section", section)]);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+/// Content section for a `new` kind: `post` is a blog post, anything else
+/// names its section directly.
+pub fn section_for(kind: &str) -> &str {
+    match kind {
+        "post" | "posts" => "blog",
+        _ => kind,
+    }
+}
+
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    let replace = |text: &str, escape: bool| {
+        values.iter().fold(text.to_string(), |text, (name, value)| {
+            let value = if escape { value.replace('\\', "\\\\").replace('"', "\\\"") } else { value.to_string() };
+            text.replace(&format!("@{{{}}}", name), &value)
+        })
+    };
+    // Front matter sits between the opening `---` line and the next one
+    let front_matter_end = template.strip_prefix("---")
+        .and_then(|rest| rest.find("\n---").map(|end| end + "---\n---".len()));
+    match front_matter_end {
+        Some(end) => replace(&template[..end], true) + &replace(&template[end..], false),
+        None => replace(template, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_content_from_section_archetype() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let content = root.join("content");
+        let archetypes = Archetypes::new(&root.join("eldroid.toml"));
+        let now = DateTime::parse_from_rfc3339("2026-03-01T09:30:00Z").unwrap().with_timezone(&Utc);
+
+        let post = archetypes.create(&content, section_for("post"), "Say \"Hi\": A Guide", now).unwrap();
+        assert_eq!(post, content.join("blog/say-hi-a-guide.md"));
+        let written = fs::read_to_string(&post).unwrap();
+        assert_eq!(written, "---\ntitle: \"Say \\\"Hi\\\": A Guide\"\ndate: \"2026-03-01T09:30:00Z\"\ntags: []\n---\n\n");
+        let front_matter: crate::markdown::BlogFrontMatter = serde_yaml::from_str(written.trim_matches(|c| c == '-' || c == '\n')).unwrap();
+        assert_eq!(front_matter.title, "Say \"Hi\": A Guide");
+        assert!(archetypes.create(&content, "blog", "Say Hi: a guide", now).is_err());
+
+        fs::create_dir_all(root.join("archetypes")).unwrap();
+        fs::write(root.join("archetypes/docs.md"), "---\ntitle: \"@{title}\"\nweight: 10\n---\n\n# @{title} (@{slug})\n").unwrap();
+        let doc = archetypes.create(&content, section_for("docs"), "Install \"fast\"", now).unwrap();
+        assert_eq!(fs::read_to_string(doc).unwrap(), "---\ntitle: \"Install \\\"fast\\\"\"\nweight: 10\n---\n\n# Install \"fast\" (install-fast)\n");
+    }
+}
@@ -0,0 +1,591 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{anyhow, Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::verify::hex;
+use walkdir::WalkDir;
+use crate::caching::is_fingerprinted;
+use crate::minify::Minifier;
+use crate::themes::Theme;
+
+lazy_static! {
+    // url(...) in stylesheets, <style> blocks, and style attributes, quoted or not
+    static ref CSS_URL_REGEX: Regex = Regex::new(r#"url\(\s*(?:"([^"]*)"|'([^']*)'|([^)"'\s]*))\s*\)"#).unwrap();
+    // @import "theme.css"; the url() form is covered above
+    static ref CSS_IMPORT_REGEX: Regex = Regex::new(r#"@import\s+(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref HTML_ATTR_REGEX: Regex = Regex::new(r#"\b(src|href|poster)=(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref SRCSET_REGEX: Regex = Regex::new(r#"\bsrcset=(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref LINK_TAG_REGEX: Regex = Regex::new(r"(?i)<link\b([^>]*)>").unwrap();
+    static ref SCRIPT_TAG_REGEX: Regex = Regex::new(r"(?i)<script\b([^>]*)>\s*</script>").unwrap();
+    static ref TAG_ATTR_REGEX: Regex = Regex::new(r#"([\w-]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?"#).unwrap();
+}
+
+/// Files that keep their names when fingerprinting, since hosts and
+/// browsers request them by name.
+const KEEP_NAMES: &[&str] = &["**/*.html", "robots.txt", "favicon.ico", ".well-known/**"];
+
+/// Static asset settings, `[assets]` in `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssetsConfig {
+    /// Directory copied to the output; default `static/` next to the content directory
+    pub dir: Option<PathBuf>,
+    /// Add a content hash to file names, e.g. `css/app.3f2a9c1b.css`, and
+    /// rewrite references to them in stylesheets and pages
+    #[serde(default = "default_fingerprint")]
+    pub fingerprint: bool,
+    /// Globs relative to the asset directory, in which `*` also matches `/`,
+    /// of more files that keep their names
+    #[serde(default)]
+    pub keep_names: Vec<String>,
+    /// Stylesheets and scripts of at most this many bytes are inlined into
+    /// the pages that link them, saving a request each
+    pub inline_limit: Option<u64>,
+}
+
+fn default_fingerprint() -> bool {
+    true
+}
+
+impl Default for AssetsConfig {
+    fn default() -> Self {
+        Self { dir: None, fingerprint: default_fingerprint(), keep_names: Vec::new(), inline_limit: None }
+    }
+}
+
+impl AssetsConfig {
+    /// The asset directory, looked up next to the content directory and then inside it.
+    pub fn dir(&self, input_dir: &Path) -> PathBuf {
+        if let Some(dir) = &self.dir {
+            return dir.clone();
+        }
+        let site_root = input_dir.parent().unwrap_or(Path::new(""));
+        [site_root.join("static"), input_dir.join("static")]
+            .into_iter()
+            .find(|dir| dir.is_dir())
+            .unwrap_or_else(|| site_root.join("static"))
+    }
+
+    fn keep_names(&self) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in KEEP_NAMES.iter().copied().chain(self.keep_names.iter().map(String::as_str)) {
+            builder.add(Glob::new(pattern).with_context(|| format!("Invalid keep_names pattern '{}'", pattern))?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// Output path of every asset keyed by its path in the asset directory,
+/// both relative and with `/` separators. Written to `assets.json` for deploy
+/// tools and for dev server rebuilds, which don't process assets again.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetManifest {
+    pub files: BTreeMap<String, String>,
+}
+
+impl AssetManifest {
+    pub const FILE_NAME: &'static str = "assets.json";
+
+    /// Read the manifest a build wrote to `output_dir`.
+    pub fn load(output_dir: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(output_dir.join(Self::FILE_NAME))?)?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Point `url(...)` and `@import` references at output paths. `dir` is
+    /// the directory references resolve against, relative to the site root.
+    /// Also returns the local references that don't match an asset.
+    pub fn rewrite_css(&self, css: &str, dir: &str) -> (String, Vec<String>) {
+        let mut unresolved = Vec::new();
+        let mut rewrite = |caps: &Captures| {
+            let (_, reference) = quoted_value(caps, 1);
+            match self.rewrite_reference(reference, dir) {
+                Ok(Some(rewritten)) => replace_value(caps, 1, &rewritten),
+                Ok(None) => caps[0].to_string(),
+                Err(()) => {
+                    unresolved.push(reference.to_string());
+                    caps[0].to_string()
+                },
+            }
+        };
+        let css = CSS_URL_REGEX.replace_all(css, &mut rewrite).into_owned();
+        let css = CSS_IMPORT_REGEX.replace_all(&css, &mut rewrite).into_owned();
+        (css, unresolved)
+    }
+
+    /// Point `src`, `href`, `poster`, and `srcset` attributes and inline
+    /// `url(...)` references of a page in `dir` at output paths.
+    pub fn rewrite_html(&self, html: &str, dir: &str) -> String {
+        let html = HTML_ATTR_REGEX.replace_all(html, |caps: &Captures| {
+            let (quote, reference) = quoted_value(caps, 2);
+            match self.rewrite_reference(reference, dir) {
+                Ok(Some(rewritten)) => format!("{}={}{}{}", &caps[1], quote, rewritten, quote),
+                _ => caps[0].to_string(),
+            }
+        });
+        let html = SRCSET_REGEX.replace_all(&html, |caps: &Captures| {
+            let (quote, srcset) = quoted_value(caps, 1);
+            let candidates: Vec<String> = srcset.split(',')
+                .map(|candidate| {
+                    let candidate = candidate.trim();
+                    let (url, descriptor) = candidate.split_once(char::is_whitespace).unwrap_or((candidate, ""));
+                    let url = self.rewrite_reference(url, dir).ok().flatten().unwrap_or_else(|| url.to_string());
+                    format!("{} {}", url, descriptor.trim()).trim_end().to_string()
+                })
+                .collect();
+            format!("srcset={}{}{}", quote, candidates.join(", "), quote)
+        });
+        self.rewrite_css(&html, dir).0
+    }
+
+    /// The reference with its file name replaced by the output's; `None` when
+    /// it is external or keeps its name, an error when no asset matches.
+    fn rewrite_reference(&self, reference: &str, dir: &str) -> Result<Option<String>, ()> {
+        let Some((key, path, suffix)) = resolve(reference, dir) else {
+            return Ok(None);
+        };
+        let output = self.files.get(&key).ok_or(())?;
+        if *output == key {
+            return Ok(None);
+        }
+        let file_name = output.rsplit('/').next().unwrap_or(output);
+        let prefix = path.rfind('/').map_or("", |i| &path[..=i]);
+        Ok(Some(format!("{}{}{}", prefix, file_name, suffix)))
+    }
+}
+
+/// The asset directory as published: files copied under fingerprinted names
+/// and stylesheets with their references rewritten.
+#[derive(Debug)]
+pub struct Assets {
+    /// File each asset is read from, keyed by its path in the asset directory
+    sources: BTreeMap<String, PathBuf>,
+    pub manifest: AssetManifest,
+    /// Processed stylesheets keyed by their path in the asset directory
+    stylesheets: BTreeMap<String, String>,
+    /// Stylesheet references that don't match an asset, as (stylesheet, reference)
+    pub unresolved: Vec<(String, String)>,
+}
+
+impl Assets {
+    /// Hash and name every file in the asset directory and the `theme`'s,
+    /// where the site's own files replace the theme's at the same path.
+    /// Stylesheets are rewritten, and minified with `minifier`, before they
+    /// are hashed, so a renamed font or image also renames the stylesheets
+    /// that use it.
+    pub fn process(config: &AssetsConfig, input_dir: &Path, theme: Option<&Theme>, minifier: Option<&Minifier>) -> Result<Self> {
+        let mut assets = Self {
+            sources: BTreeMap::new(),
+            manifest: AssetManifest::default(),
+            stylesheets: BTreeMap::new(),
+            unresolved: Vec::new(),
+        };
+        let dirs = theme.map(Theme::static_dir).into_iter().chain([config.dir(input_dir)]);
+        for dir in dirs.filter(|dir| dir.is_dir()) {
+            for entry in WalkDir::new(&dir).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+                let key = entry.path().strip_prefix(&dir)?.to_string_lossy().replace('\\', "/");
+                assets.sources.insert(key, entry.into_path());
+            }
+        }
+        let keep_names = config.keep_names()?;
+        let namer = |key: &str, bytes: &[u8]| -> String {
+            if config.fingerprint && !keep_names.is_match(key) && !is_fingerprinted(key) {
+                fingerprinted_name(key, bytes)
+            } else {
+                key.to_string()
+            }
+        };
+
+        let mut stylesheets = Vec::new();
+        for (key, path) in &assets.sources {
+            if key.ends_with(".css") {
+                stylesheets.push(key.clone());
+                continue;
+            }
+            let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let output = namer(key, &bytes);
+            assets.manifest.files.insert(key.clone(), output);
+        }
+        for key in &stylesheets {
+            assets.process_stylesheet(key, &stylesheets, &mut Vec::new(), minifier, &namer)?;
+        }
+        Ok(assets)
+    }
+
+    /// Process `key` after the stylesheets it imports, whose names it needs.
+    fn process_stylesheet(
+        &mut self,
+        key: &str,
+        stylesheets: &[String],
+        importing: &mut Vec<String>,
+        minifier: Option<&Minifier>,
+        namer: &dyn Fn(&str, &[u8]) -> String,
+    ) -> Result<()> {
+        if self.stylesheets.contains_key(key) {
+            return Ok(());
+        }
+        if importing.iter().any(|k| k == key) {
+            importing.push(key.to_string());
+            return Err(anyhow!("Stylesheets import each other: {}", importing.join(" -> ")));
+        }
+        let path = &self.sources[key];
+        let css = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let dir = key.rfind('/').map_or("", |i| &key[..i]);
+
+        importing.push(key.to_string());
+        for caps in CSS_URL_REGEX.captures_iter(&css).chain(CSS_IMPORT_REGEX.captures_iter(&css)) {
+            let (_, reference) = quoted_value(&caps, 1);
+            if let Some((import, _, _)) = resolve(reference, dir).filter(|(import, _, _)| stylesheets.contains(import)) {
+                self.process_stylesheet(&import, stylesheets, importing, minifier, namer)?;
+            }
+        }
+        importing.pop();
+
+        let (css, unresolved) = self.manifest.rewrite_css(&css, dir);
+        self.unresolved.extend(unresolved.into_iter().map(|reference| (key.to_string(), reference)));
+        let css = match minifier {
+            Some(minifier) => minifier.minify_css(&css),
+            None => css,
+        };
+        let output = namer(key, css.as_bytes());
+        self.manifest.files.insert(key.to_string(), output);
+        self.stylesheets.insert(key.to_string(), css);
+        Ok(())
+    }
+
+    /// Stylesheets and scripts whose published content is at most `limit` bytes.
+    pub fn inlinable(&self, limit: u64) -> Result<InlineAssets> {
+        let mut inline = InlineAssets::default();
+        for (key, output) in &self.manifest.files {
+            let content = match self.stylesheets.get(key) {
+                Some(css) => {
+                    let dir = output.rfind('/').map_or("", |i| &output[..i]);
+                    root_relative_css(css, dir)
+                },
+                None if key.ends_with(".js") => {
+                    let path = &self.sources[key];
+                    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+                },
+                None => continue,
+            };
+            if content.len() as u64 <= limit {
+                inline.files.insert(output.clone(), content.trim_end().to_string());
+            }
+        }
+        Ok(inline)
+    }
+
+    /// Copy the assets to `output_dir` with the manifest, removing the assets
+    /// of an earlier build that are no longer produced. Refuses assets that
+    /// would overwrite one of the rendered `pages`, which are never removed.
+    pub fn write(&self, output_dir: &Path, pages: &[PathBuf]) -> Result<()> {
+        let pages: HashSet<&Path> = pages.iter().map(PathBuf::as_path).collect();
+        let mut collisions: Vec<&str> = self.manifest.files.values()
+            .filter(|output| pages.contains(output_dir.join(output).as_path()))
+            .map(String::as_str)
+            .collect();
+        if !collisions.is_empty() {
+            collisions.sort_unstable();
+            return Err(anyhow!("Static assets have the same output path as rendered pages: {}", collisions.join(", ")));
+        }
+        if let Ok(previous) = AssetManifest::load(output_dir) {
+            let current: Vec<&String> = self.manifest.files.values().collect();
+            let stale = previous.files.values()
+                .filter(|output| !current.contains(output))
+                .map(|output| output_dir.join(output))
+                .filter(|path| !pages.contains(path.as_path()));
+            for path in stale {
+                let _ = fs::remove_file(path);
+            }
+        }
+        for (key, output) in &self.manifest.files {
+            let target = output_dir.join(output);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match self.stylesheets.get(key) {
+                Some(css) => fs::write(&target, css)?,
+                None => {
+                    fs::copy(&self.sources[key], &target)
+                        .with_context(|| format!("Failed to copy {} to {}", key, target.display()))?;
+                },
+            }
+        }
+        fs::write(output_dir.join(AssetManifest::FILE_NAME), serde_json::to_string_pretty(&self.manifest)?)?;
+        Ok(())
+    }
+}
+
+/// Stylesheets and scripts small enough to go into the pages that link
+/// them, by output path.
+#[derive(Debug, Clone, Default)]
+pub struct InlineAssets {
+    files: BTreeMap<String, String>,
+    /// Files put into at least one page so far
+    inlined: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl InlineAssets {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Replace `<link rel="stylesheet">` and empty `<script src>` tags of a
+    /// page in `dir` that load a small asset with its content. Scripts that
+    /// are `async` or `defer` keep loading on their own, since inline ones
+    /// would run earlier.
+    pub fn apply(&self, html: &str, dir: &str) -> String {
+        let html = LINK_TAG_REGEX.replace_all(html, |caps: &Captures| {
+            let tag = &caps[0];
+            let attrs = tag_attributes(&caps[1]);
+            let is_stylesheet = attrs.iter().any(|(name, value)| name == "rel" && value.eq_ignore_ascii_case("stylesheet"));
+            if !is_stylesheet || attrs.iter().any(|(name, _)| name == "integrity") {
+                return tag.to_string();
+            }
+            let href = attrs.iter().find(|(name, _)| name == "href").map(|(_, value)| value.as_str());
+            match href.and_then(|href| self.inline(href, dir, "</style")) {
+                Some(css) => match attrs.iter().find(|(name, _)| name == "media") {
+                    Some((_, media)) => format!("<style media=\"{}\">{}</style>", media, css),
+                    None => format!("<style>{}</style>", css),
+                },
+                None => tag.to_string(),
+            }
+        });
+        SCRIPT_TAG_REGEX.replace_all(&html, |caps: &Captures| {
+            let attrs = tag_attributes(&caps[1]);
+            let runs_later = attrs.iter().any(|(name, _)| matches!(name.as_str(), "async" | "defer" | "integrity"));
+            let src = attrs.iter().find(|(name, _)| name == "src").map(|(_, value)| value.as_str());
+            match src.filter(|_| !runs_later).and_then(|src| self.inline(src, dir, "</script")) {
+                Some(js) => {
+                    let kept: String = attrs.iter()
+                        .filter(|(name, _)| name == "type" || name == "nonce")
+                        .map(|(name, value)| format!(" {}=\"{}\"", name, value))
+                        .collect();
+                    format!("<script{}>{}</script>", kept, js)
+                },
+                None => caps[0].to_string(),
+            }
+        }).into_owned()
+    }
+
+    /// Content of the small asset `reference` points to, unless it contains
+    /// `closing`, which would end the element it goes into. The asset is
+    /// recorded as inlined.
+    fn inline(&self, reference: &str, dir: &str, closing: &str) -> Option<&str> {
+        let (key, _, _) = resolve(reference, dir)?;
+        let (key, content) = self.files.get_key_value(&key)
+            .filter(|(_, content)| !content.to_ascii_lowercase().contains(closing))?;
+        self.inlined.lock().insert(key.clone());
+        Some(content)
+    }
+
+    /// Delete the files that were inlined into a page and that no output
+    /// refers to any more. Pages, stylesheets, and SVGs are searched for
+    /// `src`, `href`, `srcset`, `url()`, and `@import` references resolved
+    /// against their directory; any text file for the asset's root-relative
+    /// path, as in `_headers` or a service worker's precache list. Returns
+    /// the files removed.
+    pub fn remove_unreferenced(&self, output_dir: &Path) -> Result<Vec<String>> {
+        let inlined = self.inlined.lock().clone();
+        let mut unreferenced = inlined.clone();
+        let outputs = WalkDir::new(output_dir).into_iter()
+            .filter_entry(|entry| entry.depth() != 1 || entry.file_name() != "cache");
+        for entry in outputs {
+            let entry = entry?;
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(output_dir) else { continue };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if !entry.file_type().is_file() || relative == AssetManifest::FILE_NAME || inlined.contains(&relative) {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(path) else { continue };
+            let dir = relative.rfind('/').map_or("", |i| &relative[..i]);
+            let referenced = references(&content, dir);
+            unreferenced.retain(|output| !referenced.contains(output) && !content.contains(&format!("/{}", output)));
+        }
+        let mut removed = Vec::new();
+        for output in unreferenced {
+            if fs::remove_file(output_dir.join(&output)).is_ok() {
+                removed.push(output);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Local files that markup or CSS in `dir` refers to, as paths from the
+/// site root.
+fn references(content: &str, dir: &str) -> BTreeSet<String> {
+    let attributes = HTML_ATTR_REGEX.captures_iter(content).map(|caps| quoted_value(&caps, 2).1.to_string());
+    let srcsets = SRCSET_REGEX.captures_iter(content).flat_map(|caps| {
+        quoted_value(&caps, 1).1.split(',')
+            .filter_map(|candidate| candidate.split_whitespace().next().map(str::to_string))
+            .collect::<Vec<_>>()
+    });
+    let css = CSS_URL_REGEX.captures_iter(content).chain(CSS_IMPORT_REGEX.captures_iter(content))
+        .map(|caps| quoted_value(&caps, 1).1.to_string());
+    attributes.chain(srcsets).chain(css)
+        .filter_map(|reference| resolve(&reference, dir).map(|(key, _, _)| key))
+        .collect()
+}
+
+/// Attribute names, lowercased, and values of the inside of a start tag.
+fn tag_attributes(attributes: &str) -> Vec<(String, String)> {
+    TAG_ATTR_REGEX.captures_iter(attributes.trim_end_matches(['>', '/']))
+        .map(|caps| {
+            let value = (2..=4).find_map(|i| caps.get(i)).map_or("", |value| value.as_str());
+            (caps[1].to_ascii_lowercase(), value.to_string())
+        })
+        .collect()
+}
+
+/// Root-relative `url(...)` and `@import` references of a stylesheet in
+/// `dir`, so they still resolve once it is inlined in a page elsewhere.
+fn root_relative_css(css: &str, dir: &str) -> String {
+    let rebase = |caps: &Captures| {
+        let (_, reference) = quoted_value(caps, 1);
+        match resolve(reference, dir) {
+            Some((key, path, suffix)) if !path.starts_with('/') => replace_value(caps, 1, &format!("/{}{}", key, suffix)),
+            _ => caps[0].to_string(),
+        }
+    };
+    let css = CSS_URL_REGEX.replace_all(css, rebase).into_owned();
+    CSS_IMPORT_REGEX.replace_all(&css, rebase).into_owned()
+}
+
+/// The attribute or url() value in capture group `first` or the next ones,
+/// and the quote around it.
+fn quoted_value<'c>(caps: &'c Captures, first: usize) -> (&'static str, &'c str) {
+    match (caps.get(first), caps.get(first + 1)) {
+        (Some(value), _) => ("\"", value.as_str()),
+        (_, Some(value)) => ("'", value.as_str()),
+        _ => ("", caps.get(first + 2).map_or("", |value| value.as_str())),
+    }
+}
+
+/// The whole match with the value `quoted_value` found replaced.
+fn replace_value(caps: &Captures, first: usize, replacement: &str) -> String {
+    let whole = caps.get(0).unwrap();
+    let Some(value) = (first..first + 3).find_map(|i| caps.get(i)) else {
+        return whole.as_str().to_string();
+    };
+    let (start, end) = (value.start() - whole.start(), value.end() - whole.start());
+    format!("{}{}{}", &whole.as_str()[..start], replacement, &whole.as_str()[end..])
+}
+
+/// Split a local reference into its asset key, its path as written, and any
+/// `?query` or `#fragment`, which fonts use for legacy browsers. `None` for
+/// external URLs, `data:` URIs, fragments, and paths above the site root.
+fn resolve<'r>(reference: &'r str, dir: &str) -> Option<(String, &'r str, &'r str)> {
+    let is_external = reference.starts_with("//")
+        || reference.split(['/', '?', '#']).next().is_some_and(|first| first.contains(':'));
+    if reference.is_empty() || reference.starts_with('#') || is_external {
+        return None;
+    }
+    let split = reference.find(['?', '#']).unwrap_or(reference.len());
+    let (path, suffix) = reference.split_at(split);
+    let joined = match path.strip_prefix('/') {
+        Some(root_relative) => root_relative.to_string(),
+        None if dir.is_empty() => path.to_string(),
+        None => format!("{}/{}", dir, path),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {},
+            ".." => {
+                segments.pop()?;
+            },
+            segment => segments.push(segment),
+        }
+    }
+    Some((segments.join("/"), path, suffix))
+}
+
+/// `css/app.css` as `css/app.<hash>.css`, with the first 8 hex digits of
+/// the SHA-256 of its content. Files without an extension keep their names.
+fn fingerprinted_name(key: &str, bytes: &[u8]) -> String {
+    let name_start = key.rfind('/').map_or(0, |i| i + 1);
+    let Some(dot) = key[name_start..].rfind('.').filter(|&dot| dot > 0).map(|dot| name_start + dot) else {
+        return key.to_string();
+    };
+    let hash = hex(&Sha256::digest(bytes)[..4]);
+    format!("{}.{}{}", &key[..dot], hash, &key[dot..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrites_references_to_fingerprinted_assets() {
+        let site = tempfile::tempdir().unwrap();
+        let dir = site.path().join("static");
+        fs::create_dir_all(dir.join("css")).unwrap();
+        fs::create_dir_all(dir.join("fonts")).unwrap();
+        fs::write(dir.join("fonts/inter.woff2"), "font").unwrap();
+        fs::write(dir.join("hero.png"), "png").unwrap();
+        fs::write(dir.join("robots.txt"), "").unwrap();
+        fs::write(dir.join("css/base.css"), "body { background: url(../hero.png); }").unwrap();
+        fs::write(dir.join("css/app.css"), "@import \"base.css\";\n\
+            @font-face { src: url('/fonts/inter.woff2?v=2#inter') format('woff2'); }\n\
+            .a { background: url(data:image/gif;base64,R0lG) } .b { mask: url(#m) } .c { background: url(\"../missing.png\") }").unwrap();
+
+        let config: AssetsConfig = toml::from_str("").unwrap();
+        let assets = Assets::process(&config, &site.path().join("content"), None, None).unwrap();
+        let files = &assets.manifest.files;
+        assert_eq!(files["robots.txt"], "robots.txt");
+        assert!(is_fingerprinted(&files["fonts/inter.woff2"]));
+        let base = files["css/base.css"].trim_start_matches("css/");
+        let font = files["fonts/inter.woff2"].trim_start_matches("fonts/");
+        let hero = &files["hero.png"];
+
+        let out = site.path().join("output");
+        assets.write(&out, &[]).unwrap();
+        let app = fs::read_to_string(out.join(&files["css/app.css"])).unwrap();
+        assert!(app.contains(&format!("@import \"{}\"", base)));
+        assert!(app.contains(&format!("url('/fonts/{}?v=2#inter')", font)));
+        assert!(app.contains("url(data:image/gif;base64,R0lG)") && app.contains("url(#m)"));
+        assert_eq!(fs::read_to_string(out.join(&files["css/base.css"])).unwrap(), format!("body {{ background: url(../{}); }}", hero));
+        assert_eq!(assets.unresolved, [("css/app.css".to_string(), "../missing.png".to_string())]);
+
+        let html = "<link href=\"/css/app.css\"><img src='../hero.png' srcset=\"../hero.png 2x, https://cdn/x.png 3x\"><a href=\"/about\">";
+        assert_eq!(assets.manifest.rewrite_html(html, "blog"), format!(
+            "<link href=\"/{}\"><img src='../{}' srcset=\"../{} 2x, https://cdn/x.png 3x\"><a href=\"/about\">",
+            files["css/app.css"], hero, hero,
+        ));
+        assert_eq!(AssetManifest::load(&out).unwrap(), assets.manifest);
+    }
+
+    #[test]
+    fn test_assets_never_replace_or_remove_rendered_pages() {
+        let site = tempfile::tempdir().unwrap();
+        let dir = site.path().join("static");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("about.html"), "static").unwrap();
+        let out = site.path().join("output");
+        let page = out.join("about.html");
+        let config: AssetsConfig = toml::from_str("").unwrap();
+
+        let assets = Assets::process(&config, &site.path().join("content"), None, None).unwrap();
+        let err = assets.write(&out, std::slice::from_ref(&page)).unwrap_err();
+        assert!(err.to_string().contains("about.html"), "{}", err);
+        assets.write(&out, &[]).unwrap();
+
+        // The page that replaced the static file isn't stale
+        fs::remove_file(dir.join("about.html")).unwrap();
+        fs::write(&page, "rendered").unwrap();
+        let assets = Assets::process(&config, &site.path().join("content"), None, None).unwrap();
+        assets.write(&out, std::slice::from_ref(&page)).unwrap();
+        assert_eq!(fs::read_to_string(&page).unwrap(), "rendered");
+    }
+}
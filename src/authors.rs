@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::layout::{load_layout, render_layout};
+use crate::themes::ComponentDirs;
+use crate::markdown::BlogPost;
+use crate::slug::Slugs;
+use crate::taxonomy::post_item_html;
+
+/// Author profiles, `[authors]` in `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthorsConfig {
+    /// TOML file with a table per author, keyed by what front matter `author` says
+    #[serde(default = "default_file")]
+    pub file: PathBuf,
+    /// Output subdirectory of the author pages
+    #[serde(default = "default_path")]
+    pub path: String,
+    /// Heading of the index of authors
+    #[serde(default = "default_title")]
+    pub title: String,
+    /// Optional layout containing `@{title}` and `@{yield}` placeholders
+    pub layout: Option<PathBuf>,
+}
+
+fn default_file() -> PathBuf {
+    PathBuf::from("authors.toml")
+}
+
+fn default_path() -> String {
+    "authors".to_string()
+}
+
+fn default_title() -> String {
+    "Authors".to_string()
+}
+
+/// One entry of `authors.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Author {
+    pub name: String,
+    pub bio: Option<String>,
+    /// Image URL, e.g. `/img/jane.jpg`
+    pub avatar: Option<String>,
+    /// Profiles elsewhere by site, e.g. `github = "https://github.com/jane"`
+    #[serde(default)]
+    pub links: BTreeMap<String, String>,
+    /// Path of the author's page, set when the profiles are loaded
+    #[serde(skip_deserializing)]
+    pub url: String,
+}
+
+/// Profiles from the authors file, by key.
+#[derive(Debug, Clone)]
+pub struct Authors {
+    config: AuthorsConfig,
+    profiles: BTreeMap<String, Author>,
+}
+
+impl Authors {
+    pub fn load(config: &AuthorsConfig, slugs: Slugs) -> Result<Self> {
+        let content = fs::read_to_string(&config.file)
+            .with_context(|| format!("Failed to read authors file {}", config.file.display()))?;
+        let mut profiles: BTreeMap<String, Author> = toml::from_str(&content)
+            .with_context(|| format!("Invalid authors file {}", config.file.display()))?;
+        let section = config.path.trim_matches('/');
+        for (key, author) in profiles.iter_mut() {
+            author.url = format!("/{}/{}/", section, slugs.slugify(key));
+        }
+        Ok(Self { config: config.clone(), profiles })
+    }
+
+    /// The profile front matter `author: <key>` refers to, ignoring case.
+    pub fn get(&self, key: &str) -> Option<&Author> {
+        let key = key.trim();
+        self.profiles.get(key)
+            .or_else(|| self.profiles.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, author)| author))
+    }
+
+    /// Posts `author` wrote or co-wrote, in the order given.
+    fn posts_by<'a>(&self, author: &Author, posts: &'a [BlogPost]) -> Vec<&'a BlogPost> {
+        posts.iter()
+            .filter(|post| post.authors.iter()
+                .any(|by| by.profile.as_ref().is_some_and(|profile| profile.url == author.url)))
+            .collect()
+    }
+}
+
+/// Write a page per author with their profile and posts, and an index of authors.
+pub fn generate_author_pages(authors: &Authors, posts: &[BlogPost], output_dir: &Path, components: &ComponentDirs) -> Result<Vec<PathBuf>> {
+    let layout = load_layout(components, authors.config.layout.as_deref());
+    let write_page = |path: PathBuf, title: &str, body: &str| -> Result<PathBuf> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, render_layout(&layout, title, body))?;
+        Ok(path)
+    };
+
+    let mut written = Vec::new();
+    let mut index = format!("<h1>{}</h1>\n<ul class=\"authors\">\n", html_escape::encode_text(&authors.config.title));
+    for author in authors.profiles.values() {
+        let author_posts = authors.posts_by(author, posts);
+        let name = html_escape::encode_text(&author.name);
+        let mut body = String::from("<header class=\"author\">\n");
+        if let Some(avatar) = &author.avatar {
+            body.push_str(&format!(
+                "<img class=\"avatar\" src=\"{}\" alt=\"{}\">\n",
+                html_escape::encode_double_quoted_attribute(avatar),
+                html_escape::encode_double_quoted_attribute(&author.name)
+            ));
+        }
+        body.push_str(&format!("<h1>{}</h1>\n", name));
+        if let Some(bio) = &author.bio {
+            body.push_str(&format!("<p class=\"bio\">{}</p>\n", html_escape::encode_text(bio)));
+        }
+        if !author.links.is_empty() {
+            body.push_str("<ul class=\"links\">\n");
+            for (site, url) in &author.links {
+                body.push_str(&format!(
+                    "<li><a href=\"{}\" rel=\"me\">{}</a></li>\n",
+                    html_escape::encode_double_quoted_attribute(url),
+                    html_escape::encode_text(site)
+                ));
+            }
+            body.push_str("</ul>\n");
+        }
+        body.push_str("</header>\n<ul class=\"posts\">\n");
+        for post in &author_posts {
+            body.push_str(&post_item_html(post));
+        }
+        body.push_str(&format!("</ul>\n<p><a href=\"/{}/\">&larr; All authors</a></p>", authors.config.path.trim_matches('/')));
+        let dir = output_dir.join(author.url.trim_matches('/'));
+        written.push(write_page(dir.join("index.html"), &author.name, &body)?);
+
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{}</a> <span class=\"count\">({})</span></li>\n",
+            author.url,
+            name,
+            author_posts.len()
+        ));
+    }
+    index.push_str("</ul>");
+    written.push(write_page(output_dir.join(authors.config.path.trim_matches('/')).join("index.html"), &authors.config.title, &index)?);
+
+    info!("Generated {} author pages", authors.profiles.len());
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::BlogProcessor;
+
+    #[test]
+    fn test_author_pages_list_their_posts() {
+        let site = tempfile::tempdir().unwrap();
+        let file = site.path().join("authors.toml");
+        fs::write(&file, "[jane]\nname = \"Jane Doe\"\navatar = \"/img/jane.jpg\"\nlinks = { github = \"https://github.com/jane\" }\n").unwrap();
+        let blog = site.path().join("content/blog");
+        fs::create_dir_all(&blog).unwrap();
+        fs::write(blog.join("a.md"), "---\ntitle: A\ndate: 2024-01-01T00:00:00Z\nauthor: Jane\n---\nHi").unwrap();
+        fs::write(blog.join("b.md"), "---\ntitle: B\ndate: 2024-02-01T00:00:00Z\nauthor: Someone Else\n---\nHi").unwrap();
+
+        let config: AuthorsConfig = toml::from_str(&format!("file = {:?}", file)).unwrap();
+        let authors = Authors::load(&config, Slugs::default()).unwrap();
+        let mut processor = BlogProcessor::new(site.path().join("content")).with_authors(Some(authors.clone()));
+        processor.load_posts().unwrap();
+        let posts = processor.posts();
+        assert_eq!(posts[0].author_name(), Some("Someone Else"));
+        assert_eq!(posts[1].author_name(), Some("Jane Doe"));
+        assert!(posts[1].generate_json_ld("Site", "https://example.com").unwrap()
+            .contains("\"url\": \"https://example.com/authors/jane/\""));
+
+        let out = site.path().join("output");
+        let written = generate_author_pages(&authors, posts, &out, &ComponentDirs::new(site.path().join("components"), None)).unwrap();
+        assert_eq!(written, [out.join("authors/jane/index.html"), out.join("authors/index.html")]);
+        let page = fs::read_to_string(&written[0]).unwrap();
+        assert!(page.contains("<a href=\"/blog/a\">A</a>") && !page.contains("/blog/b"));
+        assert!(page.contains("<a href=\"https://github.com/jane\" rel=\"me\">github</a>"));
+        assert!(fs::read_to_string(&written[1]).unwrap().contains("<a href=\"/authors/jane/\">Jane Doe</a> <span class=\"count\">(1)</span>"));
+    }
+
+    #[test]
+    fn test_co_authors_are_credited_everywhere() {
+        let site = tempfile::tempdir().unwrap();
+        let file = site.path().join("authors.toml");
+        fs::write(&file, "[jane]\nname = \"Jane Doe\"\n\n[raj]\nname = \"Raj Patel\"\n").unwrap();
+        let blog = site.path().join("content/blog");
+        fs::create_dir_all(&blog).unwrap();
+        fs::create_dir_all(site.path().join("components")).unwrap();
+        fs::write(site.path().join("components/blog_layout.html"), "<p>@{authors}|@{page.authors.1}</p>@{yield}").unwrap();
+        fs::write(blog.join("a.md"), "---\ntitle: A\ndate: 2024-01-01T00:00:00Z\nauthor: jane\nauthors: [Raj, Guest Writer, jane]\n---\nHi").unwrap();
+
+        let config: AuthorsConfig = toml::from_str(&format!("file = {:?}", file)).unwrap();
+        let authors = Authors::load(&config, Slugs::default()).unwrap();
+        let mut processor = BlogProcessor::new(site.path().join("content")).with_authors(Some(authors.clone()));
+        processor.load_posts().unwrap();
+        let post = &processor.posts()[0];
+        assert_eq!(post.author_names(), ["Jane Doe", "Raj Patel", "Guest Writer"]);
+        assert!(crate::markdown::is_authored_by(&blog.join("a.md"), "guest writer"));
+
+        let html = processor.process_post(post).unwrap().html;
+        assert!(html.starts_with("<p>Jane Doe, Raj Patel, Guest Writer|Raj Patel</p>"));
+        let json_ld: serde_json::Value = serde_json::from_str(&post.generate_json_ld("Site", "https://example.com").unwrap()).unwrap();
+        assert_eq!(json_ld["author"][1]["url"], "https://example.com/authors/raj/");
+        assert_eq!(json_ld["author"][2], serde_json::json!({ "@type": "Person", "name": "Guest Writer" }));
+
+        let seo: crate::seo::SEOConfig = toml::from_str("site_name = \"Site\"\ndefault_description = \"\"\ndefault_keywords = []").unwrap();
+        crate::seo_gen::generate_rss(processor.posts(), &seo, site.path().to_str().unwrap()).unwrap();
+        let rss = fs::read_to_string(site.path().join("rss.xml")).unwrap();
+        assert_eq!(rss.matches("<dc:creator>").count(), 3);
+
+        let written = generate_author_pages(&authors, processor.posts(), &site.path().join("output"), &ComponentDirs::new(site.path().join("components"), None)).unwrap();
+        assert!(fs::read_to_string(&written[1]).unwrap().contains("<a href=\"/blog/a\">A</a>"));
+    }
+}
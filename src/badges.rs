@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use chrono::DateTime;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use crate::markdown::BlogPost;
+
+/// `[badges]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BadgesConfig {
+    /// Emit `badges/posts.json` with the number of published posts
+    #[serde(default = "crate::config::default_true")]
+    pub post_count: bool,
+    /// Emit `badges/latest-post.json` with the newest post's date
+    #[serde(default = "crate::config::default_true")]
+    pub latest_post: bool,
+    #[serde(default = "default_color")]
+    pub color: String,
+    /// Badges whose message is read from a JSON or TOML data file
+    #[serde(default)]
+    pub custom: Vec<CustomBadge>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomBadge {
+    /// Output file name, without extension
+    pub name: String,
+    pub label: String,
+    pub file: PathBuf,
+    /// Dot-separated path to the value inside the file, e.g. `docs.coverage`
+    pub key: String,
+    #[serde(default)]
+    pub suffix: String,
+    /// Fixed color; numeric values are colored by percentage when unset
+    pub color: Option<String>,
+}
+
+fn default_color() -> String {
+    "blue".to_string()
+}
+
+/// shields.io endpoint badge, see <https://shields.io/badges/endpoint-badge>.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    pub schema_version: u8,
+    pub label: String,
+    pub message: String,
+    pub color: String,
+}
+
+impl Badge {
+    pub fn new(label: &str, message: impl Into<String>, color: &str) -> Self {
+        Self {
+            schema_version: 1,
+            label: label.to_string(),
+            message: message.into(),
+            color: color.to_string(),
+        }
+    }
+}
+
+/// Write shields.io-compatible JSON badges under `output_dir/badges/`.
+pub fn generate_badges(config: &BadgesConfig, posts: &[BlogPost], output_dir: &Path) -> Result<()> {
+    let badges_dir = output_dir.join("badges");
+    fs::create_dir_all(&badges_dir)?;
+    let mut count = 0;
+
+    let mut write = |name: &str, badge: Badge| -> Result<()> {
+        fs::write(badges_dir.join(format!("{}.json", name)), serde_json::to_string(&badge)?)?;
+        count += 1;
+        Ok(())
+    };
+
+    if config.post_count {
+        write("posts", Badge::new("posts", posts.len().to_string(), &config.color))?;
+    }
+
+    if config.latest_post {
+        let latest = posts.iter()
+            .filter_map(|p| DateTime::parse_from_rfc3339(&p.front_matter.date).ok())
+            .max();
+        let message = latest.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "none".to_string());
+        write("latest-post", Badge::new("latest post", message, &config.color))?;
+    }
+
+    for custom in &config.custom {
+        match read_data_value(&custom.file, &custom.key) {
+            Ok(value) => {
+                let color = custom.color.clone()
+                    .unwrap_or_else(|| percentage_color(&value).unwrap_or(&config.color).to_string());
+                write(&custom.name, Badge::new(&custom.label, format!("{}{}", value, custom.suffix), &color))?;
+            },
+            Err(e) => warn!("Skipping badge '{}': {}", custom.name, e),
+        }
+    }
+
+    info!("Generated {} badges", count);
+    Ok(())
+}
+
+fn read_data_value(file: &Path, key: &str) -> Result<String> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| anyhow!("Failed to read {}: {}", file.display(), e))?;
+    let data: serde_json::Value = match file.extension().and_then(|e| e.to_str()) {
+        Some("toml") => serde_json::to_value(toml::from_str::<toml::Value>(&content)?)?,
+        _ => serde_json::from_str(&content)?,
+    };
+
+    let value = key.split('.')
+        .try_fold(&data, |value, segment| match segment.parse::<usize>() {
+            Ok(index) => value.get(index),
+            Err(_) => value.get(segment),
+        })
+        .ok_or_else(|| anyhow!("Key '{}' not found in {}", key, file.display()))?;
+
+    Ok(match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+/// Color numeric values the way coverage badges usually are.
+fn percentage_color(value: &str) -> Option<&'static str> {
+    let number: f64 = value.trim_end_matches('%').parse().ok()?;
+    Some(match number {
+        n if n >= 90.0 => "brightgreen",
+        n if n >= 75.0 => "green",
+        n if n >= 50.0 => "yellow",
+        _ => "red",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(date: &str) -> BlogPost {
+        let metadata: serde_yaml::Mapping = serde_yaml::from_str(&format!("title: Post\ndate: {}", date)).unwrap();
+        BlogPost {
+            front_matter: serde_yaml::from_value(serde_yaml::Value::Mapping(metadata.clone())).unwrap(),
+            content: String::new(),
+            html_content: String::new(),
+            url: "/blog/post".to_string(),
+            file_path: PathBuf::from("blog/post.md"),
+            language: None,
+            authors: Vec::new(),
+            metadata,
+            excerpt: String::new(),
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn test_writes_endpoint_badges_from_posts_and_data() {
+        let temp = tempfile::tempdir().unwrap();
+        let data = temp.path().join("coverage.toml");
+        fs::write(&data, "[docs]\ncoverage = 82").unwrap();
+        let config: BadgesConfig = toml::from_str(&format!(
+            "[[custom]]\nname = \"docs\"\nlabel = \"docs\"\nfile = {:?}\nkey = \"docs.coverage\"\nsuffix = \"%\"\n\n\
+             [[custom]]\nname = \"missing\"\nlabel = \"x\"\nfile = {:?}\nkey = \"docs.nope\"",
+            data, data,
+        )).unwrap();
+        let posts = [post("2024-01-01T00:00:00Z"), post("2024-03-05T12:00:00+02:00")];
+
+        generate_badges(&config, &posts, temp.path()).unwrap();
+        let badge = |name: &str| -> serde_json::Value {
+            serde_json::from_str(&fs::read_to_string(temp.path().join(format!("badges/{}.json", name))).unwrap()).unwrap()
+        };
+        assert_eq!(badge("posts"), serde_json::json!({ "schemaVersion": 1, "label": "posts", "message": "2", "color": "blue" }));
+        assert_eq!(badge("latest-post")["message"], "2024-03-05");
+        assert_eq!((badge("docs")["message"].as_str(), badge("docs")["color"].as_str()), (Some("82%"), Some("green")));
+        assert!(!temp.path().join("badges/missing.json").exists());
+    }
+}
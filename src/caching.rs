@@ -0,0 +1,172 @@
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+lazy_static! {
+    // A content hash before the extension, e.g. `app.3f2a9c1b.js` or `logo-9f86d081.png`
+    static ref FINGERPRINT_REGEX: Regex = Regex::new(r"[.-][0-9a-f]{8,}(\.[A-Za-z0-9]+)$").unwrap();
+}
+
+/// Output files that change with every build, so they get the HTML policy.
+const SHORT_LIVED: &[&str] = &["**/*.html", "**/*.xml", "**/*.json", "**/*.txt", "**/*.webmanifest"];
+
+/// Whether an output path has a content hash in its file name.
+pub fn is_fingerprinted(path: &str) -> bool {
+    FINGERPRINT_REGEX.is_match(path)
+}
+
+/// The path an asset had before its content hash was added to the name.
+pub fn unfingerprinted(path: &str) -> Option<String> {
+    FINGERPRINT_REGEX.is_match(path).then(|| FINGERPRINT_REGEX.replace(path, "$1").into_owned())
+}
+
+/// Cache-Control policy settings, `[caching]` in `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CachingConfig {
+    /// Pages, feeds, and other files served at stable URLs that change between builds
+    #[serde(default = "default_html")]
+    pub html: String,
+    /// Assets without a content hash in their name
+    #[serde(default = "default_assets")]
+    pub assets: String,
+    /// Fingerprinted assets, which never change under the same name
+    #[serde(default = "default_immutable")]
+    pub immutable: String,
+    /// Rules that take precedence over the defaults, in order
+    #[serde(default)]
+    pub rules: Vec<CacheRule>,
+}
+
+impl Default for CachingConfig {
+    fn default() -> Self {
+        Self {
+            html: default_html(),
+            assets: default_assets(),
+            immutable: default_immutable(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+fn default_html() -> String {
+    "public, max-age=300, must-revalidate".to_string()
+}
+
+fn default_assets() -> String {
+    "public, max-age=86400".to_string()
+}
+
+fn default_immutable() -> String {
+    "public, max-age=31536000, immutable".to_string()
+}
+
+/// Cache-Control for output paths matching `pattern`, a glob relative to the
+/// output directory in which `*` also matches `/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheRule {
+    pub pattern: String,
+    pub cache_control: String,
+}
+
+/// Recommended Cache-Control per output path, written to `headers.json` for
+/// deploy adapters and servers. The first matching rule applies.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HeadersManifest {
+    pub rules: Vec<CacheRule>,
+    #[serde(skip)]
+    globs: Option<GlobSet>,
+}
+
+impl HeadersManifest {
+    pub const FILE_NAME: &'static str = "headers.json";
+
+    /// Rules for the files in `output_dir`: configured rules first, then each
+    /// fingerprinted asset, short-lived files, and everything else.
+    pub fn build(config: &CachingConfig, output_dir: &Path) -> Result<Self> {
+        let mut rules = config.rules.clone();
+        let mut fingerprinted: Vec<String> = WalkDir::new(output_dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                let relative = entry.path().strip_prefix(output_dir).ok()?.to_string_lossy().replace('\\', "/");
+                let is_page = relative.ends_with(".html");
+                (!is_page && !relative.starts_with("cache/") && is_fingerprinted(&relative)).then_some(relative)
+            })
+            .collect();
+        fingerprinted.sort();
+        rules.extend(fingerprinted.into_iter().map(|pattern| CacheRule {
+            pattern: globset::escape(&pattern),
+            cache_control: config.immutable.clone(),
+        }));
+        rules.extend(SHORT_LIVED.iter().map(|pattern| CacheRule {
+            pattern: pattern.to_string(),
+            cache_control: config.html.clone(),
+        }));
+        rules.push(CacheRule { pattern: "**".to_string(), cache_control: config.assets.clone() });
+        Self::from_rules(rules)
+    }
+
+    /// Read the manifest a build wrote to `output_dir`.
+    pub fn load(output_dir: &Path) -> Result<Self> {
+        let manifest: Self = serde_json::from_str(&fs::read_to_string(output_dir.join(Self::FILE_NAME))?)?;
+        Self::from_rules(manifest.rules)
+    }
+
+    fn from_rules(rules: Vec<CacheRule>) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for rule in &rules {
+            builder.add(Glob::new(&rule.pattern)?);
+        }
+        Ok(Self { rules, globs: Some(builder.build()?) })
+    }
+
+    /// Cache-Control for a URL path such as `/css/app.3f2a9c1b.css`.
+    pub fn cache_control(&self, url_path: &str) -> Option<&str> {
+        let globs = self.globs.as_ref()?;
+        let path = url_path.trim_start_matches('/');
+        globs.matches(path).into_iter().min().map(|i| self.rules[i].cache_control.as_str())
+    }
+
+    pub fn write(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(Self::FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        info!("Caching headers for {} patterns written to {}", self.rules.len(), path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cache_control_by_path() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("css")).unwrap();
+        for file in ["index.html", "css/app.3f2a9c1b.css", "css/site.css", "sitemap.xml"] {
+            fs::write(temp.path().join(file), "").unwrap();
+        }
+        let config = CachingConfig {
+            rules: vec![CacheRule { pattern: "downloads/**".to_string(), cache_control: "no-store".to_string() }],
+            ..CachingConfig::default()
+        };
+        let manifest = HeadersManifest::build(&config, temp.path()).unwrap();
+        manifest.write(temp.path()).unwrap();
+        let manifest = HeadersManifest::load(temp.path()).unwrap();
+
+        assert_eq!(manifest.cache_control("/css/app.3f2a9c1b.css"), Some(config.immutable.as_str()));
+        assert_eq!(manifest.cache_control("/css/site.css"), Some(config.assets.as_str()));
+        assert_eq!(manifest.cache_control("/index.html"), Some(config.html.as_str()));
+        assert_eq!(manifest.cache_control("/blog/post.html"), Some(config.html.as_str()));
+        assert_eq!(manifest.cache_control("/sitemap.xml"), Some(config.html.as_str()));
+        assert_eq!(manifest.cache_control("/downloads/app.html"), Some("no-store"));
+    }
+}
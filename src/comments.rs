@@ -0,0 +1,125 @@
+use html_escape::encode_double_quoted_attribute as attr;
+use serde::Deserialize;
+
+/// Comment threads under blog posts, `[comments]` in `eldroid.toml`.
+/// Switching `provider` changes the embed on every post.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum CommentsConfig {
+    /// GitHub Discussions through giscus.app; the ids come from its setup page
+    Giscus {
+        repo: String,
+        repo_id: String,
+        category: String,
+        category_id: String,
+        /// How a post maps to a discussion: `pathname`, `url`, `title`, or `og:title`
+        #[serde(default = "default_mapping")]
+        mapping: String,
+        #[serde(default = "default_giscus_theme")]
+        theme: String,
+        #[serde(default = "crate::config::default_true")]
+        reactions: bool,
+        #[serde(default = "default_lang")]
+        lang: String,
+    },
+    /// GitHub issues through utteranc.es
+    Utterances {
+        repo: String,
+        /// How a post maps to an issue: `pathname`, `url`, `title`, or `og:title`
+        #[serde(default = "default_mapping")]
+        issue_term: String,
+        label: Option<String>,
+        #[serde(default = "default_utterances_theme")]
+        theme: String,
+    },
+    /// Disqus, identifying each thread by the post's URL path
+    Disqus {
+        shortname: String,
+    },
+}
+
+fn default_mapping() -> String {
+    "pathname".to_string()
+}
+
+fn default_giscus_theme() -> String {
+    "preferred_color_scheme".to_string()
+}
+
+fn default_utterances_theme() -> String {
+    "github-light".to_string()
+}
+
+fn default_lang() -> String {
+    "en".to_string()
+}
+
+impl CommentsConfig {
+    /// The provider's embed snippet for the post at `url_path`.
+    pub fn embed_html(&self, url_path: &str) -> String {
+        let embed = match self {
+            Self::Giscus { repo, repo_id, category, category_id, mapping, theme, reactions, lang } => format!(
+                r#"<script src="https://giscus.app/client.js" data-repo="{}" data-repo-id="{}" data-category="{}" data-category-id="{}" data-mapping="{}" data-strict="0" data-reactions-enabled="{}" data-emit-metadata="0" data-input-position="bottom" data-theme="{}" data-lang="{}" crossorigin="anonymous" async></script>"#,
+                attr(repo), attr(repo_id), attr(category), attr(category_id), attr(mapping),
+                if *reactions { "1" } else { "0" }, attr(theme), attr(lang),
+            ),
+            Self::Utterances { repo, issue_term, label, theme } => format!(
+                r#"<script src="https://utteranc.es/client.js" repo="{}" issue-term="{}"{} theme="{}" crossorigin="anonymous" async></script>"#,
+                attr(repo), attr(issue_term),
+                label.as_ref().map(|label| format!(" label=\"{}\"", attr(label))).unwrap_or_default(),
+                attr(theme),
+            ),
+            Self::Disqus { shortname } => format!(
+                r#"<div id="disqus_thread"></div>
+<script>
+var disqus_config = function () {{ this.page.identifier = {}; }};
+(function () {{
+    var s = document.createElement('script');
+    s.src = 'https://{}.disqus.com/embed.js';
+    s.setAttribute('data-timestamp', +new Date());
+    (document.head || document.body).appendChild(s);
+}})();
+</script>
+<noscript>Please enable JavaScript to view the comments.</noscript>"#,
+                serde_json::to_string(url_path).unwrap_or_default().replace("</", "<\\/"),
+                shortname.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect::<String>(),
+            ),
+        };
+        format!("<section class=\"comments\">\n{}\n</section>", embed)
+    }
+}
+
+/// Put `comments` where the layout has `@{comments}`, or else at the end of
+/// its `<main>` or `<body>`. Empty `comments` just clears the placeholder.
+pub fn inject_comments(html: &str, comments: &str) -> String {
+    if html.contains("@{comments}") {
+        return html.replace("@{comments}", comments);
+    }
+    if comments.is_empty() {
+        return html.to_string();
+    }
+    match html.rfind("</main>").or_else(|| html.rfind("</body>")) {
+        Some(end) => format!("{}{}\n{}", &html[..end], comments, &html[end..]),
+        None => format!("{}\n{}", html, comments),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_injects_provider_embed() {
+        let config: CommentsConfig = toml::from_str("provider = \"utterances\"\nrepo = \"me/site\"\nlabel = \"comments\"").unwrap();
+        let embed = config.embed_html("/blog/post");
+        assert!(embed.contains(r#"repo="me/site" issue-term="pathname" label="comments" theme="github-light""#));
+
+        let layout = "<body><main>@{yield}</main></body>";
+        assert_eq!(inject_comments(layout, "C"), "<body><main>@{yield}C\n</main></body>");
+        assert_eq!(inject_comments("<main>@{comments}</main>", ""), "<main></main>");
+
+        let config: CommentsConfig = toml::from_str("provider = \"disqus\"\nshortname = \"my-site\"").unwrap();
+        let embed = config.embed_html("/blog/post");
+        assert!(embed.contains("this.page.identifier = \"/blog/post\"") && embed.contains("https://my-site.disqus.com/embed.js"));
+    }
+}
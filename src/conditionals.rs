@@ -0,0 +1,181 @@
+use std::env;
+use chrono::{DateTime, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+
+lazy_static! {
+    // A tag alone in a Markdown paragraph takes the paragraph with it
+    static ref TAG_REGEX: Regex = Regex::new(r"<p>\s*@\{(if\s[^}]*|else|endif)\}\s*</p>|@\{(if\s[^}]*|else|endif)\}").unwrap();
+    // Quotes may already be escaped when the block is in Markdown text
+    static ref CONDITION_REGEX: Regex = Regex::new(r#"^(not\s+)?(after|before|env)\((?:"|'|&quot;)([^"'&]+)(?:"|'|&quot;)\)$"#).unwrap();
+}
+
+/// A malformed `@{if}` block, at byte `offset` of the template.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct BlockError {
+    pub offset: usize,
+    pub message: String,
+}
+
+struct Frame {
+    /// Whether the enclosing blocks keep their content
+    parent_active: bool,
+    condition: bool,
+    in_else: bool,
+    /// Where the block's `@{if}` starts
+    offset: usize,
+}
+
+impl Frame {
+    fn active(&self) -> bool {
+        self.parent_active && self.condition != self.in_else
+    }
+}
+
+/// What `@{if ...}` blocks are evaluated against: the build time and the
+/// build environment.
+///
+/// ```text
+/// @{if after("2025-11-28")}<div class="banner">Black Friday sale!</div>@{endif}
+/// @{if env("prod")}<script src="/analytics.js"></script>@{else}<!-- dev build -->@{endif}
+/// ```
+///
+/// Conditions are `after("date")`, `before("date")`, and `env("name")`, each
+/// optionally preceded by `not`. Dates are `YYYY-MM-DD` (midnight UTC) or RFC 3339.
+#[derive(Debug, Clone)]
+pub struct Conditions {
+    now: DateTime<Utc>,
+    environment: String,
+}
+
+impl Conditions {
+    pub fn new(environment: &str) -> Self {
+        Self {
+            now: Utc::now(),
+            environment: normalize_environment(environment),
+        }
+    }
+
+    /// The environment named by `ELDROID_ENV`, otherwise `dev` for watch
+    /// mode and `prod` for builds.
+    pub fn from_env(watch: bool) -> Self {
+        match env::var("ELDROID_ENV") {
+            Ok(environment) if !environment.is_empty() => Self::new(&environment),
+            _ => Self::new(if watch { "dev" } else { "prod" }),
+        }
+    }
+
+    /// Evaluate as if building at `now`.
+    pub fn at(mut self, now: DateTime<Utc>) -> Self {
+        self.now = now;
+        self
+    }
+
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    /// Keep the branches whose conditions hold and drop the rest.
+    pub fn apply(&self, content: &str) -> Result<String, BlockError> {
+        if !TAG_REGEX.is_match(content) {
+            return Ok(content.to_string());
+        }
+
+        let mut output = String::with_capacity(content.len());
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut last = 0;
+        for caps in TAG_REGEX.captures_iter(content) {
+            let whole = caps.get(0).unwrap();
+            let active = stack.last().is_none_or(Frame::active);
+            if active {
+                output.push_str(&content[last..whole.start()]);
+            }
+            last = whole.end();
+
+            let error = |message: String| BlockError { offset: whole.start(), message };
+            match caps.get(1).or_else(|| caps.get(2)).unwrap().as_str() {
+                "else" => {
+                    let frame = stack.last_mut()
+                        .ok_or_else(|| error("@{else} without a matching @{if}".to_string()))?;
+                    if frame.in_else {
+                        return Err(error("Second @{else} in one @{if} block".to_string()));
+                    }
+                    frame.in_else = true;
+                },
+                "endif" => {
+                    stack.pop().ok_or_else(|| error("@{endif} without a matching @{if}".to_string()))?;
+                },
+                tag => {
+                    let condition = self.evaluate(tag["if".len()..].trim()).map_err(error)?;
+                    stack.push(Frame { parent_active: active, condition, in_else: false, offset: whole.start() });
+                },
+            }
+        }
+        if let Some(frame) = stack.last() {
+            return Err(BlockError { offset: frame.offset, message: "Unclosed @{if} block, expected @{endif}".to_string() });
+        }
+        output.push_str(&content[last..]);
+        Ok(output)
+    }
+
+    fn evaluate(&self, condition: &str) -> Result<bool, String> {
+        let caps = CONDITION_REGEX.captures(condition).ok_or_else(|| format!(
+            "Unknown condition '{}', expected after(\"date\"), before(\"date\"), or env(\"name\")",
+            condition
+        ))?;
+        let argument = &caps[3];
+        let holds = match &caps[2] {
+            "after" => self.now >= parse_date(argument)?,
+            "before" => self.now < parse_date(argument)?,
+            _ => normalize_environment(argument) == self.environment,
+        };
+        Ok(holds != caps.get(1).is_some())
+    }
+}
+
+fn parse_date(date: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(date) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|day| day.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD or RFC 3339", date))
+}
+
+fn normalize_environment(name: &str) -> String {
+    match name.trim().to_lowercase().as_str() {
+        "production" => "prod".to_string(),
+        "development" => "dev".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_branches_that_hold() {
+        let conditions = Conditions::new("production")
+            .at(DateTime::parse_from_rfc3339("2025-06-01T00:00:00Z").unwrap().with_timezone(&Utc));
+        let template = "@{if after(\"2025-01-01\")}new@{else}old@{endif} \
+            @{if env(\"dev\")}debug@{endif}\
+            @{if not env('prod')}x@{else}@{if before(\"2025-07-01\")}soon@{endif}@{endif}";
+        assert_eq!(conditions.apply(template).unwrap(), "new soon");
+
+        let markdown = "<p>@{if env(&quot;prod&quot;)}</p>\n<p>Live</p>\n<p>@{endif}</p>";
+        assert_eq!(conditions.apply(markdown).unwrap(), "\n<p>Live</p>\n");
+    }
+
+    #[test]
+    fn test_reports_malformed_blocks() {
+        let conditions = Conditions::new("prod");
+        assert_eq!(conditions.apply("a @{if env(\"prod\")} b").unwrap_err().offset, 2);
+        assert!(conditions.apply("@{endif}").is_err());
+        assert!(conditions.apply("@{if env(\"prod\")}@{else}@{else}@{endif}").is_err());
+        assert!(conditions.apply("@{if today()}@{endif}").is_err());
+        assert!(conditions.apply("@{if after(\"soon\")}@{endif}").is_err());
+    }
+}
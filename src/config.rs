@@ -1,8 +1,41 @@
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use clap::{Parser, Subcommand};
+use anyhow::Result;
+use crate::apidoc::ApiDocsConfig;
+use crate::i18n::I18nConfig;
+use crate::redirects::RedirectsConfig;
+use crate::releases::ReleasesConfig;
+use crate::taxonomy::TaxonomyConfig;
+use crate::sections::{PrevNext, SectionConfig};
+use crate::authors::AuthorsConfig;
+use crate::markdown::ExcerptConfig;
+use crate::git_dates::DatesConfig;
+use crate::comments::CommentsConfig;
+use crate::webmentions::WebmentionsConfig;
+use crate::badges::BadgesConfig;
+use crate::csp::CspConfig;
+use crate::search::SearchConfig;
+use crate::shortcodes::MapsConfig;
+use crate::caching::CachingConfig;
+use crate::deploy::DeployConfig;
+use crate::host_headers::HostHeadersConfig;
+use crate::assets::AssetsConfig;
+use crate::minify::MinifyConfig;
+use crate::remote_cache::RemoteCacheConfig;
+use crate::remote_data::RemoteDataConfig;
+use crate::proxy::ProxyRule;
+use crate::hooks::HooksConfig;
+use crate::themes::Theme;
+use crate::shard::Shard;
+use crate::slug::Slugs;
+use crate::verify::VerifyConfig;
+use crate::report::{AnalysisConfig, ReportFormat};
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
 pub struct CliArgs {
     /// Input directory containing content files
@@ -21,6 +54,10 @@ pub struct CliArgs {
     #[arg(long, default_value = "variables.toml")]
     pub variables_config: PathBuf,
 
+    /// Site configuration file path
+    #[arg(long, default_value = "eldroid.toml")]
+    pub site_config: PathBuf,
+
     /// Release mode with additional optimizations
     #[arg(long)]
     pub release: bool,
@@ -41,10 +78,31 @@ pub struct CliArgs {
     #[arg(long)]
     pub minify: bool,
 
+    /// Indent the HTML of pages rebuilt for SEO tags; on unless --release or --minify
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", require_equals = true)]
+    pub pretty_html: Option<bool>,
+
     /// Check for mixed content and security issues
     #[arg(long)]
     pub security_checks: bool,
 
+    /// Rewrite http:// resource URLs to https:// in the generated HTML
+    #[arg(long)]
+    pub fix_mixed_content: bool,
+
+    /// Format of security and performance analysis reports
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub report_format: ReportFormat,
+
+    /// Fail the build on internal links to missing pages, assets, or anchors
+    #[arg(long)]
+    pub check_links: bool,
+
+    /// Fail the build on unresolved variables and macros, unknown filters, and
+    /// missing components instead of leaving placeholders in the output
+    #[arg(long)]
+    pub strict: bool,
+
     /// Enable watch mode with development server
     #[arg(long)]
     pub watch: bool,
@@ -53,10 +111,66 @@ pub struct CliArgs {
     #[arg(long)]
     pub port: Option<u16>,
 
-    /// Live reload WebSocket port (random if not specified)
-    #[arg(long)]
+    /// If --port is taken, use the next free port instead of failing
+    #[arg(long, requires = "port")]
+    pub port_fallback: bool,
+
+    /// Address for the development server to listen on, e.g. 0.0.0.0 to test from other devices on the network
+    #[arg(long, visible_alias = "bind", value_name = "ADDR", default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    /// Open the development server in the default browser
+    #[arg(long, requires = "watch")]
+    pub open: bool,
+
+    /// Serve the development server over HTTPS, with a self-signed certificate unless --https-cert is given
+    #[arg(long, requires = "watch")]
+    pub https: bool,
+
+    /// PEM certificate for --https, e.g. one made with mkcert
+    #[arg(long, value_name = "FILE", requires_all = ["https", "https_key"])]
+    pub https_cert: Option<PathBuf>,
+
+    /// PEM private key for --https-cert
+    #[arg(long, value_name = "FILE", requires = "https_cert")]
+    pub https_key: Option<PathBuf>,
+
+    /// Forward dev server requests under PATH to a backend, e.g. /api=http://localhost:4000 (repeatable)
+    #[arg(long, value_name = "PATH=URL", requires = "watch")]
+    pub proxy: Vec<ProxyRule>,
+
+    /// Ignored: live reload is served on the dev server port
+    #[arg(long, hide = true)]
     pub ws_port: Option<u16>,
 
+    /// In watch mode, serve rebuilt pages from memory instead of writing them to the output directory
+    #[arg(long, requires = "watch")]
+    pub in_memory: bool,
+
+    /// In watch mode, only build and watch content whose front matter `author` matches
+    #[arg(long, value_name = "NAME", requires = "watch")]
+    pub author: Option<String>,
+
+    /// In watch mode, answer requests for directories without an index.html with 404 instead of a listing
+    #[arg(long, requires = "watch")]
+    pub no_directory_listing: bool,
+
+    /// Milliseconds without file events before watch mode handles the changes
+    #[arg(long, value_name = "MS", default_value_t = 100, requires = "watch")]
+    pub watch_debounce: u64,
+
+    /// Glob of paths for watch mode to ignore, relative to the working directory (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub watch_ignore: Vec<String>,
+
+    /// Poll for file changes, for network filesystems and Docker volumes without native events
+    #[arg(long, requires = "watch")]
+    pub watch_poll: bool,
+
+    /// Milliseconds between polls with --watch-poll
+    #[arg(long, value_name = "MS", default_value_t = 1000)]
+    pub watch_poll_interval: u64,
+
     /// Clear build cache and temporary files
     #[arg(long)]
     pub clear_cache: bool,
@@ -85,11 +199,31 @@ pub struct CliArgs {
     #[arg(long)]
     pub memory_profile: bool,
 
+    /// Build only shard N of M of the pages, e.g. 2/8, for `merge` to combine
+    #[arg(long, value_name = "N/M", conflicts_with = "watch")]
+    pub shard: Option<Shard>,
+
+    /// Export the page/component/asset dependency graph (`.json` for JSON, DOT otherwise)
+    #[arg(long, value_name = "FILE")]
+    pub graph: Option<PathBuf>,
+
+    /// After building, write the SHA-256 of every normalized output file to FILE
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    pub output_manifest: Option<PathBuf>,
+
+    /// After building, fail unless the output matches the manifest written by --output-manifest
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    pub verify_output: Option<PathBuf>,
+
+    /// Glob of output files --output-manifest leaves out and records, besides those under [verify]
+    #[arg(long = "volatile", value_name = "GLOB", conflicts_with = "watch")]
+    pub volatile: Vec<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
-#[derive(Subcommand, Debug)]
+#[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Generate a starter template site with sample pages, components, and blogs
     InitTemplate {
@@ -97,6 +231,135 @@ pub enum Commands {
         #[arg(long, default_value = "sample-site")]
         target: String,
     },
+    /// Create a content file from an archetype, e.g. `new post "My Title"`
+    /// writes `blog/my-title.md` under --input-dir
+    New {
+        /// `post` for a blog post, or the name of any other content section
+        kind: String,
+        /// Title of the page; its slug names the file
+        title: String,
+    },
+    /// Check components and layouts for undefined variables, unknown macros,
+    /// unclosed blocks, and missing components without building any content
+    LintTemplates,
+    /// Run checks against an already built output directory
+    Check {
+        #[command(subcommand)]
+        target: CheckTarget,
+    },
+    /// Print every pipeline stage applied to a single page without writing it
+    Explain {
+        /// Content file to trace, e.g. content/blog/post.md
+        file: PathBuf,
+    },
+    /// Bulk-edit front matter of content files
+    Frontmatter {
+        #[command(subcommand)]
+        action: FrontmatterAction,
+    },
+    /// Show recent builds from the build log in the cache directory
+    History {
+        /// Number of builds to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Only show failed builds
+        #[arg(long)]
+        failed: bool,
+        /// Print the records as JSON, including changed file lists
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write the posts of an existing site's RSS or Atom feed, or of a
+    /// WordPress export, as Markdown files
+    #[command(subcommand_negates_reqs = true)]
+    Import {
+        /// Feed URL, or a downloaded feed file
+        #[arg(long, value_name = "URL", required = true)]
+        from_feed: Option<String>,
+        /// Directory under --input-dir to write the posts to
+        #[arg(long, default_value = "blog", global = true)]
+        section: String,
+        /// Download each post's page and extract the article, for feeds with only summaries
+        #[arg(long)]
+        full_content: bool,
+        /// Replace existing files with the same name instead of skipping those posts
+        #[arg(long, global = true)]
+        overwrite: bool,
+        /// Import only the first N entries of the feed
+        #[arg(long, value_name = "N", global = true)]
+        limit: Option<usize>,
+        #[command(subcommand)]
+        source: Option<ImportSource>,
+    },
+    /// Push the last build's pages to the hosted search indexes in `[[search.push]]`,
+    /// with API keys from environment variables
+    DeployIndex {
+        /// Send every page, not only those changed since the last push
+        #[arg(long)]
+        all: bool,
+    },
+    /// Upload the output to the `[deploy]` target, sending only the files that
+    /// changed since the last deploy
+    Deploy {
+        /// Send every file, not only those changed since the last deploy
+        #[arg(long)]
+        all: bool,
+        /// List the files that would be uploaded and deleted without changing the target
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Notify the sites blog posts link to, using the links recorded by the
+    /// last build with `[webmentions]`; run it after deploying
+    SendWebmentions {
+        /// List the mentions that would be sent without sending them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Combine the outputs of `--shard` builds into --output-dir and generate
+    /// the sitemap, feeds, search index, and other site-wide files
+    Merge {
+        /// Output directories of the shard builds, one per shard
+        #[arg(required = true)]
+        shard_dirs: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum FrontmatterAction {
+    /// Set fields in every matching Markdown file, keeping comments and key order
+    Set {
+        /// Assignments like `draft=false` or `tags=[rust, ssg]`; values are YAML
+        #[arg(required = true, value_name = "KEY=VALUE")]
+        assignments: Vec<String>,
+        /// Files to edit, relative to the input directory
+        #[arg(long, default_value = "**")]
+        glob: String,
+        /// Only add fields that are missing, leaving existing values alone
+        #[arg(long)]
+        if_missing: bool,
+        /// List the files that would change without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ImportSource {
+    /// Convert a WordPress export (Tools > Export) into posts and pages, with
+    /// its media downloaded into the static directory
+    Wordpress {
+        /// The WXR file, e.g. mysite.WordPress.2024-01-01.xml
+        export: PathBuf,
+        /// Keep media linked to the old site instead of downloading it
+        #[arg(long)]
+        no_media: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CheckTarget {
+    /// Report internal links to missing pages, assets, or `#fragment` anchors
+    Links,
 }
 
 #[derive(Debug, Deserialize)]
@@ -110,12 +373,30 @@ pub struct BuildConfig {
     #[serde(default)]
     pub minify: bool,
     #[serde(default)]
+    pub pretty_html: bool,
+    #[serde(default)]
     pub security_checks: bool,
     #[serde(default)]
+    pub check_links: bool,
+    #[serde(default)]
+    pub fix_mixed_content: bool,
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub report_format: ReportFormat,
+    #[serde(default)]
     pub watch: bool,
     pub port: Option<u16>,
-    pub ws_port: Option<u16>,
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,
+    #[serde(default)]
+    pub watch_ignore: Vec<String>,
+    #[serde(default)]
+    pub watch_poll: bool,
+    #[serde(default = "default_watch_poll_interval_ms")]
+    pub watch_poll_interval_ms: u64,
     pub variables_config: PathBuf,
+    pub site_config: PathBuf,
     #[serde(default)]
     pub clear_cache: bool,
     #[serde(default)]
@@ -132,6 +413,19 @@ pub struct BuildConfig {
     pub memory_profile: bool,
 }
 
+fn default_watch_debounce_ms() -> u64 {
+    100
+}
+
+fn default_watch_poll_interval_ms() -> u64 {
+    1000
+}
+
+/// Serde default for settings that are on unless turned off.
+pub(crate) fn default_true() -> bool {
+    true
+}
+
 impl Default for BuildConfig {
     fn default() -> Self {
         Self {
@@ -139,11 +433,20 @@ impl Default for BuildConfig {
             analyze_performance: false,
             enable_seo: false,
             minify: false,
+            pretty_html: false,
             security_checks: false,
+            check_links: false,
+            fix_mixed_content: false,
+            strict: false,
+            report_format: ReportFormat::Text,
             watch: false,
             port: None,
-            ws_port: None,
+            watch_debounce_ms: default_watch_debounce_ms(),
+            watch_ignore: Vec::new(),
+            watch_poll: false,
+            watch_poll_interval_ms: default_watch_poll_interval_ms(),
             variables_config: PathBuf::from("variables.toml"),
+            site_config: PathBuf::from("eldroid.toml"),
             clear_cache: false,
             check_watchers: false,
             check_image_processor: false,
@@ -162,11 +465,20 @@ impl From<&CliArgs> for BuildConfig {
             analyze_performance: args.analyze_performance,
             enable_seo: args.enable_seo,
             minify: args.minify,
+            pretty_html: args.pretty_html.unwrap_or(!args.release),
             security_checks: args.security_checks,
+            check_links: args.check_links,
+            fix_mixed_content: args.fix_mixed_content,
+            strict: args.strict,
+            report_format: args.report_format,
             watch: args.watch,
             port: args.port,
-            ws_port: args.ws_port,
+            watch_debounce_ms: args.watch_debounce,
+            watch_ignore: args.watch_ignore.clone(),
+            watch_poll: args.watch_poll,
+            watch_poll_interval_ms: args.watch_poll_interval,
             variables_config: args.variables_config.clone(),
+            site_config: args.site_config.clone(),
             clear_cache: args.clear_cache,
             check_watchers: args.check_watchers,
             check_image_processor: args.check_image_processor,
@@ -181,7 +493,168 @@ impl From<&CliArgs> for BuildConfig {
             config.minify = true;
             config.security_checks = true;
         }
+        // Minified pages have no indentation to keep
+        if config.minify {
+            config.pretty_html = false;
+        }
 
         config
     }
-}
\ No newline at end of file
+}
+
+/// Site-wide settings read from `eldroid.toml`.
+#[derive(Debug, Deserialize, Default)]
+pub struct SiteConfig {
+    /// Name of the theme in `themes/` whose layouts and assets the site uses
+    pub theme: Option<String>,
+    /// Fail the build on unresolved references, like `--strict`
+    #[serde(default)]
+    pub strict: bool,
+    /// Move root-relative links in pages under the path of the SEO
+    /// `base_url`, for sites served from a subdirectory
+    #[serde(default)]
+    pub rewrite_root_links: bool,
+    /// Default front matter keyed by a glob relative to the input directory,
+    /// e.g. `[defaults."blog/**"]`. Page front matter always takes precedence.
+    #[serde(default)]
+    pub defaults: HashMap<String, toml::Table>,
+    /// API reference generated from rustdoc JSON
+    pub api_docs: Option<ApiDocsConfig>,
+    /// Multilingual content settings
+    pub i18n: Option<I18nConfig>,
+    /// Redirect rules and the formats to emit them in
+    #[serde(default)]
+    pub redirects: RedirectsConfig,
+    /// Release notes section generated from git tags or a changelog
+    pub releases: Option<ReleasesConfig>,
+    /// Front matter fields that group posts, each with index pages and feeds
+    #[serde(default)]
+    pub taxonomies: Vec<TaxonomyConfig>,
+    /// Keep accented letters and other scripts in URLs, anchors, and term
+    /// slugs instead of transliterating them to ASCII
+    #[serde(default)]
+    pub unicode_slugs: bool,
+    /// Publish Markdown and HTML pages at slugs of their file paths, which
+    /// moves pages whose names have capitals, spaces, or accents
+    #[serde(default)]
+    pub slug_paths: bool,
+    /// What to do when two content files would be written to the same path
+    #[serde(default)]
+    pub duplicate_urls: DuplicateUrls,
+    /// Layout of posts that name none, relative to the components directory,
+    /// instead of `blog_layout.html`; a built-in one is used when it doesn't exist
+    pub blog_layout: Option<PathBuf>,
+    /// Content directories besides `blog` whose Markdown files are posts,
+    /// each with its own layout, URLs, feed, and listing page
+    #[serde(default)]
+    pub sections: Vec<SectionConfig>,
+    /// Posts that prev/next links step through, unless a section sets its own
+    #[serde(default)]
+    pub prev_next: PrevNext,
+    /// Author profiles referenced from front matter, each with a page
+    pub authors: Option<AuthorsConfig>,
+    /// Length of post excerpts without a `<!--more-->` marker
+    #[serde(default)]
+    pub excerpts: ExcerptConfig,
+    /// Where page modified and created dates come from
+    #[serde(default)]
+    pub dates: DatesConfig,
+    /// Comment threads embedded under blog posts
+    pub comments: Option<CommentsConfig>,
+    /// Webmention endpoint to advertise, and outbound links to notify
+    pub webmentions: Option<WebmentionsConfig>,
+    /// shields.io endpoint badges with site statistics
+    pub badges: Option<BadgesConfig>,
+    /// Analyzer findings and budgets that fail the build
+    #[serde(default)]
+    pub analysis: AnalysisConfig,
+    /// Content-Security-Policy generated from the resources pages load
+    pub csp: Option<CspConfig>,
+    /// Search index file and hosted indexes kept in sync with the site
+    pub search: Option<SearchConfig>,
+    /// How `{{< map >}}` shortcodes are rendered
+    #[serde(default)]
+    pub maps: MapsConfig,
+    /// Static files copied to the output, fingerprinted
+    pub assets: Option<AssetsConfig>,
+    /// What minification keeps and skips when it is on
+    #[serde(default)]
+    pub minify: MinifyConfig,
+    /// Cache-Control policy written to `headers.json`
+    #[serde(default)]
+    pub caching: CachingConfig,
+    /// Security and caching headers written for Netlify or Vercel
+    pub host_headers: Option<HostHeadersConfig>,
+    /// Where `eldroid-ssg deploy` publishes the output
+    pub deploy: Option<DeployConfig>,
+    /// Store shared between machines behind the local cache directory
+    pub remote_cache: Option<RemoteCacheConfig>,
+    /// JSON fetched during the build and read like data files
+    #[serde(default)]
+    pub remote_data: Vec<RemoteDataConfig>,
+    /// Dev server routes forwarded to backend servers
+    #[serde(default)]
+    pub proxy: Vec<ProxyRule>,
+    /// Output files that `--verify-output` allows to change between builds
+    #[serde(default)]
+    pub verify: VerifyConfig,
+    /// Shell commands run before the build, after each page, and after the build
+    #[serde(default)]
+    pub hooks: HooksConfig,
+}
+
+/// What a build does when two content files map to the same output path,
+/// like `about.md` and `about.html`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateUrls {
+    /// Fail the build, naming the files
+    #[default]
+    Error,
+    /// Warn and build only the first of the files in path order
+    Warn,
+}
+
+impl SiteConfig {
+    pub fn load(config_path: &Path) -> Result<Self> {
+        if config_path.exists() {
+            let content = fs::read_to_string(config_path)?;
+            let mut config: Self = toml::from_str(&content)?;
+            config.redirects.validate()?;
+            // A theme's static files are published even without an [assets] section
+            if config.theme.is_some() && config.assets.is_none() {
+                config.assets = Some(AssetsConfig { fingerprint: false, ..AssetsConfig::default() });
+            }
+            Ok(config)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// How names and paths become slugs.
+    pub fn slugs(&self) -> Slugs {
+        Slugs { unicode: self.unicode_slugs, paths: self.slug_paths }
+    }
+
+    /// The theme selected with `theme`, for the configuration at `config_path`.
+    pub fn load_theme(&self, config_path: &Path) -> Result<Option<Theme>> {
+        self.theme.as_deref().map(|name| Theme::load(name, config_path)).transpose()
+    }
+}
+
+pub fn load_site_config(config_path: &Path) -> Result<SiteConfig> {
+    SiteConfig::load(config_path)
+}
+
+/// Per-user directory for files that must never be published with a site,
+/// like the dev server's private key: `$XDG_CACHE_HOME/eldroid-ssg`, then
+/// `~/.cache/eldroid-ssg`, then the system temporary directory.
+pub fn user_cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("eldroid-ssg")
+}
@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use base64::Engine;
+use html_escape::encode_double_quoted_attribute as attr;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::analyzer::ResourceInventory;
+
+lazy_static! {
+    static ref HEAD_OPEN_REGEX: Regex = Regex::new(r"(?i)<head(\s[^>]*)?>").unwrap();
+    static ref HTML_OPEN_REGEX: Regex = Regex::new(r"(?i)<html(\s[^>]*)?>").unwrap();
+}
+
+/// Directives in the order they are written; extra ones follow alphabetically.
+const DIRECTIVES: &[&str] = &[
+    "default-src",
+    "script-src",
+    "style-src",
+    "img-src",
+    "font-src",
+    "connect-src",
+    "media-src",
+    "frame-src",
+    "object-src",
+    "base-uri",
+    "form-action",
+];
+
+/// `[csp]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CspConfig {
+    /// Add a `<meta http-equiv="Content-Security-Policy">` tag to each page
+    #[serde(default = "crate::config::default_true")]
+    pub meta: bool,
+    /// Write the site-wide policy to `_headers` in the output directory
+    #[serde(default)]
+    pub headers_file: bool,
+    /// Allow inline scripts by SHA-256 hash rather than `'unsafe-inline'`
+    #[serde(default = "crate::config::default_true")]
+    pub hash_inline_scripts: bool,
+    /// Additional sources per directive, e.g. `connect-src = ["https://api.example.com"]`
+    #[serde(default)]
+    pub extra: BTreeMap<String, Vec<String>>,
+}
+
+/// A Content-Security-Policy as directive name to allowed sources.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentSecurityPolicy {
+    directives: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ContentSecurityPolicy {
+    /// Recommended policy allowing exactly the resources a page loads.
+    pub fn from_inventory(inventory: &ResourceInventory, config: &CspConfig) -> Self {
+        let mut policy = Self::default();
+        policy.add("default-src", ["'self'"]);
+        policy.add("object-src", ["'none'"]);
+        policy.add("base-uri", ["'self'"]);
+        policy.add("form-action", ["'self'"]);
+
+        policy.add("script-src", ["'self'"]);
+        policy.add("script-src", &inventory.script_origins);
+        let inline_scripts: Vec<_> = inventory.inline_scripts.iter()
+            .filter(|script| !script.trim().is_empty())
+            .collect();
+        if !inline_scripts.is_empty() {
+            if config.hash_inline_scripts {
+                policy.add("script-src", inline_scripts.iter().map(|script| script_hash(script)));
+            } else {
+                policy.add("script-src", ["'unsafe-inline'"]);
+            }
+        }
+
+        policy.add("style-src", ["'self'"]);
+        policy.add("style-src", &inventory.style_origins);
+        if inventory.inline_styles {
+            policy.add("style-src", ["'unsafe-inline'"]);
+        }
+
+        policy.add("img-src", ["'self'"]);
+        policy.add("img-src", &inventory.image_origins);
+        policy.add("font-src", ["'self'"]);
+        policy.add("font-src", &inventory.font_origins);
+        policy.add("connect-src", ["'self'"]);
+        policy.add("media-src", &inventory.media_origins);
+        policy.add("frame-src", &inventory.frame_origins);
+
+        for (directive, sources) in &config.extra {
+            policy.add(directive, sources);
+        }
+        policy
+    }
+
+    fn add<I, S>(&mut self, directive: &str, sources: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let entry = self.directives.entry(directive.to_string()).or_default();
+        entry.extend(sources.into_iter().map(|source| source.as_ref().to_string()));
+    }
+
+    /// Union with another page's policy, for a site-wide header.
+    pub fn merge(&mut self, other: &ContentSecurityPolicy) {
+        for (directive, sources) in &other.directives {
+            self.add(directive, sources);
+        }
+    }
+
+    /// Header value, e.g. `default-src 'self'; script-src 'self' https://cdn.example.com`.
+    pub fn to_header_value(&self) -> String {
+        let known = DIRECTIVES.iter().filter_map(|name| self.directives.get_key_value(*name));
+        let extra = self.directives.iter().filter(|(name, _)| !DIRECTIVES.contains(&name.as_str()));
+        known.chain(extra)
+            .filter(|(_, sources)| !sources.is_empty())
+            .map(|(name, sources)| {
+                // 'none' only makes sense on its own
+                let sources: Vec<_> = if sources.len() > 1 {
+                    sources.iter().filter(|s| *s != "'none'").map(String::as_str).collect()
+                } else {
+                    sources.iter().map(String::as_str).collect()
+                };
+                format!("{} {}", name, sources.join(" "))
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    pub fn meta_tag(&self) -> String {
+        format!(r#"<meta http-equiv="Content-Security-Policy" content="{}">"#, attr(&self.to_header_value()))
+    }
+}
+
+/// CSP source allowing one inline script, e.g. `'sha256-...'`.
+pub fn script_hash(script: &str) -> String {
+    let digest = Sha256::digest(script.as_bytes());
+    format!("'sha256-{}'", base64::engine::general_purpose::STANDARD.encode(digest))
+}
+
+/// Add the policy's meta tag at the start of `<head>` so it covers every
+/// script and stylesheet that follows.
+pub fn inject_csp_meta(html: &str, policy: &ContentSecurityPolicy, file_path: &Path, inventory: &ResourceInventory) -> String {
+    if inventory.inline_handlers {
+        warn!("{} uses inline event handlers, which the generated CSP blocks", file_path.display());
+    }
+    if html.to_ascii_lowercase().contains("http-equiv=\"content-security-policy\"") {
+        info!("{} sets its own Content-Security-Policy; not adding one", file_path.display());
+        return html.to_string();
+    }
+    let tag = policy.meta_tag();
+    let insert_at = [&*HEAD_OPEN_REGEX, &*HTML_OPEN_REGEX].iter()
+        .find_map(|regex| regex.find(html))
+        .map_or(0, |open| open.end());
+    format!("{}{}{}", &html[..insert_at], tag, &html[insert_at..])
+}
+
+/// Write the site-wide policy to `_headers` for hosts that read it.
+pub fn write_headers_file(policy: &ContentSecurityPolicy, output_dir: &Path) -> Result<()> {
+    let path = output_dir.join("_headers");
+    fs::write(&path, format!("/*\n  Content-Security-Policy: {}\n", policy.to_header_value()))?;
+    info!("Content-Security-Policy written to {}", path.display());
+    Ok(())
+}
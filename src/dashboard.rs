@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::Utc;
+use html_escape::encode_text;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use crate::report::{Finding, Level};
+
+/// Rows shown in each "worst offenders" list.
+const WORST_OFFENDERS: usize = 5;
+
+/// Analyzer results for one page, kept between builds for comparison.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageMetrics {
+    pub file: PathBuf,
+    /// Page size in bytes, before minification
+    pub page_size: usize,
+    pub blocking_scripts: usize,
+    /// Performance score out of 100, when performance analysis ran
+    pub score: Option<u32>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub notes: usize,
+}
+
+impl PageMetrics {
+    pub fn new(file: &Path, page_size: usize, findings: &[Finding]) -> Self {
+        let count = |level| findings.iter().filter(|f| f.level == level).count();
+        Self {
+            file: file.to_path_buf(),
+            page_size,
+            blocking_scripts: 0,
+            score: None,
+            errors: count(Level::Error),
+            warnings: count(Level::Warning),
+            notes: count(Level::Note),
+        }
+    }
+}
+
+/// `performance/manifest.json`: every page's metrics from one build.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub generated: String,
+    pub pages: Vec<PageMetrics>,
+}
+
+impl Manifest {
+    pub const FILE_NAME: &'static str = "manifest.json";
+
+    /// The manifest left in `dir` by the previous build, if any.
+    pub fn load(dir: &Path) -> Option<Self> {
+        Self::read(&dir.join(Self::FILE_NAME))
+    }
+
+    /// A manifest saved at `path`, if it exists and can be read.
+    pub fn read(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                warn!("Ignoring unreadable {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::write(dir.join(Self::FILE_NAME), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Write `index.html` summarizing every page's analysis to `dir`, compared
+/// against the previous build's manifest, then replace that manifest.
+pub fn generate_dashboard(mut pages: Vec<PageMetrics>, dir: &Path) -> Result<()> {
+    pages.sort_by(|a, b| a.file.cmp(&b.file));
+    let previous = Manifest::load(dir);
+    let manifest = Manifest {
+        generated: Utc::now().to_rfc3339(),
+        pages,
+    };
+
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("index.html"), render_dashboard(&manifest, previous.as_ref()))?;
+    manifest.write(dir)?;
+    info!("Analysis dashboard for {} pages written to {}", manifest.pages.len(), dir.join("index.html").display());
+    Ok(())
+}
+
+fn render_dashboard(current: &Manifest, previous: Option<&Manifest>) -> String {
+    let before: HashMap<&Path, &PageMetrics> = previous
+        .map(|manifest| manifest.pages.iter().map(|page| (page.file.as_path(), page)).collect())
+        .unwrap_or_default();
+
+    let totals = |pages: &[PageMetrics]| (
+        pages.iter().map(|p| p.errors).sum::<usize>(),
+        pages.iter().map(|p| p.warnings).sum::<usize>(),
+        average_score(pages),
+    );
+    let (errors, warnings, score) = totals(&current.pages);
+
+    let mut summary = format!(
+        "<p>{} pages, {} errors, {} warnings{}</p>",
+        current.pages.len(),
+        errors,
+        warnings,
+        score.map(|s| format!(", average score {:.0}", s)).unwrap_or_default(),
+    );
+    match previous {
+        Some(previous) => {
+            let (prev_errors, prev_warnings, prev_score) = totals(&previous.pages);
+            summary.push_str(&format!(
+                "<p>Compared with the build at {}: errors {}, warnings {}{}</p>",
+                encode_text(&previous.generated),
+                delta(errors as f64 - prev_errors as f64, true),
+                delta(warnings as f64 - prev_warnings as f64, true),
+                match (score, prev_score) {
+                    (Some(score), Some(prev)) => format!(", average score {}", delta(score - prev, false)),
+                    _ => String::new(),
+                },
+            ));
+        },
+        None => summary.push_str("<p>No previous build to compare with.</p>"),
+    }
+
+    let mut by_score: Vec<_> = current.pages.iter().filter(|p| p.score.is_some()).collect();
+    by_score.sort_by_key(|p| p.score);
+    let mut by_errors: Vec<_> = current.pages.iter().filter(|p| p.errors + p.warnings > 0).collect();
+    by_errors.sort_by_key(|p| std::cmp::Reverse((p.errors, p.warnings)));
+    let mut by_size: Vec<_> = current.pages.iter().collect();
+    by_size.sort_by_key(|p| std::cmp::Reverse(p.page_size));
+
+    let offenders = |title: &str, pages: &[&PageMetrics], value: &dyn Fn(&PageMetrics) -> String| {
+        if pages.is_empty() {
+            return String::new();
+        }
+        let items: String = pages.iter().take(WORST_OFFENDERS)
+            .map(|p| format!("<li>{} <span class=\"muted\">{}</span></li>", encode_text(&p.file.display().to_string()), value(p)))
+            .collect();
+        format!("<section><h2>{}</h2><ol>{}</ol></section>", title, items)
+    };
+    let worst = [
+        offenders("Lowest scores", &by_score, &|p| format!("{}/100", p.score.unwrap_or_default())),
+        offenders("Most findings", &by_errors, &|p| format!("{} errors, {} warnings", p.errors, p.warnings)),
+        offenders("Largest pages", &by_size, &|p| format!("{:.1} KB", p.page_size as f64 / 1024.0)),
+    ].concat();
+
+    let rows: String = current.pages.iter()
+        .map(|page| {
+            let prev = before.get(page.file.as_path());
+            let score_delta = match (page.score, prev.and_then(|p| p.score)) {
+                (Some(score), Some(prev)) => delta(score as f64 - prev as f64, false),
+                _ => String::new(),
+            };
+            let size_delta = prev
+                .map(|p| delta((page.page_size as f64 - p.page_size as f64) / 1024.0, true))
+                .unwrap_or_default();
+            format!(
+                "<tr><td>{}</td><td data-value=\"{}\">{} {}</td><td data-value=\"{}\">{:.1} {}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                encode_text(&page.file.display().to_string()),
+                page.score.map(i64::from).unwrap_or(-1),
+                page.score.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string()),
+                score_delta,
+                page.page_size,
+                page.page_size as f64 / 1024.0,
+                size_delta,
+                page.blocking_scripts,
+                page.errors,
+                page.warnings,
+                page.notes,
+            )
+        })
+        .collect();
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Site analysis</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; margin: 2rem; color: #1f2328; }}
+        .offenders {{ display: flex; gap: 2rem; flex-wrap: wrap; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        th, td {{ border-bottom: 1px solid #d0d7de; padding: 0.4rem 0.6rem; text-align: left; }}
+        th {{ cursor: pointer; user-select: none; }}
+        .muted {{ color: #656d76; }}
+        .better {{ color: #1a7f37; }}
+        .worse {{ color: #cf222e; }}
+    </style>
+</head>
+<body>
+    <h1>Site analysis</h1>
+    <p class="muted">Generated {generated}</p>
+    {summary}
+    <div class="offenders">{worst}</div>
+    <h2>All pages</h2>
+    <table id="pages">
+        <thead><tr><th>Page</th><th>Score</th><th>Size (KB)</th><th>Blocking scripts</th><th>Errors</th><th>Warnings</th><th>Notes</th></tr></thead>
+        <tbody>{rows}</tbody>
+    </table>
+    <script>
+        document.querySelectorAll('#pages th').forEach((th, column) => {{
+            th.addEventListener('click', () => {{
+                const tbody = document.querySelector('#pages tbody');
+                const ascending = th.dataset.order !== 'asc';
+                th.dataset.order = ascending ? 'asc' : 'desc';
+                const value = row => {{
+                    const cell = row.children[column];
+                    const text = cell.dataset.value ?? cell.textContent;
+                    const number = parseFloat(text);
+                    return isNaN(number) ? text : number;
+                }};
+                [...tbody.rows]
+                    .sort((a, b) => {{
+                        const [x, y] = [value(a), value(b)];
+                        return (x < y ? -1 : x > y ? 1 : 0) * (ascending ? 1 : -1);
+                    }})
+                    .forEach(row => tbody.appendChild(row));
+            }});
+        }});
+    </script>
+</body>
+</html>
+"#,
+        generated = encode_text(&current.generated),
+        summary = summary,
+        worst = worst,
+        rows = rows,
+    )
+}
+
+fn average_score(pages: &[PageMetrics]) -> Option<f64> {
+    let scores: Vec<_> = pages.iter().filter_map(|p| p.score).collect();
+    if scores.is_empty() {
+        None
+    } else {
+        Some(scores.iter().sum::<u32>() as f64 / scores.len() as f64)
+    }
+}
+
+/// Signed change, colored by whether it is an improvement.
+fn delta(change: f64, lower_is_better: bool) -> String {
+    if change.abs() < 0.05 {
+        return "<span class=\"muted\">±0</span>".to_string();
+    }
+    let better = (change < 0.0) == lower_is_better;
+    format!(
+        "<span class=\"{}\">{:+.1}</span>",
+        if better { "better" } else { "worse" },
+        change
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(file: &str, page_size: usize, score: u32, findings: &[Finding]) -> PageMetrics {
+        PageMetrics { score: Some(score), ..PageMetrics::new(Path::new(file), page_size, findings) }
+    }
+
+    #[test]
+    fn test_dashboard_compares_against_the_previous_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let warning = Finding::new("performance", Level::Warning, Path::new("about.html"), "Slow");
+        generate_dashboard(vec![page("index.html", 2048, 90, &[]), page("about.html", 1024, 70, &[])], dir.path()).unwrap();
+        let first = fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert!(first.contains("<p>2 pages, 0 errors, 0 warnings, average score 80</p>"), "{}", first);
+        assert!(first.contains("No previous build to compare with."));
+
+        generate_dashboard(vec![page("index.html", 2048, 90, &[]), page("about.html", 1024, 60, &[warning])], dir.path()).unwrap();
+        let second = fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert!(second.contains("warnings <span class=\"worse\">+1.0</span>, average score <span class=\"worse\">-5.0</span>"), "{}", second);
+        // The lowest score leads its worst offenders list
+        assert!(second.contains("<h2>Lowest scores</h2><ol><li>about.html <span class=\"muted\">60/100</span></li>"), "{}", second);
+        assert!(second.contains("<h2>Most findings</h2><ol><li>about.html"), "{}", second);
+        assert_eq!(Manifest::load(dir.path()).unwrap().pages.iter().map(|p| p.score).collect::<Vec<_>>(), [Some(60), Some(90)]);
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Map, Value};
+
+/// Data files for templates, by file stem: `data/team.yaml` is `team` and
+/// `data/nav/main.toml` is `nav.main`. TOML, YAML, and JSON keep their
+/// structure; CSV becomes a list of rows keyed by the header.
+pub fn load_data_dir(dir: &Path) -> Result<Map<String, Value>> {
+    let mut data = Map::new();
+    if !dir.is_dir() {
+        return Ok(data);
+    }
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let value = if path.is_dir() {
+            Value::Object(load_data_dir(&path)?)
+        } else {
+            match load_data_file(&path)? {
+                Some(value) => value,
+                None => continue,
+            }
+        };
+        if data.insert(name.clone(), value).is_some() {
+            return Err(anyhow!("More than one data file is named '{}' in {}", name, dir.display()));
+        }
+    }
+    Ok(data)
+}
+
+/// One data file's contents, or `None` for extensions that aren't data.
+fn load_data_file(path: &Path) -> Result<Option<Value>> {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if !matches!(extension.as_str(), "toml" | "yaml" | "yml" | "json" | "csv") {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read data file {}", path.display()))?;
+    let value: Result<Value> = match extension.as_str() {
+        "toml" => content.parse::<toml::Value>().map(from_toml).map_err(Into::into),
+        "yaml" | "yml" => serde_yaml::from_str(&content).map_err(Into::into),
+        "json" => serde_json::from_str(&content).map_err(Into::into),
+        _ => from_csv(&content),
+    };
+    value.map(Some).with_context(|| format!("Invalid data file {}", path.display()))
+}
+
+/// TOML dates become strings; everything else maps directly.
+pub fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(n) => n.into(),
+        toml::Value::Float(n) => n.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(date) => Value::String(date.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect()),
+    }
+}
+
+fn from_csv(content: &str) -> Result<Value> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let row: Map<String, Value> = headers.iter()
+            .zip(record.iter())
+            .map(|(header, field)| (header.to_string(), Value::String(field.to_string())))
+            .collect();
+        rows.push(Value::Object(row));
+    }
+    Ok(Value::Array(rows))
+}
+
+/// The value at a dotted path like `team.0.name`; numbers index into lists.
+pub fn lookup<'a>(data: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut value = data.get(parts.next()?)?;
+    for part in parts {
+        value = match value {
+            Value::Object(map) => map.get(part)?,
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// Text of a value in a template: lists render comma separated, and null
+/// and tables have none.
+pub fn render(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(items) => Some(items.iter().filter_map(render).collect::<Vec<_>>().join(", ")),
+        Value::Null | Value::Object(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loads_every_format_by_file_stem() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("site.toml"), "launched = 2024-01-01\n[owner]\nname = \"Jane\"").unwrap();
+        fs::write(dir.path().join("team.yaml"), "- name: Ann\n  role: Lead\n- name: Bo\n  role: Dev").unwrap();
+        fs::write(dir.path().join("stats.json"), r#"{"stars": 42, "tags": ["a", "b"], "fork": null}"#).unwrap();
+        fs::write(dir.path().join("pricing.csv"), "plan,price\nFree,0\n\"Pro, yearly\",99\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+        fs::create_dir(dir.path().join("nav")).unwrap();
+        fs::write(dir.path().join("nav/main.json"), r#"[{"title": "Home", "url": "/"}]"#).unwrap();
+
+        let data = load_data_dir(dir.path()).unwrap();
+        let get = |path: &str| lookup(&data, path).and_then(render);
+        assert_eq!(get("site.owner.name").as_deref(), Some("Jane"));
+        assert_eq!(get("site.launched").as_deref(), Some("2024-01-01"));
+        assert_eq!(get("team.1.role").as_deref(), Some("Dev"));
+        assert_eq!(get("stats.stars").as_deref(), Some("42"));
+        assert_eq!(get("stats.tags").as_deref(), Some("a, b"));
+        assert_eq!(get("pricing.1.plan").as_deref(), Some("Pro, yearly"));
+        assert_eq!(get("nav.main.0.url").as_deref(), Some("/"));
+        assert_eq!(get("stats.fork"), None);
+        assert_eq!(get("site.owner"), None);
+        assert!(!data.contains_key("notes"));
+    }
+}
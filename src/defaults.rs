@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use globset::{Glob, GlobMatcher};
+use serde_yaml::{Mapping, Value};
+
+/// Per-section front matter defaults declared in `eldroid.toml`.
+///
+/// Each rule pairs a glob (relative to the input directory) with a table of
+/// values that are merged beneath a page's own front matter.
+#[derive(Debug, Default)]
+pub struct FrontMatterDefaults {
+    rules: Vec<(GlobMatcher, Mapping)>,
+}
+
+impl FrontMatterDefaults {
+    pub fn from_config(defaults: &HashMap<String, toml::Table>) -> Result<Self> {
+        let mut patterns: Vec<_> = defaults.iter().collect();
+        // Most specific (longest) patterns first so they win over broad ones
+        patterns.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(b.0)));
+
+        let mut rules = Vec::new();
+        for (pattern, table) in patterns {
+            let matcher = Glob::new(pattern)
+                .map_err(|e| anyhow!("Invalid defaults pattern '{}': {}", pattern, e))?
+                .compile_matcher();
+            let values = match serde_yaml::to_value(table)? {
+                Value::Mapping(map) => map,
+                _ => Mapping::new(),
+            };
+            rules.push((matcher, values));
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Patterns whose defaults apply to `relative_path`, most specific first.
+    pub fn matching(&self, relative_path: &Path) -> Vec<&str> {
+        self.rules.iter()
+            .filter(|(matcher, _)| matcher.is_match(relative_path))
+            .map(|(matcher, _)| matcher.glob().glob())
+            .collect()
+    }
+
+    /// Fill in keys missing from `front_matter` with the defaults of every
+    /// rule matching `relative_path`.
+    pub fn apply(&self, relative_path: &Path, front_matter: &mut Mapping) {
+        for (matcher, values) in &self.rules {
+            if !matcher.is_match(relative_path) {
+                continue;
+            }
+            for (key, value) in values {
+                if !front_matter.contains_key(key) {
+                    front_matter.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
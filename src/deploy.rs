@@ -0,0 +1,317 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use log::{debug, info};
+use rayon::prelude::*;
+use serde::Deserialize;
+use crate::caching::HeadersManifest;
+use crate::remote_cache::S3Bucket;
+use crate::verify::OutputManifest;
+
+/// Hashes of the deployed files, kept at the root of the target so the next
+/// deploy from any machine only sends what changed.
+pub const MANIFEST_FILE: &str = ".eldroid-deploy.json";
+
+/// `[deploy]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "target", rename_all = "kebab-case")]
+pub enum DeployConfig {
+    /// An S3-compatible bucket serving the site
+    S3 {
+        /// `s3://bucket/prefix`
+        url: String,
+        /// Bucket region; defaults to `AWS_REGION`, then `us-east-1`
+        region: Option<String>,
+        /// Endpoint of an S3-compatible service such as MinIO or R2
+        endpoint: Option<String>,
+    },
+    /// A branch GitHub Pages publishes
+    GithubPages {
+        #[serde(default = "default_branch")]
+        branch: String,
+        /// Remote name in the site's repository, or a repository URL
+        #[serde(default = "default_remote")]
+        remote: String,
+        /// Custom domain written to `CNAME`
+        cname: Option<String>,
+    },
+}
+
+fn default_branch() -> String {
+    "gh-pages".to_string()
+}
+
+fn default_remote() -> String {
+    "origin".to_string()
+}
+
+impl DeployConfig {
+    /// Where the site is deployed, for the build history.
+    pub fn target(&self) -> String {
+        match self {
+            Self::S3 { url, .. } => url.clone(),
+            Self::GithubPages { branch, remote, .. } => format!("{} {}", remote, branch),
+        }
+    }
+}
+
+/// Output paths to send and to remove, relative to the output directory.
+#[derive(Debug, Default, PartialEq)]
+pub struct DeployPlan {
+    pub upload: Vec<String>,
+    pub delete: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl DeployPlan {
+    /// Files of `current` that are new or changed since `previous`, and
+    /// files of `previous` the build no longer has.
+    pub fn new(previous: &OutputManifest, current: &OutputManifest) -> Self {
+        let mut plan = Self::default();
+        for (path, hash) in &current.files {
+            match previous.files.get(path) {
+                Some(previous_hash) if previous_hash == hash => plan.unchanged += 1,
+                _ => plan.upload.push(path.clone()),
+            }
+        }
+        plan.delete = previous.files.keys()
+            .filter(|path| !current.files.contains_key(*path))
+            .cloned()
+            .collect();
+        plan
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.upload.is_empty() && self.delete.is_empty()
+    }
+}
+
+/// Send the changes in `output_dir` since the last deploy to the target, or
+/// everything with `all`. With `dry_run` only the plan is returned.
+pub fn deploy(config: &DeployConfig, output_dir: &Path, all: bool, dry_run: bool) -> Result<DeployPlan> {
+    // The build cache and analysis dashboard aren't part of the site
+    let current = OutputManifest::from_output(output_dir, &[], None)?;
+    match config {
+        DeployConfig::S3 { url, region, endpoint } => {
+            let location = url.strip_prefix("s3://")
+                .ok_or_else(|| anyhow!("Unsupported deploy URL '{}', expected s3://bucket/prefix", url))?;
+            let bucket = S3Bucket::from_env(location, region.as_deref(), endpoint.as_deref())?;
+            deploy_s3(&bucket, output_dir, &current, all, dry_run)
+        },
+        DeployConfig::GithubPages { branch, remote, cname } => {
+            deploy_github_pages(branch, remote, cname.as_deref(), output_dir, &current, all, dry_run)
+        },
+    }
+}
+
+fn deploy_s3(bucket: &S3Bucket, output_dir: &Path, current: &OutputManifest, all: bool, dry_run: bool) -> Result<DeployPlan> {
+    let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).build();
+    let previous = match all {
+        true => OutputManifest::default(),
+        false => match bucket.request(&agent, "GET", MANIFEST_FILE, &[]).call() {
+            Ok(response) => response.into_json().context("Invalid deploy manifest in the bucket")?,
+            // S3 answers 403 for missing objects unless the caller may list the bucket
+            Err(ureq::Error::Status(404 | 403, _)) => OutputManifest::default(),
+            Err(e) => return Err(anyhow!("Failed to read the deploy manifest: {}", e)),
+        },
+    };
+    let plan = DeployPlan::new(&previous, current);
+    if dry_run || plan.is_empty() {
+        return Ok(plan);
+    }
+
+    let headers = HeadersManifest::load(output_dir)
+        .with_context(|| format!("No {} in {}; build the site first", HeadersManifest::FILE_NAME, output_dir.display()))?;
+    plan.upload.par_iter().try_for_each(|path| -> Result<()> {
+        let bytes = fs::read(output_dir.join(path))?;
+        bucket.request(&agent, "PUT", path, &bytes)
+            .set("Content-Type", content_type(path))
+            .set("Cache-Control", headers.cache_control(path).unwrap_or("no-cache"))
+            .send_bytes(&bytes)
+            .map_err(|e| anyhow!("Failed to upload {}: {}", path, e))?;
+        debug!("Uploaded {}", path);
+        Ok(())
+    })?;
+    plan.delete.par_iter().try_for_each(|path| -> Result<()> {
+        match bucket.request(&agent, "DELETE", path, &[]).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(e) => Err(anyhow!("Failed to delete {}: {}", path, e)),
+        }
+    })?;
+
+    // Written last, so an interrupted deploy is finished by the next one
+    let manifest = serde_json::to_vec_pretty(current)?;
+    bucket.request(&agent, "PUT", MANIFEST_FILE, &manifest)
+        .set("Content-Type", "application/json")
+        .set("Cache-Control", "no-cache")
+        .send_bytes(&manifest)
+        .map_err(|e| anyhow!("Failed to upload the deploy manifest: {}", e))?;
+    info!("Uploaded {} and deleted {} files", plan.upload.len(), plan.delete.len());
+    Ok(plan)
+}
+
+fn deploy_github_pages(
+    branch: &str,
+    remote: &str,
+    cname: Option<&str>,
+    output_dir: &Path,
+    current: &OutputManifest,
+    all: bool,
+    dry_run: bool,
+) -> Result<DeployPlan> {
+    let site_dir = output_dir.canonicalize()?.parent().map(Path::to_path_buf).unwrap_or_default();
+    let remote_url = match remote.contains(['/', ':']) {
+        true => remote.to_string(),
+        false => git(&site_dir, &["remote", "get-url", remote])?.trim().to_string(),
+    };
+
+    // A checkout of the branch in the cache directory, reused between deploys
+    let work_dir = output_dir.join("cache").join("deploy").join(branch.replace('/', "-"));
+    if !work_dir.join(".git").exists() {
+        fs::create_dir_all(&work_dir)?;
+        git(&work_dir, &["init", "-q"])?;
+    }
+    let branch_ref = format!("refs/heads/{}", branch);
+    if git(&work_dir, &["ls-remote", "--heads", &remote_url, &branch_ref])?.trim().is_empty() {
+        info!("Creating branch {} on {}", branch, remote_url);
+        git(&work_dir, &["symbolic-ref", "HEAD", &branch_ref])?;
+        git(&work_dir, &["read-tree", "--empty"])?;
+    } else {
+        git(&work_dir, &["fetch", "-q", "--depth", "1", &remote_url, &branch_ref])?;
+        git(&work_dir, &["checkout", "-q", "--force", "-B", branch, "FETCH_HEAD"])?;
+    }
+    git(&work_dir, &["clean", "-q", "-f", "-d", "-x"])?;
+
+    let previous = match all {
+        true => OutputManifest::default(),
+        false => OutputManifest::load(&work_dir.join(MANIFEST_FILE)).unwrap_or_default(),
+    };
+    let plan = DeployPlan::new(&previous, current);
+    if dry_run {
+        return Ok(plan);
+    }
+
+    for path in &plan.upload {
+        let target = work_dir.join(path);
+        fs::create_dir_all(target.parent().unwrap_or(&work_dir))?;
+        fs::copy(output_dir.join(path), &target)?;
+    }
+    for path in &plan.delete {
+        match fs::remove_file(work_dir.join(path)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {},
+        }
+    }
+    // Without it, Pages runs Jekyll and drops files starting with `_`
+    fs::write(work_dir.join(".nojekyll"), "")?;
+    if let Some(cname) = cname {
+        fs::write(work_dir.join("CNAME"), format!("{}\n", cname))?;
+    }
+    OutputManifest { files: current.files.clone(), volatile: Vec::new() }.write(&work_dir.join(MANIFEST_FILE))?;
+
+    git(&work_dir, &["add", "-A"])?;
+    if git(&work_dir, &["status", "--porcelain"])?.trim().is_empty() {
+        return Ok(plan);
+    }
+    let message = format!("Deploy {}", Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
+    let mut commit = vec!["commit", "-q", "-m", &message];
+    // CI machines often have no identity configured
+    if git(&work_dir, &["config", "user.email"]).is_err() {
+        commit.splice(0..0, ["-c", "user.name=eldroid-ssg", "-c", "user.email=eldroid-ssg@localhost"]);
+    }
+    git(&work_dir, &commit)?;
+    git(&work_dir, &["push", "-q", &remote_url, &format!("HEAD:{}", branch_ref)])?;
+    info!("Pushed {} changed and {} deleted files to {}", plan.upload.len(), plan.delete.len(), branch);
+    Ok(plan)
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Content-Type for an output path, by its extension.
+pub fn content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension.as_deref().unwrap_or_default() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "xml" => "application/xml",
+        "rss" => "application/rss+xml",
+        "atom" => "application/atom+xml",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_pages_pushes_only_changes() {
+        let temp = tempfile::tempdir().unwrap();
+        let remote = temp.path().join("remote.git");
+        git(temp.path(), &["init", "-q", "--bare", remote.to_str().unwrap()]).unwrap();
+        let output = temp.path().join("output");
+        fs::create_dir_all(output.join("css")).unwrap();
+        fs::write(output.join("index.html"), "<p>home</p>").unwrap();
+        fs::write(output.join("old.html"), "<p>old</p>").unwrap();
+        fs::write(output.join("css/site.css"), "p{}").unwrap();
+        let config = DeployConfig::GithubPages {
+            branch: "gh-pages".to_string(),
+            remote: remote.to_string_lossy().into_owned(),
+            cname: Some("example.com".to_string()),
+        };
+
+        let plan = deploy(&config, &output, false, false).unwrap();
+        assert_eq!(plan.upload, ["css/site.css", "index.html", "old.html"]);
+        let show = |file: &str| git(&remote, &["show", &format!("gh-pages:{}", file)]);
+        assert_eq!(show("index.html").unwrap(), "<p>home</p>");
+        assert_eq!(show("CNAME").unwrap(), "example.com\n");
+
+        fs::write(output.join("index.html"), "<p>new home</p>").unwrap();
+        fs::remove_file(output.join("old.html")).unwrap();
+        fs::create_dir_all(output.join("cache")).unwrap();
+        fs::write(output.join("cache/state.json"), "{}").unwrap();
+        assert_eq!(deploy(&config, &output, false, true).unwrap(), DeployPlan {
+            upload: vec!["index.html".to_string()],
+            delete: vec!["old.html".to_string()],
+            unchanged: 1,
+        });
+        deploy(&config, &output, false, false).unwrap();
+        assert_eq!(show("index.html").unwrap(), "<p>new home</p>");
+        assert!(show("old.html").is_err());
+        assert!(show("cache/state.json").is_err());
+        assert!(deploy(&config, &output, false, false).unwrap().is_empty());
+        assert_eq!(content_type("css/site.css"), "text/css; charset=utf-8");
+    }
+}
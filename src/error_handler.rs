@@ -1,31 +1,160 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use anyhow::Result;
+use lazy_static::lazy_static;
 use log::error;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::Serialize;
+use thiserror::Error;
 
 use crate::watcher::{FileChange, ChangeType};
 
+lazy_static! {
+    // TOML, JSON, and YAML parse errors all end with a position like this
+    static ref LOCATION_REGEX: Regex = Regex::new(r"\s*at line (\d+),? column (\d+)").unwrap();
+}
+
+/// Lines of source shown on each side of the failing line.
+const FRAME_CONTEXT: usize = 2;
+
+/// An error at a known position in a source file, 1-based.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct SourceError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl SourceError {
+    /// Wrap a parser error whose message ends with "at line L column C",
+    /// counting lines from `first_line` of the file. Errors without a
+    /// position keep just the message.
+    pub fn from_parser(context: &str, err: &dyn std::error::Error, first_line: usize) -> anyhow::Error {
+        let message = err.to_string();
+        match locate(&message) {
+            Some((line, column)) => SourceError {
+                line: line + first_line - 1,
+                column,
+                message: format!("{}: {}", context, LOCATION_REGEX.replace(&message, "")),
+            }.into(),
+            None => anyhow::anyhow!("{}: {}", context, message),
+        }
+    }
+}
+
+/// Context naming the content file an error came from, so the overlay points
+/// at that page rather than at the file whose change triggered the build.
+#[derive(Debug)]
+pub struct PageError(pub PathBuf);
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to build {}", self.0.display())
+    }
+}
+
+/// A build error as the dev server overlay shows it.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildError {
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// Source lines around `line`, the failing one marked with `>`
+    pub frame: Option<String>,
+}
+
+impl BuildError {
+    pub fn new(err: &anyhow::Error, file_path: Option<&Path>) -> Self {
+        let file_path = err.downcast_ref::<PageError>().map(|page| page.0.as_path()).or(file_path);
+        let location = err.chain()
+            .find_map(|cause| cause.downcast_ref::<SourceError>())
+            .map(|source| (source.line, source.column))
+            .or_else(|| err.chain().find_map(|cause| locate(&cause.to_string())));
+        let frame = file_path
+            .zip(location)
+            .and_then(|(path, (line, column))| fs::read_to_string(path).ok().and_then(|content| code_frame(&content, line, column)));
+        Self {
+            message: format!("{:#}", err),
+            file: file_path.map(|path| path.display().to_string()),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            frame,
+        }
+    }
+}
+
+/// The line and column a parser error message ends with.
+fn locate(message: &str) -> Option<(usize, usize)> {
+    let caps = LOCATION_REGEX.captures(message)?;
+    Some((caps[1].parse().ok()?, caps[2].parse().ok()?))
+}
+
+/// `content` around `line`, numbered, with a caret under `column`.
+fn code_frame(content: &str, line: usize, column: usize) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line > lines.len() {
+        return None;
+    }
+    let first = line.saturating_sub(FRAME_CONTEXT).max(1);
+    let last = (line + FRAME_CONTEXT).min(lines.len());
+    let width = last.to_string().len();
+    let mut frame = String::new();
+    for number in first..=last {
+        let marker = if number == line { '>' } else { ' ' };
+        frame.push_str(&format!("{} {:>width$} | {}\n", marker, number, lines[number - 1]));
+        if number == line && column > 0 {
+            frame.push_str(&format!("  {:width$} | {}^\n", "", " ".repeat(column - 1)));
+        }
+    }
+    Some(frame)
+}
+
 pub struct ErrorHandler {
     tx: broadcast::Sender<FileChange>,
+    /// The error the site currently has, for browsers that connect later
+    last: RwLock<Option<BuildError>>,
 }
 
 impl ErrorHandler {
     pub fn new(tx: broadcast::Sender<FileChange>) -> Self {
-        Self { tx }
+        Self { tx, last: RwLock::new(None) }
     }
 
-    pub fn handle_error(&self, err: anyhow::Error, file_path: Option<&str>) -> Result<()> {
-        let error_message = format!("Build Error: {}\n\nLocation: {}", 
-            err, 
-            file_path.unwrap_or("Unknown"));
+    /// Start out with an error from a build that ran before the server.
+    pub fn with_last_error(self, error: Option<BuildError>) -> Self {
+        *self.last.write() = error;
+        self
+    }
 
-        error!("{}", error_message);
+    pub fn last_error(&self) -> Option<BuildError> {
+        self.last.read().clone()
+    }
+
+    /// Forget the last error once a build succeeds.
+    pub fn clear(&self) {
+        *self.last.write() = None;
+    }
+
+    pub fn handle_error(&self, err: anyhow::Error, file_path: Option<&Path>) -> Result<()> {
+        let build_error = BuildError::new(&err, file_path);
+        match (&build_error.file, build_error.line) {
+            (Some(file), Some(line)) => error!("Build Error: {} ({}:{})", build_error.message, file, line),
+            (Some(file), None) => error!("Build Error: {} ({})", build_error.message, file),
+            _ => error!("Build Error: {}", build_error.message),
+        }
 
         // Send error to client for overlay display
+        *self.last.write() = Some(build_error.clone());
         let change = FileChange {
-            path: file_path.map(std::path::PathBuf::from)
-                .unwrap_or_else(|| std::path::PathBuf::from("unknown")),
-            event_type: ChangeType::Error(error_message),
+            path: file_path.map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from("unknown")),
+            event_type: ChangeType::Error(build_error),
         };
 
         if let Err(e) = self.tx.send(change) {
@@ -48,7 +177,27 @@ impl ErrorHandlerMiddleware {
         }
     }
 
-    pub fn handle(&self, error: anyhow::Error, file_path: Option<&str>) -> Result<()> {
+    pub fn handle(&self, error: anyhow::Error, file_path: Option<&Path>) -> Result<()> {
         self.error_handler.handle_error(error, file_path)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locates_parser_errors() {
+        let err = toml::from_str::<toml::Table>("title = \"a\"\ndate = \n").unwrap_err();
+        assert_eq!(locate(&err.to_string()).map(|(line, _)| line), Some(2));
+
+        let yaml = serde_yaml::from_str::<serde_yaml::Value>("title: a\ndate: [\n").unwrap_err();
+        let err = SourceError::from_parser("Failed to parse front matter", &yaml, 2);
+        let source = err.downcast_ref::<SourceError>().unwrap();
+        assert_eq!(source.line, 4);
+        assert!(!source.message.contains("at line"));
+
+        let frame = code_frame("a\nb\nc\nd\ne\nf", 4, 3).unwrap();
+        assert_eq!(frame, "  2 | b\n  3 | c\n> 4 | d\n    |   ^\n  5 | e\n  6 | f\n");
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt::Write;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use lazy_static::lazy_static;
+use regex::Regex;
+use toml::Value;
+use crate::data::from_toml;
+use crate::markdown::markdown_to_html;
+
+lazy_static! {
+    static ref FILTER_REGEX: Regex = Regex::new(
+        r#"^\s*\|\s*(\w+)(?:\(((?:"[^"]*"|'[^']*'|[^)"'])*)\))?"#
+    ).unwrap();
+    static ref ARG_REGEX: Regex = Regex::new(r#"^\s*(?:"([^"]*)"|'([^']*)'|([^,]*?))\s*(?:,|$)"#).unwrap();
+}
+
+/// Text of a variable in a template: strings as they are, numbers,
+/// booleans, and dates in their usual form, and lists comma separated.
+/// Tables have none.
+pub fn render(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Boolean(b) => Some(b.to_string()),
+        Value::Datetime(d) => Some(d.to_string()),
+        Value::Array(items) => Some(items.iter().filter_map(render).collect::<Vec<_>>().join(", ")),
+        Value::Table(_) => None,
+    }
+}
+
+/// A step after a variable reference, like `| format("%.2f")`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub name: String,
+    pub args: Vec<Value>,
+}
+
+impl Filter {
+    /// The filters in `chain`, the text between a `@{var("...")}` reference's
+    /// closing parenthesis and its brace.
+    pub fn parse_chain(chain: &str) -> Result<Vec<Filter>> {
+        let mut filters = Vec::new();
+        let mut rest = chain;
+        while !rest.trim().is_empty() {
+            let caps = FILTER_REGEX.captures(rest)
+                .ok_or_else(|| anyhow!("Expected `| filter` at '{}'", rest.trim()))?;
+            let filter = Filter {
+                name: caps[1].to_string(),
+                args: caps.get(2).map_or(Ok(Vec::new()), |args| parse_args(args.as_str()))?,
+            };
+            if !FILTERS.contains(&filter.name.as_str()) {
+                return Err(anyhow!("Unknown filter '{}'", filter.name));
+            }
+            filters.push(filter);
+            rest = &rest[caps.get(0).unwrap().end()..];
+        }
+        Ok(filters)
+    }
+
+    pub fn apply(&self, value: Value) -> Result<Value> {
+        let text = match self.name.as_str() {
+            "format" => printf(self.string_arg(0)?, &value)?,
+            "upper" => self.text(&value)?.to_uppercase(),
+            "lower" => self.text(&value)?.to_lowercase(),
+            "truncate" => {
+                let max = match self.args.first() {
+                    Some(Value::Integer(max)) if *max >= 0 => *max as usize,
+                    _ => return Err(anyhow!("truncate() needs a length")),
+                };
+                let suffix = if self.args.len() > 1 { self.string_arg(1)? } else { "…" };
+                let text = self.text(&value)?;
+                if text.chars().count() > max {
+                    text.chars().take(max).collect::<String>().trim_end().to_string() + suffix
+                } else {
+                    text
+                }
+            },
+            "date" => format_date(&self.text(&value)?, self.string_arg(0)?)?,
+            // The first items of an array, as for a `@for` loop
+            "limit" => return match (value, self.args.first()) {
+                (Value::Array(items), Some(Value::Integer(count))) if *count >= 0 => {
+                    Ok(Value::Array(items.into_iter().take(*count as usize).collect()))
+                },
+                (Value::Array(_), _) => Err(anyhow!("limit() needs a count")),
+                _ => Err(anyhow!("limit needs an array")),
+            },
+            "markdown" => inline_markdown(&self.text(&value)?),
+            // Safe inside a <script> element
+            "json" => serde_json::to_string(&from_toml(value))?.replace("</", "<\\/"),
+            name => return Err(anyhow!("Unknown filter '{}'", name)),
+        };
+        Ok(Value::String(text))
+    }
+
+    fn text(&self, value: &Value) -> Result<String> {
+        render(value).ok_or_else(|| anyhow!("{} needs text, not a table", self.name))
+    }
+
+    fn string_arg(&self, index: usize) -> Result<&str> {
+        match self.args.get(index) {
+            Some(Value::String(arg)) => Ok(arg),
+            _ => Err(anyhow!("{}() needs a string argument", self.name)),
+        }
+    }
+}
+
+/// Filters `@{var("...") | name}` accepts.
+const FILTERS: &[&str] = &["format", "upper", "lower", "truncate", "date", "limit", "markdown", "json"];
+
+/// `date`, an RFC 3339 date and time or a plain `YYYY-MM-DD` date, in a
+/// strftime-style `format` like `%B %d, %Y`.
+pub fn format_date(date: &str, format: &str) -> Result<String> {
+    let date = date.trim();
+    let mut text = String::new();
+    let written = if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        write!(text, "{}", date.format(format))
+    } else if let Ok(date) = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S") {
+        write!(text, "{}", date.format(format))
+    } else if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        write!(text, "{}", date.format(format))
+    } else {
+        return Err(anyhow!("'{}' is not a date", date));
+    };
+    // Invalid specifiers, or times asked of a plain date, fail while writing
+    written.map_err(|_| anyhow!("Invalid date format '{}' for {}", format, date))?;
+    Ok(text)
+}
+
+/// Markdown rendered to HTML, without the paragraph around a single
+/// paragraph so it can sit inside a heading or link.
+fn inline_markdown(markdown: &str) -> String {
+    let html = markdown_to_html(markdown);
+    let html = html.trim();
+    match html.strip_prefix("<p>").and_then(|inner| inner.strip_suffix("</p>")) {
+        Some(inner) if !inner.contains("<p>") => inner.to_string(),
+        _ => html.to_string(),
+    }
+}
+
+/// Quoted strings, numbers, and booleans separated by commas.
+fn parse_args(args: &str) -> Result<Vec<Value>> {
+    let mut values = Vec::new();
+    let mut rest = args;
+    while !rest.trim().is_empty() {
+        let caps = ARG_REGEX.captures(rest).ok_or_else(|| anyhow!("Invalid filter arguments '{}'", args))?;
+        let value = match (caps.get(1).or(caps.get(2)), caps.get(3)) {
+            (Some(quoted), _) => Value::String(quoted.as_str().to_string()),
+            (None, Some(bare)) => bare.as_str().parse::<i64>().map(Value::Integer)
+                .or_else(|_| bare.as_str().parse::<f64>().map(Value::Float))
+                .or_else(|_| bare.as_str().parse::<bool>().map(Value::Boolean))
+                .map_err(|_| anyhow!("Invalid filter argument '{}'; quote strings", bare.as_str()))?,
+            (None, None) => unreachable!(),
+        };
+        values.push(value);
+        rest = &rest[caps.get(0).unwrap().end()..];
+    }
+    Ok(values)
+}
+
+/// `value` formatted by a printf-style `spec`: `%d`, `%f`, `%e`, and `%s`
+/// conversions with `-`, `+`, and `0` flags, a width, and a precision.
+pub fn printf(spec: &str, value: &Value) -> Result<String> {
+    let mut out = String::new();
+    let mut chars = spec.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let (mut left, mut plus, mut zero) = (false, false, false);
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => left = true,
+                '+' => plus = true,
+                '0' => zero = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let mut width = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            width.push(digit);
+        }
+        let mut precision = None;
+        if chars.next_if_eq(&'.').is_some() {
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            precision = Some(digits.parse::<usize>().unwrap_or(0));
+        }
+        let conversion = chars.next().ok_or_else(|| anyhow!("Format '{}' ends with an incomplete %", spec))?;
+        let number = || number(value).ok_or_else(|| anyhow!("%{} in '{}' needs a number", conversion, spec));
+        let mut text = match conversion {
+            '%' => {
+                out.push('%');
+                continue;
+            },
+            'd' | 'i' => format!("{}", number()?.round() as i64),
+            'f' => format!("{:.*}", precision.unwrap_or(6), number()?),
+            'e' => format!("{:.*e}", precision.unwrap_or(6), number()?),
+            's' => {
+                let text = render(value).unwrap_or_default();
+                match precision {
+                    Some(max) => text.chars().take(max).collect(),
+                    None => text,
+                }
+            },
+            other => return Err(anyhow!("Unsupported conversion %{} in '{}'", other, spec)),
+        };
+        let numeric = conversion != 's';
+        if plus && numeric && !text.starts_with('-') {
+            text.insert(0, '+');
+        }
+        let width = width.parse::<usize>().unwrap_or(0);
+        let padding = width.saturating_sub(text.chars().count());
+        if left {
+            text.push_str(&" ".repeat(padding));
+        } else if zero && numeric {
+            let sign = if text.starts_with(['-', '+']) { 1 } else { 0 };
+            text.insert_str(sign, &"0".repeat(padding));
+        } else {
+            text.insert_str(0, &" ".repeat(padding));
+        }
+        out.push_str(&text);
+    }
+    Ok(out)
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_format() {
+        let value: Value = toml::from_str("s = \"hi\"\nf = 3.0\nd = 1979-05-27T07:32:00Z\nl = [1, \"a\"]\nt = { a = 1 }").unwrap();
+        assert_eq!(render(&value["s"]).as_deref(), Some("hi"));
+        assert_eq!(render(&value["f"]).as_deref(), Some("3"));
+        assert_eq!(render(&value["d"]).as_deref(), Some("1979-05-27T07:32:00Z"));
+        assert_eq!(render(&value["l"]).as_deref(), Some("1, a"));
+        assert_eq!(render(&value["t"]), None);
+
+        assert_eq!(printf("$%.2f", &Value::Integer(5)).unwrap(), "$5.00");
+        assert_eq!(printf("%05d|%-4s|%+.1f%%", &Value::Float(-7.4)).unwrap(), "-0007|-7.4|-7.4%");
+        assert_eq!(printf("%6.3s", &Value::from("abcdef")).unwrap(), "   abc");
+        assert!(printf("%d", &Value::from("n/a")).is_err());
+
+        let filters = Filter::parse_chain(r#" | format("%.1f, (%s)") "#).unwrap();
+        assert_eq!(filters[0].args, [Value::from("%.1f, (%s)")]);
+        assert_eq!(filters[0].apply(Value::Float(2.26)).unwrap(), Value::from("2.3, (2.26)"));
+        assert!(Filter::parse_chain("| shout").is_err());
+        assert!(Filter::parse_chain("| format(%d)").is_err());
+    }
+
+    #[test]
+    fn test_text_date_markdown_and_json_filters() {
+        let apply = |chain: &str, value: Value| {
+            Filter::parse_chain(chain).unwrap().iter()
+                .try_fold(value, |value, filter| filter.apply(value))
+                .map(|value| render(&value).unwrap())
+        };
+        assert_eq!(apply("| upper", Value::from("Hello")).unwrap(), "HELLO");
+        assert_eq!(apply("| lower | truncate(7)", Value::from("A Long Title Here")).unwrap(), "a long…");
+        assert_eq!(apply("| truncate(4, '...')", Value::from("abcdef")).unwrap(), "abcd...");
+        assert_eq!(apply("| truncate(10)", Value::from("short")).unwrap(), "short");
+
+        let date: Value = toml::from_str("d = 2024-03-05T10:00:00Z").unwrap();
+        assert_eq!(apply(r#"| date("%B %d, %Y")"#, date["d"].clone()).unwrap(), "March 05, 2024");
+        assert_eq!(apply(r#"| date("%d/%m")"#, Value::from("2024-03-05")).unwrap(), "05/03");
+        assert!(apply(r#"| date("%H:%M")"#, Value::from("2024-03-05")).is_err());
+        assert!(apply(r#"| date("%Y")"#, Value::from("soon")).is_err());
+
+        assert_eq!(apply("| markdown", Value::from("Some *emphasis*")).unwrap(), "Some <em>emphasis</em>");
+        assert!(apply("| markdown", Value::from("One\n\nTwo")).unwrap().starts_with("<p>One</p>"));
+
+        let data: Value = toml::from_str("list = [\"</script>\", 2]").unwrap();
+        assert_eq!(apply("| json", data["list"].clone()).unwrap(), r#"["<\/script>",2]"#);
+    }
+}
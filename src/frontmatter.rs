@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use globset::Glob;
+use walkdir::WalkDir;
+
+/// A `key=value` assignment from the command line; the value is YAML.
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub key: String,
+    pub value: String,
+}
+
+impl Assignment {
+    pub fn parse(raw: &str) -> Result<Self> {
+        let (key, value) = raw.split_once('=')
+            .ok_or_else(|| anyhow!("Expected key=value, got '{}'", raw))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            return Err(anyhow!("Invalid front matter key '{}'", key));
+        }
+        let value = value.trim();
+        serde_yaml::from_str::<serde_yaml::Value>(value)
+            .map_err(|e| anyhow!("Value for '{}' is not valid YAML: {}", key, e))?;
+        Ok(Self { key: key.to_string(), value: value.to_string() })
+    }
+}
+
+/// Apply `assignments` to the front matter at the top of `content`, editing
+/// lines in place so comments, key order, and formatting elsewhere survive.
+/// Returns `None` when the file has no front matter or nothing changed.
+pub fn set_fields(content: &str, assignments: &[Assignment], if_missing: bool) -> Option<String> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    if lines.first().map(|line| line.trim_end()) != Some("---") {
+        return None;
+    }
+    let mut end = lines.iter().skip(1).position(|line| line.trim_end() == "---")? + 1;
+
+    let mut changed = false;
+    for assignment in assignments {
+        let new_line = format!("{}: {}", assignment.key, assignment.value);
+        let existing = (1..end).find(|&i| is_key_line(&lines[i], &assignment.key));
+        match existing {
+            Some(_) if if_missing => {},
+            Some(start) => {
+                // A block value continues on the following indented or list lines
+                let mut stop = start + 1;
+                while stop < end && is_continuation(&lines[stop]) {
+                    stop += 1;
+                }
+                if stop - start == 1 && lines[start] == new_line {
+                    continue;
+                }
+                lines.splice(start..stop, [new_line]);
+                end -= stop - start - 1;
+                changed = true;
+            },
+            None => {
+                lines.insert(end, new_line);
+                end += 1;
+                changed = true;
+            },
+        }
+    }
+
+    if !changed {
+        return None;
+    }
+    let mut updated = lines.join("\n");
+    if content.ends_with('\n') {
+        updated.push('\n');
+    }
+    Some(updated)
+}
+
+fn is_key_line(line: &str, key: &str) -> bool {
+    line.strip_prefix(key)
+        .is_some_and(|rest| rest.trim_start().starts_with(':'))
+}
+
+fn is_continuation(line: &str) -> bool {
+    line.starts_with(' ') || line.starts_with('\t') || line.starts_with("- ")
+}
+
+/// Set fields on every Markdown file under `input_dir` matching `glob`.
+/// Returns the files that changed; with `dry_run` nothing is written.
+pub fn set_in_files(
+    input_dir: &Path,
+    glob: &str,
+    assignments: &[Assignment],
+    if_missing: bool,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>> {
+    let matcher = Glob::new(glob)?.compile_matcher();
+    let mut changed = Vec::new();
+
+    for entry in WalkDir::new(input_dir).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+        if !matcher.is_match(path.strip_prefix(input_dir)?) {
+            continue;
+        }
+        let content = fs::read_to_string(path)?;
+        if let Some(updated) = set_fields(&content, assignments, if_missing) {
+            if !dry_run {
+                fs::write(path, updated)?;
+            }
+            changed.push(path.to_path_buf());
+        }
+    }
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignments(raw: &[&str]) -> Vec<Assignment> {
+        raw.iter().map(|a| Assignment::parse(a).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_set_fields_preserves_layout() {
+        let content = "---\n# Post metadata\ntitle: Hello\ntags:\n  - a\n  - b\ndate: 2024-01-01\n---\n\nBody\n";
+        let updated = set_fields(content, &assignments(&["tags=[rust]", "series=intro"]), false).unwrap();
+
+        assert_eq!(updated, "---\n# Post metadata\ntitle: Hello\ntags: [rust]\ndate: 2024-01-01\nseries: intro\n---\n\nBody\n");
+    }
+
+    #[test]
+    fn test_set_fields_if_missing() {
+        let content = "---\ntitle: Hello\nauthor: Ann\n---\nBody";
+
+        assert!(set_fields(content, &assignments(&["author=Bob"]), true).is_none());
+        assert!(set_fields("No front matter", &assignments(&["author=Bob"]), false).is_none());
+        assert_eq!(
+            set_fields(content, &assignments(&["draft=true"]), true).unwrap(),
+            "---\ntitle: Hello\nauthor: Ann\ndraft: true\n---\nBody"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use chrono::{DateTime, FixedOffset};
+use log::{debug, warn};
+use serde::Deserialize;
+use crate::seo_types::PageSEO;
+
+/// `[dates]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatesConfig {
+    /// Take pages' modified and created dates from git history when the
+    /// content is in a repository
+    #[serde(default = "crate::config::default_true")]
+    pub git: bool,
+}
+
+impl Default for DatesConfig {
+    fn default() -> Self {
+        Self { git: true }
+    }
+}
+
+/// When a file was first and last committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileDates {
+    pub created: DateTime<FixedOffset>,
+    pub modified: DateTime<FixedOffset>,
+}
+
+impl FileDates {
+    /// Fill in the dates `seo` doesn't set itself.
+    pub fn apply(&self, seo: &mut PageSEO) {
+        seo.published_date.get_or_insert(self.created);
+        seo.last_modified.get_or_insert(self.modified);
+    }
+}
+
+/// Commit dates of every file under a directory, read from one pass over
+/// the history. Unlike file modification times, these survive a fresh clone.
+#[derive(Debug, Clone, Default)]
+pub struct GitDates {
+    root: PathBuf,
+    /// Dates by path relative to `root`
+    files: HashMap<PathBuf, FileDates>,
+}
+
+impl GitDates {
+    /// Dates for files under `dir`, or `None` when it isn't in a git repository.
+    pub fn load(dir: &Path) -> Option<Self> {
+        let git = |args: &[&str]| -> Option<String> {
+            let output = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+            output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+        let root = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+        let root = root.canonicalize().unwrap_or(root);
+        if git(&["rev-parse", "--is-shallow-repository"]).is_some_and(|out| out.trim() == "true") {
+            warn!("{} is a shallow clone, so dates from git history only go back to its oldest commit; fetch the full history (e.g. fetch-depth: 0) for accurate dates", root.display());
+        }
+        // Commits newest first, each a record of its date and the files it touched
+        let log = git(&["-c", "core.quotePath=false", "log", "--format=%x1e%cI", "--name-only", "--no-renames", "--", "."])?;
+
+        let mut files: HashMap<PathBuf, FileDates> = HashMap::new();
+        for commit in log.split('\x1e').skip(1) {
+            let mut lines = commit.lines();
+            let Some(date) = lines.next().and_then(|date| DateTime::parse_from_rfc3339(date.trim()).ok()) else {
+                continue;
+            };
+            for file in lines.filter(|line| !line.is_empty()) {
+                files.entry(PathBuf::from(file))
+                    .and_modify(|dates| dates.created = date)
+                    .or_insert(FileDates { created: date, modified: date });
+            }
+        }
+        debug!("Read git dates of {} files", files.len());
+        Some(Self { root, files })
+    }
+
+    /// Dates of `path`, if it has been committed.
+    pub fn get(&self, path: &Path) -> Option<FileDates> {
+        let path = path.canonicalize().ok()?;
+        self.files.get(path.strip_prefix(&self.root).ok()?).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates_come_from_first_and_last_commit() {
+        let repo = tempfile::tempdir().unwrap();
+        let git = |args: &[&str], date: &str| {
+            let status = Command::new("git").arg("-C").arg(repo.path()).args(args)
+                .env("GIT_COMMITTER_DATE", date).env("GIT_AUTHOR_DATE", date)
+                .env("GIT_AUTHOR_NAME", "t").env("GIT_AUTHOR_EMAIL", "t@example.com")
+                .env("GIT_COMMITTER_NAME", "t").env("GIT_COMMITTER_EMAIL", "t@example.com")
+                .status().unwrap();
+            assert!(status.success());
+        };
+        let content = repo.path().join("content");
+        std::fs::create_dir(&content).unwrap();
+        git(&["init", "-q"], "2024-01-01T00:00:00Z");
+        std::fs::write(content.join("a.md"), "one").unwrap();
+        std::fs::write(content.join("b.md"), "one").unwrap();
+        git(&["add", "."], "2024-01-01T00:00:00Z");
+        git(&["commit", "-qm", "first"], "2024-01-01T00:00:00Z");
+        std::fs::write(content.join("a.md"), "two").unwrap();
+        git(&["commit", "-qam", "second"], "2024-03-01T12:00:00Z");
+        std::fs::write(content.join("new.md"), "draft").unwrap();
+
+        let dates = GitDates::load(&content).unwrap();
+        let a = dates.get(&content.join("a.md")).unwrap();
+        assert_eq!(a.created.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(a.modified.to_rfc3339(), "2024-03-01T12:00:00+00:00");
+        assert_eq!(dates.get(&content.join("b.md")).unwrap().modified, a.created);
+        assert_eq!(dates.get(&content.join("new.md")), None);
+        assert!(GitDates::load(&std::env::temp_dir().join("no-such-dir")).is_none());
+    }
+}
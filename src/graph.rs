@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use crate::macros::resolve_include;
+use crate::themes::ComponentDirs;
+
+lazy_static! {
+    static ref COMPONENT_REGEX: Regex = Regex::new(r#"<el-component\b[^>]*\bc_name=["']([^"'@]+)["']"#).unwrap();
+    static ref VAR_REGEX: Regex = Regex::new(r#"@\{var\(["'][^"']+["']\)"#).unwrap();
+    static ref INCLUDE_REGEX: Regex = Regex::new(r#"@\{include\(["']([^"']+)["']\)\}"#).unwrap();
+    static ref T_REGEX: Regex = Regex::new(r#"@\{t\(["'][^"']+["']\)\}"#).unwrap();
+}
+
+/// Elements whose `src`/`href` point at assets rather than other pages.
+const ASSET_SELECTOR: &str = "script[src], img[src], source[src], video[src], audio[src], \
+    link[rel~='stylesheet'][href], link[rel~='icon'][href], link[rel~='preload'][href], link[rel='manifest'][href]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Page,
+    Layout,
+    Component,
+    Asset,
+    Data,
+}
+
+impl NodeKind {
+    fn dot_shape(self) -> &'static str {
+        match self {
+            NodeKind::Page => "note",
+            NodeKind::Layout => "box3d",
+            NodeKind::Component => "component",
+            NodeKind::Asset => "ellipse",
+            NodeKind::Data => "cylinder",
+        }
+    }
+}
+
+/// Files the build reads besides the pages themselves.
+pub struct GraphInputs<'a> {
+    pub components: &'a ComponentDirs,
+    /// Layout of the Markdown page at a path
+    pub post_layout: &'a dyn Fn(&Path) -> PathBuf,
+    pub variables_config: &'a Path,
+    pub translations_dir: Option<&'a Path>,
+}
+
+/// Which layouts, components, assets, and data files each page depends on.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    nodes: BTreeMap<String, NodeKind>,
+    edges: BTreeSet<(String, String)>,
+}
+
+#[derive(Serialize)]
+struct JsonNode<'a> {
+    id: &'a str,
+    kind: NodeKind,
+    /// Number of pages that depend on this node, directly or through components
+    pages: usize,
+}
+
+#[derive(Serialize)]
+struct JsonEdge<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl DependencyGraph {
+    /// Build the graph from `(source, output)` pairs of every rendered page.
+    pub fn build(pages: &[(PathBuf, PathBuf)], inputs: &GraphInputs) -> Result<Self> {
+        let mut graph = Self::default();
+        let asset_selector = Selector::parse(ASSET_SELECTOR).unwrap();
+
+        for (source, output) in pages {
+            let page = graph.add_page(source, inputs)?;
+
+            // Assets are taken from the output so layouts' stylesheets and scripts count too
+            let html = Html::parse_document(&fs::read_to_string(output)?);
+            for element in html.select(&asset_selector) {
+                let value = element.value();
+                let url = value.attr("src").or_else(|| value.attr("href")).unwrap_or_default();
+                if is_local_asset(url) {
+                    graph.add_edge(&page, Path::new(url), NodeKind::Asset);
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Build the graph from page sources alone, without their assets.
+    pub fn from_sources(pages: &[PathBuf], inputs: &GraphInputs) -> Result<Self> {
+        let mut graph = Self::default();
+        for source in pages {
+            graph.add_page(source, inputs)?;
+        }
+        Ok(graph)
+    }
+
+    /// Add a page with the layout, data, and components it uses; returns its node.
+    fn add_page(&mut self, source: &Path, inputs: &GraphInputs) -> Result<String> {
+        let page = node_id(source);
+        self.nodes.insert(page.clone(), NodeKind::Page);
+        let content = fs::read_to_string(source)?;
+
+        if source.extension().is_some_and(|ext| ext == "md") {
+            self.add_edge(&page, &(inputs.post_layout)(source), NodeKind::Layout);
+        }
+        if VAR_REGEX.is_match(&content) {
+            self.add_edge(&page, inputs.variables_config, NodeKind::Data);
+        }
+        if let Some(dir) = inputs.translations_dir {
+            if T_REGEX.is_match(&content) {
+                self.add_edge(&page, dir, NodeKind::Data);
+            }
+        }
+        self.add_components(&page, source, &content, inputs.components, &mut HashSet::new());
+        Ok(page)
+    }
+
+    fn add_edge(&mut self, from: &str, to: &Path, kind: NodeKind) -> String {
+        let to = node_id(to);
+        self.nodes.entry(to.clone()).or_insert(kind);
+        self.edges.insert((from.to_string(), to.clone()));
+        to
+    }
+
+    /// Add the components and included partials `content` uses, and theirs;
+    /// `from_path` is the file `content` was read from.
+    fn add_components(&mut self, from: &str, from_path: &Path, content: &str, components: &ComponentDirs, visited: &mut HashSet<String>) {
+        let used = COMPONENT_REGEX.captures_iter(content)
+            .map(|caps| components.resolve(format!("{}.html", &caps[1])))
+            .chain(INCLUDE_REGEX.captures_iter(content)
+                .filter_map(|caps| resolve_include(&caps[1], Some(from_path), components)))
+            .collect::<Vec<_>>();
+        for path in used {
+            let component = self.add_edge(from, &path, NodeKind::Component);
+            if visited.insert(component.clone()) {
+                if let Ok(nested) = fs::read_to_string(&path) {
+                    self.add_components(&component, &path, &nested, components, visited);
+                }
+            }
+        }
+    }
+
+    /// Every node a page reaches through the edges.
+    fn reachable<'a>(&'a self, page: &'a str) -> HashSet<&'a str> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![page];
+        while let Some(node) = stack.pop() {
+            for (_, to) in self.edges.iter().filter(|(from, _)| from == node) {
+                if seen.insert(to.as_str()) {
+                    stack.push(to);
+                }
+            }
+        }
+        seen
+    }
+
+    fn pages(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter()
+            .filter(|(_, kind)| **kind == NodeKind::Page)
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// Number of pages reaching each node through the edges.
+    fn page_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for page in self.pages() {
+            for node in self.reachable(page) {
+                *counts.entry(node).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Pages that use `path` directly or through components.
+    pub fn dependents(&self, path: &Path) -> Vec<PathBuf> {
+        let target = node_id(path);
+        self.pages()
+            .filter(|page| self.reachable(page).contains(target.as_str()))
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let counts = self.page_counts();
+        let mut dot = String::from("digraph dependencies {\n    rankdir=LR;\n");
+        for (id, kind) in &self.nodes {
+            let label = match counts.get(id.as_str()) {
+                Some(pages) => format!("{}\n({} pages)", id, pages),
+                None => id.clone(),
+            };
+            dot.push_str(&format!("    {:?} [label={:?}, shape={}];\n", id, label, kind.dot_shape()));
+        }
+        for (from, to) in &self.edges {
+            dot.push_str(&format!("    {:?} -> {:?};\n", from, to));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let counts = self.page_counts();
+        let nodes: Vec<_> = self.nodes.iter()
+            .map(|(id, kind)| JsonNode {
+                id,
+                kind: *kind,
+                pages: counts.get(id.as_str()).copied().unwrap_or_default(),
+            })
+            .collect();
+        let edges: Vec<_> = self.edges.iter()
+            .map(|(from, to)| JsonEdge { from, to })
+            .collect();
+        Ok(serde_json::to_string_pretty(&serde_json::json!({ "nodes": nodes, "edges": edges }))?)
+    }
+
+    /// Write the graph as JSON for a `.json` path, DOT otherwise.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let content = if path.extension().is_some_and(|ext| ext == "json") {
+            self.to_json()?
+        } else {
+            self.to_dot()
+        };
+        fs::write(path, content)?;
+        info!("Dependency graph with {} nodes written to {}", self.nodes.len(), path.display());
+        Ok(())
+    }
+}
+
+fn node_id(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn is_local_asset(url: &str) -> bool {
+    !url.is_empty()
+        && !url.starts_with('#')
+        && !url.starts_with("//")
+        && !url.contains("://")
+        && !url.starts_with("data:")
+        && !url.starts_with("mailto:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_pages_through_layouts_and_nested_components() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let components = root.join("components");
+        fs::create_dir_all(&components).unwrap();
+        fs::create_dir_all(root.join("content/blog")).unwrap();
+        fs::create_dir_all(root.join("output/blog")).unwrap();
+        fs::write(components.join("header.html"), "<nav>@{include(\"logo.html\")}</nav>").unwrap();
+        fs::write(components.join("logo.html"), "<img src=\"/logo.svg\">").unwrap();
+        fs::write(root.join("content/index.html"), "<el-component c_name=\"header\"></el-component><p>@{var(\"name\")}</p>").unwrap();
+        fs::write(root.join("content/blog/post.md"), "# Post").unwrap();
+        fs::write(root.join("output/index.html"), "<link rel=\"stylesheet\" href=\"/css/site.css\"><img src=\"https://cdn.example.com/x.png\">").unwrap();
+        fs::write(root.join("output/blog/post.html"), "<link rel=\"stylesheet\" href=\"/css/site.css\"><a href=\"/\">Home</a>").unwrap();
+
+        let dirs = ComponentDirs::new(&components, None);
+        let layout = components.join("blog_layout.html");
+        let variables = root.join("variables.toml");
+        let pages = [
+            (root.join("content/index.html"), root.join("output/index.html")),
+            (root.join("content/blog/post.md"), root.join("output/blog/post.html")),
+        ];
+        let graph = DependencyGraph::build(&pages, &GraphInputs {
+            components: &dirs,
+            post_layout: &|_| layout.clone(),
+            variables_config: &variables,
+            translations_dir: None,
+        }).unwrap();
+
+        assert_eq!(graph.dependents(&components.join("logo.html")), [root.join("content/index.html")]);
+        assert_eq!(graph.dependents(&layout), [root.join("content/blog/post.md")]);
+        assert_eq!(graph.dependents(&variables), [root.join("content/index.html")]);
+        assert_eq!(graph.dependents(Path::new("/css/site.css")).len(), 2);
+        assert!(graph.dependents(Path::new("https://cdn.example.com/x.png")).is_empty());
+
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json().unwrap()).unwrap();
+        let site_css = json["nodes"].as_array().unwrap().iter().find(|node| node["id"] == "/css/site.css").unwrap();
+        assert_eq!((site_css["kind"].as_str(), site_css["pages"].as_u64()), (Some("asset"), Some(2)));
+        let dot = graph.to_dot();
+        assert!(dot.contains(&format!("{:?} -> {:?};", node_id(&components.join("header.html")), node_id(&components.join("logo.html")))), "{}", dot);
+    }
+}
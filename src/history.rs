@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+/// One line of `builds.log.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildRecord {
+    pub timestamp: String,
+    /// Hash of the build flags and configuration files
+    pub config_hash: String,
+    pub duration_ms: u128,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Analyzer errors and warnings
+    pub warnings: usize,
+    /// Inputs added, modified, or removed since the previous build
+    pub inputs_changed: Vec<String>,
+    /// Outputs added, modified, or removed by this build
+    pub outputs_changed: Vec<String>,
+    /// Where the output was deployed, for deploy runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deploy_target: Option<String>,
+}
+
+/// Content hash of every file, keyed by path, used to diff consecutive builds.
+pub type Snapshot = BTreeMap<String, String>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildState {
+    inputs: Snapshot,
+    outputs: Snapshot,
+}
+
+/// Append-only log of builds kept in the cache directory.
+pub struct BuildLog {
+    cache_dir: PathBuf,
+}
+
+impl BuildLog {
+    pub const FILE_NAME: &'static str = "builds.log.jsonl";
+    const STATE_FILE: &'static str = "build-state.json";
+
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Self { cache_dir: cache_dir.into() }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.cache_dir.join(Self::FILE_NAME)
+    }
+
+    /// Append `record`, filling in what changed since the previous build's
+    /// snapshots, and remember the new snapshots for the next build.
+    pub fn append(&self, mut record: BuildRecord, inputs: Snapshot, outputs: Snapshot) -> Result<()> {
+        fs::create_dir_all(&self.cache_dir)?;
+        let state_path = self.cache_dir.join(Self::STATE_FILE);
+        let previous: BuildState = fs::read_to_string(&state_path).ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        record.inputs_changed = changed(&previous.inputs, &inputs);
+        record.outputs_changed = changed(&previous.outputs, &outputs);
+
+        self.append_record(&record)?;
+        fs::write(state_path, serde_json::to_string(&BuildState { inputs, outputs })?)?;
+        Ok(())
+    }
+
+    /// Append `record` as it is, leaving the previous build's snapshots for
+    /// the next one, as for deploys.
+    pub fn append_record(&self, record: &BuildRecord) -> Result<()> {
+        fs::create_dir_all(&self.cache_dir)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(self.path())?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// Every recorded build, oldest first.
+    pub fn read(&self) -> Result<Vec<BuildRecord>> {
+        let path = self.path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+}
+
+impl BuildRecord {
+    pub fn new(config_hash: String, duration_ms: u128, result: &Result<usize>) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339(),
+            config_hash,
+            duration_ms,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            warnings: *result.as_ref().unwrap_or(&0),
+            inputs_changed: Vec::new(),
+            outputs_changed: Vec::new(),
+            deploy_target: None,
+        }
+    }
+
+    /// Record of a deploy to `target`, with the files it uploaded or deleted
+    /// as the changed outputs.
+    pub fn deploy(config_hash: String, duration_ms: u128, target: String, result: &Result<Vec<String>>) -> Self {
+        let summary = result.as_ref().map(|_| 0).map_err(|e| anyhow!("{:#}", e));
+        Self {
+            outputs_changed: result.as_ref().cloned().unwrap_or_default(),
+            deploy_target: Some(target),
+            ..Self::new(config_hash, duration_ms, &summary)
+        }
+    }
+}
+
+/// Hash every file under `roots` (directories or single files), skipping
+/// anything under `exclude`.
+pub fn snapshot(roots: &[&Path], exclude: Option<&Path>) -> Snapshot {
+    let mut files = Snapshot::new();
+    for root in roots {
+        for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !entry.file_type().is_file() || exclude.is_some_and(|dir| path.starts_with(dir)) {
+                continue;
+            }
+            if let Ok(content) = fs::read(path) {
+                files.insert(path.to_string_lossy().replace('\\', "/"), hash(&content));
+            }
+        }
+    }
+    files
+}
+
+/// Stable FNV-1a hash, so log entries compare across toolchains.
+pub fn hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn changed(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let mut paths: Vec<String> = after.iter()
+        .filter(|(path, hash)| before.get(*path) != Some(*hash))
+        .map(|(path, _)| path.clone())
+        .collect();
+    paths.extend(before.keys().filter(|path| !after.contains_key(*path)).cloned());
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn files(entries: &[(&str, &str)]) -> Snapshot {
+        entries.iter().map(|(path, hash)| (path.to_string(), hash.to_string())).collect()
+    }
+
+    #[test]
+    fn test_appended_builds_record_what_changed_since_the_previous_one() {
+        let temp = tempdir().unwrap();
+        let log = BuildLog::new(temp.path().join("cache"));
+        assert!(log.read().unwrap().is_empty());
+
+        let ok = BuildRecord::new("cfg".to_string(), 120, &Ok(2));
+        log.append(ok, files(&[("a.md", "1"), ("b.md", "1")]), files(&[("a.html", "1")])).unwrap();
+        let failed = BuildRecord::new("cfg".to_string(), 80, &Err(anyhow!("broken page")));
+        log.append(failed, files(&[("a.md", "2"), ("c.md", "1")]), files(&[("a.html", "1")])).unwrap();
+
+        let builds = log.read().unwrap();
+        assert_eq!(builds.len(), 2);
+        assert!(builds[0].success);
+        assert_eq!(builds[0].warnings, 2);
+        assert_eq!(builds[0].inputs_changed, ["a.md", "b.md"]);
+        assert!(!builds[1].success);
+        assert_eq!(builds[1].error.as_deref(), Some("broken page"));
+        assert_eq!(builds[1].inputs_changed, ["a.md", "b.md", "c.md"]);
+        assert!(builds[1].outputs_changed.is_empty());
+    }
+
+    #[test]
+    fn test_deploys_are_logged_without_moving_the_build_snapshots() {
+        let temp = tempdir().unwrap();
+        let log = BuildLog::new(temp.path());
+        log.append(BuildRecord::new("cfg".to_string(), 10, &Ok(0)), files(&[("a.md", "1")]), Snapshot::new()).unwrap();
+
+        let uploaded = Ok(vec!["index.html".to_string(), "old.html".to_string()]);
+        log.append_record(&BuildRecord::deploy("cfg".to_string(), 30, "s3://bucket/site".to_string(), &uploaded)).unwrap();
+        let failed = Err(anyhow!("access denied"));
+        log.append_record(&BuildRecord::deploy("cfg".to_string(), 5, "s3://bucket/site".to_string(), &failed)).unwrap();
+        log.append(BuildRecord::new("cfg".to_string(), 10, &Ok(0)), files(&[("a.md", "1")]), Snapshot::new()).unwrap();
+
+        let records = log.read().unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1].deploy_target.as_deref(), Some("s3://bucket/site"));
+        assert_eq!(records[1].outputs_changed, ["index.html", "old.html"]);
+        assert!(records[1].success);
+        assert!(!records[2].success);
+        assert_eq!(records[2].error.as_deref(), Some("access denied"));
+        assert_eq!(records[3].deploy_target, None);
+        assert!(records[3].inputs_changed.is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_skip_the_excluded_directory() {
+        let temp = tempdir().unwrap();
+        let output = temp.path().join("output");
+        fs::create_dir_all(output.join("cache")).unwrap();
+        fs::write(output.join("index.html"), "home").unwrap();
+        fs::write(output.join("cache/builds.log.jsonl"), "{}").unwrap();
+
+        let files = snapshot(&[&output], Some(&output.join("cache")));
+        let index = output.join("index.html").to_string_lossy().replace('\\', "/");
+        assert_eq!(files.keys().collect::<Vec<_>>(), [&index]);
+        assert_eq!(files[&index], hash(b"home"));
+        assert_eq!(hash(b""), "cbf29ce484222325");
+        assert_ne!(hash(b"home"), hash(b"hone"));
+    }
+}
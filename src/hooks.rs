@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::Deserialize;
+use crate::config::CliArgs;
+use crate::conditionals::Conditions;
+
+/// `[hooks]` section of `eldroid.toml`: shell commands run at points of the
+/// build, in order, from the working directory.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HooksConfig {
+    /// Commands run before any page is built, e.g. a CSS or JS bundler
+    #[serde(default)]
+    pub before_build: Vec<String>,
+    /// Commands run after each page is written; pages are built in
+    /// parallel, so these can run concurrently
+    #[serde(default)]
+    pub after_page: Vec<String>,
+    /// Commands run once every page and site-wide file is written
+    #[serde(default)]
+    pub after_build: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hook {
+    BeforeBuild,
+    AfterPage,
+    AfterBuild,
+}
+
+impl Hook {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::BeforeBuild => "before_build",
+            Hook::AfterPage => "after_page",
+            Hook::AfterBuild => "after_build",
+        }
+    }
+}
+
+/// Environment variables hook commands get, describing the build.
+#[derive(Debug, Clone, Default)]
+pub struct HookEnv {
+    vars: Vec<(&'static str, String)>,
+}
+
+impl HookEnv {
+    /// `ELDROID_INPUT_DIR`, `ELDROID_OUTPUT_DIR`, `ELDROID_ENV`, and
+    /// `ELDROID_WATCH` for a build run with `args`.
+    pub fn new(args: &CliArgs) -> Self {
+        Self {
+            vars: vec![
+                ("ELDROID_INPUT_DIR", args.input_dir.clone()),
+                ("ELDROID_OUTPUT_DIR", args.output_dir.clone()),
+                ("ELDROID_ENV", Conditions::from_env(args.watch).environment().to_string()),
+                ("ELDROID_WATCH", if args.watch { "1" } else { "0" }.to_string()),
+                ("ELDROID_CHANGED_FILES", String::new()),
+            ],
+        }
+    }
+
+    /// Files whose change started a watch-mode rebuild, one per line in
+    /// `ELDROID_CHANGED_FILES`. Empty for full builds.
+    pub fn with_changed_files(self, files: &[PathBuf]) -> Self {
+        let files = files.iter().map(|file| file.display().to_string()).collect::<Vec<_>>().join("\n");
+        self.with_var("ELDROID_CHANGED_FILES", files)
+    }
+
+    /// The page an `after_page` hook runs for: `ELDROID_PAGE_SOURCE` and
+    /// `ELDROID_PAGE_OUTPUT`.
+    pub fn with_page(self, source: &Path, output: &Path) -> Self {
+        self.with_var("ELDROID_PAGE_SOURCE", source.display().to_string())
+            .with_var("ELDROID_PAGE_OUTPUT", output.display().to_string())
+    }
+
+    fn with_var(mut self, name: &'static str, value: String) -> Self {
+        self.vars.retain(|(existing, _)| *existing != name);
+        self.vars.push((name, value));
+        self
+    }
+}
+
+impl HooksConfig {
+    pub fn commands(&self, hook: Hook) -> &[String] {
+        match hook {
+            Hook::BeforeBuild => &self.before_build,
+            Hook::AfterPage => &self.after_page,
+            Hook::AfterBuild => &self.after_build,
+        }
+    }
+
+    /// Run the commands of `hook` through the shell, stopping at the first
+    /// that fails. Their output goes straight to the terminal.
+    pub fn run(&self, hook: Hook, env: &HookEnv) -> Result<()> {
+        for command in self.commands(hook) {
+            info!("Running {} hook: {}", hook.name(), command);
+            let mut shell = if cfg!(target_os = "windows") {
+                let mut shell = Command::new("cmd");
+                shell.arg("/C");
+                shell
+            } else {
+                let mut shell = Command::new("sh");
+                shell.arg("-c");
+                shell
+            };
+            let status = shell
+                .arg(command)
+                .env("ELDROID_HOOK", hook.name())
+                .envs(env.vars.iter().map(|(name, value)| (name, value)))
+                .status()
+                .map_err(|e| anyhow!("Failed to run {} hook `{}`: {}", hook.name(), command, e))?;
+            if !status.success() {
+                return Err(anyhow!("{} hook `{}` failed with {}", hook.name(), command, status));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_hooks_run_in_order_with_build_environment() {
+        let temp = tempfile::tempdir().unwrap();
+        let log = temp.path().join("log");
+        let hooks: HooksConfig = toml::from_str(&format!(
+            "after_page = [\"echo \\\"$ELDROID_HOOK $ELDROID_PAGE_OUTPUT $ELDROID_OUTPUT_DIR\\\" >> {0}\", \"echo second >> {0}\"]\n\
+             after_build = [\"false\", \"echo unreachable >> {0}\"]",
+            log.display(),
+        )).unwrap();
+        let args = CliArgs::parse_from(["eldroid-ssg", "--output-dir", "public"]);
+
+        let env = HookEnv::new(&args).with_page(Path::new("content/a.md"), Path::new("public/a.html"));
+        hooks.run(Hook::AfterPage, &env).unwrap();
+        hooks.run(Hook::BeforeBuild, &env).unwrap();
+        let err = hooks.run(Hook::AfterBuild, &HookEnv::new(&args)).unwrap_err();
+        assert!(err.to_string().starts_with("after_build hook `false` failed"), "{}", err);
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "after_page public/a.html public\nsecond\n");
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use log::info;
+use serde::Deserialize;
+use walkdir::WalkDir;
+use crate::caching::{CachingConfig, HeadersManifest};
+use crate::csp::ContentSecurityPolicy;
+
+/// Configuration the host reads rather than serves.
+const HOST_FILES: &[&str] = &["_headers", "_redirects", "vercel.json"];
+
+/// Cache-Control for pages, which browsers and CDNs revalidate before each use.
+const NO_CACHE: &str = "no-cache";
+
+/// `[host_headers]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HostHeadersConfig {
+    /// Header files to write for the host
+    pub formats: Vec<HostFormat>,
+    /// `Strict-Transport-Security` value; empty to leave it out
+    #[serde(default = "default_hsts")]
+    pub hsts: String,
+    /// More headers for every path, e.g. `Referrer-Policy = "same-origin"`
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+fn default_hsts() -> String {
+    "max-age=31536000; includeSubDomains".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostFormat {
+    /// Netlify `_headers` file
+    Netlify,
+    /// `headers` array in `vercel.json`
+    Vercel,
+}
+
+/// Response headers for a static host: security headers on every path,
+/// `no-cache` for pages, and Cache-Control for each other file whose policy
+/// differs from the HTML one.
+///
+/// Hosts apply every rule that matches a path, and Netlify joins repeated
+/// headers into one value, so Cache-Control rules never overlap. Feeds and
+/// other short-lived files keep the host's default, which revalidates them
+/// on every request and is purged on each deploy.
+#[derive(Debug, Default, PartialEq)]
+pub struct HostHeaders {
+    pub global: Vec<(String, String)>,
+    /// Cache-Control by URL path
+    pub cache_control: Vec<(String, String)>,
+}
+
+impl HostHeaders {
+    pub fn new(
+        config: &HostHeadersConfig,
+        caching: &CachingConfig,
+        manifest: &HeadersManifest,
+        csp: Option<&ContentSecurityPolicy>,
+        output_dir: &Path,
+    ) -> Self {
+        let mut global = vec![("X-Content-Type-Options".to_string(), "nosniff".to_string())];
+        if !config.hsts.is_empty() {
+            global.push(("Strict-Transport-Security".to_string(), config.hsts.clone()));
+        }
+        if let Some(policy) = csp.map(ContentSecurityPolicy::to_header_value).filter(|value| !value.is_empty()) {
+            global.push(("Content-Security-Policy".to_string(), policy));
+        }
+        global.extend(config.extra.iter().map(|(name, value)| (name.clone(), value.clone())));
+
+        let mut cache_control: Vec<(String, String)> = WalkDir::new(output_dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .flat_map(|entry| {
+                let Some(relative) = entry.path().strip_prefix(output_dir).ok().map(|path| path.to_string_lossy().replace('\\', "/")) else {
+                    return Vec::new();
+                };
+                if relative.starts_with("cache/") || relative.starts_with("performance/") || HOST_FILES.contains(&relative.as_str()) {
+                    return Vec::new();
+                }
+                let Some(policy) = manifest.cache_control(&relative) else { return Vec::new() };
+                if policy != caching.html {
+                    return vec![(format!("/{}", relative), policy.to_string())];
+                }
+                if !relative.ends_with(".html") {
+                    return Vec::new();
+                }
+                // Pages are revalidated on every request, under both their file and directory URLs
+                let mut paths = vec![format!("/{}", relative)];
+                if let Some(dir) = relative.strip_suffix("index.html").filter(|dir| dir.is_empty() || dir.ends_with('/')) {
+                    paths.push(format!("/{}", dir));
+                }
+                paths.into_iter().map(|path| (path, NO_CACHE.to_string())).collect()
+            })
+            .collect();
+        cache_control.sort();
+        Self { global, cache_control }
+    }
+
+    /// Netlify `_headers` content.
+    pub fn netlify(&self) -> String {
+        let mut content = String::from("/*\n");
+        for (name, value) in &self.global {
+            content.push_str(&format!("  {}: {}\n", name, value));
+        }
+        for (path, value) in &self.cache_control {
+            content.push_str(&format!("{}\n  Cache-Control: {}\n", path, value));
+        }
+        content
+    }
+
+    /// The `headers` array of `vercel.json`.
+    pub fn vercel(&self) -> serde_json::Value {
+        let entry = |source: String, headers: &[(String, String)]| serde_json::json!({
+            "source": source,
+            "headers": headers.iter()
+                .map(|(key, value)| serde_json::json!({ "key": key, "value": value }))
+                .collect::<Vec<_>>(),
+        });
+        let mut entries = vec![entry("/(.*)".to_string(), &self.global)];
+        entries.extend(self.cache_control.iter().map(|(path, value)| {
+            entry(vercel_source(path), &[("Cache-Control".to_string(), value.clone())])
+        }));
+        serde_json::Value::Array(entries)
+    }
+
+    pub fn write(&self, formats: &[HostFormat], output_dir: &Path) -> Result<()> {
+        for format in formats {
+            match format {
+                HostFormat::Netlify => fs::write(output_dir.join("_headers"), self.netlify())?,
+                HostFormat::Vercel => {
+                    let path = output_dir.join("vercel.json");
+                    // Keep the redirects and any other settings already written
+                    let mut config = fs::read_to_string(&path).ok()
+                        .and_then(|existing| serde_json::from_str(&existing).ok())
+                        .unwrap_or_else(|| serde_json::json!({}));
+                    config["headers"] = self.vercel();
+                    fs::write(&path, serde_json::to_string_pretty(&config)?)?;
+                },
+            }
+        }
+        info!("Host headers for {} files written in {} format(s)", self.cache_control.len(), formats.len());
+        Ok(())
+    }
+}
+
+/// A literal path as a Vercel `source`, whose pattern syntax gives `:`, `(`,
+/// `)`, `*`, `+`, and `?` a meaning.
+fn vercel_source(path: &str) -> String {
+    path.chars()
+        .flat_map(|c| match c {
+            ':' | '(' | ')' | '*' | '+' | '?' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_cache_only_for_assets_and_security_everywhere() {
+        let temp = tempfile::tempdir().unwrap();
+        let out = temp.path();
+        fs::create_dir_all(out.join("css")).unwrap();
+        fs::create_dir_all(out.join("cache")).unwrap();
+        fs::create_dir_all(out.join("blog")).unwrap();
+        for file in ["index.html", "blog/index.html", "css/app.3f2a9c1b.css", "css/site.css", "feed.xml", "cache/state.json"] {
+            fs::write(out.join(file), "").unwrap();
+        }
+        fs::write(out.join("vercel.json"), r#"{"redirects": []}"#).unwrap();
+        fs::write(out.join("_headers"), "/*\n  X-Frame-Options: DENY\n").unwrap();
+        let caching = CachingConfig::default();
+        let manifest = HeadersManifest::build(&caching, out).unwrap();
+        let config: HostHeadersConfig = toml::from_str("formats = [\"netlify\", \"vercel\"]\nextra = { Referrer-Policy = \"same-origin\" }").unwrap();
+
+        let headers = HostHeaders::new(&config, &caching, &manifest, None, out);
+        headers.write(&config.formats, out).unwrap();
+        assert_eq!(fs::read_to_string(out.join("_headers")).unwrap(), format!(
+            "/*\n  X-Content-Type-Options: nosniff\n  Strict-Transport-Security: {}\n  Referrer-Policy: same-origin\n\
+             /\n  Cache-Control: no-cache\n/blog/\n  Cache-Control: no-cache\n/blog/index.html\n  Cache-Control: no-cache\n\
+             /css/app.3f2a9c1b.css\n  Cache-Control: {}\n/css/site.css\n  Cache-Control: {}\n/index.html\n  Cache-Control: no-cache\n",
+            default_hsts(), caching.immutable, caching.assets,
+        ));
+        let vercel: serde_json::Value = serde_json::from_str(&fs::read_to_string(out.join("vercel.json")).unwrap()).unwrap();
+        assert_eq!(vercel["redirects"], serde_json::json!([]));
+        assert_eq!(vercel["headers"][1]["source"], "/");
+        assert_eq!(vercel["headers"][1]["headers"][0]["value"], "no-cache");
+        assert_eq!(vercel["headers"][4]["source"], "/css/app.3f2a9c1b.css");
+        assert_eq!(vercel["headers"][4]["headers"][0]["value"], caching.immutable.as_str());
+        assert_eq!(vercel_source("/a(1).png"), "/a\\(1\\).png");
+    }
+}
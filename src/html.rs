@@ -1,15 +1,114 @@
-use scraper::{Html, Selector, Node};
+use chrono::{DateTime, Utc};
+use scraper::{Html, Selector};
 use log::warn;
-use std::path::Path;
-use crate::seo::{PageSEO, SEOConfig};
-use crate::variables::Variables;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use crate::layout::{load_layout, read_layout, render_layout};
+use crate::seo::{parse_page_seo, PageSEO, SEOConfig};
+use crate::seo_types::{AlternateLink, Author as Person};
+use crate::git_dates::FileDates;
+use crate::seo_html::{
+    analytics_script, expand_protected_seo_macros, generate_head_tags, generate_json_ld_script,
+    inject_meta_tags, protect_seo_macros, seo_macros_used,
+};
+use crate::variables::{merge_vars, Variables};
 use crate::macros::MacroProcessor;
+use crate::themes::ComponentDirs;
 
+/// YAML front matter at the top of an HTML page, between `---` lines: the
+/// structured alternative to an `<!-- SEO {...} -->` comment.
+#[derive(Debug, Default, Deserialize)]
+pub struct HtmlFrontMatter {
+    /// Page title; the other SEO fields only apply when it is set
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub canonical_url: Option<String>,
+    pub image: Option<String>,
+    pub author: Option<String>,
+    /// Co-authors, credited after `author`
+    #[serde(default)]
+    pub authors: Vec<String>,
+    pub tags: Option<Vec<String>>,
+    pub schema_type: Option<String>,
+    pub change_frequency: Option<String>,
+    pub priority: Option<f32>,
+    pub robots: Option<String>,
+    /// Layout in the components directory the page is placed in at `@{yield}`
+    pub layout: Option<PathBuf>,
+    /// Variables for `@{var("...")}` on this page, over the site's
+    #[serde(default)]
+    pub variables: HashMap<String, toml::Value>,
+}
+
+impl HtmlFrontMatter {
+    /// The front matter and body of `content`, or `None` when it doesn't
+    /// start with a `---` line.
+    pub fn split(content: &str) -> Result<Option<(Self, &str)>> {
+        let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+            return Ok(None);
+        };
+        let (yaml, body) = match rest.find("\n---") {
+            Some(end) => {
+                let body = &rest[end + "\n---".len()..];
+                (&rest[..end], body.split_once('\n').map_or("", |(_, body)| body))
+            },
+            None => return Err(anyhow!("Front matter has no closing ---")),
+        };
+        let front_matter = if yaml.trim().is_empty() {
+            Self::default()
+        } else {
+            serde_yaml::from_str(yaml).map_err(|e| anyhow!("Failed to parse front matter: {}", e))?
+        };
+        Ok(Some((front_matter, body)))
+    }
+
+    pub fn page_seo(&self) -> Option<PageSEO> {
+        Some(PageSEO {
+            title: self.title.clone()?,
+            description: self.description.clone(),
+            keywords: self.keywords.clone(),
+            canonical_url: self.canonical_url.clone(),
+            image: self.image.clone(),
+            author: self.author.clone(),
+            co_authors: self.authors.iter().map(|name| Person {
+                type_: "Person".to_string(),
+                name: name.clone(),
+                url: None,
+                image: None,
+            }).collect(),
+            tags: self.tags.clone(),
+            schema_type: self.schema_type.clone(),
+            change_frequency: self.change_frequency.clone(),
+            priority: self.priority,
+            robots: self.robots.clone(),
+            ..PageSEO::default()
+        })
+    }
+}
+
+/// An HTML page rendered by [`HtmlGenerator::generate_page`].
+pub struct GeneratedPage {
+    pub html: String,
+    /// From the front matter, or else the page's SEO comment
+    pub seo: Option<PageSEO>,
+    /// Layout named in the front matter or a `_vars.toml`
+    pub layout: Option<PathBuf>,
+}
+
+#[derive(Clone)]
 pub struct HtmlGenerator {
     variables: Option<Variables>,
     macro_processor: Option<MacroProcessor>,
     dev_mode: bool,
-    ws_port: Option<u16>,
+}
+
+impl Default for HtmlGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HtmlGenerator {
@@ -18,7 +117,6 @@ impl HtmlGenerator {
             variables: None,
             macro_processor: None,
             dev_mode: false,
-            ws_port: None,
         }
     }
 
@@ -32,17 +130,78 @@ impl HtmlGenerator {
         self
     }
 
-    pub fn with_dev_mode(mut self, enabled: bool, ws_port: Option<u16>) -> Self {
+    pub fn with_dev_mode(mut self, enabled: bool) -> Self {
         self.dev_mode = enabled;
-        self.ws_port = ws_port;
+        self
+    }
+
+    /// Evaluate the date macros as if building at `now`.
+    pub fn at(mut self, now: DateTime<Utc>) -> Self {
+        self.macro_processor = self.macro_processor.map(|processor| processor.at(now));
         self
     }
 
     pub fn generate(&self, content: &str) -> String {
+        self.generate_with(content, None, self.variables.as_ref())
+    }
+
+    /// Render an HTML page, reading the front matter it may start with.
+    /// `dir_vars` come from the `_vars.toml` files above the page; the front
+    /// matter's variables take precedence over them, and they over the
+    /// site's. A `layout` from either is resolved in `components`. `path`
+    /// is the page's file, which relative includes are resolved against.
+    pub fn generate_page(
+        &self,
+        content: &str,
+        path: &Path,
+        components: &ComponentDirs,
+        dir_vars: &HashMap<String, toml::Value>,
+    ) -> Result<GeneratedPage> {
+        let (front_matter, body) = match HtmlFrontMatter::split(content)? {
+            Some(split) => split,
+            None if dir_vars.is_empty() => {
+                let html = self.generate_with(content, Some(path), self.variables.as_ref());
+                let seo = parse_page_seo(&html);
+                return Ok(GeneratedPage { html, seo, layout: None });
+            },
+            None => (HtmlFrontMatter::default(), content),
+        };
+
+        let layout = front_matter.layout.clone()
+            .or_else(|| dir_vars.get("layout").and_then(toml::Value::as_str).map(PathBuf::from));
+        let body = match &layout {
+            Some(layout) => {
+                // A layout the page names itself has to exist
+                let layout = match front_matter.layout {
+                    Some(_) => read_layout(components, layout).with_context(|| format!("Layout named in {}", path.display()))?,
+                    None => load_layout(components, Some(layout)),
+                };
+                render_layout(&layout, front_matter.title.as_deref().unwrap_or(""), body)
+            },
+            None => body.to_string(),
+        };
+        let page_vars = (!front_matter.variables.is_empty() || !dir_vars.is_empty()).then(|| {
+            let mut page_vars = dir_vars.clone();
+            merge_vars(&mut page_vars, front_matter.variables.clone());
+            let mut vars = self.variables.clone().unwrap_or_default();
+            vars.set_page_vars(page_vars);
+            vars
+        });
+        let html = self.generate_with(&body, Some(path), page_vars.as_ref().or(self.variables.as_ref()));
+        let seo = front_matter.page_seo().or_else(|| parse_page_seo(&html));
+        Ok(GeneratedPage { html, seo, layout })
+    }
+
+    fn generate_with(&self, content: &str, path: Option<&Path>, variables: Option<&Variables>) -> String {
         let mut processed = content.to_string();
 
+        // Pull in partials first, so their variables are the page's
+        if let Some(processor) = &self.macro_processor {
+            processed = processor.include(&processed, path);
+        }
+
         // Process variables if configured
-        if let Some(vars) = &self.variables {
+        if let Some(vars) = variables {
             processed = vars.substitute(&processed);
         }
 
@@ -53,29 +212,28 @@ impl HtmlGenerator {
 
         // Inject hot reload script in dev mode
         if self.dev_mode {
-            if let Some(port) = self.ws_port {
-                processed = self.inject_hot_reload(&processed, port);
-            }
+            processed = self.inject_hot_reload(&processed);
         }
 
         processed
     }
 
-    fn inject_hot_reload(&self, html: &str, ws_port: u16) -> String {
-        let hot_reload_script = format!(
-            r#"<script>
+    fn inject_hot_reload(&self, html: &str) -> String {
+        let hot_reload_script = r#"<script>
             // Hot Reload Client
-            (function() {{
-                const ws = new WebSocket(`ws://localhost:{}/ws`);
+            (function() {
+                // Same host and port as the page, so it works behind proxies
+                const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+                const ws = new WebSocket(`${protocol}//${location.host}/ws`);
                 
-                // Create error overlay
+                // Error overlay, shown until dismissed or the next successful rebuild
                 const errorOverlay = document.createElement('div');
                 errorOverlay.style.cssText = `
                     position: fixed;
                     top: 0;
                     left: 0;
                     right: 0;
-                    background: rgba(200, 0, 0, 0.85);
+                    background: rgba(200, 0, 0, 0.92);
                     color: white;
                     padding: 20px;
                     font-family: monospace;
@@ -86,47 +244,237 @@ impl HtmlGenerator {
                     max-height: 50vh;
                     overflow-y: auto;
                 `;
+                const dismiss = document.createElement('button');
+                dismiss.textContent = '\u00d7';
+                dismiss.title = 'Dismiss (Esc)';
+                dismiss.style.cssText = 'float: right; background: none; border: none; color: white; font-size: 24px; cursor: pointer;';
+                const errorLocation = document.createElement('div');
+                errorLocation.style.cssText = 'font-weight: bold; margin-bottom: 10px;';
+                const errorMessage = document.createElement('div');
+                const errorFrame = document.createElement('pre');
+                errorFrame.style.cssText = 'background: rgba(0, 0, 0, 0.3); padding: 10px; margin: 10px 0 0; overflow-x: auto;';
+                errorOverlay.append(dismiss, errorLocation, errorMessage, errorFrame);
                 document.body.appendChild(errorOverlay);
 
-                ws.onmessage = (event) => {{
-                    try {{
+                const hideError = () => { errorOverlay.style.display = 'none'; };
+                dismiss.onclick = hideError;
+                document.addEventListener('keydown', (e) => {
+                    if (e.key === 'Escape') hideError();
+                });
+                const showError = (error) => {
+                    let location = error.file || 'Build Error';
+                    if (error.file && error.line) {
+                        location += `:${error.line}` + (error.column ? `:${error.column}` : '');
+                    }
+                    errorLocation.textContent = location;
+                    errorMessage.textContent = error.message;
+                    errorFrame.textContent = error.frame || '';
+                    errorFrame.style.display = error.frame ? 'block' : 'none';
+                    errorOverlay.style.display = 'block';
+                };
+
+                // Analyzer findings for this page as of its last build,
+                // collapsed to a count until clicked
+                const findingsBox = document.createElement('div');
+                findingsBox.style.cssText = 'display: none; position: fixed; bottom: 12px; left: 12px; z-index: 9998; max-width: 50vw; font: 12px sans-serif;';
+                const findingsList = document.createElement('ul');
+                findingsList.style.cssText = `
+                    display: none;
+                    margin: 0 0 6px;
+                    padding: 8px 12px 8px 28px;
+                    max-height: 40vh;
+                    overflow-y: auto;
+                    background: rgba(30, 30, 30, 0.92);
+                    color: white;
+                    border-radius: 8px;
+                `;
+                const findingsToggle = document.createElement('button');
+                findingsToggle.title = 'Show analyzer findings for this page';
+                findingsToggle.style.cssText = 'padding: 6px 10px; background: rgba(30, 30, 30, 0.88); color: white; border: none; border-radius: 16px; cursor: pointer;';
+                findingsBox.append(findingsList, findingsToggle);
+                document.body.appendChild(findingsBox);
+
+                // Stays open across the reloads that follow each save
+                const findingsOpenKey = 'eldroid-ssg-findings-open';
+                const setFindingsOpen = (open) => {
+                    findingsList.style.display = open ? 'block' : 'none';
+                    sessionStorage.setItem(findingsOpenKey, open ? '1' : '');
+                };
+                findingsToggle.onclick = () => setFindingsOpen(findingsList.style.display === 'none');
+                const levelColors = { error: '#ff6b6b', warning: '#f5a623', note: '#8ab4f8' };
+                const showFindings = (findings) => {
+                    findingsBox.style.display = findings.length ? 'block' : 'none';
+                    const errors = findings.filter(f => f.level === 'error').length;
+                    findingsToggle.textContent = `${errors ? '\u26D4' : '\u26A0'} ${findings.length} finding${findings.length === 1 ? '' : 's'}`;
+                    findingsList.replaceChildren(...findings.map(f => {
+                        const item = document.createElement('li');
+                        item.style.cssText = `color: ${levelColors[f.level] || 'white'}; margin-bottom: 4px;`;
+                        const rule = document.createElement('strong');
+                        rule.textContent = `${f.rule}: `;
+                        const message = document.createElement('span');
+                        message.textContent = f.message;
+                        message.style.color = 'white';
+                        item.append(rule, message);
+                        return item;
+                    }));
+                    setFindingsOpen(sessionStorage.getItem(findingsOpenKey) === '1');
+                };
+
+                // Review presence: who else has the site open, and pings
+                // asking everyone to scroll to a section
+                const nameKey = 'eldroid-ssg-reviewer';
+                const hello = () => ws.send(JSON.stringify({
+                    type: 'hello', name: localStorage.getItem(nameKey), path: location.pathname,
+                }));
+                ws.onopen = hello;
+
+                const presence = document.createElement('div');
+                presence.style.cssText = `
+                    display: none;
+                    align-items: center;
+                    gap: 6px;
+                    max-width: 60vw;
+                    padding: 6px 10px;
+                    background: rgba(30, 30, 30, 0.88);
+                    color: white;
+                    font: 12px sans-serif;
+                    border-radius: 16px;
+                `;
+                const reviewerList = document.createElement('span');
+                const presenceButton = (label, title, onclick) => {
+                    const button = document.createElement('button');
+                    button.textContent = label;
+                    button.title = title;
+                    button.onclick = onclick;
+                    button.style.cssText = 'background: none; border: none; color: white; cursor: pointer; padding: 0 2px;';
+                    return button;
+                };
+                const pingStatus = document.createElement('div');
+                pingStatus.style.cssText = 'display: none; margin-bottom: 4px;';
+                const presenceBox = document.createElement('div');
+                presenceBox.style.cssText = 'position: fixed; bottom: 12px; right: 12px; z-index: 9998; text-align: right; font: 12px sans-serif;';
+                presenceBox.append(pingStatus, presence);
+                document.body.appendChild(presenceBox);
+
+                const headings = () => Array.from(document.querySelectorAll('h1, h2, h3, h4, h5, h6'));
+                const sectionOf = (heading) => heading.id || '#' + headings().indexOf(heading);
+                const findSection = (section) => section.startsWith('#')
+                    ? headings()[Number(section.slice(1))]
+                    : document.getElementById(section);
+                // The last heading scrolled past, or the first one
+                const currentHeading = () => {
+                    const all = headings();
+                    return all.filter(h => h.getBoundingClientRect().top <= 80).pop() || all[0];
+                };
+
+                let pingTimer;
+                const showPing = (text, href) => {
+                    pingStatus.replaceChildren();
+                    const note = document.createElement(href ? 'a' : 'span');
+                    note.textContent = text;
+                    if (href) note.href = href;
+                    note.style.cssText = 'display: inline-block; padding: 6px 10px; background: rgba(30, 30, 30, 0.88); color: white; border-radius: 16px;';
+                    pingStatus.appendChild(note);
+                    pingStatus.style.display = 'block';
+                    clearTimeout(pingTimer);
+                    pingTimer = setTimeout(() => { pingStatus.style.display = 'none'; }, 6000);
+                };
+                // Only links to this site's own pages, whatever the server let through
+                const isSitePath = (path) => typeof path === 'string' && path.startsWith('/') && !path.startsWith('//') && !path.includes('\\');
+                const receivePing = (ping) => {
+                    if (!isSitePath(ping.path)) return;
+                    const target = ping.path === location.pathname && findSection(ping.section);
+                    if (target) {
+                        target.scrollIntoView({ behavior: 'smooth', block: 'start' });
+                        const outline = target.style.outline;
+                        target.style.outline = '3px solid #f5a623';
+                        setTimeout(() => { target.style.outline = outline; }, 2000);
+                        showPing(`${ping.name} pointed you here`);
+                    } else {
+                        const anchor = ping.section.startsWith('#') ? '' : '#' + encodeURIComponent(ping.section);
+                        showPing(`${ping.name} is looking at ${ping.label || ping.path} \u2192`, ping.path + anchor);
+                    }
+                };
+
+                const pingButton = presenceButton('\u{1F4CD}', 'Ask the other reviewers to scroll to this section', () => {
+                    const heading = currentHeading();
+                    ws.send(JSON.stringify({
+                        type: 'ping',
+                        path: location.pathname,
+                        section: heading ? sectionOf(heading) : '#0',
+                        label: heading ? heading.textContent.trim() : document.title,
+                    }));
+                    showPing(heading ? `Pinged: ${heading.textContent.trim()}` : 'Pinged this page');
+                });
+                const renameButton = presenceButton('\u270E', 'Change the name other reviewers see', () => {
+                    const name = prompt('Your name for other reviewers', localStorage.getItem(nameKey) || '');
+                    if (name === null) return;
+                    localStorage.setItem(nameKey, name);
+                    hello();
+                });
+                presence.append(reviewerList, pingButton, renameButton);
+
+                // Only shown while someone else is connected
+                const showPresence = (you, reviewers) => {
+                    const others = reviewers.filter(r => r.id !== you);
+                    presence.style.display = others.length ? 'flex' : 'none';
+                    reviewerList.replaceChildren(...others.map(r => {
+                        const reviewer = document.createElement('span');
+                        const here = r.path === location.pathname;
+                        reviewer.textContent = (here ? '\u25CF ' : '\u25CB ') + r.name;
+                        reviewer.title = here ? `${r.name} is on this page` : `${r.name} is on ${r.path}`;
+                        reviewer.style.marginRight = '6px';
+                        return reviewer;
+                    }));
+                };
+
+                ws.onmessage = (event) => {
+                    try {
                         const data = JSON.parse(event.data);
                         
-                        if (data.type === 'css') {{
+                        if (data.type === 'css') {
                             // Handle CSS hot reload
                             const links = document.querySelectorAll('link[rel="stylesheet"]');
-                            links.forEach(link => {{
-                                if (link.href.includes(data.path)) {{
+                            links.forEach(link => {
+                                if (link.href.includes(data.path)) {
                                     const newHref = link.href.split('?')[0] + '?t=' + Date.now();
                                     link.href = newHref;
-                                }}
-                            }});
-                        }} else if (data.type === 'error') {{
-                            // Show error overlay
-                            errorOverlay.textContent = data.message;
-                            errorOverlay.style.display = 'block';
-                            setTimeout(() => {{
-                                errorOverlay.style.display = 'none';
-                            }}, 5000);
-                        }} else if (event.data === 'reload') {{
+                                }
+                            });
+                        } else if (data.type === 'pages') {
+                            // A rebuild succeeded, so any earlier error is fixed
+                            hideError();
+                            // Reload only if this page was rebuilt
+                            const path = location.pathname;
+                            const served = [path, path + '.html', path.replace(/\/?$/, '/') + 'index.html'];
+                            if (served.some(p => data.paths.includes(p))) {
+                                window.location.reload();
+                            }
+                        } else if (data.type === 'error') {
+                            showError(data.error);
+                        } else if (data.type === 'findings') {
+                            showFindings(data.findings);
+                        } else if (data.type === 'presence') {
+                            showPresence(data.you, data.reviewers);
+                        } else if (data.type === 'ping') {
+                            receivePing(data.ping);
+                        } else if (event.data === 'reload') {
                             window.location.reload();
-                        }}
-                    }} catch (e) {{
-                        if (event.data === 'reload') {{
+                        }
+                    } catch (e) {
+                        if (event.data === 'reload') {
                             window.location.reload();
-                        }}
-                    }}
-                }};
+                        }
+                    }
+                };
 
-                ws.onclose = () => {{
-                    setTimeout(() => {{
+                ws.onclose = () => {
+                    setTimeout(() => {
                         window.location.reload();
-                    }}, 1000);
-                }};
-            }})();
-            </script>"#,
-            ws_port
-        );
+                    }, 1000);
+                };
+            })();
+            </script>"#;
 
         if let Some(body_end) = html.rfind("</body>") {
             format!("{}{}{}", &html[..body_end], hot_reload_script, &html[body_end..])
@@ -145,11 +493,39 @@ impl HtmlGenerator {
 }
 
 pub fn generate_html_with_seo(content: &str, site_seo: &SEOConfig, html_gen: &HtmlGenerator) -> String {
+    generate_localized_html_with_seo(content, site_seo, html_gen, &[], None)
+}
+
+/// Like [`generate_html_with_seo`], additionally linking the page's
+/// translations and dating it by `dates` where it has no dates of its own.
+pub fn generate_localized_html_with_seo(
+    content: &str,
+    site_seo: &SEOConfig,
+    html_gen: &HtmlGenerator,
+    alternates: &[AlternateLink],
+    dates: Option<FileDates>,
+) -> String {
     let html = html_gen.generate(content);
-    if let Some(page_seo) = crate::seo::parse_page_seo(&html) {
-        update_seo_tags(&html, &page_seo, site_seo, Path::new(""))
+    update_page_seo(&html, parse_page_seo(&html), site_seo, alternates, dates)
+}
+
+/// Replace the SEO tags of rendered `html` with those for `page_seo`, or for
+/// the site defaults when the page has none.
+pub fn update_page_seo(
+    html: &str,
+    page_seo: Option<PageSEO>,
+    site_seo: &SEOConfig,
+    alternates: &[AlternateLink],
+    dates: Option<FileDates>,
+) -> String {
+    if let Some(mut page_seo) = page_seo {
+        page_seo.alternates = alternates.to_vec();
+        if let Some(dates) = dates {
+            dates.apply(&mut page_seo);
+        }
+        update_seo_tags(html, &page_seo, site_seo, Path::new(""))
     } else {
-        let default_page_seo = PageSEO {
+        let mut default_page_seo = PageSEO {
             title: site_seo.site_name.clone(),
             description: Some(site_seo.default_description.clone()),
             keywords: Some(site_seo.default_keywords.clone()),
@@ -158,6 +534,9 @@ pub fn generate_html_with_seo(content: &str, site_seo: &SEOConfig, html_gen: &Ht
             path: "".to_string(),
             image: None,
             author: None,
+            author_url: None,
+            author_image: None,
+            co_authors: Vec::new(),
             published_date: None,
             last_modified: None,
             category: None,
@@ -166,129 +545,77 @@ pub fn generate_html_with_seo(content: &str, site_seo: &SEOConfig, html_gen: &Ht
             structured_data: None,
             change_frequency: None,
             priority: None,
+            alternates: alternates.to_vec(),
+            robots: None,
+            section_titles: Default::default(),
         };
-        update_seo_tags(&html, &default_page_seo, site_seo, Path::new(""))
+        if let Some(dates) = dates {
+            dates.apply(&mut default_page_seo);
+        }
+        update_seo_tags(html, &default_page_seo, site_seo, Path::new(""))
     }
 }
 
+/// Selectors for the tags [`generate_head_tags`] manages; existing ones are
+/// replaced so the page ends up with exactly one set.
+const MANAGED_TAG_SELECTORS: &[&str] = &[
+    "title",
+    "meta[name='description']",
+    "meta[name='keywords']",
+    "meta[name='robots']",
+    "link[rel='canonical']",
+    "link[rel='alternate'][hreflang]",
+    "meta[property^='og:']",
+    "meta[property^='article:']",
+    "meta[name^='twitter:']",
+];
+
+/// Replace the page's SEO tags with generated ones. Layouts that place
+/// `@{og_tags()}`, `@{json_ld()}`, or `@{analytics()}` themselves get that
+/// markup where the macro is instead of at the end of `<head>`.
 pub fn update_seo_tags(html_str: &str, page_seo: &PageSEO, site_seo: &SEOConfig, file_path: &Path) -> String {
-    let mut document = Html::parse_document(html_str);
+    let placed = seo_macros_used(html_str);
+    let is_placed = |name: &str| placed.iter().any(|used| used == name);
+    let mut document = Html::parse_document(&protect_seo_macros(html_str));
     let head_selector = Selector::parse("head").unwrap();
-    let title_selector = Selector::parse("title").unwrap();
-    let meta_desc_selector = Selector::parse("meta[name='description']").unwrap();
-    let canonical_selector = Selector::parse("link[rel='canonical']").unwrap();
-
-    if let Some(head) = document.select(&head_selector).next() {
-        let head_id = head.id();
-
-        // Update title
-        let title = format!("{} | {}", &page_seo.title, site_seo.site_name);
-        let title_html = format!("<head><title>{}</title></head>", title);
-        let title_frag = Html::parse_fragment(&title_html);
-        
-        // Remove existing title and add new one
-        {
-            let existing_title_id = document.select(&title_selector)
-                .next()
-                .map(|el| el.id());
-            
-            if let Some(id) = existing_title_id {
-                document.tree.get_mut(id).unwrap().detach();
-            }
-
-            if let Some(title_elem) = title_frag.select(&Selector::parse("title").unwrap()).next() {
-                document.tree.get_mut(head_id).unwrap()
-                    .append(Node::Element(title_elem.value().clone()));
-            }
-        }
 
-        // Update description
-        if let Some(description) = &page_seo.description {
-            let desc_html = format!("<head><meta name=\"description\" content=\"{}\"></head>", description);
-            let desc_frag = Html::parse_fragment(&desc_html);
-            
-            let existing_desc_id = document.select(&meta_desc_selector)
-                .next()
-                .map(|el| el.id());
-            
-            if let Some(id) = existing_desc_id {
-                document.tree.get_mut(id).unwrap().detach();
-            }
-            
-            if let Some(meta_elem) = desc_frag.select(&Selector::parse("meta").unwrap()).next() {
-                document.tree.get_mut(head_id).unwrap()
-                    .append(Node::Element(meta_elem.value().clone()));
-            }
-        }
+    let Some(head) = document.select(&head_selector).next() else {
+        warn!("No <head> tag found in {}", file_path.display());
+        return expand_protected_seo_macros(&document.html(), page_seo, site_seo);
+    };
 
-        // Update canonical URL
-        if let Some(canonical_url) = &page_seo.canonical_url {
-            let canonical_html = format!("<head><link rel=\"canonical\" href=\"{}\"></head>", canonical_url);
-            let canonical_frag = Html::parse_fragment(&canonical_html);
-            
-            let existing_canonical_id = document.select(&canonical_selector)
-                .next()
-                .map(|el| el.id());
-            
-            if let Some(id) = existing_canonical_id {
-                document.tree.get_mut(id).unwrap().detach();
-            }
-            
-            if let Some(link_elem) = canonical_frag.select(&Selector::parse("link").unwrap()).next() {
-                document.tree.get_mut(head_id).unwrap()
-                    .append(Node::Element(link_elem.value().clone()));
-            }
-        }
+    // Structured data the page embeds itself takes precedence over ours
+    let has_json_ld = head.select(&Selector::parse("script[type='application/ld+json']").unwrap())
+        .next()
+        .is_some();
 
-        // Update Open Graph tags
-        let og_tags = vec![
-            ("og:title".to_string(), page_seo.title.clone()),
-            ("og:description".to_string(), page_seo.description.clone().unwrap_or_else(|| site_seo.default_description.clone())),
-            ("og:type".to_string(), "website".to_string()),
-            ("og:url".to_string(), page_seo.url.clone().unwrap_or_default()),
-            ("og:site_name".to_string(), site_seo.site_name.clone()),
-        ];
-
-        for (property, content) in og_tags {
-            let meta_html = format!("<head><meta property=\"{}\" content=\"{}\"></head>", property, content);
-            let meta_frag = Html::parse_fragment(&meta_html);
-            if let Some(meta_elem) = meta_frag.select(&Selector::parse("meta").unwrap()).next() {
-                document.tree.get_mut(head_id).unwrap()
-                    .append(Node::Element(meta_elem.value().clone()));
-            }
-        }
+    // Remove existing managed tags before adding the generated ones
+    let mut existing = Vec::new();
+    for selector in MANAGED_TAG_SELECTORS {
+        let selector = Selector::parse(selector).unwrap();
+        existing.extend(head.select(&selector).map(|el| el.id()));
+    }
+    for id in existing {
+        document.tree.get_mut(id).unwrap().detach();
+    }
 
-        // Update keywords if available
-        if let Some(keywords) = &page_seo.keywords {
-            let keywords_html = format!("<head><meta name=\"keywords\" content=\"{}\"></head>", keywords.join(", "));
-            let keywords_frag = Html::parse_fragment(&keywords_html);
-            if let Some(meta_elem) = keywords_frag.select(&Selector::parse("meta").unwrap()).next() {
-                document.tree.get_mut(head_id).unwrap()
-                    .append(Node::Element(meta_elem.value().clone()));
-            }
-        }
+    let mut tags = String::new();
+    if !is_placed("og_tags") {
+        tags.push_str(&generate_head_tags(page_seo, site_seo));
+    }
+    if !has_json_ld && !is_placed("json_ld") {
+        tags.push_str(&generate_json_ld_script(page_seo, site_seo));
+    }
 
-        // Add Google Analytics if configured
-        if let Some(ga_id) = &site_seo.google_site_verification {
-            let script_html = format!(
-                "<head><script async src=\"https://www.googletagmanager.com/gtag/js?id={}\"></script>\
-                <script>\
-                window.dataLayer = window.dataLayer || [];\
-                function gtag(){{dataLayer.push(arguments);}}\
-                gtag('js', new Date());\
-                gtag('config', '{}');\
-                </script></head>",
-                ga_id, ga_id
-            );
-            let script_frag = Html::parse_fragment(&script_html);
-            for script_elem in script_frag.select(&Selector::parse("script").unwrap()) {
-                document.tree.get_mut(head_id).unwrap()
-                    .append(Node::Element(script_elem.value().clone()));
-            }
-        }
-    } else {
-        warn!("No <head> tag found in {}", file_path.display());
+    // Add Google Analytics if configured
+    if !is_placed("analytics") {
+        tags.push_str(&analytics_script(site_seo));
     }
 
-    document.html()
-}
\ No newline at end of file
+    let html = expand_protected_seo_macros(&document.html(), page_seo, site_seo);
+    if tags.is_empty() {
+        html
+    } else {
+        inject_meta_tags(&html, &tags)
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use anyhow::Result;
+use html_escape::encode_double_quoted_attribute as attr;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use crate::seo_types::AlternateLink;
+use crate::slug::Slugs;
+
+lazy_static! {
+    static ref T_REGEX: Regex = Regex::new(r#"@\{t\(["']([^"']+)["']\)\}"#).unwrap();
+}
+
+/// `[i18n]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct I18nConfig {
+    pub default_language: String,
+    pub languages: Vec<String>,
+    /// Directory holding one `<lang>.toml` file of UI strings per language
+    #[serde(default = "default_translations_dir")]
+    pub translations_dir: PathBuf,
+}
+
+fn default_translations_dir() -> PathBuf {
+    PathBuf::from("i18n")
+}
+
+/// A content path split into its language and language-neutral location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalizedPath {
+    pub language: String,
+    pub path: PathBuf,
+}
+
+impl I18nConfig {
+    /// Detect the language of a content file from either a leading language
+    /// directory (`fr/blog/post.md`) or a language suffix (`blog/post.fr.md`).
+    pub fn localize(&self, relative_path: &Path) -> LocalizedPath {
+        let mut components = relative_path.components();
+        if let Some(Component::Normal(first)) = components.next() {
+            let first = first.to_string_lossy();
+            if self.languages.iter().any(|lang| *lang == first) && components.clone().next().is_some() {
+                return LocalizedPath {
+                    language: first.to_string(),
+                    path: components.as_path().to_path_buf(),
+                };
+            }
+        }
+
+        let stem = relative_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        if let Some((base, lang)) = stem.rsplit_once('.') {
+            if self.languages.iter().any(|l| l == lang) {
+                let mut file_name = base.to_string();
+                if let Some(ext) = relative_path.extension() {
+                    file_name.push('.');
+                    file_name.push_str(&ext.to_string_lossy());
+                }
+                return LocalizedPath {
+                    language: lang.to_string(),
+                    path: relative_path.with_file_name(file_name),
+                };
+            }
+        }
+
+        LocalizedPath {
+            language: self.default_language.clone(),
+            path: relative_path.to_path_buf(),
+        }
+    }
+
+    /// Output location relative to the output directory. The default
+    /// language is written at the root, other languages under `<lang>/`.
+    pub fn output_path(&self, localized: &LocalizedPath) -> PathBuf {
+        if localized.language == self.default_language {
+            localized.path.clone()
+        } else {
+            Path::new(&localized.language).join(&localized.path)
+        }
+    }
+
+    /// Directories of a content section to scan for posts, one per language.
+    pub fn section_dirs(&self, section: &Path) -> Vec<PathBuf> {
+        let mut dirs = vec![section.to_path_buf()];
+        dirs.extend(self.languages.iter().map(|lang| Path::new(lang).join(section)));
+        dirs
+    }
+}
+
+/// UI strings for every configured language, looked up by `@{t("key")}`.
+#[derive(Debug, Default)]
+pub struct Translations {
+    strings: HashMap<String, HashMap<String, String>>,
+    default_language: String,
+}
+
+impl Translations {
+    pub fn load(config: &I18nConfig) -> Result<Self> {
+        let mut strings = HashMap::new();
+        for lang in &config.languages {
+            let path = config.translations_dir.join(format!("{}.toml", lang));
+            if !path.exists() {
+                log::warn!("No translation file for '{}' at {}", lang, path.display());
+                continue;
+            }
+            let table: toml::Table = toml::from_str(&fs::read_to_string(&path)?)?;
+            let mut flat = HashMap::new();
+            flatten_table("", &table, &mut flat);
+            strings.insert(lang.clone(), flat);
+        }
+        Ok(Self {
+            strings,
+            default_language: config.default_language.clone(),
+        })
+    }
+
+    pub fn get(&self, language: &str, key: &str) -> Option<&str> {
+        self.strings.get(language)
+            .and_then(|s| s.get(key))
+            .or_else(|| self.strings.get(&self.default_language).and_then(|s| s.get(key)))
+            .map(String::as_str)
+    }
+
+    pub fn substitute(&self, content: &str, language: &str) -> String {
+        T_REGEX.replace_all(content, |caps: &regex::Captures| {
+            let key = &caps[1];
+            match self.get(language, key) {
+                Some(value) => value.to_string(),
+                None => {
+                    log::warn!("Translation '{}' not found for language '{}'", key, language);
+                    key.to_string()
+                }
+            }
+        }).to_string()
+    }
+}
+
+fn flatten_table(prefix: &str, table: &toml::Table, out: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let full_key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(nested) => flatten_table(&full_key, nested, out),
+            toml::Value::String(s) => { out.insert(full_key, s.clone()); },
+            other => { out.insert(full_key, other.to_string()); },
+        }
+    }
+}
+
+/// Maps each language-neutral page to its translations so pages can link
+/// to their alternates with `hreflang`.
+#[derive(Debug, Default)]
+pub struct TranslationIndex {
+    pages: HashMap<PathBuf, Vec<(String, PathBuf)>>,
+    default_language: String,
+    slugs: Slugs,
+}
+
+impl TranslationIndex {
+    pub fn build(config: &I18nConfig, files: &[PathBuf], input_dir: &Path, slugs: Slugs) -> Self {
+        let mut pages: HashMap<PathBuf, Vec<(String, PathBuf)>> = HashMap::new();
+        for file in files {
+            let Ok(relative) = file.strip_prefix(input_dir) else { continue };
+            let localized = config.localize(relative);
+            let output = output_page_path(&config.output_path(&localized), slugs);
+            pages.entry(output_page_path(&localized.path, slugs))
+                .or_default()
+                .push((localized.language, output));
+        }
+        for variants in pages.values_mut() {
+            variants.sort();
+        }
+        Self {
+            pages,
+            default_language: config.default_language.clone(),
+            slugs,
+        }
+    }
+
+    /// Alternate links for a page, including an `x-default` entry. Empty when
+    /// the page exists in a single language.
+    pub fn alternates(&self, localized: &LocalizedPath, base_url: &str) -> Vec<AlternateLink> {
+        let variants = match self.pages.get(&output_page_path(&localized.path, self.slugs)) {
+            Some(variants) if variants.len() > 1 => variants,
+            _ => return Vec::new(),
+        };
+
+        let href = |path: &Path| format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            path.to_string_lossy().replace('\\', "/")
+        );
+
+        let mut links: Vec<AlternateLink> = variants.iter()
+            .map(|(lang, path)| AlternateLink { hreflang: lang.clone(), href: href(path) })
+            .collect();
+        if let Some((_, path)) = variants.iter().find(|(lang, _)| *lang == self.default_language) {
+            links.push(AlternateLink { hreflang: "x-default".to_string(), href: href(path) });
+        }
+        links
+    }
+}
+
+/// Markdown sources are published as `.html`, and pages under slugs of
+/// their paths when `slugs` says so.
+pub(crate) fn output_page_path(path: &Path, slugs: Slugs) -> PathBuf {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("md") => slugs.page_path(&path.with_extension("html")),
+        Some("html") => slugs.page_path(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `<link rel="alternate">` tags for pages that don't go through `update_seo_tags`.
+pub fn alternate_link_tags(alternates: &[AlternateLink]) -> String {
+    alternates.iter()
+        .map(|alt| format!(
+            "<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\">",
+            attr(&alt.hreflang), attr(&alt.href)
+        ))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> I18nConfig {
+        I18nConfig {
+            default_language: "en".to_string(),
+            languages: vec!["en".to_string(), "fr".to_string()],
+            translations_dir: PathBuf::from("i18n"),
+        }
+    }
+
+    #[test]
+    fn test_localize_language_directory() {
+        let localized = config().localize(Path::new("fr/blog/post.md"));
+        assert_eq!(localized.language, "fr");
+        assert_eq!(localized.path, PathBuf::from("blog/post.md"));
+        assert_eq!(config().output_path(&localized), PathBuf::from("fr/blog/post.md"));
+    }
+
+    #[test]
+    fn test_localize_language_suffix() {
+        let localized = config().localize(Path::new("blog/post.fr.md"));
+        assert_eq!(localized.language, "fr");
+        assert_eq!(localized.path, PathBuf::from("blog/post.md"));
+
+        let default = config().localize(Path::new("blog/post.md"));
+        assert_eq!(default.language, "en");
+        assert_eq!(config().output_path(&default), PathBuf::from("blog/post.md"));
+    }
+
+    #[test]
+    fn test_alternates_link_translations() {
+        let files = vec![
+            PathBuf::from("content/about.html"),
+            PathBuf::from("content/fr/about.html"),
+            PathBuf::from("content/contact.html"),
+        ];
+        let index = TranslationIndex::build(&config(), &files, Path::new("content"), Slugs::default());
+
+        let about = config().localize(Path::new("fr/about.html"));
+        let links = index.alternates(&about, "https://example.com/");
+        assert_eq!(links.len(), 3);
+        assert!(links.contains(&AlternateLink {
+            hreflang: "fr".to_string(),
+            href: "https://example.com/fr/about.html".to_string(),
+        }));
+        assert!(links.contains(&AlternateLink {
+            hreflang: "x-default".to_string(),
+            href: "https://example.com/about.html".to_string(),
+        }));
+
+        let contact = config().localize(Path::new("contact.html"));
+        assert!(index.alternates(&contact, "https://example.com").is_empty());
+    }
+
+    #[test]
+    fn test_alternate_link_tags_escape_their_attributes() {
+        let alternates = [
+            AlternateLink { hreflang: "fr".to_string(), href: "https://example.com/fr/?a=1&b=\"2\"".to_string() },
+            AlternateLink { hreflang: "x-default\"><script>".to_string(), href: "/".to_string() },
+        ];
+        assert_eq!(
+            alternate_link_tags(&alternates),
+            "<link rel=\"alternate\" hreflang=\"fr\" href=\"https://example.com/fr/?a=1&amp;b=&quot;2&quot;\">\n\
+             <link rel=\"alternate\" hreflang=\"x-default&quot;&gt;&lt;script&gt;\" href=\"/\">",
+        );
+    }
+}
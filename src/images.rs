@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+/// Largest width or height worth publishing when the page doesn't say how
+/// big the image is shown.
+pub const MAX_DIMENSION: u32 = 2000;
+
+/// Pixels per CSS pixel on high-density screens, the most an image needs.
+const MAX_DENSITY: u32 = 2;
+
+/// Roughly how much smaller a photo is as a lossy JPEG or WebP than as a PNG.
+const PNG_PHOTO_SAVINGS: f64 = 0.7;
+
+/// Roughly how much smaller a WebP is than the same JPEG or PNG.
+const WEBP_SAVINGS: f64 = 0.25;
+
+/// Pixels sampled along each side when telling photos from graphics.
+const SAMPLE_GRID: u32 = 64;
+
+/// An image file measured on disk.
+#[derive(Debug, Clone)]
+pub struct ImageFile {
+    pub path: PathBuf,
+    /// Size in bytes
+    pub bytes: u64,
+    /// Width and height in pixels, when the format can be read
+    pub dimensions: Option<(u32, u32)>,
+    pub format: Option<ImageFormat>,
+    /// An opaque PNG with photographic detail, which compresses far better lossily
+    pub png_photo: bool,
+}
+
+/// How to make an image lighter, and by roughly how much.
+#[derive(Debug, Default)]
+pub struct ImageAdvice {
+    pub issues: Vec<String>,
+    /// Estimated bytes saved by following every piece of advice
+    pub savings: u64,
+}
+
+impl ImageFile {
+    /// Measure the image at `path`, or `None` when there is no such file.
+    pub fn inspect(path: &Path) -> Option<Self> {
+        let bytes = fs::metadata(path).ok().filter(|metadata| metadata.is_file())?.len();
+        let format = ImageFormat::from_path(path).ok();
+        let dimensions = image::image_dimensions(path).ok();
+        let png_photo = format == Some(ImageFormat::Png) && image::open(path).is_ok_and(|img| is_photo(&img));
+        Some(Self { path: path.to_path_buf(), bytes, dimensions, format, png_photo })
+    }
+
+    /// A WebP version next to the file, like `hero.webp` for `hero.jpg`.
+    pub fn webp_copy(&self) -> Option<PathBuf> {
+        let webp = self.path.with_extension("webp");
+        (webp != self.path && webp.is_file()).then_some(webp)
+    }
+
+    /// Advice for the image shown `display_width` CSS pixels wide, when the
+    /// page says, and where the page may already offer a WebP in its place.
+    pub fn advise(&self, display_width: Option<u32>, serves_webp: bool) -> ImageAdvice {
+        let mut advice = ImageAdvice::default();
+        let mut remaining = self.bytes as f64;
+
+        if let Some((width, height)) = self.dimensions {
+            let (scale, reason) = match display_width.filter(|shown| *shown > 0) {
+                Some(shown) => (
+                    shown.saturating_mul(MAX_DENSITY) as f64 / width as f64,
+                    format!("shown {} pixels wide", shown),
+                ),
+                None => (
+                    MAX_DIMENSION as f64 / width.max(height) as f64,
+                    format!("larger than {} pixels", MAX_DIMENSION),
+                ),
+            };
+            if scale < 1.0 {
+                let resized = remaining * scale * scale;
+                advice.issues.push(format!(
+                    "{} is {}x{} but {}; resize it to {}x{} to save about {}",
+                    self.path.display(), width, height, reason,
+                    (width as f64 * scale).round(), (height as f64 * scale).round(), kilobytes(remaining - resized)
+                ));
+                remaining = resized;
+            }
+        }
+
+        if self.png_photo {
+            let saved = remaining * PNG_PHOTO_SAVINGS;
+            advice.issues.push(format!(
+                "{} is a photo saved as PNG; as JPEG or WebP it would be about {} smaller",
+                self.path.display(), kilobytes(saved)
+            ));
+            remaining -= saved;
+        } else if !serves_webp && matches!(self.format, Some(ImageFormat::Jpeg | ImageFormat::Png)) {
+            let saved = remaining * WEBP_SAVINGS;
+            let fix = match self.webp_copy() {
+                Some(_) => "offer its WebP copy with <picture>",
+                None => "add a WebP version",
+            };
+            advice.issues.push(format!("{} has no WebP alternative; {} to save about {}", self.path.display(), fix, kilobytes(saved)));
+            remaining -= saved;
+        }
+
+        advice.savings = self.bytes.saturating_sub(remaining.round() as u64);
+        advice
+    }
+}
+
+/// Whether the image is opaque with most sampled pixels a different color,
+/// as photos are and screenshots, logos, and diagrams aren't.
+fn is_photo(img: &DynamicImage) -> bool {
+    let (width, height) = img.dimensions();
+    if width < SAMPLE_GRID || height < SAMPLE_GRID {
+        return false;
+    }
+    let mut colors = HashSet::new();
+    for y in 0..SAMPLE_GRID {
+        for x in 0..SAMPLE_GRID {
+            let pixel = img.get_pixel(x * width / SAMPLE_GRID, y * height / SAMPLE_GRID);
+            if pixel[3] < u8::MAX {
+                return false;
+            }
+            colors.insert(pixel.0);
+        }
+    }
+    colors.len() > (SAMPLE_GRID * SAMPLE_GRID / 2) as usize
+}
+
+/// `bytes` in kilobytes, for messages.
+pub fn kilobytes(bytes: f64) -> String {
+    format!("{:.1} KB", bytes / 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    #[test]
+    fn test_images_are_measured_and_advised_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("photo.png");
+        RgbImage::from_fn(3000, 1500, |x, y| Rgb([(x * 7 % 256) as u8, (y * 13 % 256) as u8, ((x + y) % 256) as u8]))
+            .save(&photo).unwrap();
+        let logo = dir.path().join("logo.png");
+        RgbaImage::from_fn(200, 100, |x, _| if x < 100 { Rgba([0, 0, 0, 0]) } else { Rgba([200, 30, 30, 255]) })
+            .save(&logo).unwrap();
+
+        let photo = ImageFile::inspect(&photo).unwrap();
+        assert_eq!(photo.dimensions, Some((3000, 1500)));
+        assert!(photo.png_photo);
+        let advice = photo.advise(None, false);
+        assert_eq!(advice.issues.len(), 2);
+        assert!(advice.issues[0].contains("3000x1500 but larger than 2000 pixels; resize it to 2000x1000"));
+        assert!(advice.issues[1].contains("photo saved as PNG"));
+        let scale = 2000.0 / 3000.0;
+        let resized = photo.bytes as f64 * scale * scale;
+        assert_eq!(advice.savings, photo.bytes - (resized - resized * 0.7).round() as u64);
+
+        let logo = ImageFile::inspect(&logo).unwrap();
+        assert!(!logo.png_photo);
+        assert!(logo.advise(Some(100), true).issues.is_empty());
+        let advice = logo.advise(Some(50), false);
+        assert!(advice.issues[0].contains("shown 50 pixels wide; resize it to 100x50"));
+        assert!(advice.issues[1].contains("add a WebP version"));
+
+        fs::write(dir.path().join("logo.webp"), b"").unwrap();
+        assert!(logo.advise(None, false).issues[0].contains("offer its WebP copy with <picture>"));
+        assert!(ImageFile::inspect(&dir.path().join("missing.png")).is_none());
+    }
+}
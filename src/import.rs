@@ -0,0 +1,406 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+use roxmltree::{Document, Node, ParsingOptions};
+use scraper::{ElementRef, Html, Selector};
+use serde_yaml::{Mapping, Value};
+use crate::slug::Slugs;
+
+lazy_static! {
+    // Page furniture that isn't part of an article
+    static ref CHROME_REGEXES: Vec<Regex> = ["script", "style", "noscript", "nav", "aside", "form", "footer", "header", "iframe"]
+        .iter()
+        .map(|tag| Regex::new(&format!(r"(?is)<{0}\b.*?</{0}\s*>", tag)).unwrap())
+        .collect();
+    static ref WHITESPACE_REGEX: Regex = Regex::new(r"\s+").unwrap();
+    // RSS <author> is an email address, optionally followed by a name
+    static ref RSS_AUTHOR_REGEX: Regex = Regex::new(r"^\S+@\S+\s+\((.+)\)$").unwrap();
+    static ref LEADING_H1_REGEX: Regex = Regex::new(r"(?is)^\s*<h1\b[^>]*>(.*?)</h1\s*>").unwrap();
+}
+
+/// Elements that hold the article on common blogging platforms, best first.
+const ARTICLE_SELECTORS: &[&str] = &[
+    "[itemprop=articleBody]",
+    ".entry-content",
+    ".post-content",
+    ".article-content",
+    "article section",
+    "article",
+    "main",
+];
+
+/// Longest description taken from an entry's summary.
+const DESCRIPTION_LENGTH: usize = 160;
+
+/// Settings for `import --from-feed`.
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Directory the Markdown files are written to
+    pub dir: PathBuf,
+    /// Download each entry's page and extract the article, for feeds that
+    /// only carry summaries
+    pub full_content: bool,
+    /// Replace files that already exist instead of skipping them
+    pub overwrite: bool,
+    /// Import at most this many entries, newest first as the feed lists them
+    pub limit: Option<usize>,
+    /// How file names are slugged
+    pub slugs: Slugs,
+}
+
+/// A post read from an RSS or Atom feed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FeedEntry {
+    pub title: String,
+    pub link: Option<String>,
+    pub date: Option<DateTime<FixedOffset>>,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+    /// Summary HTML
+    pub summary: Option<String>,
+    /// Full content HTML, when the feed has it
+    pub content: Option<String>,
+}
+
+impl FeedEntry {
+    /// File name stem: the last segment of the post's old URL, or its title.
+    pub fn slug(&self, slugs: Slugs) -> String {
+        let from_link = self.link.as_deref()
+            .and_then(|link| url::Url::parse(link).ok())
+            .and_then(|url| url.path_segments()?.rev().find(|s| !s.is_empty()).map(String::from))
+            .map(|segment| slugs.slugify(segment.trim_end_matches(".html").trim_end_matches(".htm")))
+            .filter(|slug| !slug.is_empty() && slug.chars().any(|c| !c.is_ascii_digit()));
+        from_link.unwrap_or_else(|| slugs.slugify(&self.title))
+    }
+
+    /// The post as Markdown with YAML front matter, using `body` as its HTML.
+    pub fn to_markdown(&self, body: &str) -> Result<String> {
+        markdown_document(&self.front_matter(), &self.title, body)
+    }
+
+    /// Front matter for the post, with its old path as an alias.
+    pub fn front_matter(&self) -> Mapping {
+        let mut front_matter = Mapping::new();
+        front_matter.insert("title".into(), self.title.clone().into());
+        let date = self.date
+            .map(|date| date.to_rfc3339())
+            .unwrap_or_else(|| Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+        front_matter.insert("date".into(), date.into());
+        if let Some(author) = &self.author {
+            front_matter.insert("author".into(), author.clone().into());
+        }
+        if !self.tags.is_empty() {
+            front_matter.insert("tags".into(), self.tags.iter().cloned().map(Value::from).collect::<Vec<_>>().into());
+        }
+        if let Some(description) = self.summary.as_deref().map(plain_text).filter(|d| !d.is_empty()) {
+            front_matter.insert("description".into(), truncate(&description, DESCRIPTION_LENGTH).into());
+        }
+        // The old path keeps working if the site moves to this generator on the same domain
+        if let Some(path) = self.link.as_deref().and_then(|link| url::Url::parse(link).ok()).map(|url| url.path().to_string()) {
+            if path != "/" {
+                front_matter.insert("aliases".into(), vec![Value::from(path)].into());
+            }
+        }
+        front_matter
+    }
+}
+
+/// A Markdown file with `front_matter` and `body` HTML converted to Markdown.
+pub(crate) fn markdown_document(front_matter: &Mapping, title: &str, body: &str) -> Result<String> {
+    // Pages repeat the title above the article, and the layout shows it already
+    let body = strip_chrome(body);
+    let body = match LEADING_H1_REGEX.captures(&body) {
+        Some(caps) if plain_text(&caps[1]) == title => body[caps[0].len()..].to_string(),
+        _ => body,
+    };
+    let markdown = html2md::parse_html(&body);
+    Ok(format!("---\n{}---\n\n{}\n", serde_yaml::to_string(front_matter)?, markdown.trim()))
+}
+
+/// Entries of an RSS 2.0, RSS 1.0, or Atom feed, in feed order.
+pub fn parse_feed(xml: &str) -> Result<Vec<FeedEntry>> {
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(xml, options).context("Failed to parse feed")?;
+    let root = document.root_element();
+    let entries = match root.tag_name().name() {
+        "feed" => children(root, "entry").map(|entry| atom_entry(&document, entry)).collect(),
+        // RSS 2.0 nests items in <channel>, RSS 1.0 puts them next to it
+        "rss" | "RDF" => root.descendants()
+            .filter(|node| node.is_element() && node.tag_name().name() == "item")
+            .map(rss_item)
+            .collect(),
+        other => return Err(anyhow!("Not an RSS or Atom feed: the root element is <{}>", other)),
+    };
+    Ok(entries)
+}
+
+fn rss_item(item: Node) -> FeedEntry {
+    let mut entry = FeedEntry {
+        title: child_text(item, "title").unwrap_or_default(),
+        link: child_text(item, "link")
+            .or_else(|| children(item, "guid").find(|guid| guid.attribute("isPermaLink") != Some("false")).map(text)),
+        date: child_text(item, "pubDate").or_else(|| child_text(item, "date")).and_then(|date| parse_date(&date)),
+        author: child_text(item, "creator").or_else(|| child_text(item, "author").map(|author| {
+            RSS_AUTHOR_REGEX.captures(&author).map(|caps| caps[1].to_string()).unwrap_or(author)
+        })),
+        summary: child_text(item, "description"),
+        // content:encoded
+        content: child_text(item, "encoded"),
+        ..FeedEntry::default()
+    };
+    entry.tags = children(item, "category").map(text).filter(|tag| !tag.is_empty()).collect();
+    entry
+}
+
+fn atom_entry(document: &Document, entry: Node) -> FeedEntry {
+    let link = children(entry, "link")
+        .find(|link| link.attribute("rel").is_none_or(|rel| rel == "alternate"))
+        .and_then(|link| link.attribute("href"))
+        .map(String::from);
+    FeedEntry {
+        title: child_text(entry, "title").unwrap_or_default(),
+        link,
+        date: child_text(entry, "published").or_else(|| child_text(entry, "updated")).and_then(|date| parse_date(&date)),
+        author: children(entry, "author").next().and_then(|author| child_text(author, "name")),
+        tags: children(entry, "category").filter_map(|c| c.attribute("term")).map(String::from).collect(),
+        summary: children(entry, "summary").next().map(|node| atom_html(document, node)),
+        content: children(entry, "content").next().map(|node| atom_html(document, node)),
+    }
+}
+
+/// An Atom text construct as HTML.
+fn atom_html(document: &Document, node: Node) -> String {
+    match node.attribute("type") {
+        // Inline XHTML wrapped in a <div>, kept as written
+        Some("xhtml") => node.children()
+            .find(|child| child.is_element())
+            .map(|div| div.children().map(|child| &document.input_text()[child.range()]).collect())
+            .unwrap_or_default(),
+        Some("html") => text(node),
+        _ => format!("<p>{}</p>", html_escape::encode_text(&text(node))),
+    }
+}
+
+pub(crate) fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+pub(crate) fn child_text(node: Node, name: &'static str) -> Option<String> {
+    children(node, name).next().map(text).filter(|text| !text.is_empty())
+}
+
+/// All text inside `node`, CDATA included.
+pub(crate) fn text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+pub(crate) fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc2822(date.trim())
+        .or_else(|_| DateTime::parse_from_rfc3339(date.trim()))
+        .ok()
+}
+
+/// The article in a downloaded page: the first element matching a known
+/// content container, or else the element with the most paragraph text.
+pub fn extract_article(html: &str) -> Option<String> {
+    let document = Html::parse_document(&strip_chrome(html));
+    for selector in ARTICLE_SELECTORS {
+        let selector = Selector::parse(selector).unwrap();
+        if let Some(element) = document.select(&selector).find(|element| paragraph_text(element) > 200) {
+            return Some(element.inner_html());
+        }
+    }
+    let containers = Selector::parse("div, section, td").unwrap();
+    document.select(&containers)
+        .map(|element| (paragraph_text(&element), element))
+        .filter(|(score, _)| *score > 200)
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, element)| element.inner_html())
+}
+
+/// Length of the text in an element's own paragraphs.
+fn paragraph_text(element: &ElementRef) -> usize {
+    element.children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "p")
+        .map(|p| p.text().map(str::len).sum::<usize>())
+        .sum::<usize>()
+        + element.children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| matches!(child.value().name(), "section" | "article"))
+            .map(|child| paragraph_text(&child))
+            .sum::<usize>()
+}
+
+fn strip_chrome(html: &str) -> String {
+    CHROME_REGEXES.iter().fold(html.to_string(), |html, regex| regex.replace_all(&html, "").into_owned())
+}
+
+fn plain_text(html: &str) -> String {
+    let text: String = Html::parse_fragment(html).root_element().text().collect();
+    WHITESPACE_REGEX.replace_all(text.trim(), " ").into_owned()
+}
+
+/// At most `max` characters, cut at a word boundary.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}…", cut.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+}
+
+/// Files an import wrote, and those it left alone because they existed.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub written: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    /// Images and other media downloaded for the posts
+    pub media: Vec<PathBuf>,
+}
+
+/// Download `source`, an http(s) URL or a local file, and write each entry
+/// as a Markdown post.
+pub fn import_feed(source: &str, options: &ImportOptions) -> Result<ImportReport> {
+    let agent = agent();
+    let xml = if source.starts_with("http://") || source.starts_with("https://") {
+        fetch(&agent, source)?
+    } else {
+        fs::read_to_string(source).with_context(|| format!("Failed to read {}", source))?
+    };
+    let mut entries = parse_feed(&xml)?;
+    if let Some(limit) = options.limit {
+        entries.truncate(limit);
+    }
+    info!("Importing {} entries from {}", entries.len(), source);
+
+    fs::create_dir_all(&options.dir)?;
+    let mut report = ImportReport::default();
+    for entry in &entries {
+        let path = unique_path(&options.dir, &entry.slug(options.slugs), &report.written);
+        if path.exists() && !options.overwrite {
+            report.skipped.push(path);
+            continue;
+        }
+        let body = match (&entry.link, options.full_content) {
+            (Some(link), true) => match fetch(&agent, link).map(|page| extract_article(&page)) {
+                Ok(Some(article)) => Some(article),
+                Ok(None) => {
+                    warn!("No article found in {}, using the feed content", link);
+                    None
+                },
+                Err(e) => {
+                    warn!("{:#}, using the feed content", e);
+                    None
+                },
+            },
+            _ => None,
+        };
+        let body = body
+            .or_else(|| entry.content.clone())
+            .or_else(|| entry.summary.clone())
+            .unwrap_or_default();
+        fs::write(&path, entry.to_markdown(&body)?)?;
+        report.written.push(path);
+    }
+    Ok(report)
+}
+
+pub(crate) fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("eldroid-ssg/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+pub(crate) fn fetch(agent: &ureq::Agent, url: &str) -> Result<String> {
+    agent.get(url)
+        .call()
+        .map_err(|e| anyhow!("Failed to download {}: {}", url, e))?
+        .into_string()
+        .with_context(|| format!("Failed to read {}", url))
+}
+
+/// `<dir>/<slug>.md`, numbered when an earlier entry of this import took it.
+pub(crate) fn unique_path(dir: &Path, slug: &str, taken: &[PathBuf]) -> PathBuf {
+    let slug = if slug.is_empty() { "post" } else { slug };
+    let mut path = dir.join(format!("{}.md", slug));
+    let mut n = 2;
+    while taken.contains(&path) {
+        path = dir.join(format!("{}-{}.md", slug, n));
+        n += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_rss_and_atom() {
+        let rss = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<channel><title>Old blog</title>
+<item>
+  <title>Hello &amp; welcome</title>
+  <link>https://old.example.com/2024/01/hello-world/</link>
+  <pubDate>Tue, 02 Jan 2024 10:00:00 +0000</pubDate>
+  <author>jane@example.com (Jane Doe)</author>
+  <category>rust</category><category>ssg</category>
+  <description><![CDATA[<p>A <b>short</b> summary.</p>]]></description>
+  <content:encoded><![CDATA[<h1>Hello &amp; welcome</h1><h2>Intro</h2><p>Full <em>text</em>.</p><script>track()</script>]]></content:encoded>
+</item>
+</channel></rss>"#;
+        let entries = parse_feed(rss).unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.title, "Hello & welcome");
+        assert_eq!(entry.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(entry.tags, ["rust", "ssg"]);
+        assert_eq!(entry.slug(Slugs::default()), "hello-world");
+        let markdown = entry.to_markdown(entry.content.as_deref().unwrap()).unwrap();
+        assert!(markdown.starts_with("---\ntitle: Hello & welcome\ndate: 2024-01-02T10:00:00+00:00\n"));
+        assert!(markdown.contains("description: A short summary.\n"));
+        assert!(markdown.contains("- /2024/01/hello-world/\n"));
+        assert!(markdown.contains("Full *text*."));
+        assert!(!markdown.contains("track()"));
+        assert!(!markdown.contains("welcome\n===") && markdown.contains("Intro"));
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+<entry>
+  <title>Second</title>
+  <link rel="alternate" href="https://medium.com/@jane/second-post-3f2a9c1b"/>
+  <updated>2024-02-01T00:00:00Z</updated>
+  <author><name>Jane</name></author>
+  <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><p>Body</p></div></content>
+</entry></feed>"#;
+        let entry = &parse_feed(atom).unwrap()[0];
+        assert!(entry.content.as_deref().unwrap().contains(">Body</p>"));
+        assert_eq!(entry.slug(Slugs::default()), "second-post-3f2a9c1b");
+        assert!(parse_feed("<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_extracts_article() {
+        let paragraph = "<p>".to_string() + &"Words of the article. ".repeat(20) + "</p>";
+        let page = format!(
+            "<html><body><nav><p>{0}</p></nav><div class=\"sidebar\"><p>Short</p></div><div id=\"story\">{0}{0}</div></body></html>",
+            paragraph
+        );
+        let article = extract_article(&page).unwrap();
+        assert_eq!(article.matches("<p>").count(), 2);
+        assert!(extract_article("<html><body><p>Too short</p></body></html>").is_none());
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result};
+use log::warn;
+use crate::themes::ComponentDirs;
+
+/// Minimal page layout used by generated sections when no layout is configured.
+pub const DEFAULT_LAYOUT: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>@{title}</title>
+    <link rel="stylesheet" href="/css/markdown.css">
+    <link rel="stylesheet" href="/css/syntax.css">
+</head>
+<body>
+    <main class="markdown-content">
+        @{yield}
+    </main>
+</body>
+</html>"#;
+
+/// Read a layout from the components directories, falling back to
+/// [`DEFAULT_LAYOUT`] when none is configured or it can't be read.
+pub fn load_layout(components: &ComponentDirs, layout: Option<&Path>) -> String {
+    match layout {
+        Some(path) => read_layout(components, path).unwrap_or_else(|e| {
+            warn!("{:#}, using default", e);
+            DEFAULT_LAYOUT.to_string()
+        }),
+        None => DEFAULT_LAYOUT.to_string(),
+    }
+}
+
+/// Read a layout a page names from the components directories, without a
+/// fallback: a name that doesn't exist is most likely a typo.
+pub fn read_layout(components: &ComponentDirs, layout: &Path) -> Result<String> {
+    let path = components.resolve(layout);
+    fs::read_to_string(&path).with_context(|| format!("Failed to read layout {}", path.display()))
+}
+
+/// Fill a layout's `@{title}` and `@{yield}` placeholders.
+pub fn render_layout(layout: &str, title: &str, body: &str) -> String {
+    layout
+        .replace("@{title}", &html_escape::encode_text(title))
+        .replace("@{yield}", body)
+}
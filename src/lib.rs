@@ -1,25 +1,78 @@
 pub mod config;
+pub mod apidoc;
+pub mod layout;
+pub mod defaults;
 pub mod analyzer;
 pub mod html;
+pub mod images;
 pub mod minify;
+pub mod pretty;
 pub mod seo;
 pub mod seo_gen;
 pub mod variables;
+pub mod filters;
+pub mod data;
 pub mod macros;
+pub mod conditionals;
+pub mod loops;
 pub mod watcher;
+pub mod tls;
+pub mod proxy;
+pub mod presence;
+pub mod social_preview;
 pub mod markdown;
+pub mod page;
+pub mod i18n;
+pub mod redirects;
+pub mod releases;
+pub mod taxonomy;
+pub mod slug;
+pub mod sections;
+pub mod authors;
+pub mod git_dates;
+pub mod comments;
+pub mod webmentions;
+pub mod badges;
+pub mod graph;
+pub mod report;
+pub mod dashboard;
+pub mod history;
+pub mod csp;
+pub mod caching;
+pub mod host_headers;
+pub mod deploy;
+pub mod assets;
+pub mod remote_cache;
+pub mod remote_data;
+pub mod shard;
+pub mod verify;
+pub mod frontmatter;
+pub mod import;
+pub mod wordpress;
+pub mod search;
+pub mod shortcodes;
+pub mod theme;
+pub mod variants;
 pub mod template_gen;
+pub mod template_lint;
+pub mod trace;
 pub mod troubleshooting;
 pub mod error_handler;
+pub mod site;
+pub mod hooks;
+pub mod plugins;
+pub mod themes;
+pub mod archetypes;
 
 // Re-export commonly used types
-pub use config::{CliArgs, BuildConfig};
-pub use analyzer::{Analyzer, SecurityReport, PerformanceReport};
-pub use html::{HtmlGenerator, generate_html_with_seo}; 
+pub use config::{CliArgs, BuildConfig, SiteConfig, load_site_config};
+pub use defaults::FrontMatterDefaults;
+pub use analyzer::{Analyzer, SecurityReport, PerformanceReport, NoJsReport, LinkReport, PageMetadata};
+pub use html::{HtmlGenerator, generate_html_with_seo, generate_localized_html_with_seo};
 pub use minify::Minifier;
 pub mod seo_types;
 pub mod seo_html;
-pub use seo::{SEOConfig, PageSEO, load_seo_config, Organization, SocialMedia, StructuredData};
+pub use seo::{SEOConfig, PageSEO, load_seo_config, Organization, SocialMedia, StructuredData, RobotsConfig};
 pub use seo_types::JsonLd;
 pub use seo_html::{generate_meta_tags, inject_meta_tags};
 pub use seo_gen::{generate_sitemap, generate_rss, generate_robots_txt};
@@ -27,4 +80,6 @@ pub use variables::{Variables, load_variables};
 pub use macros::MacroProcessor;
 pub use watcher::DevServer;
 pub use markdown::*;
-pub use troubleshooting::Troubleshooter;
\ No newline at end of file
+pub use troubleshooting::Troubleshooter;
+pub use site::{Build, Site};
+pub use plugins::{ContentTransformer, HtmlPostProcessor, OutputGenerator, PageInfo, SiteInfo};
\ No newline at end of file
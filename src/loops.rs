@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use toml::Value;
+use crate::filters::{self, Filter};
+use crate::variables::{lookup_path, Variables};
+
+lazy_static! {
+    static ref FOR_REGEX: Regex = Regex::new(
+        r#"@for\(\s*(\w+)\s+in\s+([\w.\[\]-]+)((?:\s*\|\s*\w+(?:\((?:"[^"]*"|'[^']*'|[^)"'])*\))?)*)\s*\)\s*\{"#
+    ).unwrap();
+    static ref ITEM_REGEX: Regex = Regex::new(
+        r#"@\{(\w+)((?:\.[\w-]+|\[\d+\])*)(\??)((?:\s*\|\s*\w+(?:\((?:"[^"]*"|'[^']*'|[^)"'])*\))?)*)\s*\}"#
+    ).unwrap();
+}
+
+/// What `@for` loops iterate over: `posts`, every blog post newest first,
+/// the current page's front matter lists like `tags`, and array variables
+/// by name or dotted path.
+///
+/// ```text
+/// <ul>@for(post in posts | limit(5)) {
+///   <li><a href="@{post.url}">@{post.title}</a> @{post.date | date("%b %d")}</li>
+/// }</ul>
+/// ```
+///
+/// In the body, `@{post.<field>}` is a field of the current item, with the
+/// same filters as `@{var("...")}`, and `@{loop.index}`, `@{loop.first}`,
+/// and `@{loop.last}` tell where it is. A `?` after the field, as in
+/// `@{post.image?}`, makes a missing field empty. Loops can nest, iterating
+/// over a field of an outer item like `post.tags`.
+pub struct Collections<'a> {
+    posts: &'a [Value],
+    page: Option<&'a HashMap<String, Value>>,
+    variables: Option<&'a Variables>,
+}
+
+impl<'a> Collections<'a> {
+    pub fn new(posts: &'a [Value], variables: Option<&'a Variables>) -> Self {
+        Self { posts, page: None, variables }
+    }
+
+    /// Also iterate over the front matter of the page being rendered.
+    pub fn with_page(mut self, page: &'a HashMap<String, Value>) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// `content` with each loop repeated for the items of its collection.
+    pub fn apply(&self, content: &str) -> Result<String> {
+        if !content.contains("@for(") {
+            return Ok(content.to_string());
+        }
+        self.expand(content, &HashMap::new())
+    }
+
+    fn expand(&self, content: &str, scope: &HashMap<String, Value>) -> Result<String> {
+        let mut output = String::new();
+        let mut rest = content;
+        while let Some(caps) = FOR_REGEX.captures(rest) {
+            let header = caps.get(0).unwrap();
+            let body = &rest[header.end()..];
+            let body_len = closing_brace(body)
+                .ok_or_else(|| anyhow!("@for({} in {}) has no closing }}", &caps[1], &caps[2]))?;
+            let items = self.collection(&caps[2], &caps[3], scope)?;
+
+            output.push_str(&rest[..header.start()]);
+            for (index, item) in items.iter().enumerate() {
+                let mut inner = scope.clone();
+                inner.insert(caps[1].to_string(), item.clone());
+                let position = toml::Table::from_iter([
+                    ("index".to_string(), Value::Integer(index as i64 + 1)),
+                    ("first".to_string(), Value::Boolean(index == 0)),
+                    ("last".to_string(), Value::Boolean(index + 1 == items.len())),
+                ]);
+                inner.insert("loop".to_string(), Value::Table(position));
+                let expanded = self.expand(&body[..body_len], &inner)?;
+                output.push_str(&substitute_items(&expanded, &inner));
+            }
+            rest = &body[body_len + 1..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// The items `path` names, through the loop's filters.
+    fn collection(&self, path: &str, chain: &str, scope: &HashMap<String, Value>) -> Result<Vec<Value>> {
+        let value = match lookup_path(scope, path) {
+            Some(value) => value.clone(),
+            None if path == "posts" => Value::Array(self.posts.to_vec()),
+            None => self.page.and_then(|page| lookup_path(page, path))
+                .or_else(|| self.variables.and_then(|vars| vars.get(path))).cloned()
+                .ok_or_else(|| anyhow!("@for over unknown collection '{}'", path))?,
+        };
+        let value = Filter::parse_chain(chain)
+            .and_then(|filters| filters.iter().try_fold(value, |value, filter| filter.apply(value)))
+            .map_err(|e| anyhow!("@for over '{}': {}", path, e))?;
+        match value {
+            Value::Array(items) => Ok(items),
+            _ => Err(anyhow!("@for over '{}', which is not a list", path)),
+        }
+    }
+}
+
+/// Replace `@{name.field}` references to the items in `scope`.
+fn substitute_items(content: &str, scope: &HashMap<String, Value>) -> String {
+    ITEM_REGEX.replace_all(content, |caps: &Captures| {
+        if !scope.contains_key(&caps[1]) {
+            return caps[0].to_string();
+        }
+        let path = format!("{}{}", &caps[1], &caps[2]);
+        let Some(value) = lookup_path(scope, &path) else {
+            if caps[3].is_empty() {
+                log::warn!("Loop item has no '{}'", path);
+                return caps[0].to_string();
+            }
+            return String::new();
+        };
+        let filtered = Filter::parse_chain(&caps[4])
+            .and_then(|filters| filters.iter().try_fold(value.clone(), |value, filter| filter.apply(value)));
+        match filtered.map(|value| filters::render(&value)) {
+            Ok(Some(text)) => text,
+            Ok(None) => {
+                log::warn!("Loop item '{}' is a table and has no text", path);
+                caps[0].to_string()
+            },
+            Err(e) => {
+                log::warn!("Loop item '{}': {}", path, e);
+                caps[0].to_string()
+            },
+        }
+    }).to_string()
+}
+
+/// Length of the body before the brace that closes a block opened just
+/// before `body`.
+fn closing_brace(body: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (offset, c) in body.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(offset);
+                }
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loops_repeat_their_body_for_each_item() {
+        let posts: Vec<Value> = vec![
+            toml::from_str("title = \"Newest\"\nurl = \"/blog/newest.html\"\ndate = \"2026-02-01\"\ntags = [\"rust\", \"ssg\"]\nimage = \"/n.png\"").unwrap(),
+            toml::from_str("title = \"Older\"\nurl = \"/blog/older.html\"\ndate = \"2026-01-15\"\ntags = []").unwrap(),
+            toml::from_str("title = \"Oldest\"\nurl = \"/blog/oldest.html\"\ndate = \"2025-12-01\"\ntags = []").unwrap(),
+        ];
+        let vars: Variables = toml::from_str("[nav]\nlinks = [{ label = \"Home\" }, { label = \"About\" }]").unwrap();
+        let collections = Collections::new(&posts, Some(&vars));
+
+        let template = "<ul>@for(post in posts | limit(2)) {<li class=\"@{loop.index}\">\
+                        <a href=\"@{post.url}\">@{post.title | upper}</a> @{post.date | date(\"%b %d\")}\
+                        <img src=\"@{post.image?}\">@for(tag in post.tags) {<i>@{tag}@{post.nope}</i>}</li>}</ul>\
+                        @for(link in nav.links) {@{link.label}@{loop.last}|}";
+        assert_eq!(
+            collections.apply(template).unwrap(),
+            "<ul><li class=\"1\"><a href=\"/blog/newest.html\">NEWEST</a> Feb 01<img src=\"/n.png\">\
+             <i>rust@{post.nope}</i><i>ssg@{post.nope}</i></li>\
+             <li class=\"2\"><a href=\"/blog/older.html\">OLDER</a> Jan 15<img src=\"\"></li></ul>\
+             Homefalse|Abouttrue|",
+        );
+
+        assert!(collections.apply("@for(post in drafts) {x}").is_err());
+        assert!(collections.apply("@for(post in posts) {x").is_err());
+        assert!(collections.apply("@for(link in nav) {x}").is_err());
+    }
+}
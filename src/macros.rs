@@ -1,19 +1,52 @@
-use regex::Regex;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use chrono::{DateTime, SecondsFormat, Utc};
 use lazy_static::lazy_static;
-use crate::variables::Variables;
+use regex::{Captures, Regex};
+use crate::markdown::markdown_to_html;
+use crate::themes::ComponentDirs;
+use crate::variables::{resolve_escapes, Variables};
 
 lazy_static! {
-    static ref VAR_REGEX: Regex = Regex::new(r#"@\{var\(["']([^"']+)["']\)\}"#).unwrap();
+    /// An include, along with the paragraph Markdown wraps it in when it
+    /// stands alone on a line.
+    static ref INCLUDE_REGEX: Regex = Regex::new(
+        r#"(<p>\s*)?@\{include\(["']([^"']+)["']\)\}(\s*</p>)?"#
+    ).unwrap();
+    static ref NOW_MACRO_REGEX: Regex = Regex::new(r#"@\{now\((?:["']([^"']*)["'])?\)\}|@\{build_time\}"#).unwrap();
+    static ref URL_MACRO_REGEX: Regex = Regex::new(r#"@\{url\(["']([^"']*)["']\)\}"#).unwrap();
+    /// A URL attribute, with its quote
+    static ref URL_ATTR_REGEX: Regex = Regex::new(r#"(\s(?:href|src|action|poster|srcset)\s*=\s*)(["'])([^"']*)"#).unwrap();
 }
 
+/// How deep includes may nest before the innermost is left unexpanded.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Clone)]
 pub struct MacroProcessor {
-    variables: Option<Variables>
+    variables: Option<Variables>,
+    /// Where `@{include("...")}` looks for partials
+    includes: ComponentDirs,
+    /// Path the site is served under, like `/project`, or empty at the root
+    base_path: String,
+    /// When the build started, for `@{now(...)}` and `@{build_time}`
+    build_time: DateTime<Utc>,
+}
+
+impl Default for MacroProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MacroProcessor {
     pub fn new() -> Self {
         Self {
-            variables: None
+            variables: None,
+            includes: ComponentDirs::new("components", None),
+            base_path: String::new(),
+            build_time: Utc::now(),
         }
     }
 
@@ -22,19 +55,229 @@ impl MacroProcessor {
         self
     }
 
+    /// Look up included partials in these directories.
+    pub fn with_includes(mut self, dirs: ComponentDirs) -> Self {
+        self.includes = dirs;
+        self
+    }
+
+    /// Prefix `@{url("...")}` paths with `base_path`, as from [`base_path`].
+    pub fn with_base_path(mut self, base_path: impl Into<String>) -> Self {
+        self.base_path = base_path.into();
+        self
+    }
+
+    /// Evaluate the date macros as if building at `now`.
+    pub fn at(mut self, now: DateTime<Utc>) -> Self {
+        self.build_time = now;
+        self
+    }
+
     pub fn process(&self, content: &str) -> String {
-        if let Some(vars) = &self.variables {
-            VAR_REGEX.replace_all(content, |caps: &regex::Captures| {
-                let var_name = &caps[1];
-                if let Some(value) = vars.get(var_name) {
-                    value.to_string()
-                } else {
-                    log::warn!("Variable '{}' not found", var_name);
-                    format!("@{{var(\"{var_name}\")}}")
-                }
-            }).to_string()
+        let content = self.include(content, None);
+        let content = match &self.variables {
+            Some(vars) => vars.substitute_vars(&content),
+            None => content,
+        };
+        // Every page of a build gets the same time, taken when it started
+        let content = NOW_MACRO_REGEX.replace_all(&content, |caps: &Captures| {
+            let Some(format) = caps.get(1) else {
+                return self.build_time.to_rfc3339_opts(SecondsFormat::Secs, true);
+            };
+            let mut text = String::new();
+            match write!(text, "{}", self.build_time.format(format.as_str())) {
+                Ok(()) => text,
+                Err(_) => {
+                    log::warn!("Invalid date format '{}'", format.as_str());
+                    caps[0].to_string()
+                },
+            }
+        });
+        URL_MACRO_REGEX.replace_all(&content, |caps: &Captures| self.url(&caps[1])).to_string()
+    }
+
+    /// `path` on the site: under the base path unless it's absolute already.
+    pub fn url(&self, path: &str) -> String {
+        if is_external(path) || path.starts_with('#') {
+            return path.to_string();
+        }
+        format!("{}/{}", self.base_path, path.trim_start_matches('/'))
+    }
+
+    /// Replace `@{include("...")}` with the partial it names, whose own
+    /// includes are expanded in turn. Markdown partials are rendered to
+    /// HTML first. `from` is the file `content` was read from, which paths
+    /// starting with `./` or `../` are relative to; other paths are looked
+    /// up in the include directories. Includes that can't be read, or that
+    /// nest too deep or include themselves, are left as they are.
+    pub fn include(&self, content: &str, from: Option<&Path>) -> String {
+        self.expand(content, from, &mut Vec::new())
+    }
+
+    fn expand(&self, content: &str, from: Option<&Path>, stack: &mut Vec<PathBuf>) -> String {
+        let content = resolve_escapes(content);
+        if !content.contains("@{include(") {
+            return content;
+        }
+        INCLUDE_REGEX.replace_all(&content, |caps: &Captures| {
+            let Some(path) = resolve_include(&caps[2], from, &self.includes) else {
+                log::warn!("Include '{}' is relative, but its page's location is unknown", &caps[2]);
+                return caps[0].to_string();
+            };
+            if stack.len() >= MAX_INCLUDE_DEPTH || stack.contains(&path) {
+                log::warn!("Include '{}' nests too deep or includes itself", path.display());
+                return caps[0].to_string();
+            }
+            let partial = match fs::read_to_string(&path) {
+                Ok(partial) if path.extension().is_some_and(|ext| ext == "md") => markdown_to_html(&partial),
+                Ok(partial) => partial,
+                Err(e) => {
+                    log::warn!("Failed to include {}: {}", path.display(), e);
+                    return caps[0].to_string();
+                },
+            };
+            stack.push(path.clone());
+            let partial = self.expand(&partial, Some(&path), stack);
+            stack.pop();
+            // Keep the paragraph unless the include filled it on its own
+            match (caps.get(1), caps.get(3)) {
+                (Some(_), Some(_)) => partial,
+                (open, close) => format!("{}{}{}", open.map_or("", |m| m.as_str()), partial, close.map_or("", |m| m.as_str())),
+            }
+        }).to_string()
+    }
+}
+
+/// The file an `@{include("...")}` of `target` reads: next to `from` for a
+/// path starting with `./` or `../`, else in `dirs`. `None` for a relative
+/// path without a `from`.
+pub fn resolve_include(target: &str, from: Option<&Path>, dirs: &ComponentDirs) -> Option<PathBuf> {
+    let target = Path::new(target);
+    match target.components().next() {
+        Some(Component::CurDir | Component::ParentDir) => {
+            let relative: PathBuf = target.components().filter(|part| *part != Component::CurDir).collect();
+            Some(from?.parent().unwrap_or(Path::new("")).join(relative))
+        },
+        _ => Some(dirs.resolve(target.strip_prefix("/").unwrap_or(target))),
+    }
+}
+
+/// The path of `base_url` without a trailing slash: `/project` for
+/// `https://example.github.io/project/`, empty for a site at the root.
+pub fn base_path(base_url: Option<&str>) -> String {
+    base_url.and_then(|base_url| url::Url::parse(base_url).ok())
+        .map(|url| url.path().trim_end_matches('/').to_string())
+        .unwrap_or_default()
+}
+
+/// Move root-relative links in `html` under `base_path`, for a site served
+/// from a subdirectory. Links already under it are left alone.
+pub fn prefix_root_links(html: &str, base_path: &str) -> String {
+    if base_path.is_empty() {
+        return html.to_string();
+    }
+    let prefix = |url: &str| {
+        let under_base = url.strip_prefix(base_path).is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']));
+        if url.starts_with('/') && !url.starts_with("//") && !under_base {
+            format!("{}{}", base_path, url)
         } else {
-            content.to_string()
+            url.to_string()
         }
+    };
+    URL_ATTR_REGEX.replace_all(html, |caps: &Captures| {
+        let value = if caps[1].trim_start().starts_with("srcset") {
+            // Candidates are a URL and an optional descriptor, comma-separated
+            caps[3].split(',')
+                .map(|candidate| {
+                    let candidate = candidate.trim_start();
+                    let (url, descriptor) = candidate.split_once(' ').unwrap_or((candidate, ""));
+                    let url = prefix(url);
+                    if descriptor.is_empty() { url } else { format!("{} {}", url, descriptor) }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        } else {
+            prefix(&caps[3])
+        };
+        format!("{}{}{}", &caps[1], &caps[2], value)
+    }).to_string()
+}
+
+fn is_external(url: &str) -> bool {
+    url.starts_with("//") || url.split_once(':').is_some_and(|(scheme, _)| {
+        !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls_follow_the_base_path() {
+        assert_eq!(base_path(Some("https://example.github.io/project/")), "/project");
+        assert_eq!(base_path(Some("https://example.com")), "");
+        assert_eq!(base_path(None), "");
+
+        let processor = MacroProcessor::new().with_base_path("/project");
+        assert_eq!(
+            processor.process("@{url(\"/blog/post\")} @{url('css/site.css')} @{url(\"/\")} @{url(\"https://example.com/x\")}"),
+            "/project/blog/post /project/css/site.css /project/ https://example.com/x",
+        );
+        assert_eq!(MacroProcessor::new().process("@{url(\"/blog/post\")}"), "/blog/post");
+
+        let html = "<a href=\"/blog/\">Blog</a><a href='/project/about'>About</a><a href=\"//cdn.example.com/x.js\"></a>\
+                    <img src=\"/a.png\" srcset=\"/a-1x.png 1x, /a-2x.png 2x\"><a href=\"#top\"></a><a href=\"/projects\"></a>";
+        assert_eq!(
+            prefix_root_links(html, "/project"),
+            "<a href=\"/project/blog/\">Blog</a><a href='/project/about'>About</a><a href=\"//cdn.example.com/x.js\"></a>\
+             <img src=\"/project/a.png\" srcset=\"/project/a-1x.png 1x, /project/a-2x.png 2x\"><a href=\"#top\"></a><a href=\"/project/projects\"></a>",
+        );
+        assert_eq!(prefix_root_links(html, ""), html);
+    }
+
+    #[test]
+    fn test_date_macros_use_the_build_time() {
+        let built = DateTime::parse_from_rfc3339("2026-03-01T09:30:00Z").unwrap().with_timezone(&Utc);
+        let processor = MacroProcessor::new().at(built);
+        assert_eq!(
+            processor.process("© @{now(\"%Y\")} · @{build_time} · app.js?v=@{now('%s')} · @{now()} · @{now(\"%Q\")}"),
+            "© 2026 · 2026-03-01T09:30:00Z · app.js?v=1772357400 · 2026-03-01T09:30:00Z · @{now(\"%Q\")}",
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_includes_resolve_nest_and_stop_at_cycles() {
+        let temp = tempfile::tempdir().unwrap();
+        let components = temp.path().join("components");
+        let content = temp.path().join("content");
+        fs::create_dir_all(components.join("partials")).unwrap();
+        fs::create_dir_all(content.join("docs")).unwrap();
+        fs::write(components.join("partials/footer.html"), "<footer>@{include(\"./note.md\")}</footer>").unwrap();
+        fs::write(components.join("partials/note.md"), "Made with *care*").unwrap();
+        fs::write(components.join("partials/loop.html"), "[@{include(\"partials/loop.html\")}]").unwrap();
+        fs::write(content.join("docs/aside.html"), "<aside>@{var(\"name\")}</aside>").unwrap();
+
+        let vars: Variables = toml::from_str("name = \"Ada\"").unwrap();
+        let processor = MacroProcessor::new()
+            .with_variables(vars)
+            .with_includes(ComponentDirs::new(&components, None));
+        let page = content.join("docs/page.html");
+
+        assert_eq!(
+            processor.include("@{include(\"partials/footer.html\")}", Some(&page)),
+            "<footer><p>Made with <em>care</em></p>\n</footer>",
+        );
+        assert_eq!(
+            processor.include("<p>@{include(\"../docs/aside.html\")}</p>\n<p>See @{include(\"./aside.html\")}</p>", Some(&page)),
+            "<aside>@{var(\"name\")}</aside>\n<p>See <aside>@{var(\"name\")}</aside></p>",
+        );
+        assert_eq!(processor.process("@{include(\"./aside.html\")}"), "@{include(\"./aside.html\")}");
+        assert_eq!(processor.process("@{include(\"partials/missing.html\")}"), "@{include(\"partials/missing.html\")}");
+        assert_eq!(processor.process("@@{include(\"partials/footer.html\")}"), "&#64;{include(\"partials/footer.html\")}");
+        assert_eq!(
+            processor.process("@{include(\"partials/loop.html\")}"),
+            "[@{include(\"partials/loop.html\")}]",
+        );
+    }
+}
@@ -2,42 +2,39 @@ use clap::Parser;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use parking_lot::Mutex;
-use rayon::prelude::*;
-use log::{error, info};
-use tokio;
+use log::{error, info, warn};
 use anyhow::{Result, anyhow};
 
 use eldroid_ssg::{
-    config::{CliArgs, BuildConfig},
-    seo::{load_seo_config, SEOConfig},
-    html::{generate_html_with_seo, HtmlGenerator},
-    seo_gen::{generate_sitemap, generate_rss, generate_robots_txt},
-    minify::Minifier,
+    config::{CliArgs, BuildConfig, CheckTarget, FrontmatterAction, ImportSource, load_site_config},
+    history::{BuildLog, BuildRecord},
+    caching::HeadersManifest,
+    deploy,
+    remote_cache::RemoteCache,
+    verify::OutputManifest,
+    frontmatter::{set_in_files, Assignment},
+    import::{import_feed, ImportOptions, ImportReport},
+    wordpress::{import_wordpress, WordPressOptions},
+    search::{self, PushOptions},
+    webmentions::{self, Outcome},
+    seo::load_seo_config,
+    html::HtmlGenerator,
+    i18n::Translations,
     analyzer::Analyzer,
-    variables::load_variables,
+    variables::{data_dir, load_variables},
     macros::MacroProcessor,
-    watcher::DevServer,
+    watcher::{DevServer, PageFindings, WatchOptions},
+    error_handler::BuildError,
+    tls::TlsIdentity,
+    proxy::ProxyRule,
     troubleshooting::Troubleshooter,
-    BlogPost,
-    BlogProcessor,
+    trace::PipelineTrace,
+    site::{check_links, config_files, config_hash, theme_dir, IncrementalBuild, Site},
 };
+use eldroid_ssg::archetypes::{section_for, Archetypes};
+use eldroid_ssg::slug::Slugs;
 use eldroid_ssg::template_gen::generate_template_site;
-
-fn walk_dir_recursive(dir: &Path) -> Vec<std::path::PathBuf> {
-    let mut files = Vec::new();
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if path.is_dir() {
-                files.extend(walk_dir_recursive(&path));
-            } else if path.is_file() && path.extension().map_or(false, |ext| ext == "html" || ext == "md") {
-                files.push(path);
-            }
-        }
-    }
-    files
-}
+use eldroid_ssg::template_lint::{Severity, TemplateLinter};
 
 #[tokio::main]
 async fn main() {
@@ -76,89 +73,257 @@ async fn main() {
                     }
                 }
             }
+            eldroid_ssg::config::Commands::New { kind, title } => {
+                let created = site_slugs(&args).and_then(|slugs| {
+                    Archetypes::new(&args.site_config).with_slugs(slugs)
+                        .create(Path::new(&args.input_dir), section_for(kind), title, chrono::Utc::now())
+                });
+                match created {
+                    Ok(path) => {
+                        println!("Created {}", path.display());
+                        std::process::exit(0);
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to create {}: {:#}", kind, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::LintTemplates => {
+                match lint_templates(&args) {
+                    Ok(true) => std::process::exit(0),
+                    Ok(false) => std::process::exit(1),
+                    Err(e) => {
+                        eprintln!("Failed to lint templates: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::Check { target: CheckTarget::Links } => {
+                let analyzer = Analyzer::new(load_seo_config(&args.seo_config).and_then(|cfg| cfg.base_url));
+                match check_links(&analyzer, &args) {
+                    Ok(report) => {
+                        for link in &report.broken {
+                            println!("{}", link);
+                        }
+                        println!(
+                            "Checked {} links across {} pages, {} broken",
+                            report.links_checked, report.pages_checked, report.broken.len()
+                        );
+                        std::process::exit(if report.broken.is_empty() { 0 } else { 1 });
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to check links: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::Frontmatter {
+                action: FrontmatterAction::Set { assignments, glob, if_missing, dry_run },
+            } => {
+                let result = assignments.iter()
+                    .map(|raw| Assignment::parse(raw))
+                    .collect::<Result<Vec<_>>>()
+                    .and_then(|assignments| set_in_files(
+                        Path::new(&args.input_dir), glob, &assignments, *if_missing, *dry_run,
+                    ));
+                match result {
+                    Ok(changed) => {
+                        for path in &changed {
+                            println!("{}", path.display());
+                        }
+                        println!("{} {} file(s)", if *dry_run { "Would update" } else { "Updated" }, changed.len());
+                        std::process::exit(0);
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to update front matter: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::Import { from_feed, section, full_content, overwrite, limit, source } => {
+                let slugs = match site_slugs(&args) {
+                    Ok(slugs) => slugs,
+                    Err(e) => {
+                        eprintln!("Failed to load the site configuration: {:#}", e);
+                        std::process::exit(1);
+                    }
+                };
+                let options = ImportOptions {
+                    dir: Path::new(&args.input_dir).join(section),
+                    full_content: *full_content,
+                    overwrite: *overwrite,
+                    limit: *limit,
+                    slugs,
+                };
+                let (source, result) = match source {
+                    Some(ImportSource::Wordpress { export, no_media }) => {
+                        (export.display().to_string(), import_wordpress_export(&args, export, &options, *no_media))
+                    },
+                    None => {
+                        let from_feed = from_feed.as_deref().unwrap_or_default();
+                        (from_feed.to_string(), import_feed(from_feed, &options))
+                    },
+                };
+                match result {
+                    Ok(report) => {
+                        for path in &report.written {
+                            println!("{}", path.display());
+                        }
+                        for path in &report.skipped {
+                            println!("Skipped {}, which already exists (pass --overwrite to replace it)", path.display());
+                        }
+                        println!("Imported {} post(s) into {}", report.written.len(), options.dir.display());
+                        if !report.media.is_empty() {
+                            println!("Downloaded {} media file(s)", report.media.len());
+                        }
+                        std::process::exit(0);
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to import {}: {:#}", source, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::DeployIndex { all } => {
+                match deploy_index(&args, *all) {
+                    Ok(()) => std::process::exit(0),
+                    Err(e) => {
+                        eprintln!("Failed to deploy the search index: {:#}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::Deploy { all, dry_run } => {
+                match deploy_site(&args, *all, *dry_run) {
+                    Ok(()) => std::process::exit(0),
+                    Err(e) => {
+                        eprintln!("Failed to deploy: {:#}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::SendWebmentions { dry_run } => {
+                match send_webmentions(&args, *dry_run) {
+                    Ok(()) => std::process::exit(0),
+                    Err(e) => {
+                        eprintln!("Failed to send webmentions: {:#}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            eldroid_ssg::config::Commands::History { limit, failed, json } => {
+                match print_history(&args, *limit, *failed, *json) {
+                    Ok(()) => std::process::exit(0),
+                    Err(e) => {
+                        eprintln!("Failed to read build history: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            // Need the fully configured pipeline, handled below
+            eldroid_ssg::config::Commands::Explain { .. } | eldroid_ssg::config::Commands::Merge { .. } => {}
         }
     }
 
+    // Ensure output directories exist
     let perf_dir = format!("{}/performance", args.output_dir);
-
-    // Initialize components
-    let minifier = if config.minify {
-        Some(Minifier::default())
-    } else {
-        None
-    };
-
-    let analyzer = if config.analyze_performance || config.security_checks {
-        let base_url = load_seo_config(&args.seo_config)
-            .and_then(|cfg| cfg.base_url);
-        Some(Analyzer::new(base_url))
-    } else {
-        None
-    };
-
-    // Load SEO config if enabled
-    let seo_config = if config.enable_seo {
-        match load_seo_config(&args.seo_config) {
-            Some(config) => {
-                info!("SEO configuration loaded successfully");
-                Some(config)
-            },
-            None => {
-                error!("Failed to load SEO configuration");
-                None
-            }
+    for dir in [&args.output_dir, &perf_dir] {
+        if let Err(e) = fs::create_dir_all(dir) {
+            error!("Failed to create directory {}: {}", dir, e);
+            std::process::exit(1);
         }
-    } else {
-        None
-    };
+    }
 
-    // Load variables configuration
-    let variables = match load_variables(&args.variables_config) {
-        Ok(vars) => {
-            info!("Variables configuration loaded successfully");
-            Some(vars)
-        },
+    let site = match Site::new(args.clone()) {
+        Ok(site) => site,
         Err(e) => {
-            error!("Failed to load variables configuration: {}", e);
-            None
+            error!("Failed to load the site configuration: {:#}", e);
+            std::process::exit(1);
         }
     };
 
-    // Initialize macro processor
-    let macro_processor = MacroProcessor::new();
-
-    // Ensure output directories exist
-    for dir in [&args.output_dir, &perf_dir] {
-        if let Err(e) = fs::create_dir_all(dir) {
-            error!("Failed to create directory {}: {}", dir, e);
+    if let Some(eldroid_ssg::config::Commands::Explain { file }) = &args.command {
+        if let Err(e) = explain_page(&site, file) {
+            eprintln!("Failed to explain {}: {}", file.display(), e);
             std::process::exit(1);
         }
+        std::process::exit(0);
     }
 
-    // Initialize HtmlGenerator
-    let html_gen = Arc::new(
-        HtmlGenerator::new()
-            .with_variables(variables.unwrap_or_default())
-            .with_macros(macro_processor)
-            .with_dev_mode(args.watch, args.ws_port)
-    );
+    if let Some(eldroid_ssg::config::Commands::Merge { shard_dirs }) = &args.command {
+        if let Err(e) = site.merge(shard_dirs) {
+            eprintln!("Failed to merge shards: {}", e);
+            std::process::exit(1);
+        }
+        std::process::exit(if check_output(&args) { 0 } else { 1 });
+    }
 
     // Start development server if watch mode is enabled
     if args.watch {
+        if args.ws_port.is_some() {
+            warn!("--ws-port is ignored; live reload now shares the --port of the dev server");
+        }
+        let tls = match load_tls(&args) {
+            Ok(tls) => tls,
+            Err(e) => {
+                error!("Failed to load the HTTPS certificate: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let watched = proxy_rules(&args).and_then(|proxy| Ok((proxy, asset_dir(&args)?, theme_dir(&args)?)));
+        let (proxy, asset_dir, theme_dir) = match watched {
+            Ok(watched) => watched,
+            Err(e) => {
+                error!("Failed to load the site configuration: {:#}", e);
+                std::process::exit(1);
+            }
+        };
         // Start watcher in development mode
         let dev_server = DevServer::new(
             args.input_dir.clone(),
             args.output_dir.clone(),
-            format!("{}/components", args.input_dir), // Components directory
+            args.components_dir.clone(),
             args.port,
-            args.ws_port
-        );
+        )
+        .with_host(args.host)
+        .with_port_fallback(args.port_fallback)
+        .with_open_browser(args.open)
+        .with_tls(tls)
+        .with_proxy(proxy)
+        .with_author(args.author.clone())
+        .with_directory_listing(!args.no_directory_listing)
+        .with_config_files(config_files(&args))
+        .with_asset_dir(asset_dir)
+        .with_data_dir(data_dir(&args.variables_config))
+        .with_theme_dir(theme_dir)
+        .with_watch_options(WatchOptions::from(&config));
         
-        // Process files initially
-        if let Err(e) = process_files(&args, &config, &html_gen, &minifier, &analyzer, &seo_config, &perf_dir) {
-            error!("Failed to process files: {}", e);
-            std::process::exit(1);
-        }
+        // Process files initially. A broken page shouldn't stop the server,
+        // which shows the error in the browser until the page is fixed.
+        let site = site.with_page_findings(Some(PageFindings::default()));
+        let dev_server = match site.build() {
+            Ok(_) => dev_server,
+            Err(e) => {
+                error!("Failed to process files: {:#}", e);
+                dev_server.with_build_error(Some(BuildError::new(&e, None)))
+            },
+        };
+
+        // Afterwards, rebuild only the pages a change affects
+        let dev_server = match IncrementalBuild::new(site) {
+            Ok(build) => {
+                let build = Arc::new(build);
+                dev_server
+                    .with_memory_pages(build.memory_pages())
+                    .with_page_findings(Some(build.page_findings()))
+                    .with_rebuild(Arc::new(move |changed: &Path| build.rebuild(changed)))
+            },
+            Err(e) => {
+                error!("Failed to prepare incremental rebuilds: {}", e);
+                std::process::exit(1);
+            }
+        };
         
         // Start the development server
         if let Err(e) = dev_server.start().await {
@@ -167,11 +332,162 @@ async fn main() {
         }
     } else {
         // One-time build
-        if let Err(e) = process_files(&args, &config, &html_gen, &minifier, &analyzer, &seo_config, &perf_dir) {
+        if let Err(e) = site.build() {
             error!("Failed to process files: {}", e);
             std::process::exit(1);
         }
+        if !check_output(&args) {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write and verify the output manifest as requested; false if the output
+/// doesn't match or either step fails.
+fn check_output(args: &CliArgs) -> bool {
+    if args.output_manifest.is_none() && args.verify_output.is_none() {
+        return true;
+    }
+    let result = load_site_config(&args.site_config).and_then(|site| {
+        let output_dir = Path::new(&args.output_dir);
+        if let Some(path) = &args.output_manifest {
+            let volatile: Vec<String> = site.verify.volatile.iter().chain(&args.volatile).cloned().collect();
+            let manifest = OutputManifest::from_output(output_dir, &volatile, Some(path))?;
+            manifest.write(path)?;
+            info!("Output manifest with {} files written to {}", manifest.files.len(), path.display());
+        }
+        let Some(path) = &args.verify_output else {
+            return Ok(true);
+        };
+        // The tree being verified doesn't get to say which of its files may differ
+        let expected = OutputManifest::load(path)?;
+        let volatile: Vec<String> = expected.volatile.iter().chain(&args.volatile).cloned().collect();
+        let actual = OutputManifest::from_output(output_dir, &volatile, Some(path))?;
+        let diff = expected.diff(&actual)?;
+        for (label, paths) in [("changed", &diff.changed), ("missing", &diff.missing), ("unexpected", &diff.unexpected)] {
+            for file in paths {
+                warn!("{:<10} {}", label, file);
+            }
+        }
+        if diff.is_empty() {
+            info!("Output matches {} ({} files)", path.display(), actual.files.len());
+        } else {
+            error!(
+                "Output differs from {}: {} changed, {} missing, {} unexpected",
+                path.display(), diff.changed.len(), diff.missing.len(), diff.unexpected.len()
+            );
+        }
+        Ok(diff.is_empty())
+    });
+    result.unwrap_or_else(|e| {
+        error!("Failed to verify the output: {:#}", e);
+        false
+    })
+}
+
+/// Certificate for `--https`: the given files, or a self-signed one kept in
+/// the user's cache, away from the served output.
+fn load_tls(args: &CliArgs) -> Result<Option<TlsIdentity>> {
+    if !args.https {
+        return Ok(None);
     }
+    let identity = match (&args.https_cert, &args.https_key) {
+        (Some(cert), Some(key)) => TlsIdentity::load(cert, key)?,
+        _ => TlsIdentity::self_signed(&TlsIdentity::default_dir(), args.host)?,
+    };
+    Ok(Some(identity))
+}
+
+/// Dev server proxy routes: `--proxy` flags first, then `[[proxy]]` rules.
+fn proxy_rules(args: &CliArgs) -> Result<Vec<ProxyRule>> {
+    let mut rules = args.proxy.clone();
+    rules.extend(load_site_config(&args.site_config)?.proxy);
+    Ok(rules)
+}
+
+/// Static asset directory from `[assets]`, if the site has one.
+fn asset_dir(args: &CliArgs) -> Result<Option<PathBuf>> {
+    let site_config = load_site_config(&args.site_config)?;
+    Ok(site_config.assets.map(|assets| assets.dir(Path::new(&args.input_dir))))
+}
+
+/// How the site slugs the names of new and imported files.
+fn site_slugs(args: &CliArgs) -> Result<Slugs> {
+    Ok(load_site_config(&args.site_config)?.slugs())
+}
+
+/// Import a WordPress export with pages at the top of the input directory
+/// and media in the asset directory.
+fn import_wordpress_export(args: &CliArgs, export: &Path, options: &ImportOptions, no_media: bool) -> Result<ImportReport> {
+    let asset_dir = asset_dir(args)?;
+    let media_dir = asset_dir.clone().unwrap_or_else(|| {
+        let site_root = Path::new(&args.input_dir).parent().unwrap_or(Path::new(""));
+        site_root.join("static")
+    });
+    let wordpress = WordPressOptions {
+        pages_dir: PathBuf::from(&args.input_dir),
+        media_dir: (!no_media).then(|| media_dir.clone()),
+    };
+    let report = import_wordpress(export, options, &wordpress)?;
+    if !report.media.is_empty() && asset_dir.is_none() {
+        println!("Media was saved to {}; add an [assets] section to eldroid.toml so builds copy it to the output", media_dir.display());
+    }
+    Ok(report)
+}
+
+/// Report template problems; returns whether the templates are free of errors.
+fn lint_templates(args: &CliArgs) -> Result<bool> {
+    let site_config = load_site_config(&args.site_config)?;
+    let mut linter = TemplateLinter::new(&args.components_dir)
+        .with_theme(site_config.load_theme(&args.site_config)?.as_ref())
+        .with_variables(load_variables(&args.variables_config)?);
+    if let Some(i18n) = &site_config.i18n {
+        linter = linter.with_translations(Translations::load(i18n)?, &i18n.default_language);
+    }
+
+    let issues = linter.lint_all()?;
+    for issue in &issues {
+        println!("{}", issue);
+    }
+
+    let errors = issues.iter().filter(|i| i.severity == Severity::Error).count();
+    println!("{} error(s), {} warning(s) in {}", errors, issues.len() - errors, args.components_dir);
+    Ok(errors == 0)
+}
+
+/// Print the most recent builds from the build log, newest first.
+fn print_history(args: &CliArgs, limit: usize, failed_only: bool, json: bool) -> Result<()> {
+    let records = BuildLog::new(format!("{}/cache", args.output_dir)).read()?;
+    let records: Vec<_> = records.iter().rev()
+        .filter(|record| !failed_only || !record.success)
+        .take(limit)
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+    if records.is_empty() {
+        println!("No builds recorded yet");
+        return Ok(());
+    }
+    for record in records {
+        println!(
+            "{}  {:<6} {:>6} ms  config {}  {} inputs / {} outputs changed, {} warnings{}",
+            record.timestamp,
+            if record.success { "ok" } else { "failed" },
+            record.duration_ms,
+            &record.config_hash[..8.min(record.config_hash.len())],
+            record.inputs_changed.len(),
+            record.outputs_changed.len(),
+            record.warnings,
+            record.deploy_target.as_ref().map(|target| format!(", deployed to {}", target)).unwrap_or_default(),
+        );
+        if let Some(error) = &record.error {
+            println!("    error: {}", error);
+        }
+    }
+    Ok(())
 }
 
 fn handle_troubleshooting(args: &CliArgs, troubleshooter: &Troubleshooter) -> Result<()> {
@@ -202,127 +518,107 @@ fn handle_troubleshooting(args: &CliArgs, troubleshooter: &Troubleshooter) -> Re
     if args.memory_profile {
         // Wrap the build process in memory profiling
         troubleshooter.memory_profile(|| {
-            process_files(args, &BuildConfig::from(args), &Arc::new(
-                HtmlGenerator::new()
-                    .with_variables(load_variables(&args.variables_config).unwrap_or_default())
-                    .with_macros(MacroProcessor::new())
-            ), &None, &None, &None, &format!("{}/performance", args.output_dir))
+            Site::new(args.clone())?
+                .with_html_generator(Arc::new(
+                    HtmlGenerator::new()
+                        .with_variables(load_variables(&args.variables_config)?)
+                        .with_macros(MacroProcessor::new())
+                ))
+                .with_minifier(None)
+                .with_analyzer(None)
+                .with_seo_config(None)
+                .build()
+                .map(|_| ())
         })?;
     }
 
     Ok(())
 }
 
-fn process_files(
-    args: &CliArgs,
-    config: &BuildConfig,
-    html_gen: &Arc<HtmlGenerator>,
-    minifier: &Option<Minifier>,
-    analyzer: &Option<Analyzer>,
-    seo_config: &Option<SEOConfig>,
-    perf_dir: &str,
-) -> Result<()> {
-    let processed_files = Arc::new(Mutex::new(Vec::new()));
-    let content_files = walk_dir_recursive(Path::new(&args.input_dir));
-    let mut blog_processor = BlogProcessor::with_option_components(
-        Path::new(&args.input_dir).to_path_buf(),
-        html_gen.get_variables().clone()
-    );
-    
-    // Load posts for next/prev navigation
-    blog_processor.load_posts()?;
-    
-    let file_results: Vec<Result<PathBuf>> = content_files
-        .par_iter()
-        .map(|file_path| -> Result<PathBuf> {
-            // Read content
-            let content = fs::read_to_string(file_path)?;
-            
-            // Process content based on file type
-            let processed_content = if file_path.extension().map_or(false, |ext| ext == "md") {
-                let post = BlogPost::from_file(file_path, Path::new(&args.input_dir))?;
-                blog_processor.process_post(&post)?
-            } else if let Some(seo) = seo_config {
-                generate_html_with_seo(&content, seo, html_gen)
-            } else {
-                html_gen.generate(&content)
-            };
-
-            // Run analysis if enabled
-            if let Some(analyzer) = analyzer {
-                if config.security_checks {
-                    let security_report = analyzer.analyze_security(&processed_content, file_path);
-                    if !security_report.mixed_content.is_empty() {
-                        error!("Mixed content found in {}: {:?}", file_path.display(), security_report.mixed_content);
-                    }
-                    if !security_report.insecure_links.is_empty() {
-                        error!("Insecure links found in {}: {:?}", file_path.display(), security_report.insecure_links);
-                    }
-                }
-                
-                if config.analyze_performance {
-                    let perf_report = analyzer.analyze_performance(&processed_content, file_path);
-                    let perf_file = Path::new(perf_dir)
-                        .join(file_path.file_name().unwrap())
-                        .with_extension("perf.txt");
-                    fs::write(&perf_file, format!(
-                        "Performance Analysis for {}\n\n{}\n\nRecommendations:\n{}",
-                        file_path.display(),
-                        perf_report.details,
-                        perf_report.recommendations.join("\n")
-                    ))?;
-                }
-            }
+/// Print every pipeline stage applied to a single content file, without writing it.
+fn explain_page(site: &Site, file: &Path) -> Result<()> {
+    let mut trace = PipelineTrace::new();
+    let page = site.render_page(file, &mut trace)?;
 
-            // Apply minification if enabled
-            let final_content = if let Some(minifier) = minifier {
-                minifier.minify_html(&processed_content)
-            } else {
-                processed_content
-            };
-
-            // Write output file
-            let out_path = Path::new(&args.output_dir)
-                .join(file_path.strip_prefix(&args.input_dir)?);
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            
-            // Use .html extension for markdown files
-            let out_path = if file_path.extension().map_or(false, |ext| ext == "md") {
-                out_path.with_extension("html")
-            } else {
-                out_path
-            };
-
-            fs::write(&out_path, final_content)?;
-            processed_files.lock().push(out_path.clone());
-            Ok(out_path)
-        })
-        .collect();
+    println!("Pipeline for {}:\n", file.display());
+    print!("{}", trace.report());
+    println!("\nWould write {} bytes to {}", page.content.len(), page.out_path.display());
+    Ok(())
+}
 
-    // Check for errors
-    let errors: Vec<_> = file_results.iter()
-        .filter_map(|r| r.as_ref().err())
-        .collect();
-    
-    if !errors.is_empty() {
-        error!("Failed to process some files:");
-        for err in errors {
-            error!("  {}", err);
-        }
-        return Err(anyhow!("Some files failed to process"));
-    }
+/// Push the pages of the last build to the hosted indexes in `[[search.push]]`.
+fn deploy_index(args: &CliArgs, all: bool) -> Result<()> {
+    let site_config = load_site_config(&args.site_config)?;
+    let targets = site_config.search.as_ref()
+        .map(|search| search.push.as_slice())
+        .filter(|targets| !targets.is_empty())
+        .ok_or_else(|| anyhow!("No [[search.push]] targets in {}", args.site_config.display()))?;
+    let cache_dir = Path::new(&args.output_dir).join("cache");
+    let documents = search::read_records(&cache_dir)?;
+    let remote_cache = site_config.remote_cache.as_ref().map(RemoteCache::from_config).transpose()?;
+    search::push_changes(targets, &documents, &cache_dir, remote_cache.as_ref(), PushOptions { all, require_key: true })?;
+    println!("Synced {} pages with {} search index(es)", documents.len(), targets.len());
+    Ok(())
+}
 
-    // Generate SEO files if enabled
-    if config.enable_seo {
-        if let Some(seo) = seo_config {
-            let processed = processed_files.lock();
-            generate_sitemap(&processed, seo, &args.output_dir)?;
-            generate_rss(&processed, seo, &args.output_dir)?;
-            generate_robots_txt(seo, &args.output_dir)?;
+/// Publish the last build's output to the `[deploy]` target.
+fn deploy_site(args: &CliArgs, all: bool, dry_run: bool) -> Result<()> {
+    let site_config = load_site_config(&args.site_config)?;
+    let config = site_config.deploy
+        .ok_or_else(|| anyhow!("No [deploy] section in {}", args.site_config.display()))?;
+    let output_dir = Path::new(&args.output_dir);
+    if !output_dir.join(HeadersManifest::FILE_NAME).exists() {
+        return Err(anyhow!("{} has no {}; build the site before deploying", output_dir.display(), HeadersManifest::FILE_NAME));
+    }
+    let started = std::time::Instant::now();
+    let result = deploy::deploy(&config, output_dir, all, dry_run);
+    if !dry_run {
+        let changed = result.as_ref()
+            .map(|plan| plan.upload.iter().chain(&plan.delete).cloned().collect())
+            .map_err(|e| anyhow!("{:#}", e));
+        let record = BuildRecord::deploy(config_hash(args, &BuildConfig::from(args)), started.elapsed().as_millis(), config.target(), &changed);
+        if let Err(e) = BuildLog::new(output_dir.join("cache")).append_record(&record) {
+            warn!("Failed to record the deploy in {}: {}", BuildLog::FILE_NAME, e);
         }
     }
+    let plan = result?;
+    for path in &plan.upload {
+        println!("{} {}", if dry_run { "Would upload" } else { "Uploaded" }, path);
+    }
+    for path in &plan.delete {
+        println!("{} {}", if dry_run { "Would delete" } else { "Deleted" }, path);
+    }
+    println!("{} changed, {} deleted, {} unchanged", plan.upload.len(), plan.delete.len(), plan.unchanged);
+    Ok(())
+}
 
+/// Send webmentions for the outbound links recorded by the last build.
+fn send_webmentions(args: &CliArgs, dry_run: bool) -> Result<()> {
+    let site_config = load_site_config(&args.site_config)?;
+    if site_config.webmentions.is_none() {
+        return Err(anyhow!("No [webmentions] section in {}", args.site_config.display()));
+    }
+    let cache_dir = Path::new(&args.output_dir).join("cache");
+    let outbound = webmentions::read_outbound(&cache_dir)?;
+    let remote_cache = site_config.remote_cache.as_ref().map(RemoteCache::from_config).transpose()?;
+    let deliveries = webmentions::send_webmentions(&outbound, &cache_dir, remote_cache.as_ref(), dry_run)?;
+    let mut failed = 0;
+    for delivery in &deliveries {
+        let status = match &delivery.outcome {
+            Outcome::Sent => "sent".to_string(),
+            Outcome::Unsupported => "no endpoint".to_string(),
+            Outcome::Pending => "would send".to_string(),
+            Outcome::Failed(e) => {
+                failed += 1;
+                format!("failed: {}", e)
+            },
+        };
+        println!("{} -> {}: {}", delivery.source, delivery.target, status);
+    }
+    println!("{} webmention(s) {}", deliveries.len() - failed, if dry_run { "to send" } else { "handled" });
+    if failed > 0 {
+        return Err(anyhow!("{} webmention(s) failed; they are retried on the next run", failed));
+    }
     Ok(())
 }
+
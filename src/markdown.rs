@@ -1,25 +1,80 @@
 use std::path::{Path, PathBuf};
 use anyhow::{Result, anyhow};
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset};
 use chrono_humanize::HumanTime;
 use pulldown_cmark::{Parser, html, Options, Event, Tag, TagEnd, CodeBlockKind};
 use serde::{Serialize, Deserialize};
 use yaml_front_matter::{YamlFrontMatter};
-use crate::variables::Variables;
+use crate::variables::{merge_vars, DirectoryVariables, Variables};
+use crate::defaults::FrontMatterDefaults;
+use crate::seo::PageSEO;
+use crate::seo_types::{absolute_url, robots_noindex, Author as Person};
+use crate::seo_html::inject_meta_tags;
+use crate::i18n::I18nConfig;
+use crate::shortcodes::Shortcodes;
+use crate::taxonomy::TaxonomyConfig;
+use crate::authors::{Author, Authors};
+use crate::git_dates::GitDates;
+use crate::comments::{inject_comments, CommentsConfig};
+use crate::page::PageFields;
+use crate::error_handler::SourceError;
+use crate::plugins::Plugins;
+use crate::macros::MacroProcessor;
+use crate::sections::{PrevNext, SectionConfig, SECTION_INDEX_FILE};
+use crate::layout::DEFAULT_LAYOUT;
+use crate::slug::Slugs;
+use crate::themes::{ComponentDirs, Theme};
 use std::fs;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
 use syntect::html::highlighted_html_for_string;
 use html_escape;
 use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // Ends the part of a post shown in listings, feeds, and descriptions
+    static ref MORE_REGEX: Regex = Regex::new(r"<!--\s*more\s*-->").unwrap();
+}
+
+/// Words in a post's excerpt when it has no `<!--more-->` marker.
+pub const DEFAULT_EXCERPT_WORDS: usize = 50;
+
+/// Excerpt settings, `[excerpts]` in `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExcerptConfig {
+    /// Words kept from posts without a `<!--more-->` marker
+    #[serde(default = "default_excerpt_words")]
+    pub words: usize,
+}
+
+impl Default for ExcerptConfig {
+    fn default() -> Self {
+        Self { words: DEFAULT_EXCERPT_WORDS }
+    }
+}
+
+fn default_excerpt_words() -> usize {
+    DEFAULT_EXCERPT_WORDS
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BlogFrontMatter {
     pub title: String,
     #[serde(default)]
     pub author: Option<String>,
+    /// Co-authors, credited after `author`
+    #[serde(default)]
+    pub authors: Vec<String>,
     pub date: String,
+    /// When the post last changed in substance, while `date` stays the
+    /// publication date
+    #[serde(default)]
+    pub updated: Option<String>,
+    /// Dated notes on what changed, in any order
+    #[serde(default)]
+    pub changelog: Vec<ChangelogEntry>,
     #[serde(default)]
     pub tags: Vec<String>,
     #[serde(default)]
@@ -32,6 +87,66 @@ pub struct BlogFrontMatter {
     pub structured_data: Option<String>,
     #[serde(default)]
     pub image: Option<String>, // For og:image and twitter:image
+    #[serde(default)]
+    pub schema_type: Option<String>,
+    #[serde(default)]
+    pub change_frequency: Option<String>,
+    #[serde(default)]
+    pub priority: Option<f32>,
+    /// Old URLs that should redirect to this post
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Robots directives, e.g. `noindex` to keep the post out of search engines
+    #[serde(default)]
+    pub robots: Option<String>,
+    /// A/B test arms, each a mapping of front matter overrides
+    #[serde(default)]
+    pub variants: serde_yaml::Mapping,
+    /// `false` leaves the comments block off this post
+    #[serde(default = "default_comments")]
+    pub comments: bool,
+    /// Layout to render the post into, relative to the components directory
+    #[serde(default)]
+    pub layout: Option<PathBuf>,
+    /// Keys the fields above don't cover, available as `@{var("...")}`
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_yaml::Value>,
+}
+
+fn default_comments() -> bool {
+    true
+}
+
+/// One item of a post's front matter `changelog`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    pub date: String,
+    #[serde(default)]
+    pub note: String,
+}
+
+/// A post rendered with the blog layout.
+#[derive(Debug)]
+pub struct RenderedPost {
+    pub html: String,
+    /// `@{page.<field>}` references in the layout that the post has no value for
+    pub missing_fields: Vec<String>,
+}
+
+/// One of a post's authors.
+#[derive(Debug, Clone)]
+pub struct PostAuthor {
+    /// As front matter gives it, a key of the authors file or a name
+    pub key: String,
+    /// Profile from the authors file that `key` refers to
+    pub profile: Option<Author>,
+}
+
+impl PostAuthor {
+    /// The profile's name, or the key when there is no profile.
+    pub fn name(&self) -> &str {
+        self.profile.as_ref().map_or(self.key.as_str(), |profile| profile.name.as_str())
+    }
 }
 
 #[derive(Debug)]
@@ -41,32 +156,228 @@ pub struct BlogPost {
     pub html_content: String,
     pub url: String,
     pub file_path: PathBuf,
+    /// Content language, set when i18n is configured
+    pub language: Option<String>,
+    /// Front matter after defaults and overrides, including keys
+    /// `BlogFrontMatter` doesn't know, such as custom taxonomies
+    pub metadata: serde_yaml::Mapping,
+    /// Front matter `author` followed by `authors`, without repeats
+    pub authors: Vec<PostAuthor>,
+    /// Plain text up to `<!--more-->`, or the first words of the post
+    pub excerpt: String,
+    /// Date of the last commit to the post, when git dates are on
+    pub last_modified: Option<DateTime<FixedOffset>>,
+}
+
+/// An RFC 3339 date and time, or a plain `YYYY-MM-DD` date at midnight UTC.
+fn parse_post_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+    DateTime::parse_from_rfc3339(date).ok().or_else(|| {
+        let midnight = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0)?;
+        Some(midnight.and_utc().fixed_offset())
+    })
 }
 
 impl BlogPost {
     pub fn from_file(file_path: &Path, content_dir: &Path) -> Result<Self> {
+        Self::from_file_with_defaults(file_path, content_dir, &FrontMatterDefaults::default())
+    }
+
+    pub fn from_file_with_defaults(file_path: &Path, content_dir: &Path, defaults: &FrontMatterDefaults) -> Result<Self> {
+        Self::from_file_with_overrides(file_path, content_dir, defaults, &serde_yaml::Mapping::new(), Slugs::default())
+    }
+
+    /// Read a post with `overrides` replacing its own front matter values,
+    /// as for one arm of its `variants`.
+    pub fn from_file_with_overrides(
+        file_path: &Path,
+        content_dir: &Path,
+        defaults: &FrontMatterDefaults,
+        overrides: &serde_yaml::Mapping,
+        slugs: Slugs,
+    ) -> Result<Self> {
         let content = fs::read_to_string(file_path)?;
-        let yaml_content = YamlFrontMatter::parse::<BlogFrontMatter>(&content)
+        // The YAML starts on the line after the opening ---
+        let yaml_content = YamlFrontMatter::parse::<serde_yaml::Mapping>(&content)
+            .map_err(|e| SourceError::from_parser("Failed to parse front matter", &*e, 2))?;
+
+        let relative_path = file_path.strip_prefix(content_dir)?;
+
+        // Merge section defaults beneath the page's own values
+        let mut metadata = yaml_content.metadata;
+        defaults.apply(relative_path, &mut metadata);
+        for (key, value) in overrides {
+            metadata.insert(key.clone(), value.clone());
+        }
+        let front_matter: BlogFrontMatter = serde_yaml::from_value(serde_yaml::Value::Mapping(metadata.clone()))
             .map_err(|e| anyhow!("Failed to parse front matter: {}", e))?;
 
         let markdown_content = yaml_content.content;
-        let html_content = markdown_to_html(&markdown_content);
+        let html_content = markdown_to_html_with(&markdown_content, slugs);
+        let excerpt = excerpt(&markdown_content, &html_content, DEFAULT_EXCERPT_WORDS);
         
         // Generate URL from file path
-        let url = file_path.strip_prefix(content_dir)?
-            .with_extension("")
+        let url = slugs.page_path(&relative_path.with_extension(""))
             .to_string_lossy()
             .to_string();
 
         Ok(BlogPost {
-            front_matter: yaml_content.metadata,
             content: markdown_content,
             html_content,
             url: format!("/{}", url),
             file_path: file_path.to_path_buf(),
+            language: None,
+            authors: post_authors(&front_matter),
+            front_matter,
+            metadata,
+            excerpt,
+            last_modified: None,
         })
     }
 
+    /// Front matter `description`, or the excerpt when there is none.
+    pub fn description(&self) -> Option<&str> {
+        self.front_matter.description.as_deref()
+            .or_else(|| Some(self.excerpt.as_str()).filter(|excerpt| !excerpt.is_empty()))
+    }
+
+    /// Front matter `updated`, or else the newest `changelog` date.
+    pub fn updated(&self) -> Option<DateTime<FixedOffset>> {
+        let front_matter = &self.front_matter;
+        match &front_matter.updated {
+            Some(updated) => parse_post_date(updated),
+            None => front_matter.changelog.iter().filter_map(|entry| parse_post_date(&entry.date)).max(),
+        }
+    }
+
+    /// When the post last changed: the date front matter gives, or the
+    /// last commit when it gives none.
+    pub fn modified(&self) -> Option<DateTime<FixedOffset>> {
+        self.updated().or(self.last_modified)
+    }
+
+    /// The lead author's name from their profile, or as front matter gives it.
+    pub fn author_name(&self) -> Option<&str> {
+        self.authors.first().map(PostAuthor::name)
+    }
+
+    /// The lead author's profile from the authors file.
+    pub fn author_profile(&self) -> Option<&Author> {
+        self.authors.first().and_then(|author| author.profile.as_ref())
+    }
+
+    /// Names of all the post's authors, lead author first.
+    pub fn author_names(&self) -> Vec<&str> {
+        self.authors.iter().map(PostAuthor::name).collect()
+    }
+
+    /// Build the page-level SEO metadata for this post from its front matter.
+    pub fn page_seo(&self) -> PageSEO {
+        PageSEO {
+            title: self.front_matter.title.clone(),
+            description: self.description().map(str::to_string),
+            keywords: self.front_matter.keywords.clone(),
+            url: Some(self.url.clone()),
+            canonical_url: self.front_matter.canonical_url.clone(),
+            path: self.url.clone(),
+            image: self.front_matter.image.clone(),
+            author: self.author_name().map(str::to_string),
+            author_url: self.author_profile().map(|profile| profile.url.clone()),
+            author_image: self.author_profile().and_then(|profile| profile.avatar.clone()),
+            co_authors: self.authors.iter().skip(1).map(|author| Person {
+                type_: "Person".to_string(),
+                name: author.name().to_string(),
+                url: author.profile.as_ref().map(|profile| profile.url.clone()),
+                image: author.profile.as_ref().and_then(|profile| profile.avatar.clone()),
+            }).collect(),
+            published_date: DateTime::parse_from_rfc3339(&self.front_matter.date).ok(),
+            last_modified: self.modified(),
+            category: None,
+            tags: if self.front_matter.tags.is_empty() { None } else { Some(self.front_matter.tags.clone()) },
+            schema_type: self.front_matter.schema_type.clone(),
+            structured_data: self.front_matter.structured_data.as_deref()
+                .and_then(|data| serde_json::from_str(data).ok()),
+            change_frequency: self.front_matter.change_frequency.clone(),
+            priority: self.front_matter.priority,
+            alternates: Vec::new(),
+            robots: self.front_matter.robots.clone(),
+            section_titles: Default::default(),
+        }
+    }
+
+    pub fn is_noindex(&self) -> bool {
+        self.front_matter.robots.as_deref().is_some_and(robots_noindex)
+    }
+
+    /// Extra front matter keys as page variables, with the entries of a
+    /// `variables` mapping taking precedence. Nulls have no TOML form and
+    /// are left out.
+    pub fn page_vars(&self) -> HashMap<String, toml::Value> {
+        let to_toml = |value: &serde_yaml::Value| toml::Value::try_from(value).ok();
+        let mut vars: HashMap<String, toml::Value> = self.front_matter.extra.iter()
+            .filter(|(key, _)| *key != "variables")
+            .filter_map(|(key, value)| Some((key.clone(), to_toml(value)?)))
+            .collect();
+        if let Some(serde_yaml::Value::Mapping(variables)) = self.front_matter.extra.get("variables") {
+            vars.extend(variables.iter().filter_map(|(key, value)| Some((key.as_str()?.to_string(), to_toml(value)?))));
+        }
+        vars
+    }
+
+    /// Front matter with its own types, including keys only defaults set.
+    /// Declared fields come with their defaults, so a post without tags has
+    /// an empty list.
+    pub fn fields(&self) -> Result<serde_yaml::Mapping> {
+        let mut fields = match serde_yaml::to_value(&self.front_matter)? {
+            serde_yaml::Value::Mapping(fields) => fields,
+            _ => serde_yaml::Mapping::new(),
+        };
+        for (key, value) in &self.metadata {
+            if !fields.contains_key(key) {
+                fields.insert(key.clone(), value.clone());
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Front matter as the page's own `@for` collections, like `tags`.
+    /// Nulls have no TOML form and are left out.
+    pub fn loop_fields(&self) -> Result<HashMap<String, toml::Value>> {
+        Ok(self.fields()?.iter()
+            .filter_map(|(key, value)| Some((key.as_str()?.to_string(), toml::Value::try_from(value).ok()?)))
+            .collect())
+    }
+
+    /// The post as an item of the `posts` collection that `@for` loops
+    /// iterate over.
+    pub fn listing_entry(&self) -> toml::Value {
+        let front_matter = &self.front_matter;
+        let mut entry = toml::Table::new();
+        entry.insert("title".to_string(), front_matter.title.clone().into());
+        entry.insert("url".to_string(), self.url.clone().into());
+        entry.insert("date".to_string(), front_matter.date.clone().into());
+        entry.insert("tags".to_string(), front_matter.tags.clone().into());
+        entry.insert("excerpt".to_string(), self.excerpt.clone().into());
+        if let Some(updated) = self.updated() {
+            entry.insert("updated".to_string(), updated.to_rfc3339().into());
+        }
+        if !self.authors.is_empty() {
+            entry.insert("authors".to_string(), self.author_names().into());
+        }
+        let optional = [
+            ("image", &front_matter.image),
+            ("description", &front_matter.description),
+            ("author", &front_matter.author),
+            ("language", &self.language),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                entry.insert(key.to_string(), value.clone().into());
+            }
+        }
+        toml::Value::Table(entry)
+    }
+
     pub fn formatted_date(&self) -> Result<String> {
         let date = DateTime::parse_from_rfc3339(&self.front_matter.date)
             .map_err(|e| anyhow!("Invalid date format: {}", e))?;
@@ -77,10 +388,9 @@ impl BlogPost {
     pub fn generate_json_ld(&self, site_name: &str, base_url: &str) -> Result<String> {
         let mut json_ld = serde_json::json!({
             "@context": "https://schema.org",
-            "@type": "Article",
+            "@type": self.front_matter.schema_type.as_deref().unwrap_or("Article"),
             "headline": self.front_matter.title,
             "datePublished": self.front_matter.date,
-            "dateModified": self.front_matter.date,
             "url": format!("{}{}", base_url.trim_end_matches('/'), self.url),
             "publisher": {
                 "@type": "Organization",
@@ -90,17 +400,38 @@ impl BlogPost {
         });
 
         // Add optional fields if they exist
-        if let Some(description) = &self.front_matter.description {
+        if let Some(modified) = self.modified() {
             json_ld.as_object_mut().unwrap()
-                .insert("description".to_string(), serde_json::Value::String(description.clone()));
+                .insert("dateModified".to_string(), serde_json::Value::String(modified.to_rfc3339()));
         }
 
-        if let Some(author) = &self.front_matter.author {
+        if let Some(description) = self.description() {
             json_ld.as_object_mut().unwrap()
-                .insert("author".to_string(), serde_json::json!({
-                    "@type": "Person",
-                    "name": author
-                }));
+                .insert("description".to_string(), serde_json::Value::String(description.to_string()));
+        }
+
+        // One author is a Person, co-authors make a list of them
+        let absolute = |path: &str| absolute_url(base_url, path);
+        let mut people: Vec<serde_json::Value> = self.authors.iter().map(|author| {
+            let mut person = serde_json::json!({
+                "@type": "Person",
+                "name": author.name()
+            });
+            if let Some(profile) = &author.profile {
+                person["url"] = serde_json::Value::String(absolute(&profile.url));
+                if let Some(avatar) = &profile.avatar {
+                    person["image"] = serde_json::Value::String(absolute(avatar));
+                }
+            }
+            person
+        }).collect();
+        let author = match people.len() {
+            0 => None,
+            1 => people.pop(),
+            _ => Some(serde_json::Value::Array(people)),
+        };
+        if let Some(author) = author {
+            json_ld.as_object_mut().unwrap().insert("author".to_string(), author);
         }
 
         if let Some(image) = &self.front_matter.image {
@@ -126,7 +457,116 @@ impl BlogPost {
     }
 }
 
+/// Plain text of a post up to its `<!--more-->` marker, or else its first
+/// `words` words with an ellipsis when there are more.
+pub fn excerpt(markdown: &str, html: &str, words: usize) -> String {
+    let text = match MORE_REGEX.find(markdown) {
+        Some(marker) => return collapse_whitespace(&html_text(&markdown_to_html(&markdown[..marker.start()]))),
+        None => html_text(html),
+    };
+    let all: Vec<&str> = text.split_whitespace().collect();
+    if all.len() <= words {
+        return all.join(" ");
+    }
+    format!("{}…", all[..words].join(" ").trim_end_matches(|c: char| c.is_ascii_punctuation()))
+}
+
+fn html_text(html: &str) -> String {
+    scraper::Html::parse_fragment(html).root_element().text().collect()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Slug of the first value of front matter `field` of `post`, which may be
+/// a single value or a list.
+fn first_value_slug(post: &BlogPost, field: &str, slugs: Slugs) -> Option<String> {
+    let value = match post.metadata.get(field)? {
+        serde_yaml::Value::Sequence(values) => values.first()?,
+        value => value,
+    };
+    let text = match value {
+        serde_yaml::Value::String(text) => text.clone(),
+        serde_yaml::Value::Number(number) => number.to_string(),
+        _ => return None,
+    };
+    Some(slugs.slugify(&text)).filter(|slug| !slug.is_empty())
+}
+
+/// A neighbouring post as `@{prev_post.url}` and `@{prev_post.title}`.
+fn nav_link(post: &BlogPost) -> serde_yaml::Mapping {
+    let mut link = serde_yaml::Mapping::new();
+    link.insert("url".into(), post.url.clone().into());
+    link.insert("title".into(), post.front_matter.title.clone().into());
+    link
+}
+
+/// The "last updated" banner of a post that gives an `updated` date or a
+/// changelog, empty otherwise.
+fn last_updated_html(post: &BlogPost) -> String {
+    let Some(updated) = post.updated() else { return String::new() };
+    format!(
+        "<p class=\"last-updated\">Last updated <time datetime=\"{}\">{}</time></p>",
+        updated.to_rfc3339(),
+        updated.format("%B %-d, %Y")
+    )
+}
+
+/// A post's `changelog` as a list, newest first.
+fn changelog_html(post: &BlogPost) -> String {
+    let mut entries: Vec<(DateTime<FixedOffset>, &str)> = post.front_matter.changelog.iter()
+        .filter_map(|entry| Some((parse_post_date(&entry.date)?, entry.note.as_str())))
+        .collect();
+    if entries.is_empty() {
+        return String::new();
+    }
+    entries.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+    let mut html = String::from("<ul class=\"changelog\">");
+    for (date, note) in entries {
+        html.push_str(&format!(
+            "<li><time datetime=\"{}\">{}</time> {}</li>",
+            date.to_rfc3339(),
+            date.format("%B %-d, %Y"),
+            html_escape::encode_text(note)
+        ));
+    }
+    html.push_str("</ul>");
+    html
+}
+
+/// Authors of a post by front matter `author` and then `authors`, each once.
+fn post_authors(front_matter: &BlogFrontMatter) -> Vec<PostAuthor> {
+    let mut authors: Vec<PostAuthor> = Vec::new();
+    for key in front_matter.author.iter().chain(&front_matter.authors) {
+        let key = key.trim();
+        if !key.is_empty() && !authors.iter().any(|author| author.key.eq_ignore_ascii_case(key)) {
+            authors.push(PostAuthor { key: key.to_string(), profile: None });
+        }
+    }
+    authors
+}
+
+/// Whether the front matter of the content file at `path` names `author`,
+/// as `author` or among `authors`, ignoring case and surrounding whitespace.
+pub fn is_authored_by(path: &Path, author: &str) -> bool {
+    let Ok(content) = fs::read_to_string(path) else { return false };
+    let Ok(document) = YamlFrontMatter::parse::<serde_yaml::Mapping>(&content) else { return false };
+    let co_authors = document.metadata.get("authors")
+        .and_then(|value| value.as_sequence())
+        .into_iter()
+        .flatten();
+    document.metadata.get("author").into_iter().chain(co_authors)
+        .filter_map(|value| value.as_str())
+        .any(|name| name.trim().eq_ignore_ascii_case(author.trim()))
+}
+
 pub fn markdown_to_html(content: &str) -> String {
+    markdown_to_html_with(content, Slugs::default())
+}
+
+/// [`markdown_to_html`] with heading anchors slugged by `slugs`.
+pub fn markdown_to_html_with(content: &str, slugs: Slugs) -> String {
     lazy_static! {
         static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
         static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
@@ -145,13 +585,39 @@ pub fn markdown_to_html(content: &str) -> String {
     let mut in_code_block = false;
     let mut code_content = String::new();
     let mut code_lang = String::new();
+    // Headings get an anchor from their text: where the open one starts, and its text
+    let mut heading: Option<(usize, String)> = None;
+    let mut anchors: HashMap<String, usize> = HashMap::new();
     
     for event in parser {
         match event {
             Event::Code(code) => {
+                if let Some((_, text)) = heading.as_mut() {
+                    text.push_str(&code);
+                }
                 let escaped = html_escape::encode_text(&code);
                 html_output.push_str(&format!("<code>{}</code>", escaped));
             },
+            Event::Start(Tag::Heading { .. }) => {
+                heading = Some((html_output.len(), String::new()));
+            },
+            Event::End(TagEnd::Heading(level)) => {
+                let Some((start, text)) = heading.take() else { continue };
+                let inner = html_output.split_off(start);
+                let mut id = slugs.slugify(&text);
+                if id.is_empty() {
+                    id = "section".to_string();
+                }
+                let count = anchors.entry(id.clone()).or_insert(0);
+                if *count > 0 {
+                    id = format!("{}-{}", id, count);
+                }
+                *count += 1;
+                if !html_output.is_empty() && !html_output.ends_with('\n') {
+                    html_output.push('\n');
+                }
+                html_output.push_str(&format!("<{} id=\"{}\">{}</{}>\n", level, id, inner, level));
+            },
             Event::Start(Tag::CodeBlock(kind)) => {
                 in_code_block = true;
                 code_content.clear();
@@ -190,6 +656,9 @@ pub fn markdown_to_html(content: &str) -> String {
                 if in_code_block {
                     code_content.push_str(&text);
                 } else {
+                    if let Some((_, heading_text)) = heading.as_mut() {
+                        heading_text.push_str(&text);
+                    }
                     html::push_html(&mut html_output, std::iter::once(Event::Text(text)));
                 }
             },
@@ -207,39 +676,370 @@ pub fn markdown_to_html(content: &str) -> String {
 pub struct BlogProcessor {
     posts: Vec<BlogPost>,
     content_dir: PathBuf,
+    defaults: FrontMatterDefaults,
+    i18n: Option<I18nConfig>,
+    shortcodes: Option<Shortcodes>,
+    taxonomies: Vec<TaxonomyConfig>,
+    authors: Option<Authors>,
+    git_dates: Option<GitDates>,
+    comments: Option<CommentsConfig>,
+    excerpt_words: usize,
+    /// Content transformers applied to each post's Markdown
+    plugins: Plugins,
+    /// Theme whose layout posts use unless the site has its own
+    theme: Option<Theme>,
+    /// Site variables for `@{var("...")}` in posts and the blog layout
+    variables: Option<Variables>,
+    /// Variables from `_vars.toml` files above each post
+    dir_vars: DirectoryVariables,
+    /// Expands `@{include("...")}` in posts and the blog layout
+    macro_processor: Option<MacroProcessor>,
+    /// Directories posts are read from, with their layouts and URLs
+    sections: Vec<SectionConfig>,
+    /// Site components, searched before the theme's
+    components_dir: PathBuf,
+    /// Layout of posts that name none, relative to the components directories
+    layout: PathBuf,
+    /// Posts prev/next links step through where the section doesn't say
+    prev_next: PrevNext,
+    /// How post URLs, heading anchors, and terms are slugged
+    slugs: Slugs,
+}
+
+/// Layout posts are rendered into unless the site configures another.
+pub const DEFAULT_BLOG_LAYOUT: &str = "blog_layout.html";
+
+/// `components` next to the content directory.
+fn default_components_dir(content_dir: &Path) -> PathBuf {
+    content_dir.parent().unwrap_or(Path::new("")).join("components")
 }
 
 impl BlogProcessor {
     pub fn new(content_dir: PathBuf) -> Self {
         Self {
             posts: Vec::new(),
+            defaults: FrontMatterDefaults::default(),
+            i18n: None,
+            shortcodes: None,
+            taxonomies: Vec::new(),
+            authors: None,
+            git_dates: None,
+            comments: None,
+            excerpt_words: DEFAULT_EXCERPT_WORDS,
+            plugins: Plugins::default(),
+            theme: None,
+            variables: None,
+            dir_vars: DirectoryVariables::default(),
+            macro_processor: None,
+            sections: SectionConfig::with_blog(&[]),
+            components_dir: default_components_dir(&content_dir),
+            layout: PathBuf::from(DEFAULT_BLOG_LAYOUT),
+            prev_next: PrevNext::default(),
+            slugs: Slugs::default(),
             content_dir,
         }
     }
 
-    pub fn with_option_components(content_dir: PathBuf, _vars: Option<Variables>) -> Self {
+    pub fn with_option_components(content_dir: PathBuf, vars: Option<Variables>) -> Self {
         Self {
             posts: Vec::new(),
+            defaults: FrontMatterDefaults::default(),
+            i18n: None,
+            shortcodes: None,
+            taxonomies: Vec::new(),
+            authors: None,
+            git_dates: None,
+            comments: None,
+            excerpt_words: DEFAULT_EXCERPT_WORDS,
+            plugins: Plugins::default(),
+            theme: None,
+            variables: vars,
+            dir_vars: DirectoryVariables::default(),
+            macro_processor: None,
+            sections: SectionConfig::with_blog(&[]),
+            components_dir: default_components_dir(&content_dir),
+            layout: PathBuf::from(DEFAULT_BLOG_LAYOUT),
+            prev_next: PrevNext::default(),
+            slugs: Slugs::default(),
             content_dir,
         }
     }
 
+    pub fn with_dir_vars(mut self, dir_vars: DirectoryVariables) -> Self {
+        self.dir_vars = dir_vars;
+        self
+    }
+
+    pub fn with_macros(mut self, processor: Option<MacroProcessor>) -> Self {
+        self.macro_processor = processor;
+        self
+    }
+
+    /// Look for layouts in `dir` before the theme's components.
+    pub fn with_components_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.components_dir = dir.into();
+        self
+    }
+
+    /// Render posts into `layout`, or `blog_layout.html` when it is `None`,
+    /// unless they or their section name another.
+    pub fn with_layout(mut self, layout: Option<PathBuf>) -> Self {
+        self.layout = layout.unwrap_or_else(|| PathBuf::from(DEFAULT_BLOG_LAYOUT));
+        self
+    }
+
+    /// Read posts from these sections as well as `blog`.
+    pub fn with_sections(mut self, sections: &[SectionConfig]) -> Self {
+        self.sections = SectionConfig::with_blog(sections);
+        self
+    }
+
+    pub fn sections(&self) -> &[SectionConfig] {
+        &self.sections
+    }
+
+    pub fn with_slugs(mut self, slugs: Slugs) -> Self {
+        self.slugs = slugs;
+        self
+    }
+
+    pub fn slugs(&self) -> Slugs {
+        self.slugs
+    }
+
+    pub fn with_prev_next(mut self, prev_next: PrevNext) -> Self {
+        self.prev_next = prev_next;
+        self
+    }
+
+    pub fn with_defaults(mut self, defaults: FrontMatterDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    pub fn with_i18n(mut self, i18n: I18nConfig) -> Self {
+        self.i18n = Some(i18n);
+        self
+    }
+
+    pub fn with_shortcodes(mut self, shortcodes: Shortcodes) -> Self {
+        self.shortcodes = Some(shortcodes);
+        self
+    }
+
+    /// Cut excerpts of posts without a `<!--more-->` marker after `words` words.
+    pub fn with_excerpt_words(mut self, words: usize) -> Self {
+        self.excerpt_words = words;
+        self
+    }
+
+    /// Resolve front matter `author` keys to these profiles.
+    pub fn with_authors(mut self, authors: Option<Authors>) -> Self {
+        self.authors = authors;
+        self
+    }
+
+    pub fn authors(&self) -> Option<&Authors> {
+        self.authors.as_ref()
+    }
+
+    /// Date posts by their last commit, for `dateModified` and the sitemap.
+    pub fn with_git_dates(mut self, git_dates: Option<GitDates>) -> Self {
+        self.git_dates = git_dates;
+        self
+    }
+
+    pub fn git_dates(&self) -> Option<&GitDates> {
+        self.git_dates.as_ref()
+    }
+
+    /// Add this provider's comment thread to every post that doesn't opt out.
+    pub fn with_comments(mut self, comments: Option<CommentsConfig>) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Run each post's Markdown through the content transformers of `plugins`.
+    pub fn with_plugins(mut self, plugins: Plugins) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Option<Theme>) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Give posts an `@{<name>}` variable linking their terms in each taxonomy.
+    pub fn with_taxonomies(mut self, taxonomies: Vec<TaxonomyConfig>) -> Self {
+        self.taxonomies = taxonomies;
+        self
+    }
+
+    pub fn defaults(&self) -> &FrontMatterDefaults {
+        &self.defaults
+    }
+
+    /// Layout posts are rendered into by default: the site's own, or the theme's.
+    pub fn layout_path(&self) -> PathBuf {
+        self.components().resolve(&self.layout)
+    }
+
+    fn components(&self) -> ComponentDirs {
+        ComponentDirs::new(&self.components_dir, self.theme.as_ref())
+    }
+
+    /// Path of a content file relative to the content directory, without
+    /// its language directory or suffix.
+    pub fn neutral_path(&self, path: &Path) -> PathBuf {
+        let relative = path.strip_prefix(&self.content_dir).unwrap_or(path);
+        match &self.i18n {
+            Some(i18n) => i18n.localize(relative).path,
+            None => relative.to_path_buf(),
+        }
+    }
+
+    /// Section of the content file at `path`, the innermost one when
+    /// sections nest.
+    pub fn section_of(&self, path: &Path) -> Option<&SectionConfig> {
+        let relative = self.neutral_path(path);
+        self.sections.iter()
+            .filter(|section| section.contains(&relative))
+            .max_by_key(|section| section.path.components().count())
+    }
+
+    /// Layout `post` is rendered into: the one its front matter names, then
+    /// a `layout` from the `_vars.toml` files above it, its section's, or the
+    /// blog layout.
+    pub fn layout_of(&self, post: &BlogPost) -> PathBuf {
+        match &post.front_matter.layout {
+            Some(layout) => self.components().resolve(layout),
+            None => self.layout_for(&post.file_path),
+        }
+    }
+
+    /// Layout of the Markdown file at `path` besides the one its front
+    /// matter names, as for [`BlogProcessor::layout_of`].
+    pub fn layout_for(&self, path: &Path) -> PathBuf {
+        let relative_path = path.strip_prefix(&self.content_dir).unwrap_or(path);
+        let dir_layout = self.dir_vars.for_page(relative_path).get("layout")
+            .and_then(toml::Value::as_str)
+            .map(PathBuf::from);
+        match dir_layout.or_else(|| self.section_of(path).and_then(|section| section.layout.clone())) {
+            Some(layout) => self.components().resolve(layout),
+            None => self.layout_path(),
+        }
+    }
+
+    /// Layout of the Markdown file at `path`, from its front matter and
+    /// front matter defaults when they name one. Reads only the front matter.
+    pub fn layout_of_file(&self, path: &Path) -> PathBuf {
+        let front_matter = fs::read_to_string(path).ok()
+            .and_then(|content| YamlFrontMatter::parse::<serde_yaml::Mapping>(&content).ok())
+            .map(|document| document.metadata);
+        let mut metadata = front_matter.unwrap_or_default();
+        self.defaults.apply(path.strip_prefix(&self.content_dir).unwrap_or(path), &mut metadata);
+        match metadata.get("layout").and_then(serde_yaml::Value::as_str) {
+            Some(layout) => self.components().resolve(layout),
+            None => self.layout_for(path),
+        }
+    }
+
+    /// URL of the post at `path` from its section's permalink pattern, under
+    /// its language's prefix. `None` when the section has no pattern.
+    fn permalink(&self, path: &Path, date: &str) -> Result<Option<String>> {
+        let Some(section) = self.section_of(path) else { return Ok(None) };
+        let relative_path = path.strip_prefix(&self.content_dir)?;
+        let localized = self.i18n.as_ref().map(|i18n| (i18n, i18n.localize(relative_path)));
+        let neutral = localized.as_ref().map_or(relative_path, |(_, localized)| localized.path.as_path());
+        let permalink = section.permalink(neutral, date, self.slugs)?;
+        Ok(permalink.map(|permalink| match &localized {
+            Some((i18n, localized)) if localized.language != i18n.default_language => format!("/{}{}", localized.language, permalink),
+            _ => permalink,
+        }))
+    }
+
+    /// Permalink of the Markdown file at `path`, as for a post read from it,
+    /// reading only its front matter.
+    pub fn permalink_of_file(&self, path: &Path) -> Result<Option<String>> {
+        if self.section_of(path).is_none_or(|section| section.permalink.is_none()) {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        let mut metadata = YamlFrontMatter::parse::<serde_yaml::Mapping>(&content)
+            .map_err(|e| SourceError::from_parser("Failed to parse front matter", &*e, 2))?
+            .metadata;
+        self.defaults.apply(path.strip_prefix(&self.content_dir)?, &mut metadata);
+        let date = metadata.get("date").and_then(serde_yaml::Value::as_str).unwrap_or_default();
+        self.permalink(path, date)
+    }
+
+    /// Read a single post, applying the configured front matter defaults
+    /// and, with i18n enabled, its language-specific URL.
+    pub fn read_post(&self, path: &Path) -> Result<BlogPost> {
+        self.read_post_with(path, &serde_yaml::Mapping::new())
+    }
+
+    /// Read a single post with front matter `overrides`, e.g. from a variant.
+    pub fn read_post_with(&self, path: &Path, overrides: &serde_yaml::Mapping) -> Result<BlogPost> {
+        let mut post = BlogPost::from_file_with_overrides(path, &self.content_dir, &self.defaults, overrides, self.slugs)?;
+        let transformed = !self.plugins.transformers.is_empty();
+        if transformed {
+            post.content = self.plugins.transform(path, std::mem::take(&mut post.content))?;
+        }
+        if let Some(shortcodes) = self.shortcodes.as_ref().filter(|_| post.content.contains("{{<")) {
+            post.html_content = markdown_to_html_with(&shortcodes.expand(&post.content, path), self.slugs);
+        } else if transformed {
+            post.html_content = markdown_to_html_with(&post.content, self.slugs);
+        }
+        post.excerpt = excerpt(&post.content, &post.html_content, self.excerpt_words);
+        if let Some(i18n) = &self.i18n {
+            let localized = i18n.localize(path.strip_prefix(&self.content_dir)?);
+            let url = self.slugs.page_path(&i18n.output_path(&localized).with_extension(""));
+            post.url = format!("/{}", url.to_string_lossy().replace('\\', "/"));
+            post.language = Some(localized.language);
+        }
+        if let Some(permalink) = self.permalink(path, &post.front_matter.date)? {
+            post.url = permalink;
+        }
+        if let Some(authors) = &self.authors {
+            for author in &mut post.authors {
+                author.profile = authors.get(&author.key).cloned();
+            }
+        }
+        // A post committed ahead of its date was not modified before it was published
+        if let Some(dates) = self.git_dates.as_ref().and_then(|git| git.get(path)) {
+            let published = DateTime::parse_from_rfc3339(&post.front_matter.date).ok();
+            post.last_modified = Some(published.map_or(dates.modified, |published| published.max(dates.modified)));
+        }
+        Ok(post)
+    }
+
     pub fn load_posts(&mut self) -> Result<()> {
         self.posts.clear();
-        let blog_dir = self.content_dir.join("blog");
-        
-        if !blog_dir.exists() {
-            return Ok(());
-        }
-
-        for entry in fs::read_dir(blog_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.extension().map_or(false, |ext| ext == "md") {
-                match BlogPost::from_file(&path, &self.content_dir) {
-                    Ok(post) => self.posts.push(post),
-                    Err(e) => log::warn!("Failed to load blog post {}: {}", path.display(), e),
+        let section_dirs: Vec<PathBuf> = self.sections.iter()
+            .flat_map(|section| match &self.i18n {
+                Some(i18n) => i18n.section_dirs(&section.path),
+                None => vec![section.path.clone()],
+            })
+            .collect();
+
+        // Nested sections are walked by their parents too
+        let mut seen = HashSet::new();
+        for section_dir in section_dirs {
+            let section_dir = self.content_dir.join(section_dir);
+            if !section_dir.exists() {
+                continue;
+            }
+
+            for entry in walkdir::WalkDir::new(section_dir).sort_by_file_name() {
+                let path = entry?.into_path();
+                let is_index = path.file_name().is_some_and(|name| name == SECTION_INDEX_FILE);
+                if path.extension().is_some_and(|ext| ext == "md") && !is_index && seen.insert(path.clone()) {
+                    match self.read_post(&path) {
+                        Ok(post) => self.posts.push(post),
+                        Err(e) => log::warn!("Failed to load blog post {}: {}", path.display(), e),
+                    }
                 }
             }
         }
@@ -252,16 +1052,37 @@ impl BlogProcessor {
         Ok(())
     }
 
-    pub fn process_post(&self, post: &BlogPost) -> Result<String> {
-        // Find prev/next posts
-        let post_idx = self.posts.iter().position(|p| p.url == post.url);
-        let prev_post = post_idx.and_then(|idx| self.posts.get(idx + 1));
-        let next_post = post_idx.and_then(|idx| idx.checked_sub(1).and_then(|i| self.posts.get(i)));
+    /// Posts in the same language as `post` that its prev/next links step
+    /// through, newest first, by its section's `prev_next` or the site's.
+    fn siblings(&self, post: &BlogPost) -> Vec<&BlogPost> {
+        let section = self.section_of(&post.file_path);
+        let scope = section.and_then(|section| section.prev_next.as_ref()).unwrap_or(&self.prev_next);
+        let same_language = self.posts.iter().filter(|p| p.language == post.language);
+        match scope {
+            PrevNext::All => same_language.collect(),
+            PrevNext::Section => same_language
+                .filter(|p| self.section_of(&p.file_path).map(|s| &s.path) == section.map(|s| &s.path))
+                .collect(),
+            PrevNext::Field(field) => match first_value_slug(post, field, self.slugs) {
+                Some(value) => same_language
+                    .filter(|p| first_value_slug(p, field, self.slugs).as_ref() == Some(&value))
+                    .collect(),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    pub fn process_post(&self, post: &BlogPost) -> Result<RenderedPost> {
+        // Find prev/next posts among its siblings
+        let siblings = self.siblings(post);
+        let post_idx = siblings.iter().position(|p| p.url == post.url);
+        let prev_post = post_idx.and_then(|idx| siblings.get(idx + 1));
+        let next_post = post_idx.and_then(|idx| idx.checked_sub(1).and_then(|i| siblings.get(i)));
 
         // Set up variables for the blog template
-        let mut variables = HashMap::new();
-        variables.insert("title".to_string(), post.front_matter.title.clone());
-        variables.insert("date".to_string(), post.formatted_date()?);
+        let mut variables = PageFields::default();
+        variables.set("title", post.front_matter.title.clone());
+        variables.set("date", post.formatted_date()?);
 
         // SEO metadata
         let mut seo_comment = format!(
@@ -269,10 +1090,11 @@ impl BlogProcessor {
             &post.front_matter.title
         );
 
-        if let Some(desc) = &post.front_matter.description {
-            variables.insert("description".to_string(), desc.clone());
-            seo_comment.push_str(&format!("  \"description\": \"{}\",\n", desc));
+        if let Some(desc) = post.description() {
+            variables.set("description", desc.to_string());
+            seo_comment.push_str(&format!("  \"description\": {},\n", serde_json::to_string(desc)?));
         }
+        variables.set("excerpt", html_escape::encode_text(&post.excerpt).into_owned());
 
         if let Some(keywords) = &post.front_matter.keywords {
             seo_comment.push_str(&format!("  \"keywords\": {},\n", serde_json::to_string(keywords)?));
@@ -284,51 +1106,131 @@ impl BlogProcessor {
             seo_comment.push_str(&format!("  \"canonical_url\": \"{}\",\n", canonical));
         }
 
-        // Generate JSON-LD if not provided in front matter
+        // Generate JSON-LD if not provided in front matter, or if what was provided isn't JSON
         let structured_data = match &post.front_matter.structured_data {
-            Some(data) => data.clone(),
+            Some(data) if serde_json::from_str::<serde_json::Value>(data).is_ok() => data.clone(),
+            Some(_) => {
+                log::warn!("Ignoring structured_data in {}: not valid JSON", post.file_path.display());
+                post.generate_json_ld("Eldroid SSG", "https://eldroid-ssg.dev")?
+            },
             None => post.generate_json_ld("Eldroid SSG", "https://eldroid-ssg.dev")?
         };
         seo_comment.push_str(&format!("  \"structured_data\": {},\n", structured_data));
 
         if let Some(image) = &post.front_matter.image {
-            variables.insert("og_image".to_string(), image.clone());
+            variables.set("og_image", image.clone());
         }
         
-        if let Some(author) = &post.front_matter.author {
-            variables.insert("author".to_string(), author.clone());
+        if let Some(author) = post.author_name() {
+            variables.set("author", author.to_string());
+            variables.set("authors", post.author_names().join(", "));
+        }
+        if let Some(profile) = post.author_profile() {
+            variables.set("author_url", profile.url.clone());
         }
 
         seo_comment.push_str("}} -->\n");
-        variables.insert("seo_meta".to_string(), seo_comment);
-        variables.insert("json_ld".to_string(), structured_data);
+        variables.set("seo_meta", seo_comment);
+        variables.set("json_ld", structured_data);
         
+        variables.set("last_updated", last_updated_html(post));
+        variables.set("changelog", changelog_html(post));
+
         // Navigation
         if let Some(prev) = prev_post {
-            variables.insert("prev_post.url".to_string(), prev.url.clone());
-            variables.insert("prev_post.title".to_string(), prev.front_matter.title.clone());
+            variables.set("prev_post", nav_link(prev));
         }
 
         if let Some(next) = next_post {
-            variables.insert("next_post.url".to_string(), next.url.clone());
-            variables.insert("next_post.title".to_string(), next.front_matter.title.clone());
+            variables.set("next_post", nav_link(next));
         }
 
-        variables.insert("navigation_tree".to_string(), self.generate_navigation_tree());
-        variables.insert("site_title".to_string(), "Blog".to_string());
+        for taxonomy in &self.taxonomies {
+            variables.set(&taxonomy.name, taxonomy.links_html(post, self.slugs));
+        }
+
+        variables.set("navigation_tree", self.generate_navigation_tree());
+        variables.set("site_title", "Blog".to_string());
 
         // Generate final HTML using the blog layout
-        let blog_layout = fs::read_to_string(self.content_dir.parent().unwrap().join("components/blog_layout.html"))?;
+        let layout_path = self.layout_of(post);
+        let blog_layout = match fs::read_to_string(&layout_path) {
+            Ok(layout) => layout,
+            // A layout the post names itself has to exist
+            Err(e) if post.front_matter.layout.is_some() => {
+                return Err(anyhow!("Failed to read layout {} named in {}: {}", layout_path.display(), post.file_path.display(), e));
+            },
+            Err(e) => {
+                log::warn!("Failed to read layout {}: {}, using default", layout_path.display(), e);
+                DEFAULT_LAYOUT.to_string()
+            },
+        };
+
+        // Front matter with its own types, plus computed fields, as @{page.<field>}
+        let mut page = PageFields::new(&post.fields()?);
+        page.set("url", post.url.clone());
+        page.set("relative_date", post.formatted_date()?);
+        page.set("excerpt", post.excerpt.clone());
+        if let Some(modified) = &post.last_modified {
+            page.set("last_modified", modified.to_rfc3339());
+        }
+        if let Some(language) = &post.language {
+            page.set("language", language.clone());
+        }
+        if let Some(profile) = post.author_profile() {
+            page.set("author_profile", serde_yaml::to_value(profile)?);
+        }
+        if !post.authors.is_empty() {
+            let profiles: Vec<&Author> = post.authors.iter().filter_map(|author| author.profile.as_ref()).collect();
+            page.set("authors", serde_yaml::to_value(post.author_names())?);
+            page.set("author_profiles", serde_yaml::to_value(profiles)?);
+        }
+        let (blog_layout, missing_fields) = page.substitute(&blog_layout);
         
+        // Comments go in before the post, so a post can't place its own thread
+        let comments = match &self.comments {
+            Some(comments) if post.front_matter.comments => comments.embed_html(&post.url),
+            _ => String::new(),
+        };
+        let blog_layout = inject_comments(&blog_layout, &comments);
+
         // Inject the post content and variables into the template
         let mut content = blog_layout.replace("@{yield}", &post.html_content);
 
         // Process variables
-        for (key, value) in variables {
-            content = content.replace(&format!("@{{{}}}",key), &value);
+        content = variables.substitute_named(&content);
+
+        if let Some(processor) = &self.macro_processor {
+            content = processor.include(&content, Some(&post.file_path));
+        }
+
+        // @{var("...")} in the layout and post, with the post's front matter
+        // over its directories' _vars.toml and those over the site's
+        let relative_path = post.file_path.strip_prefix(&self.content_dir).unwrap_or(&post.file_path);
+        let mut page_vars = self.dir_vars.for_page(relative_path);
+        merge_vars(&mut page_vars, post.page_vars());
+        if self.variables.is_some() || !page_vars.is_empty() {
+            let mut vars = self.variables.clone().unwrap_or_default();
+            vars.set_page_vars(page_vars);
+            content = vars.substitute(&content);
+        }
+        if let Some(processor) = &self.macro_processor {
+            content = processor.process(&content);
+        }
+
+        if let Some(robots) = &post.front_matter.robots {
+            content = inject_meta_tags(&content, &format!("<meta name=\"robots\" content=\"{}\">", html_escape::encode_double_quoted_attribute(robots)));
         }
 
-        Ok(content)
+        Ok(RenderedPost { html: content, missing_fields })
+    }
+
+    pub fn posts(&self) -> &[BlogPost] {
+        &self.posts
+    }
+
+    pub fn into_posts(self) -> Vec<BlogPost> {
+        self.posts
     }
 
     pub fn generate_navigation_tree(&self) -> String {
@@ -346,3 +1248,206 @@ impl BlogProcessor {
         html
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt_stops_at_more_marker_or_word_limit() {
+        let markdown = "Intro with *emphasis*.\n\nSecond paragraph.\n\n<!-- more -->\n\nThe rest.";
+        assert_eq!(excerpt(markdown, &markdown_to_html(markdown), 3), "Intro with emphasis. Second paragraph.");
+
+        let markdown = "# Title\n\nOne two, three four.";
+        let html = markdown_to_html(markdown);
+        assert_eq!(excerpt(markdown, &html, 3), "Title One two…");
+        assert_eq!(excerpt(markdown, &html, 10), "Title One two, three four.");
+    }
+
+    #[test]
+    fn test_urls_and_heading_anchors_are_slugs() {
+        let html = markdown_to_html("# Café `Crème`\n\nText\n\n## Setup\n\n## Setup\n\n## ???");
+        assert_eq!(html, "<h1 id=\"cafe-creme\">Café <code>Crème</code></h1>\n<p>Text</p>\n\
+                          <h2 id=\"setup\">Setup</h2>\n<h2 id=\"setup-1\">Setup</h2>\n<h2 id=\"section\">???</h2>\n");
+
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog/Été 2024")).unwrap();
+        fs::write(content.join("blog/Été 2024/Mon Café.md"), "---\ntitle: T\ndate: 2024-01-01T00:00:00Z\n---\n").unwrap();
+        let path = content.join("blog/Été 2024/Mon Café.md");
+        assert_eq!(BlogPost::from_file(&path, &content).unwrap().url, "/blog/Été 2024/Mon Café");
+
+        let processor = BlogProcessor::new(content.clone()).with_slugs(Slugs { unicode: false, paths: true });
+        assert_eq!(processor.read_post(&path).unwrap().url, "/blog/ete-2024/mon-cafe");
+        let processor = BlogProcessor::new(content).with_slugs(Slugs { unicode: true, paths: true });
+        let post = processor.read_post(&path).unwrap();
+        assert_eq!(post.url, "/blog/été-2024/mon-café");
+        assert_eq!(markdown_to_html_with("## Été", processor.slugs()), "<h2 id=\"été\">Été</h2>\n");
+    }
+
+    #[test]
+    fn test_extra_front_matter_fields_are_page_variables() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::create_dir_all(temp.path().join("components")).unwrap();
+        fs::write(
+            temp.path().join("components/blog_layout.html"),
+            r#"<body data-series="@{var("series")}">@{yield}<footer>@{var("sidebar")} @{var("hero.alt")}</footer></body>"#,
+        ).unwrap();
+        fs::write(content.join("blog/_vars.toml"), "sidebar = \"blog\"\nseries = \"none\"").unwrap();
+        fs::write(
+            content.join("blog/post.md"),
+            "---\ntitle: Post\ndate: 2024-01-01T00:00:00Z\nseries: Rust 101\nhero:\n  alt: A crab\nempty: null\n---\n\nPart of @{var(\"series\")}.\n",
+        ).unwrap();
+
+        let processor = BlogProcessor::with_option_components(content.clone(), Some(Variables::default()))
+            .with_dir_vars(DirectoryVariables::load(&content).unwrap());
+        let post = processor.read_post(&content.join("blog/post.md")).unwrap();
+        assert_eq!(post.front_matter.extra["series"], serde_yaml::Value::from("Rust 101"));
+        assert!(!post.page_vars().contains_key("empty"));
+        let html = processor.process_post(&post).unwrap().html;
+        assert!(html.starts_with(r#"<body data-series="Rust 101"><p>Part of Rust 101.</p>"#), "{}", html);
+        assert!(html.contains("<footer>blog A crab</footer>"), "{}", html);
+    }
+
+    #[test]
+    fn test_posts_pick_their_layout_from_front_matter() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::create_dir_all(content.join("guides")).unwrap();
+        fs::create_dir_all(temp.path().join("components")).unwrap();
+        fs::write(temp.path().join("components/blog_layout.html"), "<main>@{yield}</main>").unwrap();
+        fs::write(temp.path().join("components/docs.html"), "<article>@{yield}</article>").unwrap();
+        fs::write(temp.path().join("components/guide.html"), "<section>@{yield}</section>").unwrap();
+        fs::write(content.join("guides/_vars.toml"), "layout = \"guide.html\"").unwrap();
+        for (path, layout) in [("blog/plain.md", ""), ("blog/docs.md", "layout: docs.html\n"), ("guides/setup.md", "")] {
+            fs::write(content.join(path), format!("---\ntitle: T\ndate: 2024-01-01T00:00:00Z\n{}---\n\nBody\n", layout)).unwrap();
+        }
+
+        let processor = BlogProcessor::new(content.clone()).with_dir_vars(DirectoryVariables::load(&content).unwrap());
+        let render = |path: &str| processor.process_post(&processor.read_post(&content.join(path)).unwrap()).unwrap().html;
+        assert!(render("blog/plain.md").starts_with("<main>"));
+        assert!(render("blog/docs.md").starts_with("<article>"));
+        assert!(render("guides/setup.md").starts_with("<section>"));
+        assert_eq!(processor.layout_of_file(&content.join("blog/docs.md")), temp.path().join("components/docs.html"));
+    }
+
+    #[test]
+    fn test_robots_front_matter_is_escaped() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::create_dir_all(temp.path().join("components")).unwrap();
+        fs::write(temp.path().join("components/blog_layout.html"), "<html><head></head><body>@{yield}</body></html>").unwrap();
+        fs::write(content.join("blog/post.md"), "---\ntitle: Hello\ndate: 2024-01-01T00:00:00Z\nrobots: 'noindex\" onload=\"alert(1)'\n---\n\nBody\n").unwrap();
+
+        let processor = BlogProcessor::new(content.clone());
+        let post = processor.read_post(&content.join("blog/post.md")).unwrap();
+        let html = processor.process_post(&post).unwrap().html;
+        assert!(html.contains("<meta name=\"robots\" content=\"noindex&quot; onload=&quot;alert(1)\">"), "{}", html);
+    }
+
+    #[test]
+    fn test_configured_layout_falls_back_to_built_in_one() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::create_dir_all(temp.path().join("templates")).unwrap();
+        fs::write(temp.path().join("templates/post.html"), "<article>@{yield}</article>").unwrap();
+        fs::write(content.join("blog/post.md"), "---\ntitle: Hello\ndate: 2024-01-01T00:00:00Z\n---\n\nBody\n").unwrap();
+
+        let processor = BlogProcessor::new(content.clone())
+            .with_components_dir(temp.path().join("templates"))
+            .with_layout(Some(PathBuf::from("post.html")));
+        let post = processor.read_post(&content.join("blog/post.md")).unwrap();
+        assert_eq!(processor.process_post(&post).unwrap().html, "<article><p>Body</p>\n</article>");
+
+        let processor = processor.with_layout(Some(PathBuf::from("missing.html")));
+        let html = processor.process_post(&post).unwrap().html;
+        assert!(html.contains("<title>Hello</title>") && html.contains("<p>Body</p>"), "{}", html);
+    }
+
+    #[test]
+    fn test_missing_front_matter_layout_is_an_error() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::write(content.join("blog/post.md"), "---\ntitle: Hello\ndate: 2024-01-01T00:00:00Z\nlayout: psot.html\n---\n\nBody\n").unwrap();
+
+        let processor = BlogProcessor::new(content.clone()).with_components_dir(temp.path().join("components"));
+        let post = processor.read_post(&content.join("blog/post.md")).unwrap();
+        let err = processor.process_post(&post).unwrap_err().to_string();
+        assert!(err.contains("psot.html") && err.contains("post.md"), "{}", err);
+    }
+
+    #[test]
+    fn test_updated_date_is_separate_from_publication_date() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::create_dir_all(temp.path().join("components")).unwrap();
+        fs::write(temp.path().join("components/blog_layout.html"), "@{last_updated}@{changelog}@{yield}").unwrap();
+        fs::write(content.join("blog/fresh.md"), "---\ntitle: Fresh\ndate: 2024-01-01T00:00:00Z\n---\n\nBody\n").unwrap();
+        fs::write(content.join("blog/revised.md"), "---\ntitle: Revised\ndate: 2024-01-01T00:00:00Z\n\
+            changelog:\n  - date: 2024-02-01\n    note: Fixed a typo\n  - date: 2024-03-05\n    note: Covered <v2>\n---\n\nBody\n").unwrap();
+
+        let processor = BlogProcessor::new(content.clone());
+        let fresh = processor.read_post(&content.join("blog/fresh.md")).unwrap();
+        assert_eq!(fresh.page_seo().last_modified, None);
+        assert!(!fresh.generate_json_ld("Site", "https://example.com").unwrap().contains("dateModified"));
+        assert_eq!(processor.process_post(&fresh).unwrap().html, "<p>Body</p>\n");
+
+        let mut revised = processor.read_post(&content.join("blog/revised.md")).unwrap();
+        let json_ld: serde_json::Value = serde_json::from_str(&revised.generate_json_ld("Site", "https://example.com").unwrap()).unwrap();
+        assert_eq!(json_ld["datePublished"], "2024-01-01T00:00:00Z");
+        assert_eq!(json_ld["dateModified"], "2024-03-05T00:00:00+00:00");
+        let html = processor.process_post(&revised).unwrap().html;
+        assert!(html.starts_with("<p class=\"last-updated\">Last updated <time datetime=\"2024-03-05T00:00:00+00:00\">March 5, 2024</time></p>\
+            <ul class=\"changelog\"><li><time datetime=\"2024-03-05T00:00:00+00:00\">March 5, 2024</time> Covered &lt;v2&gt;</li>"), "{}", html);
+
+        // A date front matter gives beats the last commit
+        revised.front_matter.updated = Some("2024-04-01T12:00:00Z".to_string());
+        revised.last_modified = DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z").ok();
+        assert_eq!(revised.page_seo().last_modified.unwrap().to_rfc3339(), "2024-04-01T12:00:00+00:00");
+    }
+
+    #[test]
+    fn test_prev_next_links_stay_within_their_scope() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path().join("content");
+        fs::create_dir_all(content.join("blog")).unwrap();
+        fs::create_dir_all(content.join("docs")).unwrap();
+        fs::create_dir_all(temp.path().join("components")).unwrap();
+        fs::write(temp.path().join("components/blog_layout.html"), "@{prev_post.url}|@{next_post.url}").unwrap();
+        let posts = [
+            ("blog/a.md", "2024-01-01", "tags: [rust]"),
+            ("blog/b.md", "2024-02-01", "tags: [go]"),
+            ("blog/c.md", "2024-03-01", "tags: [Rust, wasm]"),
+            ("docs/install.md", "2024-01-15", "series: setup"),
+            ("docs/configure.md", "2024-02-15", "series: setup"),
+        ];
+        for (path, date, fields) in posts {
+            fs::write(content.join(path), format!("---\ntitle: T\ndate: {}T00:00:00Z\n{}\n---\n", date, fields)).unwrap();
+        }
+        let sections: Vec<SectionConfig> = toml::from_str::<toml::Table>("[[sections]]\npath = \"docs\"\nprev_next = \"section\"")
+            .unwrap()["sections"].clone().try_into().unwrap();
+
+        let links = |prev_next: &str| {
+            let mut processor = BlogProcessor::new(content.clone())
+                .with_sections(&sections)
+                .with_prev_next(PrevNext::from(prev_next.to_string()));
+            processor.load_posts().unwrap();
+            let render = |url: &str| {
+                let post = processor.posts().iter().find(|post| post.url == url).unwrap();
+                // Links a post doesn't have are left as they are
+                processor.process_post(post).unwrap().html.replace("@{prev_post.url}", "").replace("@{next_post.url}", "")
+            };
+            [render("/blog/a"), render("/blog/b"), render("/docs/configure")]
+        };
+        assert_eq!(links("all"), ["|/docs/install", "/docs/install|/docs/configure", "/docs/install|"]);
+        assert_eq!(links("section"), ["|/blog/b", "/blog/a|/blog/c", "/docs/install|"]);
+        assert_eq!(links("tag"), ["|/blog/c", "|", "/docs/install|"]);
+    }
+}
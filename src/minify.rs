@@ -3,11 +3,69 @@ use lightningcss::{
     stylesheet::{MinifyOptions, ParserOptions, StyleSheet},
     targets::Browsers,
 };
-use log::warn;
+use log::{info, warn};
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex, RegexSet};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::plugins::{HtmlPostProcessor, PageInfo};
+
+lazy_static! {
+    static ref BETWEEN_TAGS_REGEX: Regex = Regex::new(r">\s+<").unwrap();
+    static ref COMMENT_REGEX: Regex = Regex::new(r"(?s)<!--(.*?)-->").unwrap();
+}
+
+/// Minification settings, `[minify]` in `eldroid.toml`. They apply when
+/// `--minify` or `--release` turns minification on.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MinifyConfig {
+    /// Keep every HTML comment
+    #[serde(default)]
+    pub keep_comments: bool,
+    /// Minify stylesheets and CSS in pages
+    #[serde(default = "crate::config::default_true")]
+    pub minify_css: bool,
+    /// Minify scripts in pages
+    #[serde(default = "crate::config::default_true")]
+    pub minify_js: bool,
+    /// Regular expressions for HTML comments kept when the rest are removed,
+    /// like license headers
+    #[serde(default)]
+    pub preserve_comments: Vec<String>,
+    /// Globs of output pages, relative to the output directory, that are
+    /// written as they are, e.g. `legacy/**`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl Default for MinifyConfig {
+    fn default() -> Self {
+        Self {
+            keep_comments: false,
+            minify_css: true,
+            minify_js: true,
+            preserve_comments: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// Generated files compacted with the pages: the sitemap, feeds, and search index.
+const GENERATED_FILES: [&str; 4] = ["sitemap.xml", "rss.xml", "atom.xml", "search-index.json"];
 
 pub struct Minifier {
     html_config: minify_html::Cfg,
     css_options: MinifyOptions,
+    /// Whether stylesheets are minified
+    css: bool,
+    /// Comments kept though `html_config` removes comments
+    preserve_comments: Option<RegexSet>,
+    /// Pages left alone, matched in `output_dir`
+    exclude: GlobSet,
+    output_dir: PathBuf,
 }
 
 impl Default for Minifier {
@@ -34,19 +92,63 @@ impl Default for Minifier {
                 targets: Browsers::default().into(),
                 ..MinifyOptions::default()
             },
+            css: true,
+            preserve_comments: None,
+            exclude: GlobSet::empty(),
+            output_dir: PathBuf::new(),
         }
     }
 }
 
 impl Minifier {
+    /// A minifier with the choices of `config`, for pages written to `output_dir`.
+    pub fn from_config(config: &MinifyConfig, output_dir: &Path) -> Result<Self> {
+        let mut minifier = Self::default();
+        minifier.html_config.keep_comments = config.keep_comments;
+        minifier.html_config.minify_css = config.minify_css;
+        minifier.html_config.minify_js = config.minify_js;
+        minifier.css = config.minify_css;
+
+        // Comments are kept through minification, then the others removed
+        if !config.keep_comments && !config.preserve_comments.is_empty() {
+            let patterns = RegexSet::new(&config.preserve_comments).context("Invalid preserve_comments pattern")?;
+            minifier.html_config.keep_comments = true;
+            minifier.preserve_comments = Some(patterns);
+        }
+
+        let mut exclude = GlobSetBuilder::new();
+        for pattern in &config.exclude {
+            exclude.add(Glob::new(pattern).with_context(|| format!("Invalid minify exclude pattern '{}'", pattern))?);
+        }
+        minifier.exclude = exclude.build()?;
+        minifier.output_dir = output_dir.to_path_buf();
+        Ok(minifier)
+    }
+
+    /// Whether the page written to `output` is left unminified.
+    pub fn is_excluded(&self, output: &Path) -> bool {
+        output.strip_prefix(&self.output_dir).is_ok_and(|relative| self.exclude.is_match(relative))
+    }
     pub fn minify_html(&self, content: &str) -> String {
-        String::from_utf8_lossy(&minify_html_content(
+        let minified = String::from_utf8_lossy(&minify_html_content(
             content.as_bytes(),
             &self.html_config
-        )).into_owned()
+        )).into_owned();
+        match &self.preserve_comments {
+            Some(patterns) => COMMENT_REGEX.replace_all(&minified, |caps: &Captures| {
+                match patterns.is_match(&caps[1]) {
+                    true => caps[0].to_string(),
+                    false => String::new(),
+                }
+            }).into_owned(),
+            None => minified,
+        }
     }
 
     pub fn minify_css(&self, content: &str) -> String {
+        if !self.css {
+            return content.to_string();
+        }
         let mut stylesheet = match StyleSheet::parse(content, ParserOptions::default()) {
             Ok(stylesheet) => stylesheet,
             Err(e) => {
@@ -56,7 +158,7 @@ impl Minifier {
         };
 
         match stylesheet.minify(MinifyOptions {
-            targets: self.css_options.targets.clone(),
+            targets: self.css_options.targets,
             ..MinifyOptions::default()
         }) {
             Ok(_) => stylesheet.to_css(Default::default())
@@ -72,9 +174,134 @@ impl Minifier {
         }
     }
 
+    /// `content` without the whitespace between tags. CDATA sections, which
+    /// hold the HTML of feed entries, are kept as they are.
+    pub fn minify_xml(&self, content: &str) -> String {
+        let mut output = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find("<![CDATA[") {
+            let end = rest[start..].find("]]>").map_or(rest.len(), |end| start + end + "]]>".len());
+            output.push_str(&BETWEEN_TAGS_REGEX.replace_all(&rest[..start], "><"));
+            output.push_str(&rest[start..end]);
+            rest = &rest[end..];
+        }
+        output.push_str(&BETWEEN_TAGS_REGEX.replace_all(rest, "><"));
+        output.trim().to_string()
+    }
+
+    /// `content` without whitespace outside strings, keys in their order.
+    pub fn minify_json(&self, content: &str) -> String {
+        let mut output = String::with_capacity(content.len());
+        let (mut in_string, mut escaped) = (false, false);
+        for c in content.chars() {
+            if in_string {
+                in_string = escaped || c != '"';
+                escaped = !escaped && c == '\\';
+            } else if c == '"' {
+                in_string = true;
+            } else if c.is_whitespace() {
+                continue;
+            }
+            output.push(c);
+        }
+        output
+    }
+
+    /// Compact the sitemap, feeds, and search index written under
+    /// `output_dir`, leaving the build cache alone.
+    pub fn minify_generated(&self, output_dir: &Path) -> Result<()> {
+        let (mut files, mut saved) = (0, 0);
+        let entries = walkdir::WalkDir::new(output_dir).into_iter()
+            .filter_entry(|entry| entry.depth() != 1 || entry.file_name() != "cache");
+        for entry in entries {
+            let path = entry?.into_path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else { continue };
+            if !GENERATED_FILES.contains(&name) {
+                continue;
+            }
+            let content = fs::read_to_string(&path)?;
+            let minified = match name.ends_with(".json") {
+                true => self.minify_json(&content),
+                false => self.minify_xml(&content),
+            };
+            if minified.len() < content.len() {
+                saved += content.len() - minified.len();
+                files += 1;
+                fs::write(&path, minified)?;
+            }
+        }
+        if files > 0 {
+            info!("Minified {} generated file(s), {} bytes smaller", files, saved);
+        }
+        Ok(())
+    }
+
     pub fn minify_js(&self, content: &str) -> String {
         // For now, return unminified content since we removed swc
         // TODO: Implement JS minification using lightningcss or another library
         content.to_string()
     }
-}
\ No newline at end of file
+}
+
+impl HtmlPostProcessor for Minifier {
+    fn name(&self) -> &str {
+        "minify"
+    }
+
+    fn process(&self, page: &PageInfo, html: String) -> Result<String> {
+        if self.is_excluded(page.output) {
+            return Ok(html);
+        }
+        Ok(self.minify_html(&html))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_xml_and_json_lose_their_indentation() {
+        let minifier = Minifier::default();
+        let rss = "<?xml version=\"1.0\"?>\n<rss>\n    <item>\n        <title>A  b</title>\n        \
+                   <description><![CDATA[<pre>x\n    <b>y</b></pre>]]></description>\n    </item>\n</rss>\n";
+        assert_eq!(
+            minifier.minify_xml(rss),
+            "<?xml version=\"1.0\"?><rss><item><title>A  b</title>\
+             <description><![CDATA[<pre>x\n    <b>y</b></pre>]]></description></item></rss>",
+        );
+        let json = "{\n  \"title\": \"Say \\\"hi\\\" there\",\n  \"tags\": [ \"a b\", \"c\" ]\n}";
+        assert_eq!(minifier.minify_json(json), r#"{"title":"Say \"hi\" there","tags":["a b","c"]}"#);
+
+        let out = tempfile::tempdir().unwrap();
+        fs::create_dir_all(out.path().join("tags/rust")).unwrap();
+        fs::create_dir_all(out.path().join("cache")).unwrap();
+        fs::write(out.path().join("tags/rust/atom.xml"), "<feed>\n  <entry/>\n</feed>").unwrap();
+        fs::write(out.path().join("cache/sitemap.xml"), "<urlset>\n</urlset>").unwrap();
+        fs::write(out.path().join("feed.xml"), "<a>\n</a>").unwrap();
+        minifier.minify_generated(out.path()).unwrap();
+        assert_eq!(fs::read_to_string(out.path().join("tags/rust/atom.xml")).unwrap(), "<feed><entry/></feed>");
+        assert_eq!(fs::read_to_string(out.path().join("cache/sitemap.xml")).unwrap(), "<urlset>\n</urlset>");
+        assert_eq!(fs::read_to_string(out.path().join("feed.xml")).unwrap(), "<a>\n</a>");
+    }
+
+    #[test]
+    fn test_config_keeps_license_comments_and_skips_excluded_pages() {
+        let config: MinifyConfig = toml::from_str(
+            "minify_css = false\npreserve_comments = [\"@license\", \"^!\"]\nexclude = [\"legacy/**\"]"
+        ).unwrap();
+        let out = Path::new("public");
+        let minifier = Minifier::from_config(&config, out).unwrap();
+        let html = "<html>\n  <!-- @license MIT -->\n  <!-- TODO: drop -->\n  <!--! keep -->\n</html>";
+        assert_eq!(minifier.minify_html(html), "<html><!-- @license MIT --><!--! keep --></html>");
+        assert_eq!(minifier.minify_css("a {  color: red; }"), "a {  color: red; }");
+
+        let legacy = out.join("legacy/old/index.html");
+        let page = PageInfo { source: Path::new("content/old.html"), output: &legacy, seo: None };
+        assert_eq!(minifier.process(&page, html.to_string()).unwrap(), html);
+        assert!(!minifier.is_excluded(&out.join("index.html")));
+
+        let bad: MinifyConfig = toml::from_str("exclude = [\"[\"]").unwrap();
+        assert!(Minifier::from_config(&bad, out).is_err());
+    }
+}
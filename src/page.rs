@@ -0,0 +1,117 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_yaml::{Mapping, Value};
+
+lazy_static! {
+    // @{page.tags} or @{page.author.name}; a trailing ? marks the field optional
+    static ref PAGE_FIELD_REGEX: Regex = Regex::new(r"@\{page\.([A-Za-z_][\w-]*(?:\.[\w-]+)*)(\?)?\}").unwrap();
+    // @{title} or @{prev_post.url}
+    static ref NAMED_FIELD_REGEX: Regex = Regex::new(r"@\{([A-Za-z_][\w-]*(?:\.[\w-]+)*)\}").unwrap();
+}
+
+/// A page's front matter plus fields the build computes, as `@{page.<field>}`
+/// in layouts. Values keep their front matter types: lists render comma
+/// separated, and mappings are reached with more dots, e.g. `@{page.author.name}`.
+#[derive(Debug, Clone, Default)]
+pub struct PageFields {
+    fields: Mapping,
+}
+
+impl PageFields {
+    pub fn new(front_matter: &Mapping) -> Self {
+        Self { fields: front_matter.clone() }
+    }
+
+    /// Set a computed field, replacing any front matter value of that name.
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) {
+        self.fields.insert(name.into(), value.into());
+    }
+
+    /// The value at a dotted path; numbers index into lists.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut parts = path.split('.');
+        let mut value = self.fields.get(parts.next()?)?;
+        for part in parts {
+            value = match value {
+                Value::Mapping(map) => map.get(part)?,
+                Value::Sequence(items) => items.get(part.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Fill in every `@{page.<field>}` of `template`. Fields without a value
+    /// render empty; the required ones among them are returned.
+    pub fn substitute(&self, template: &str) -> (String, Vec<String>) {
+        let mut missing: Vec<String> = Vec::new();
+        let html = PAGE_FIELD_REGEX.replace_all(template, |caps: &Captures| {
+            match self.get(&caps[1]) {
+                Some(Value::Null) | None => {
+                    if caps.get(2).is_none() && !missing.iter().any(|field| field == &caps[1]) {
+                        missing.push(caps[1].to_string());
+                    }
+                    String::new()
+                },
+                Some(value) => render(value).unwrap_or_default(),
+            }
+        });
+        (html.into_owned(), missing)
+    }
+
+    /// Fill in `@{<field>}` for the fields that are set, leaving any other
+    /// `@{...}` for later stages.
+    pub fn substitute_named(&self, template: &str) -> String {
+        NAMED_FIELD_REGEX.replace_all(template, |caps: &Captures| {
+            match self.get(&caps[1]) {
+                Some(value) => render(value).unwrap_or_default(),
+                None => caps[0].to_string(),
+            }
+        }).into_owned()
+    }
+}
+
+/// Text of a value in a template; `None` for null and for mappings, which
+/// only their fields can stand in for.
+fn render(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Sequence(items) => Some(items.iter().filter_map(render).collect::<Vec<_>>().join(", ")),
+        Value::Tagged(tagged) => render(&tagged.value),
+        Value::Null | Value::Mapping(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitutes_typed_fields() {
+        let front_matter: Mapping = serde_yaml::from_str(
+            "title: Hello\ntags: [rust, ssg]\ndraft: false\nauthor:\n  name: Jane\nseries: ~",
+        ).unwrap();
+        let mut page = PageFields::new(&front_matter);
+        page.set("url", "/blog/hello");
+
+        let template = "@{page.title} @{page.tags} @{page.tags.1} @{page.draft} @{page.author.name} \
+                        @{page.url}|@{page.image?}|@{page.series}|@{page.author}|@{page.series}";
+        let (html, missing) = page.substitute(template);
+        assert_eq!(html, "Hello rust, ssg ssg false Jane /blog/hello||||");
+        assert_eq!(missing, ["series"]);
+    }
+
+    #[test]
+    fn test_substitute_named_leaves_unset_fields() {
+        let mut page = PageFields::default();
+        page.set("title", "Hello");
+        let mut prev = Mapping::new();
+        prev.insert("url".into(), "/blog/first".into());
+        page.set("prev_post", prev);
+
+        let html = page.substitute_named("@{title} @{prev_post.url} @{next_post.url} @{yield}");
+        assert_eq!(html, "Hello /blog/first @{next_post.url} @{yield}");
+    }
+}
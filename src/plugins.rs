@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::Result;
+use crate::markdown::BlogPost;
+use crate::seo::{PageSEO, SEOConfig};
+
+/// Rewrites a page's source before it is rendered: the Markdown body of a
+/// post, after its front matter, or the whole of an HTML page.
+pub trait ContentTransformer: Send + Sync {
+    /// Stage name in `explain` output.
+    fn name(&self) -> &str;
+
+    fn transform(&self, source: &Path, content: &str) -> Result<String>;
+}
+
+/// Rewrites a page's HTML once it is rendered, before it is written.
+pub trait HtmlPostProcessor: Send + Sync {
+    /// Stage name in `explain` output.
+    fn name(&self) -> &str;
+
+    fn process(&self, page: &PageInfo, html: String) -> Result<String>;
+}
+
+/// Writes site-wide files once every page is built.
+pub trait OutputGenerator: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn generate(&self, site: &SiteInfo) -> Result<()>;
+}
+
+/// The page an `HtmlPostProcessor` runs on.
+pub struct PageInfo<'a> {
+    /// Content file the page is built from
+    pub source: &'a Path,
+    /// Where the page will be written
+    pub output: &'a Path,
+    pub seo: Option<&'a PageSEO>,
+}
+
+/// The finished build an `OutputGenerator` runs on.
+pub struct SiteInfo<'a> {
+    pub output_dir: &'a Path,
+    /// Pages in the sitemap, as paths in the output directory
+    pub pages: &'a [PathBuf],
+    /// SEO data by page path
+    pub page_seo: &'a HashMap<PathBuf, PageSEO>,
+    /// Blog posts in listing order, newest first
+    pub posts: &'a [BlogPost],
+    /// `seo_config.toml`, when SEO is enabled
+    pub seo_config: Option<&'a SEOConfig>,
+}
+
+/// Stages registered on a `Site`, run in registration order after the
+/// built-in ones of the same kind. Post-processors run before minification,
+/// so they see readable HTML.
+#[derive(Clone, Default)]
+pub struct Plugins {
+    pub transformers: Vec<Arc<dyn ContentTransformer>>,
+    pub post_processors: Vec<Arc<dyn HtmlPostProcessor>>,
+    pub generators: Vec<Arc<dyn OutputGenerator>>,
+}
+
+impl Plugins {
+    /// Apply every transformer to `content` in turn.
+    pub fn transform(&self, source: &Path, content: String) -> Result<String> {
+        self.transformers.iter()
+            .try_fold(content, |content, transformer| transformer.transform(source, &content))
+    }
+}
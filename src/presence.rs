@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Longest reviewer name shown to others.
+const MAX_NAME: usize = 40;
+
+/// Longest page path or section id accepted from a reviewer.
+const MAX_PATH: usize = 2048;
+
+/// A browser connected to the dev server's live reload socket.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reviewer {
+    pub id: u64,
+    pub name: String,
+    /// URL path of the page the reviewer has open
+    pub path: String,
+}
+
+/// A section of a page one reviewer asks the others to scroll to.
+#[derive(Debug, Clone, Serialize)]
+pub struct Ping {
+    pub from: u64,
+    pub name: String,
+    pub path: String,
+    /// `id` of the section's heading, or `#N` for the Nth heading without one
+    pub section: String,
+    /// Text of the heading
+    pub label: String,
+}
+
+/// Messages the injected client sends over `/ws`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ClientMessage {
+    /// Sent on connect and after renaming
+    Hello { name: Option<String>, path: String },
+    Ping {
+        path: String,
+        section: String,
+        #[serde(default)]
+        label: String,
+    },
+}
+
+/// Reviewers with the site open, shared by every live reload connection.
+#[derive(Debug, Clone, Default)]
+pub struct Presence {
+    next_id: Arc<AtomicU64>,
+    reviewers: Arc<RwLock<BTreeMap<u64, Reviewer>>>,
+}
+
+impl Presence {
+    /// An id for a new connection, which shows up once it says hello.
+    pub fn connect(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Record the name and page of connection `id`; reviewers without a
+    /// name are numbered. False, recording nothing, when `path` isn't a page
+    /// path on this site, since the others are shown it as a link.
+    pub fn hello(&self, id: u64, name: Option<String>, path: String) -> bool {
+        if !is_site_path(&path) {
+            return false;
+        }
+        let name = name
+            .map(|name| name.trim().chars().take(MAX_NAME).collect::<String>())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("Reviewer {}", id));
+        self.reviewers.write().insert(id, Reviewer { id, name, path });
+        true
+    }
+
+    /// Forget connection `id`; false if it never said hello.
+    pub fn disconnect(&self, id: u64) -> bool {
+        self.reviewers.write().remove(&id).is_some()
+    }
+
+    pub fn reviewers(&self) -> Vec<Reviewer> {
+        self.reviewers.read().values().cloned().collect()
+    }
+
+    /// A ping from connection `id`, if it has said hello and points at a
+    /// page of this site.
+    pub fn ping(&self, id: u64, path: String, section: String, label: String) -> Option<Ping> {
+        if !is_site_path(&path) || section.len() > MAX_PATH || section.contains(char::is_whitespace) {
+            return None;
+        }
+        let name = self.reviewers.read().get(&id)?.name.clone();
+        Some(Ping { from: id, name, path, section, label: label.chars().take(200).collect() })
+    }
+}
+
+/// Whether `path` is a root-relative path on this site: no scheme, no other
+/// host (`//host` or `/\host`), and nothing a link would interpret.
+pub fn is_site_path(path: &str) -> bool {
+    path.starts_with('/')
+        && !path.starts_with("//")
+        && path.len() <= MAX_PATH
+        && !path.contains(['\\', '"', '<', '>'])
+        && !path.chars().any(char::is_control)
+}
+
+/// Whether a WebSocket upgrade with these `Origin` and `Host` headers comes
+/// from a page this server served. Clients that aren't browsers send no origin.
+pub fn same_origin(origin: Option<&str>, host: Option<&str>) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+    let Some(url) = Url::parse(origin).ok().filter(|url| matches!(url.scheme(), "http" | "https")) else {
+        return false;
+    };
+    let authority = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        _ => return false,
+    };
+    host.is_some_and(|host| host.eq_ignore_ascii_case(&authority))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reviewers_say_hello_ping_and_leave() {
+        let presence = Presence::default();
+        let (ann, bo) = (presence.connect(), presence.connect());
+        assert!(presence.hello(ann, Some("  Ann  ".to_string()), "/blog/post/".to_string()));
+        assert!(presence.hello(bo, None, "/".to_string()));
+        let names: Vec<String> = presence.reviewers().into_iter().map(|r| r.name).collect();
+        assert_eq!(names, ["Ann", &format!("Reviewer {}", bo)]);
+
+        // Links other reviewers are shown must stay on this site
+        for path in ["javascript:alert(1)", "//evil.example/", "/\\evil.example/", "https://evil.example/", "", "/a\"onmouseover=\"x"] {
+            assert!(!presence.hello(ann, None, path.to_string()), "{}", path);
+            assert!(presence.ping(ann, path.to_string(), "intro".to_string(), String::new()).is_none(), "{}", path);
+        }
+        assert_eq!(presence.reviewers()[0].path, "/blog/post/");
+
+        let ping = presence.ping(ann, "/blog/post/".to_string(), "intro".to_string(), "Intro".repeat(100)).unwrap();
+        assert_eq!((ping.from, ping.name.as_str(), ping.section.as_str()), (ann, "Ann", "intro"));
+        assert_eq!(ping.label.chars().count(), 200);
+        assert!(presence.ping(ann, "/".to_string(), "a b".to_string(), String::new()).is_none());
+        assert!(presence.ping(presence.connect(), "/".to_string(), "intro".to_string(), String::new()).is_none());
+
+        // Leaving removes the reviewer, once
+        assert!(presence.disconnect(ann));
+        assert!(!presence.disconnect(ann));
+        assert!(presence.ping(ann, "/".to_string(), "intro".to_string(), String::new()).is_none());
+        assert_eq!(presence.reviewers().len(), 1);
+    }
+
+    #[test]
+    fn test_socket_origin_must_match_the_host() {
+        assert!(same_origin(None, Some("localhost:3000")));
+        assert!(same_origin(Some("http://localhost:3000"), Some("localhost:3000")));
+        assert!(same_origin(Some("https://Example.com"), Some("example.com")));
+        assert!(same_origin(Some("http://[::1]:8080"), Some("[::1]:8080")));
+        assert!(!same_origin(Some("http://evil.example"), Some("localhost:3000")));
+        assert!(!same_origin(Some("http://localhost:3001"), Some("localhost:3000")));
+        assert!(!same_origin(Some("null"), Some("localhost:3000")));
+        assert!(!same_origin(Some("http://localhost:3000"), None));
+    }
+}
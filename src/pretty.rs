@@ -0,0 +1,133 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node};
+
+/// Elements that start on a line of their own.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "html", "head", "body", "title", "meta", "link", "base", "style", "script", "noscript", "template",
+    "header", "footer", "main", "nav", "section", "article", "aside", "div", "p", "hr", "address",
+    "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "li", "dl", "dt", "dd", "menu",
+    "table", "caption", "colgroup", "col", "thead", "tbody", "tfoot", "tr", "td", "th",
+    "form", "fieldset", "legend", "figure", "figcaption", "blockquote", "pre", "details", "summary",
+    "dialog", "picture", "video", "audio", "source", "track", "iframe", "canvas", "svg", "select", "option",
+];
+
+/// Elements whose content is written as it is, since whitespace in it matters.
+const RAW_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+const INDENT: &str = "  ";
+
+/// `html` indented for reading, a block element per line. Runs of text and
+/// inline elements stay on one line with their spacing collapsed at the
+/// ends only, and `pre`, `textarea`, `script`, and `style` are untouched.
+pub fn pretty_html(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut output = String::new();
+    write_children(document.tree.root(), 0, &mut output);
+    output
+}
+
+fn is_block(node: NodeRef<Node>) -> bool {
+    match node.value() {
+        Node::Element(element) => BLOCK_ELEMENTS.contains(&element.name()),
+        Node::Comment(_) | Node::Doctype(_) => true,
+        _ => false,
+    }
+}
+
+/// The children of `parent`, block ones on their own lines at `depth`, and
+/// the inline ones between them joined on a line.
+fn write_children(parent: NodeRef<Node>, depth: usize, output: &mut String) {
+    let mut inline = String::new();
+    for child in parent.children() {
+        if is_block(child) {
+            write_line(&inline, depth, output);
+            inline.clear();
+            write_block(child, depth, output);
+        } else {
+            inline.push_str(&serialize(child));
+        }
+    }
+    write_line(&inline, depth, output);
+}
+
+fn write_line(text: &str, depth: usize, output: &mut String) {
+    let text = text.trim();
+    if !text.is_empty() {
+        output.push_str(&INDENT.repeat(depth));
+        output.push_str(text);
+        output.push('\n');
+    }
+}
+
+fn write_block(node: NodeRef<Node>, depth: usize, output: &mut String) {
+    let element = match node.value() {
+        Node::Element(element) => element,
+        Node::Doctype(doctype) => return write_line(&format!("<!DOCTYPE {}>", doctype.name()), depth, output),
+        _ => return write_line(&serialize(node), depth, output),
+    };
+    let name = element.name();
+    let has_blocks = node.children().any(is_block);
+    if VOID_ELEMENTS.contains(&name) || RAW_ELEMENTS.contains(&name) || !has_blocks {
+        let element = ElementRef::wrap(node).expect("element node");
+        return write_line(&element.html(), depth, output);
+    }
+    write_line(&start_tag(node), depth, output);
+    write_children(node, depth + 1, output);
+    write_line(&format!("</{}>", name), depth, output);
+}
+
+fn start_tag(node: NodeRef<Node>) -> String {
+    let Node::Element(element) = node.value() else { return String::new() };
+    let mut tag = format!("<{}", element.name());
+    for (name, value) in element.attrs() {
+        tag.push_str(&format!(" {}=\"{}\"", name, html_escape::encode_double_quoted_attribute(value)));
+    }
+    tag.push('>');
+    tag
+}
+
+fn serialize(node: NodeRef<Node>) -> String {
+    match node.value() {
+        Node::Text(text) => html_escape::encode_text(&**text).into_owned(),
+        Node::Comment(comment) => format!("<!--{}-->", &**comment),
+        Node::Element(_) => ElementRef::wrap(node).map_or_else(String::new, |element| element.html()),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_are_indented_and_inline_text_kept() {
+        let html = "<!DOCTYPE html><html><head><title>Hi</title><style>a  { b: c }</style></head>\
+                    <body><!-- nav --><nav><ul><li><a href=\"/\">Home</a></li><li>A &amp; B</li></ul></nav>\
+                    <p>Some <em>text</em>   here</p><pre>  keep\n  this</pre> trailing <b>bits</b></body></html>";
+        assert_eq!(pretty_html(html), "\
+<!DOCTYPE html>
+<html>
+  <head>
+    <title>Hi</title>
+    <style>a  { b: c }</style>
+  </head>
+  <body>
+    <!-- nav -->
+    <nav>
+      <ul>
+        <li><a href=\"/\">Home</a></li>
+        <li>A &amp; B</li>
+      </ul>
+    </nav>
+    <p>Some <em>text</em>   here</p>
+    <pre>  keep\n  this</pre>
+    trailing <b>bits</b>
+  </body>
+</html>
+");
+    }
+}
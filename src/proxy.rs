@@ -0,0 +1,163 @@
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use log::{debug, warn};
+use serde::Deserialize;
+use warp::http::{HeaderMap, Method, Response, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::path::FullPath;
+use warp::{Filter, Rejection};
+
+/// Headers that describe one connection rather than the message.
+const HOP_BY_HOP: &[&str] = &[
+    "connection", "keep-alive", "proxy-authenticate", "proxy-authorization",
+    "te", "trailer", "transfer-encoding", "upgrade", "host",
+    // The proxy reads bodies whole, so it picks the encoding and length
+    "accept-encoding", "content-encoding", "content-length",
+];
+
+/// A dev server route forwarded to another server, `[[proxy]]` in
+/// `eldroid.toml` or `--proxy /api=http://localhost:4000`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxyRule {
+    /// Path prefix to forward, e.g. `/api` or `/api/*`
+    pub path: String,
+    /// Base URL of the backend
+    pub target: String,
+    /// Remove the prefix before forwarding, so `/api/users` goes to `{target}/users`
+    #[serde(default)]
+    pub strip_prefix: bool,
+}
+
+impl FromStr for ProxyRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, target) = s.split_once('=')
+            .ok_or_else(|| format!("Invalid proxy rule '{}', expected PATH=URL like /api=http://localhost:4000", s))?;
+        Ok(Self { path: path.trim().to_string(), target: target.trim().to_string(), strip_prefix: false })
+    }
+}
+
+impl ProxyRule {
+    fn prefix(&self) -> &str {
+        self.path.trim_end_matches('*').trim_end_matches('/')
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let prefix = self.prefix();
+        prefix.is_empty()
+            || path == prefix
+            || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// Backend URL for a request to `path` with the raw `query`.
+    fn url(&self, path: &str, query: &str) -> String {
+        let path = if self.strip_prefix { &path[self.prefix().len()..] } else { path };
+        let path = if path.starts_with('/') { path.to_string() } else { format!("/{}", path) };
+        let query = if query.is_empty() { String::new() } else { format!("?{}", query) };
+        format!("{}{}{}", self.target.trim_end_matches('/'), path, query)
+    }
+}
+
+/// Forward requests under each rule's prefix to its backend; other requests
+/// fall through to the rest of the dev server. `secure` is whether the dev
+/// server itself is served over HTTPS.
+pub fn route(rules: Vec<ProxyRule>, secure: bool) -> impl Filter<Extract = (Response<Vec<u8>>,), Error = Rejection> + Clone {
+    let rules = Arc::new(rules);
+    let agent = ureq::AgentBuilder::new()
+        .redirects(0)
+        .timeout(Duration::from_secs(60))
+        .build();
+    warp::path::full()
+        .and_then(move |path: FullPath| {
+            let rule = rules.iter().find(|rule| rule.matches(path.as_str())).cloned();
+            async move { rule.ok_or_else(warp::reject::not_found) }
+        })
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .and_then(move |rule: ProxyRule, method: Method, path: FullPath, query: String, headers: HeaderMap, body: Bytes| {
+            let agent = agent.clone();
+            async move {
+                let url = rule.url(path.as_str(), &query);
+                let forwarded = tokio::task::spawn_blocking(move || forward(&agent, &method, &url, &headers, &body, secure))
+                    .await
+                    .unwrap_or_else(|e| bad_gateway(&format!("Proxy task failed: {}", e)));
+                Ok::<_, Rejection>(forwarded)
+            }
+        })
+}
+
+fn forward(agent: &ureq::Agent, method: &Method, url: &str, headers: &HeaderMap, body: &[u8], secure: bool) -> Response<Vec<u8>> {
+    let mut request = agent.request(method.as_str(), url);
+    for (name, value) in headers {
+        if HOP_BY_HOP.contains(&name.as_str()) {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            request = request.set(name.as_str(), value);
+        }
+    }
+    if let Some(host) = headers.get("host").and_then(|host| host.to_str().ok()) {
+        request = request.set("X-Forwarded-Host", host);
+    }
+    request = request.set("X-Forwarded-Proto", if secure { "https" } else { "http" });
+
+    let response = match request.send_bytes(body) {
+        Ok(response) => response,
+        // Error statuses are the backend's answer, passed on as they are
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => {
+            warn!("Proxy request to {} failed: {}", url, e);
+            return bad_gateway(&format!("Could not reach {}: {}", url, e));
+        },
+    };
+    debug!("Proxied {} {} -> {}", method, url, response.status());
+
+    let mut reply = Response::builder().status(response.status());
+    let mut names = response.headers_names();
+    names.sort();
+    names.dedup();
+    for name in names {
+        if HOP_BY_HOP.contains(&name.as_str()) {
+            continue;
+        }
+        for value in response.all(&name) {
+            reply = reply.header(name.as_str(), value);
+        }
+    }
+    let mut bytes = Vec::new();
+    if let Err(e) = response.into_reader().read_to_end(&mut bytes) {
+        return bad_gateway(&format!("Failed to read the response from {}: {}", url, e));
+    }
+    reply.body(bytes).unwrap_or_else(|e| bad_gateway(&format!("Invalid response from {}: {}", url, e)))
+}
+
+fn bad_gateway(message: &str) -> Response<Vec<u8>> {
+    let mut response = Response::new(message.as_bytes().to_vec());
+    *response.status_mut() = StatusCode::BAD_GATEWAY;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_match_prefixes() {
+        let rule: ProxyRule = "/api/*=http://localhost:4000/".parse().unwrap();
+        assert!(rule.matches("/api"));
+        assert!(rule.matches("/api/users"));
+        assert!(!rule.matches("/apiary"));
+        assert_eq!(rule.url("/api/users", "page=2"), "http://localhost:4000/api/users?page=2");
+
+        let stripped = ProxyRule { strip_prefix: true, ..rule };
+        assert_eq!(stripped.url("/api/users", ""), "http://localhost:4000/users");
+        assert_eq!(stripped.url("/api", ""), "http://localhost:4000/");
+        assert!("/api".parse::<ProxyRule>().is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/// `[redirects]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RedirectsConfig {
+    /// Output formats to generate
+    #[serde(default = "default_formats")]
+    pub formats: Vec<RedirectFormat>,
+    #[serde(default)]
+    pub rules: Vec<Redirect>,
+}
+
+impl RedirectsConfig {
+    pub fn validate(&self) -> Result<()> {
+        self.rules.iter().try_for_each(Redirect::validate)
+    }
+}
+
+impl Default for RedirectsConfig {
+    fn default() -> Self {
+        Self {
+            formats: default_formats(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+fn default_formats() -> Vec<RedirectFormat> {
+    vec![RedirectFormat::Html]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedirectFormat {
+    /// Meta-refresh stub pages at the old locations
+    Html,
+    /// Netlify `_redirects` file
+    Netlify,
+    /// `redirects` array in `vercel.json`
+    Vercel,
+    /// nginx `map` include
+    Nginx,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+    #[serde(default = "default_status")]
+    pub status: u16,
+}
+
+fn default_status() -> u16 {
+    301
+}
+
+const REDIRECT_STATUSES: [u16; 5] = [301, 302, 303, 307, 308];
+
+impl Redirect {
+    /// Permanent redirect created from a page's `aliases:` front matter.
+    /// Whitespace in the page's URL is percent-encoded.
+    pub fn alias(from: &str, to: &str) -> Self {
+        Self {
+            from: format!("/{}", from.trim_start_matches('/')),
+            to: encode_whitespace(to),
+            status: default_status(),
+        }
+    }
+
+    /// Both paths have to be a single field of a `_redirects` line or an
+    /// nginx `map` entry, so they can't contain whitespace.
+    pub fn validate(&self) -> Result<()> {
+        for path in [&self.from, &self.to] {
+            if path.is_empty() || path.contains(char::is_whitespace) {
+                return Err(anyhow!("Redirect path '{}' is empty or contains whitespace; write spaces as %20", path));
+            }
+        }
+        if !REDIRECT_STATUSES.contains(&self.status) {
+            return Err(anyhow!("Redirect from {} has status {}; use 301, 302, 303, 307, or 308", self.from, self.status));
+        }
+        Ok(())
+    }
+
+    fn is_permanent(&self) -> bool {
+        matches!(self.status, 301 | 308)
+    }
+}
+
+/// Write every configured redirect format into `output_dir`.
+pub fn generate_redirects(redirects: &[Redirect], formats: &[RedirectFormat], output_dir: &Path) -> Result<()> {
+    if redirects.is_empty() {
+        return Ok(());
+    }
+
+    for format in formats {
+        match format {
+            RedirectFormat::Html => write_html_stubs(redirects, output_dir)?,
+            RedirectFormat::Netlify => write_netlify(redirects, output_dir)?,
+            RedirectFormat::Vercel => write_vercel(redirects, output_dir)?,
+            RedirectFormat::Nginx => write_nginx(redirects, output_dir)?,
+        }
+    }
+
+    info!("Generated {} redirects", redirects.len());
+    Ok(())
+}
+
+fn stub_path(output_dir: &Path, from: &str) -> PathBuf {
+    let relative = from.trim_start_matches('/');
+    if relative.ends_with(".html") {
+        output_dir.join(relative)
+    } else {
+        output_dir.join(relative).join("index.html")
+    }
+}
+
+fn write_html_stubs(redirects: &[Redirect], output_dir: &Path) -> Result<()> {
+    for redirect in redirects {
+        let path = stub_path(output_dir, &redirect.from);
+        if path.exists() {
+            warn!("Skipping redirect stub for {}: {} already exists", redirect.from, path.display());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let target = html_escape::encode_double_quoted_attribute(&redirect.to);
+        fs::write(&path, format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Redirecting&hellip;</title>
+    <link rel="canonical" href="{0}">
+    <meta name="robots" content="noindex">
+    <meta http-equiv="refresh" content="0; url={0}">
+</head>
+<body>
+    <p>This page has moved to <a href="{0}">{0}</a>.</p>
+</body>
+</html>
+"#, target))?;
+    }
+    Ok(())
+}
+
+fn write_netlify(redirects: &[Redirect], output_dir: &Path) -> Result<()> {
+    let mut content = String::new();
+    for redirect in redirects {
+        content.push_str(&format!("{} {} {}\n", redirect.from, redirect.to, redirect.status));
+    }
+    fs::write(output_dir.join("_redirects"), content)?;
+    Ok(())
+}
+
+fn write_vercel(redirects: &[Redirect], output_dir: &Path) -> Result<()> {
+    let path = output_dir.join("vercel.json");
+
+    // Keep any other settings already written to vercel.json
+    let mut config = match fs::read_to_string(&path) {
+        Ok(existing) => serde_json::from_str(&existing).unwrap_or_else(|_| serde_json::json!({})),
+        Err(_) => serde_json::json!({}),
+    };
+
+    let entries: Vec<_> = redirects.iter()
+        .map(|r| serde_json::json!({
+            "source": r.from,
+            "destination": r.to,
+            "permanent": r.is_permanent(),
+        }))
+        .collect();
+    config["redirects"] = serde_json::Value::Array(entries);
+
+    fs::write(&path, serde_json::to_string_pretty(&config)?)?;
+    Ok(())
+}
+
+/// One map per status, since nginx's `return` needs the code written out.
+fn write_nginx(redirects: &[Redirect], output_dir: &Path) -> Result<()> {
+    let mut by_status: BTreeMap<u16, Vec<&Redirect>> = BTreeMap::new();
+    for redirect in redirects {
+        by_status.entry(redirect.status).or_default().push(redirect);
+    }
+
+    let mut content = String::from("# Include inside the http block and add to the server block:\n");
+    for status in by_status.keys() {
+        content.push_str(&format!("#   if ($redirect_{0}) {{ return {0} $redirect_{0}; }}\n", status));
+    }
+    for (status, redirects) in &by_status {
+        content.push_str(&format!("map $uri $redirect_{} {{\n", status));
+        for redirect in redirects {
+            content.push_str(&format!("    {} {};\n", redirect.from, redirect.to));
+        }
+        content.push_str("}\n");
+    }
+    fs::write(output_dir.join("redirects.map"), content)?;
+    Ok(())
+}
+
+/// `url` with each whitespace character percent-encoded.
+fn encode_whitespace(url: &str) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for c in url.chars() {
+        if c.is_whitespace() {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn rules() -> Vec<Redirect> {
+        vec![
+            Redirect { from: "/old-docs/setup".to_string(), to: "/docs/getting-started".to_string(), status: 301 },
+            Redirect { from: "/sale.html".to_string(), to: "/offers/".to_string(), status: 302 },
+            Redirect { from: "/feed".to_string(), to: "/rss.xml".to_string(), status: 308 },
+        ]
+    }
+
+    #[test]
+    fn test_html_stubs_point_at_the_target_and_skip_existing_pages() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("sale.html"), "<p>Still here</p>").unwrap();
+
+        generate_redirects(&rules(), &[RedirectFormat::Html], temp.path()).unwrap();
+
+        let stub = fs::read_to_string(temp.path().join("old-docs/setup/index.html")).unwrap();
+        assert!(stub.contains(r#"<meta http-equiv="refresh" content="0; url=/docs/getting-started">"#));
+        assert!(stub.contains(r#"<link rel="canonical" href="/docs/getting-started">"#));
+        assert_eq!(fs::read_to_string(temp.path().join("sale.html")).unwrap(), "<p>Still here</p>");
+    }
+
+    #[test]
+    fn test_netlify_and_vercel_keep_each_status() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("vercel.json"), r#"{"cleanUrls": true}"#).unwrap();
+
+        generate_redirects(&rules(), &[RedirectFormat::Netlify, RedirectFormat::Vercel], temp.path()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(temp.path().join("_redirects")).unwrap(),
+            "/old-docs/setup /docs/getting-started 301\n/sale.html /offers/ 302\n/feed /rss.xml 308\n",
+        );
+        let vercel: serde_json::Value = serde_json::from_str(&fs::read_to_string(temp.path().join("vercel.json")).unwrap()).unwrap();
+        assert_eq!(vercel["cleanUrls"], true);
+        let permanent: Vec<_> = vercel["redirects"].as_array().unwrap().iter()
+            .map(|r| (r["source"].as_str().unwrap(), r["permanent"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(permanent, [("/old-docs/setup", true), ("/sale.html", false), ("/feed", true)]);
+    }
+
+    #[test]
+    fn test_nginx_returns_each_rules_status() {
+        let temp = tempdir().unwrap();
+
+        generate_redirects(&rules(), &[RedirectFormat::Nginx], temp.path()).unwrap();
+
+        let map = fs::read_to_string(temp.path().join("redirects.map")).unwrap();
+        assert!(map.contains("#   if ($redirect_302) { return 302 $redirect_302; }\n"));
+        assert!(map.contains("map $uri $redirect_301 {\n    /old-docs/setup /docs/getting-started;\n}\n"));
+        assert!(map.contains("map $uri $redirect_302 {\n    /sale.html /offers/;\n}\n"));
+        assert!(map.contains("map $uri $redirect_308 {\n    /feed /rss.xml;\n}\n"));
+    }
+
+    #[test]
+    fn test_rules_with_whitespace_or_other_statuses_are_rejected() {
+        let config: RedirectsConfig = toml::from_str("[[rules]]\nfrom = \"/old page\"\nto = \"/new\"").unwrap();
+        assert!(config.validate().is_err());
+        let config: RedirectsConfig = toml::from_str("[[rules]]\nfrom = \"/old\"\nto = \"/new\"\nstatus = 200").unwrap();
+        assert!(config.validate().is_err());
+        let config: RedirectsConfig = toml::from_str("[[rules]]\nfrom = \"/old%20page\"\nto = \"/new\"\nstatus = 307").unwrap();
+        assert!(config.validate().is_ok());
+
+        let alias = Redirect::alias("old-cafe", "/blog/Mon Café.html");
+        assert_eq!(alias.to, "/blog/Mon%20Café.html");
+        assert!(alias.validate().is_ok());
+        assert!(Redirect::alias("old cafe", "/blog/cafe.html").validate().is_err());
+    }
+}
@@ -0,0 +1,374 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use serde::Deserialize;
+use crate::layout::{load_layout, render_layout};
+use crate::themes::ComponentDirs;
+use crate::markdown::markdown_to_html;
+
+lazy_static! {
+    static ref CONVENTIONAL_COMMIT: Regex = Regex::new(
+        r"^(?P<type>[a-zA-Z]+)(?:\((?P<scope>[^)]+)\))?(?P<breaking>!)?:\s*(?P<desc>.+)$"
+    ).unwrap();
+    static ref CHANGELOG_HEADING: Regex = Regex::new(
+        r"^##\s+\[?(?P<version>[^\]\s]+)\]?(?:\s*[-(]\s*(?P<date>\d{4}-\d{2}-\d{2})\)?)?"
+    ).unwrap();
+}
+
+/// `[releases]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleasesConfig {
+    #[serde(default)]
+    pub source: ReleaseSource,
+    /// Git repository to read tags from
+    #[serde(default = "default_repo")]
+    pub repo: PathBuf,
+    /// Changelog file used with `source = "changelog"`
+    #[serde(default = "default_changelog")]
+    pub changelog: PathBuf,
+    /// Output subdirectory for the releases section
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default = "default_title")]
+    pub title: String,
+    #[serde(default = "default_per_page")]
+    pub per_page: usize,
+    /// Optional layout containing `@{title}` and `@{yield}` placeholders
+    pub layout: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseSource {
+    /// Git tags with conventional-commit messages between them
+    #[default]
+    Git,
+    /// `## [version] - date` sections of a CHANGELOG.md
+    Changelog,
+}
+
+fn default_repo() -> PathBuf {
+    PathBuf::from(".")
+}
+
+fn default_changelog() -> PathBuf {
+    PathBuf::from("CHANGELOG.md")
+}
+
+fn default_path() -> String {
+    "releases".to_string()
+}
+
+fn default_title() -> String {
+    "Releases".to_string()
+}
+
+fn default_per_page() -> usize {
+    10
+}
+
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: String,
+    pub date: Option<DateTime<FixedOffset>>,
+    /// Release notes as Markdown
+    pub notes: String,
+}
+
+impl Release {
+    fn slug(&self) -> String {
+        self.version.replace(['/', ' '], "-")
+    }
+}
+
+/// Commit groups in the order they appear in generated notes.
+const COMMIT_GROUPS: [(&str, &str); 5] = [
+    ("breaking", "Breaking Changes"),
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("docs", "Documentation"),
+];
+
+/// Load releases newest first from the configured source.
+pub fn load_releases(config: &ReleasesConfig) -> Result<Vec<Release>> {
+    match config.source {
+        ReleaseSource::Git => releases_from_git(&config.repo),
+        ReleaseSource::Changelog => {
+            let content = fs::read_to_string(&config.changelog)
+                .map_err(|e| anyhow!("Failed to read {}: {}", config.changelog.display(), e))?;
+            Ok(parse_changelog(&content))
+        }
+    }
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").arg("-C").arg(repo).args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn releases_from_git(repo: &Path) -> Result<Vec<Release>> {
+    let tags = git(repo, &["tag", "--sort=-creatordate", "--format=%(refname:short)|%(creatordate:iso-strict)"])?;
+    let tags: Vec<(&str, Option<DateTime<FixedOffset>>)> = tags.lines()
+        .filter_map(|line| line.split_once('|'))
+        .map(|(tag, date)| (tag, DateTime::parse_from_rfc3339(date).ok()))
+        .collect();
+
+    let mut releases = Vec::new();
+    for (i, (tag, date)) in tags.iter().enumerate() {
+        let range = match tags.get(i + 1) {
+            Some((previous, _)) => format!("{}..{}", previous, tag),
+            None => tag.to_string(),
+        };
+        let subjects = git(repo, &["log", "--format=%s%n%b%x00", &range])?;
+        releases.push(Release {
+            version: tag.to_string(),
+            date: *date,
+            notes: notes_from_commits(subjects.split('\0')),
+        });
+    }
+    Ok(releases)
+}
+
+/// Group conventional-commit messages (`type(scope)!: description`) into
+/// Markdown release notes. Commits that don't follow the convention, or use
+/// types like `chore` and `ci`, are left out.
+pub fn notes_from_commits<'a>(messages: impl Iterator<Item = &'a str>) -> String {
+    let mut groups: Vec<Vec<String>> = vec![Vec::new(); COMMIT_GROUPS.len()];
+
+    for message in messages {
+        let message = message.trim();
+        let subject = message.lines().next().unwrap_or("");
+        let Some(caps) = CONVENTIONAL_COMMIT.captures(subject) else { continue };
+
+        let mut entry = caps["desc"].to_string();
+        if let Some(scope) = caps.name("scope") {
+            entry = format!("**{}:** {}", scope.as_str(), entry);
+        }
+
+        let breaking = caps.name("breaking").is_some() || message.contains("BREAKING CHANGE");
+        let kind = if breaking { "breaking" } else { &caps["type"] };
+        if let Some(group) = COMMIT_GROUPS.iter().position(|(k, _)| *k == kind.to_ascii_lowercase()) {
+            groups[group].push(entry);
+        }
+    }
+
+    let mut notes = String::new();
+    for (entries, (_, heading)) in groups.iter().zip(COMMIT_GROUPS.iter()) {
+        if entries.is_empty() {
+            continue;
+        }
+        notes.push_str(&format!("### {}\n\n", heading));
+        for entry in entries {
+            notes.push_str(&format!("- {}\n", entry));
+        }
+        notes.push('\n');
+    }
+    notes
+}
+
+/// Split a Keep a Changelog style file into releases on its `##` headings.
+pub fn parse_changelog(content: &str) -> Vec<Release> {
+    let mut releases: Vec<Release> = Vec::new();
+    for line in content.lines() {
+        if let Some(caps) = CHANGELOG_HEADING.captures(line) {
+            let date = caps.name("date")
+                .and_then(|d| NaiveDate::parse_from_str(d.as_str(), "%Y-%m-%d").ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|d| d.and_utc().fixed_offset());
+            releases.push(Release {
+                version: caps["version"].to_string(),
+                date,
+                notes: String::new(),
+            });
+        } else if let Some(release) = releases.last_mut() {
+            release.notes.push_str(line);
+            release.notes.push('\n');
+        }
+    }
+    releases.retain(|r| !r.version.eq_ignore_ascii_case("unreleased"));
+    releases
+}
+
+/// Generate per-release pages, paginated listings, and an Atom feed.
+/// Returns the HTML pages written.
+pub fn generate_releases(
+    config: &ReleasesConfig,
+    releases: &[Release],
+    output_dir: &Path,
+    components: &ComponentDirs,
+    base_url: &str,
+) -> Result<Vec<PathBuf>> {
+    let section = config.path.trim_matches('/');
+    let root = output_dir.join(section);
+    let layout = load_layout(components, config.layout.as_deref());
+    let mut written = Vec::new();
+
+    let write_page = |path: PathBuf, title: &str, body: &str| -> Result<PathBuf> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, render_layout(&layout, title, body))?;
+        Ok(path)
+    };
+
+    // One page per release
+    for release in releases {
+        let body = format!(
+            "<h1>{}</h1>\n{}\n{}<p><a href=\"/{}/\">&larr; All releases</a></p>",
+            html_escape::encode_text(&release.version),
+            release_date_html(release),
+            markdown_to_html(&release.notes),
+            section
+        );
+        let path = root.join(release.slug()).join("index.html");
+        written.push(write_page(path, &format!("{} - {}", release.version, config.title), &body)?);
+    }
+
+    // Paginated listing
+    let per_page = config.per_page.max(1);
+    let pages: Vec<&[Release]> = releases.chunks(per_page).collect();
+    let total = pages.len().max(1);
+    for page_num in 0..total {
+        let mut body = format!("<h1>{}</h1>\n", html_escape::encode_text(&config.title));
+        for release in pages.get(page_num).copied().unwrap_or(&[]) {
+            body.push_str(&format!(
+                "<section class=\"release\">\n<h2><a href=\"/{}/{}/\">{}</a></h2>\n{}\n{}</section>\n",
+                section,
+                release.slug(),
+                html_escape::encode_text(&release.version),
+                release_date_html(release),
+                markdown_to_html(&release.notes)
+            ));
+        }
+        body.push_str(&pagination_html(section, page_num + 1, total));
+
+        let path = if page_num == 0 {
+            root.join("index.html")
+        } else {
+            root.join("page").join((page_num + 1).to_string()).join("index.html")
+        };
+        written.push(write_page(path, &config.title, &body)?);
+    }
+
+    fs::write(root.join("atom.xml"), releases_atom(config, releases, base_url))?;
+
+    info!("Generated {} release pages", releases.len());
+    Ok(written)
+}
+
+fn release_date_html(release: &Release) -> String {
+    release.date
+        .map(|d| format!("<time datetime=\"{}\">{}</time>", d.to_rfc3339(), d.format("%B %-d, %Y")))
+        .unwrap_or_default()
+}
+
+fn page_url(section: &str, page: usize) -> String {
+    if page == 1 {
+        format!("/{}/", section)
+    } else {
+        format!("/{}/page/{}/", section, page)
+    }
+}
+
+fn pagination_html(section: &str, page: usize, total: usize) -> String {
+    if total <= 1 {
+        return String::new();
+    }
+    let mut html = String::from("<nav class=\"pagination\">");
+    if page > 1 {
+        html.push_str(&format!("<a href=\"{}\" class=\"prev\">Newer</a>", page_url(section, page - 1)));
+    }
+    html.push_str(&format!("<span>Page {} of {}</span>", page, total));
+    if page < total {
+        html.push_str(&format!("<a href=\"{}\" class=\"next\">Older</a>", page_url(section, page + 1)));
+    }
+    html.push_str("</nav>");
+    html
+}
+
+fn releases_atom(config: &ReleasesConfig, releases: &[Release], base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    let section = config.path.trim_matches('/');
+    let updated = releases.iter()
+        .filter_map(|r| r.date)
+        .max()
+        .map(|d| d.to_rfc3339())
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+    let mut feed = format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+    <title>{}</title>
+    <link href="{}/{}/atom.xml" rel="self"/>
+    <link href="{}/{}/"/>
+    <id>{}/{}/</id>
+    <updated>{}</updated>"#,
+        html_escape::encode_text(&config.title),
+        base, section,
+        base, section,
+        base, section,
+        updated
+    );
+
+    for release in releases {
+        let url = format!("{}/{}/{}/", base, section, release.slug());
+        let date = release.date.map(|d| d.to_rfc3339()).unwrap_or_else(|| updated.clone());
+        feed.push_str(&format!(r#"
+    <entry>
+        <title>{}</title>
+        <link href="{}"/>
+        <id>{}</id>
+        <updated>{}</updated>
+        <content type="html"><![CDATA[{}]]></content>
+    </entry>"#,
+            html_escape::encode_text(&release.version),
+            url,
+            url,
+            date,
+            markdown_to_html(&release.notes)
+        ));
+    }
+
+    feed.push_str("\n</feed>\n");
+    feed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_from_commits() {
+        let commits = [
+            "feat(blog): add tag pages",
+            "fix: handle empty front matter",
+            "chore: bump deps",
+            "refactor!: drop legacy config",
+            "not a conventional commit",
+        ];
+        let notes = notes_from_commits(commits.into_iter());
+        assert!(notes.contains("### Breaking Changes\n\n- drop legacy config"));
+        assert!(notes.contains("### Features\n\n- **blog:** add tag pages"));
+        assert!(notes.contains("### Bug Fixes\n\n- handle empty front matter"));
+        assert!(!notes.contains("bump deps"));
+    }
+
+    #[test]
+    fn test_parse_changelog() {
+        let changelog = "# Changelog\n\n## [Unreleased]\n- wip\n\n## [1.1.0] - 2025-03-01\n### Added\n- Feeds\n\n## 1.0.0 (2025-01-15)\n- Initial release\n";
+        let releases = parse_changelog(changelog);
+        assert_eq!(releases.len(), 2);
+        assert_eq!(releases[0].version, "1.1.0");
+        assert!(releases[0].notes.contains("- Feeds"));
+        assert_eq!(releases[1].version, "1.0.0");
+        assert_eq!(releases[1].date.unwrap().format("%Y-%m-%d").to_string(), "2025-01-15");
+    }
+}
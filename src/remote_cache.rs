@@ -0,0 +1,332 @@
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::verify::hex;
+
+/// `[remote_cache]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteCacheConfig {
+    /// `https://cache.example.com/site` for a store that takes GET and PUT at
+    /// `{url}/{key}`, or `s3://bucket/prefix` for an S3-compatible bucket
+    pub url: String,
+    /// Environment variable holding a bearer token for an HTTP store
+    pub token_env: Option<String>,
+    /// Bucket region; defaults to `AWS_REGION`, then `us-east-1`
+    pub region: Option<String>,
+    /// Endpoint of an S3-compatible service such as MinIO or R2, addressed path-style
+    pub endpoint: Option<String>,
+    /// Upload entries this build creates; turn off for read-only clients
+    #[serde(default = "crate::config::default_true")]
+    pub upload: bool,
+    /// Seconds to wait for the store before building without it
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+#[derive(Clone)]
+enum Store {
+    Http { base_url: String, token: Option<String> },
+    S3(S3Bucket),
+}
+
+/// An S3-compatible bucket, with credentials from `AWS_ACCESS_KEY_ID`,
+/// `AWS_SECRET_ACCESS_KEY`, and `AWS_SESSION_TOKEN`.
+#[derive(Clone)]
+pub(crate) struct S3Bucket {
+    bucket: String,
+    prefix: String,
+    region: String,
+    endpoint: Option<String>,
+    credentials: S3Credentials,
+}
+
+#[derive(Clone)]
+struct S3Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl S3Bucket {
+    /// The bucket at `location`, `bucket/prefix` without the `s3://`. The
+    /// region defaults to `AWS_REGION`, then `us-east-1`.
+    pub(crate) fn from_env(location: &str, region: Option<&str>, endpoint: Option<&str>) -> Result<Self> {
+        let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let (Some(access_key), Some(secret_key)) = (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) else {
+            return Err(anyhow!("AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set for s3://{}", location));
+        };
+        Ok(Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+            region: region.map(str::to_string)
+                .or_else(|| var("AWS_REGION"))
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            endpoint: endpoint.map(|endpoint| endpoint.trim_end_matches('/').to_string()),
+            credentials: S3Credentials { access_key, secret_key, session_token: var("AWS_SESSION_TOKEN") },
+        })
+    }
+
+    /// Request for the object `key` under the prefix, authorized for a body of `payload`.
+    pub(crate) fn request(&self, agent: &ureq::Agent, method: &str, key: &str, payload: &[u8]) -> ureq::Request {
+        let object = if self.prefix.is_empty() { key.to_string() } else { format!("{}/{}", self.prefix, key) };
+        let (host, path) = match &self.endpoint {
+            Some(endpoint) => {
+                let host = endpoint.split_once("://").map_or(endpoint.as_str(), |(_, host)| host);
+                (host.to_string(), format!("/{}/{}", self.bucket, uri_encode_path(&object)))
+            },
+            None => (format!("{}.s3.{}.amazonaws.com", self.bucket, self.region), format!("/{}", uri_encode_path(&object))),
+        };
+        let scheme = match &self.endpoint {
+            Some(endpoint) if endpoint.starts_with("http://") => "http",
+            _ => "https",
+        };
+        let signed = sign_s3(method, &host, &path, payload, &self.region, &self.credentials, &Utc::now().format("%Y%m%dT%H%M%SZ").to_string());
+        let mut request = agent.request(method, &format!("{}://{}{}", scheme, host, path));
+        for (name, value) in signed {
+            request = request.set(name, &value);
+        }
+        request
+    }
+}
+
+/// A cache shared between machines behind the local cache directory.
+/// Local entries are used first, misses are read through from the store, and
+/// new entries are uploaded. The store is only ever an optimization: when it
+/// fails, the build carries on with the local cache.
+#[derive(Clone)]
+pub struct RemoteCache {
+    store: Store,
+    upload: bool,
+    agent: ureq::Agent,
+    /// Set after the first connection failure, so later lookups don't wait on it
+    unavailable: Arc<AtomicBool>,
+}
+
+impl RemoteCache {
+    pub fn from_config(config: &RemoteCacheConfig) -> Result<Self> {
+        let mut upload = config.upload;
+        let store = match config.url.strip_prefix("s3://") {
+            Some(location) => Store::S3(S3Bucket::from_env(location, config.region.as_deref(), config.endpoint.as_deref())?),
+            None if config.url.starts_with("http://") || config.url.starts_with("https://") => {
+                let token = config.token_env.as_ref().map(|name| env::var(name).ok().filter(|token| !token.is_empty()));
+                // Without its token, a machine can still read from the store
+                if let Some(None) = token {
+                    info!("{} is not set; reading from the remote cache without uploading", config.token_env.as_deref().unwrap_or_default());
+                    upload = false;
+                }
+                Store::Http { base_url: config.url.trim_end_matches('/').to_string(), token: token.flatten() }
+            },
+            None => return Err(anyhow!("Unsupported remote cache URL '{}', expected http(s):// or s3://", config.url)),
+        };
+        Ok(Self {
+            store,
+            upload,
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(config.timeout_secs)).build(),
+            unavailable: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Make sure `local` holds the entry for `key`, downloading it on a local
+    /// miss. Returns false when neither cache has it.
+    pub fn fetch(&self, key: &str, local: &Path) -> bool {
+        if local.exists() {
+            return true;
+        }
+        if self.unavailable.load(Ordering::Relaxed) {
+            return false;
+        }
+        match self.get(key) {
+            Ok(Some(bytes)) => {
+                let written = local.parent().map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(local, bytes));
+                match written {
+                    Ok(()) => {
+                        debug!("Remote cache hit for {}", key);
+                        true
+                    },
+                    Err(e) => {
+                        warn!("Failed to save remote cache entry {}: {}", key, e);
+                        false
+                    },
+                }
+            },
+            Ok(None) => false,
+            Err(e) => {
+                self.disable(&e);
+                false
+            },
+        }
+    }
+
+    /// Upload the local entry for `key`, created by this build.
+    pub fn store(&self, key: &str, local: &Path) {
+        if !self.upload || self.unavailable.load(Ordering::Relaxed) {
+            return;
+        }
+        let result = fs::read(local).map_err(anyhow::Error::from)
+            .and_then(|bytes| self.put(key, &bytes));
+        match result {
+            Ok(()) => debug!("Uploaded {} to the remote cache", key),
+            Err(e) => self.disable(&e),
+        }
+    }
+
+    fn disable(&self, error: &anyhow::Error) {
+        if !self.unavailable.swap(true, Ordering::Relaxed) {
+            warn!("Remote cache unavailable, using the local cache only: {}", error);
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.request("GET", key, &[]).call() {
+            Ok(response) => {
+                let mut bytes = Vec::new();
+                response.into_reader().read_to_end(&mut bytes)?;
+                Ok(Some(bytes))
+            },
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            // S3 answers 403 for missing objects unless the caller may list the bucket
+            Err(ureq::Error::Status(403, _)) if matches!(self.store, Store::S3(_)) => Ok(None),
+            Err(e) => Err(anyhow!("GET {}: {}", key, e)),
+        }
+    }
+
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        self.request("PUT", key, bytes)
+            .send_bytes(bytes)
+            .map_err(|e| anyhow!("PUT {}: {}", key, e))?;
+        Ok(())
+    }
+
+    /// Request for `key`, authorized for a body of `payload`.
+    fn request(&self, method: &str, key: &str, payload: &[u8]) -> ureq::Request {
+        match &self.store {
+            Store::Http { base_url, token } => {
+                let request = self.agent.request(method, &format!("{}/{}", base_url, key));
+                match token {
+                    Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+                    None => request,
+                }
+            },
+            Store::S3(bucket) => bucket.request(&self.agent, method, key, payload),
+        }
+    }
+}
+
+/// Headers signing an S3 request with AWS Signature Version 4, at `amz_date`
+/// (`YYYYMMDDTHHMMSSZ`).
+fn sign_s3(
+    method: &str,
+    host: &str,
+    path: &str,
+    payload: &[u8],
+    region: &str,
+    credentials: &S3Credentials,
+    amz_date: &str,
+) -> Vec<(&'static str, String)> {
+    let payload_hash = hex(&Sha256::digest(payload));
+    let mut headers = vec![
+        ("host", host.to_string()),
+        ("x-amz-content-sha256", payload_hash.clone()),
+        ("x-amz-date", amz_date.to_string()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!("{}\n{}\n\n{}\n{}\n{}", method, path, canonical_headers, signed_headers, payload_hash);
+
+    let scope = format!("{}/{}/s3/aws4_request", &amz_date[..8], region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date, scope, hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = signing_key(&credentials.secret_key, &amz_date[..8], region, "s3");
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    headers.retain(|(name, _)| *name != "host");
+    headers.push(("Authorization", format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key, scope, signed_headers, signature
+    )));
+    headers
+}
+
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<_>>();
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().to_vec()
+}
+
+/// Percent-encode an object key for a URL path, keeping `/` between segments.
+fn uri_encode_path(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // From the AWS Signature Version 4 documentation
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+        assert_eq!(uri_encode_path("maps/a b+c.png"), "maps/a%20b%2Bc.png");
+    }
+
+    #[test]
+    fn test_unreachable_store_falls_back_to_local() {
+        let temp = tempfile::tempdir().unwrap();
+        // A port that was just free, so nothing answers on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let cache = RemoteCache::from_config(&RemoteCacheConfig {
+            url: format!("http://127.0.0.1:{}", port),
+            token_env: None,
+            region: None,
+            endpoint: None,
+            upload: true,
+            timeout_secs: 1,
+        }).unwrap();
+        let local = temp.path().join("maps/x.png");
+        assert!(!cache.fetch("maps/x.png", &local));
+        fs::create_dir_all(local.parent().unwrap()).unwrap();
+        fs::write(&local, "png").unwrap();
+        cache.store("maps/x.png", &local);
+        assert!(cache.fetch("maps/x.png", &local));
+    }
+}
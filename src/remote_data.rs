@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use anyhow::{Context, Result, anyhow};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::remote_cache::RemoteCache;
+
+/// One `[[remote_data]]` entry of `eldroid.toml`: JSON fetched during the
+/// build and read like a data file, as `@{data.<name>.<key>}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteDataConfig {
+    pub name: String,
+    pub url: String,
+    /// Seconds a fetched response is reused before fetching again
+    #[serde(default = "default_ttl")]
+    pub ttl_secs: u64,
+    /// Extra request headers, e.g. `Accept = "application/vnd.github+json"`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Environment variable holding a bearer token for the request
+    pub token_env: Option<String>,
+    /// Seconds to wait for the response
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_ttl() -> u64 {
+    3600
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl RemoteDataConfig {
+    /// Key of the cached response, relative to the cache directory.
+    fn cache_key(&self) -> String {
+        format!("remote-data/{}.json", self.name)
+    }
+
+    fn cache_file(&self, cache_dir: &Path) -> PathBuf {
+        cache_dir.join(self.cache_key())
+    }
+
+    /// The response, from the cache while it is younger than `ttl_secs`.
+    /// When a fetch fails, an expired cached response is used instead, or
+    /// without one, the response another machine put in `remote_cache`.
+    pub fn load(&self, cache_dir: &Path, remote_cache: Option<&RemoteCache>) -> Result<Value> {
+        // The name is part of the cache path, so it can't leave the cache directory
+        let valid_name = !self.name.is_empty() && self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(anyhow!("Invalid remote data name '{}': use letters, digits, '_', and '-'", self.name));
+        }
+        let cache_file = self.cache_file(cache_dir);
+        let age = fs::metadata(&cache_file).and_then(|meta| meta.modified()).ok()
+            .map(|modified| SystemTime::now().duration_since(modified).unwrap_or_default());
+        let cached = || -> Result<Value> {
+            Ok(serde_json::from_str(&fs::read_to_string(&cache_file)?)?)
+        };
+
+        if age.is_some_and(|age| age < Duration::from_secs(self.ttl_secs)) {
+            if let Ok(value) = cached() {
+                return Ok(value);
+            }
+        }
+
+        match self.fetch() {
+            Ok(value) => {
+                fs::create_dir_all(cache_file.parent().unwrap_or(cache_dir))?;
+                fs::write(&cache_file, serde_json::to_string(&value)?)?;
+                info!("Fetched remote data '{}' from {}", self.name, self.url);
+                if let Some(remote) = remote_cache {
+                    remote.store(&self.cache_key(), &cache_file);
+                }
+                Ok(value)
+            },
+            Err(e) => {
+                // A fresh runner can still use the response another machine fetched
+                if let Some(remote) = remote_cache {
+                    remote.fetch(&self.cache_key(), &cache_file);
+                }
+                match cached() {
+                    Ok(value) => {
+                        warn!("Using the cached remote data '{}', since fetching it failed: {:#}", self.name, e);
+                        Ok(value)
+                    },
+                    Err(_) => Err(e),
+                }
+            },
+        }
+    }
+
+    fn fetch(&self) -> Result<Value> {
+        let mut request = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .build()
+            .get(&self.url);
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        if let Some(token_env) = &self.token_env {
+            let token = env::var(token_env)
+                .map_err(|_| anyhow!("{} is not set; it holds the token for remote data '{}'", token_env, self.name))?;
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response = request.call()
+            .map_err(|e| anyhow!("Failed to fetch remote data '{}' from {}: {}", self.name, self.url, e))?;
+        response.into_json()
+            .with_context(|| format!("Remote data '{}' from {} is not JSON", self.name, self.url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uses_cache_within_ttl_and_when_fetch_fails() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        // A port that was just free, so nothing answers on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut config: RemoteDataConfig = toml::from_str(&format!(
+            "name = \"releases\"\nurl = \"http://127.0.0.1:{}/releases\"\ntimeout_secs = 1", port,
+        )).unwrap();
+        assert!(config.load(cache_dir.path(), None).is_err());
+
+        let cache_file = config.cache_file(cache_dir.path());
+        fs::create_dir_all(cache_file.parent().unwrap()).unwrap();
+        fs::write(&cache_file, r#"{"tag": "v1.0"}"#).unwrap();
+        assert_eq!(config.load(cache_dir.path(), None).unwrap()["tag"], "v1.0");
+
+        // Expired, and the URL is unreachable
+        config.ttl_secs = 0;
+        assert_eq!(config.load(cache_dir.path(), None).unwrap()["tag"], "v1.0");
+
+        config.name = "../../escape".to_string();
+        let err = config.load(cache_dir.path(), None).unwrap_err();
+        assert!(err.to_string().contains("Invalid remote data name"), "{}", err);
+    }
+}
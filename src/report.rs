@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use clap::ValueEnum;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use crate::analyzer::PerformanceReport;
+use crate::dashboard::{Manifest, PageMetrics};
+
+/// Output format for security and performance analysis results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Per-page `.perf.txt` files and log output
+    #[default]
+    Text,
+    /// A single `report.json`
+    Json,
+    /// A single `report.sarif` for code-scanning UIs
+    Sarif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+/// Analyzer rules, with the description shown by code-scanning UIs.
+pub const RULES: &[(&str, &str)] = &[
+    ("mixed_content", "HTTP resource loaded on an HTTPS page"),
+    ("insecure_links", "External resource loaded over plain HTTP"),
+    ("structured_data", "JSON-LD block violates schema.org expectations"),
+    ("seo_metadata", "Missing, duplicate, or badly sized title, description, or canonical URL"),
+    ("no_js_fallback", "Content or widget without a no-JavaScript fallback"),
+    ("performance", "Performance recommendation"),
+    ("budget", "Page exceeds a configured performance budget"),
+    ("regression", "Page got slower or larger than in the baseline build"),
+    ("missing_field", "Layout uses a front matter field the page doesn't set"),
+];
+
+/// A single analyzer result for one page.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub level: Level,
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(rule: &'static str, level: Level, file: &Path, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            level,
+            file: file.to_path_buf(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: [{}] {}", self.file.display(), self.rule, self.message)
+    }
+}
+
+/// `[analysis]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AnalysisConfig {
+    /// Rules whose findings fail the build, e.g. `["mixed_content", "insecure_links"]`
+    #[serde(default)]
+    pub fail_on: Vec<String>,
+    /// Limits checked with `--analyze-performance`; exceeding one fails the build
+    #[serde(default)]
+    pub budgets: Budgets,
+    /// Hosts without TLS support, left on http:// by `--fix-mixed-content`
+    #[serde(default)]
+    pub http_allowlist: Vec<String>,
+    /// How much worse pages may get than in the baseline build
+    pub regressions: Option<Regressions>,
+    /// Analyzer passes to skip, keyed by a glob relative to the input
+    /// directory, e.g. `"embeds/**" = ["security"]`
+    #[serde(default)]
+    pub exclude: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Budgets {
+    /// Largest allowed page, in kilobytes
+    pub max_page_size_kb: Option<usize>,
+    /// Most scripts without `async` or `defer` allowed on a page
+    pub max_blocking_scripts: Option<usize>,
+}
+
+impl Budgets {
+    /// Budget findings for one page's performance report.
+    pub fn check(&self, report: &PerformanceReport, file: &Path) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        if let Some(max_kb) = self.max_page_size_kb {
+            if report.page_size > max_kb * 1024 {
+                findings.push(Finding::new("budget", Level::Error, file, format!(
+                    "Page size {:.2} KB exceeds budget of {} KB", report.page_size as f64 / 1024.0, max_kb
+                )));
+            }
+        }
+        if let Some(max_scripts) = self.max_blocking_scripts {
+            if report.blocking_scripts > max_scripts {
+                findings.push(Finding::new("budget", Level::Error, file, format!(
+                    "{} blocking scripts exceed budget of {}", report.blocking_scripts, max_scripts
+                )));
+            }
+        }
+        findings
+    }
+}
+
+/// `[analysis.regressions]`: limits on how much a page's metrics may worsen
+/// compared with the baseline.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Regressions {
+    /// Metrics to compare against, e.g. a manifest committed to the repository.
+    /// Without it, each build that passes becomes the baseline for the next.
+    pub baseline: Option<PathBuf>,
+    /// Points a page's performance score may drop
+    #[serde(default = "default_max_score_drop")]
+    pub max_score_drop: u32,
+    /// Percent a page may grow
+    #[serde(default = "default_max_size_increase_percent")]
+    pub max_size_increase_percent: f64,
+    /// Growth below this many kilobytes is allowed whatever the percentage
+    #[serde(default = "default_min_size_increase_kb")]
+    pub min_size_increase_kb: f64,
+    /// Blocking scripts a page may add
+    #[serde(default)]
+    pub max_blocking_scripts_added: usize,
+    /// Fail the build on a regression; otherwise only warn
+    #[serde(default = "crate::config::default_true")]
+    pub fail: bool,
+}
+
+fn default_max_score_drop() -> u32 {
+    5
+}
+
+fn default_max_size_increase_percent() -> f64 {
+    10.0
+}
+
+fn default_min_size_increase_kb() -> f64 {
+    1.0
+}
+
+impl Regressions {
+    /// Baseline kept in the performance directory when none is configured.
+    pub const BASELINE_FILE: &'static str = "baseline.json";
+
+    /// Compare `current` with the baseline. When nothing regressed beyond
+    /// the limits, fold `current` into the baseline kept in `perf_dir`.
+    pub fn check_and_update(&self, current: &[PageMetrics], perf_dir: &Path) -> Result<Vec<Finding>> {
+        let path = self.baseline.clone().unwrap_or_else(|| perf_dir.join(Self::BASELINE_FILE));
+        let baseline = Manifest::read(&path);
+        let findings = match &baseline {
+            Some(baseline) => self.check(current, baseline),
+            None => {
+                info!("No performance baseline at {}; this build becomes it", path.display());
+                Vec::new()
+            },
+        };
+
+        let failed = findings.iter().any(|f| f.level == Level::Error);
+        if !failed && self.baseline.is_none() {
+            // Pages this build skipped keep their previous metrics
+            let mut pages = baseline.map(|baseline| baseline.pages).unwrap_or_default();
+            pages.retain(|page| !current.iter().any(|p| p.file == page.file));
+            pages.extend(current.iter().cloned());
+            pages.sort_by(|a, b| a.file.cmp(&b.file));
+            fs::create_dir_all(perf_dir)?;
+            fs::write(&path, serde_json::to_string_pretty(&Manifest { generated: Utc::now().to_rfc3339(), pages })?)?;
+        }
+        Ok(findings)
+    }
+
+    /// Findings for every page in both `current` and `baseline` that got
+    /// worse by more than the limits allow.
+    pub fn check(&self, current: &[PageMetrics], baseline: &Manifest) -> Vec<Finding> {
+        let level = if self.fail { Level::Error } else { Level::Warning };
+        let mut findings = Vec::new();
+        for page in current {
+            let Some(before) = baseline.pages.iter().find(|p| p.file == page.file) else {
+                continue;
+            };
+            if let (Some(score), Some(before)) = (page.score, before.score) {
+                if score + self.max_score_drop < before {
+                    findings.push(Finding::new("regression", level, &page.file, format!(
+                        "Performance score dropped from {} to {}", before, score
+                    )));
+                }
+            }
+            let growth = page.page_size as f64 - before.page_size as f64;
+            let percent = growth / before.page_size.max(1) as f64 * 100.0;
+            if growth > self.min_size_increase_kb * 1024.0 && percent > self.max_size_increase_percent {
+                findings.push(Finding::new("regression", level, &page.file, format!(
+                    "Page size grew {:.0}% from {:.2} KB to {:.2} KB",
+                    percent, before.page_size as f64 / 1024.0, page.page_size as f64 / 1024.0
+                )));
+            }
+            if page.blocking_scripts > before.blocking_scripts + self.max_blocking_scripts_added {
+                findings.push(Finding::new("regression", level, &page.file, format!(
+                    "Blocking scripts went from {} to {}", before.blocking_scripts, page.blocking_scripts
+                )));
+            }
+        }
+        findings
+    }
+}
+
+impl AnalysisConfig {
+    /// Findings that should fail the build: any rule listed in `fail_on`, plus
+    /// every budget violation and failing regression.
+    pub fn failures<'a>(&self, findings: &'a [Finding]) -> Vec<&'a Finding> {
+        for rule in &self.fail_on {
+            if !RULES.iter().any(|(id, _)| id == rule) {
+                warn!("Unknown rule in analysis.fail_on: {}", rule);
+            }
+        }
+        findings.iter()
+            .filter(|f| f.rule == "budget"
+                || (f.rule == "regression" && f.level == Level::Error)
+                || self.fail_on.iter().any(|rule| rule == f.rule))
+            .collect()
+    }
+}
+
+/// Write `findings` to `dir` as `report.json` or `report.sarif`; text reports
+/// are written per page while building.
+pub fn write_report(findings: &[Finding], format: ReportFormat, dir: &Path) -> Result<()> {
+    let (file, content) = match format {
+        ReportFormat::Text => return Ok(()),
+        ReportFormat::Json => ("report.json", to_json(findings)?),
+        ReportFormat::Sarif => ("report.sarif", to_sarif(findings)?),
+    };
+    fs::create_dir_all(dir)?;
+    let path = dir.join(file);
+    fs::write(&path, content)?;
+    info!("Analysis report with {} findings written to {}", findings.len(), path.display());
+    Ok(())
+}
+
+pub fn to_json(findings: &[Finding]) -> Result<String> {
+    let count = |level| findings.iter().filter(|f| f.level == level).count();
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "summary": {
+            "errors": count(Level::Error),
+            "warnings": count(Level::Warning),
+            "notes": count(Level::Note),
+        },
+        "findings": findings,
+    }))?)
+}
+
+/// SARIF 2.1.0 log with one run for this build.
+pub fn to_sarif(findings: &[Finding]) -> Result<String> {
+    let rules: Vec<_> = RULES.iter()
+        .map(|(id, description)| serde_json::json!({
+            "id": id,
+            "shortDescription": { "text": description },
+        }))
+        .collect();
+
+    let results: Vec<_> = findings.iter()
+        .map(|finding| serde_json::json!({
+            "ruleId": finding.rule,
+            "ruleIndex": RULES.iter().position(|(id, _)| *id == finding.rule),
+            "level": finding.level,
+            "message": { "text": finding.message },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": {
+                        "uri": finding.file.to_string_lossy().replace('\\', "/"),
+                    },
+                },
+            }],
+        }))
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    }))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(file: &str, page_size: usize, blocking_scripts: usize, score: u32) -> PageMetrics {
+        PageMetrics {
+            file: PathBuf::from(file),
+            page_size,
+            blocking_scripts,
+            score: Some(score),
+            errors: 0,
+            warnings: 0,
+            notes: 0,
+        }
+    }
+
+    #[test]
+    fn test_regressions_beyond_limits() {
+        let regressions: Regressions = toml::from_str("").unwrap();
+        let baseline = Manifest {
+            generated: String::new(),
+            pages: vec![metrics("a.md", 20_000, 0, 90), metrics("b.md", 4_000, 1, 80)],
+        };
+        let current = [
+            metrics("a.md", 30_000, 1, 84),
+            metrics("b.md", 4_800, 1, 76),
+            metrics("new.md", 900_000, 5, 10),
+        ];
+        let findings = regressions.check(&current, &baseline);
+        let messages: Vec<_> = findings.iter().map(|f| (f.file.to_str().unwrap(), f.level)).collect();
+        assert_eq!(messages, vec![("a.md", Level::Error); 3]);
+
+        let temp = tempfile::tempdir().unwrap();
+        assert!(regressions.check_and_update(&current, temp.path()).unwrap().is_empty());
+        let failing = [metrics("a.md", 20_000, 3, 90)];
+        assert_eq!(regressions.check_and_update(&failing, temp.path()).unwrap().len(), 1);
+        // A failing build doesn't lower the bar for the next one
+        assert_eq!(regressions.check_and_update(&failing, temp.path()).unwrap().len(), 1);
+    }
+}
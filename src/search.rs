@@ -0,0 +1,449 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{Context, Result, anyhow};
+use log::{info, warn};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use url::Url;
+use crate::history::{self, Snapshot};
+use crate::remote_cache::RemoteCache;
+
+/// Characters of body text kept per page; hosted indexes cap record sizes.
+const MAX_CONTENT_CHARS: usize = 5000;
+/// Documents sent per request.
+const BATCH_SIZE: usize = 500;
+
+/// `[search]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchConfig {
+    /// Write every page to `search-index.json` in the output directory
+    #[serde(default = "crate::config::default_true")]
+    pub index_file: bool,
+    /// Hosted indexes kept in sync after each build, as `[[search.push]]`
+    #[serde(default)]
+    pub push: Vec<PushTarget>,
+    /// Push to the hosted indexes at the end of each build; turn off to push
+    /// only with `deploy-index`
+    #[serde(default = "crate::config::default_true")]
+    pub push_on_build: bool,
+    /// Run Pagefind over the output after each build, as `[search.pagefind]`
+    pub pagefind: Option<PagefindConfig>,
+}
+
+/// The Pagefind CLI, run with `--site <output dir>` once the site is built.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PagefindConfig {
+    /// Program and leading arguments, e.g. `npx -y pagefind`
+    #[serde(default = "default_pagefind_command")]
+    pub command: String,
+    /// More arguments, e.g. `["--exclude-selectors", "nav"]`
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+fn default_pagefind_command() -> String {
+    "pagefind".to_string()
+}
+
+/// How `push_changes` treats each target.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PushOptions {
+    /// Upsert every page, not just those changed since the last push
+    pub all: bool,
+    /// Fail when a target's API key is not set instead of skipping it
+    pub require_key: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchProvider {
+    Algolia,
+    Meilisearch,
+    Typesense,
+}
+
+/// One hosted index to upsert changed pages into.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PushTarget {
+    pub provider: SearchProvider,
+    /// Index name, or collection name for Typesense
+    pub index: String,
+    /// Server URL for Meilisearch and Typesense
+    pub host: Option<String>,
+    /// Application ID for Algolia
+    pub app_id: Option<String>,
+    /// Environment variable holding the admin API key
+    pub api_key_env: Option<String>,
+}
+
+impl PushTarget {
+    fn api_key_env(&self) -> &str {
+        self.api_key_env.as_deref().unwrap_or(match self.provider {
+            SearchProvider::Algolia => "ALGOLIA_API_KEY",
+            SearchProvider::Meilisearch => "MEILISEARCH_API_KEY",
+            SearchProvider::Typesense => "TYPESENSE_API_KEY",
+        })
+    }
+
+    fn host(&self) -> Result<&str> {
+        self.host.as_deref()
+            .map(|host| host.trim_end_matches('/'))
+            .ok_or_else(|| anyhow!("[[search.push]] for {:?} index '{}' needs a host", self.provider, self.index))
+    }
+
+    /// `base` with `path` appended, where `{index}` stands for the index
+    /// name, percent-encoded so any name stays one path segment.
+    fn endpoint(&self, base: &str, path: &[&str]) -> Result<String> {
+        let mut url = Url::parse(base).with_context(|| format!("Invalid search host '{}'", base))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid search host '{}'", base))?
+            .pop_if_empty()
+            .extend(path.iter().map(|segment| if *segment == "{index}" { self.index.as_str() } else { segment }));
+        Ok(url.into())
+    }
+
+    /// Cache file remembering what was last pushed to this index, with the
+    /// name percent-encoded so it can't leave the cache directory.
+    fn state_file(&self, cache_dir: &Path) -> PathBuf {
+        let provider = format!("{:?}", self.provider).to_lowercase();
+        let index: String = url::form_urlencoded::byte_serialize(self.index.as_bytes()).collect();
+        cache_dir.join(format!("search-{}-{}.json", provider, index))
+    }
+}
+
+/// A page as stored in the search index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchDocument {
+    /// Stable ID derived from the URL, safe for every provider
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub content: String,
+}
+
+impl SearchDocument {
+    /// Index entry for a built page, or `None` for pages marked `noindex`.
+    pub fn from_html(url: &str, html: &str) -> Option<Self> {
+        let document = Html::parse_document(html);
+        let select = |selector: &str| Selector::parse(selector).ok()
+            .and_then(|selector| document.select(&selector).next());
+
+        let noindex = select("meta[name='robots']")
+            .and_then(|el| el.value().attr("content"))
+            .is_some_and(|content| content.to_lowercase().contains("noindex"));
+        if noindex {
+            return None;
+        }
+
+        let title = select("title")
+            .map(|el| el.text().collect::<String>().trim().to_string())
+            .unwrap_or_default();
+        let description = select("meta[name='description']")
+            .and_then(|el| el.value().attr("content"))
+            .map(|content| content.trim().to_string())
+            .filter(|content| !content.is_empty());
+        let content = select("main").or_else(|| select("body"))
+            .map(|el| el.descendants()
+                .filter_map(|node| node.value().as_text().map(|text| (node, text)))
+                .filter(|(node, _)| !node.ancestors().any(|ancestor| ancestor.value().as_element()
+                    .is_some_and(|el| matches!(el.name(), "script" | "noscript" | "style" | "template"))))
+                .flat_map(|(_, text)| text.split_whitespace())
+                .collect::<Vec<_>>()
+                .join(" "))
+            .unwrap_or_default();
+
+        Some(Self {
+            id: history::hash(url.as_bytes()),
+            url: url.to_string(),
+            title,
+            description,
+            content: content.chars().take(MAX_CONTENT_CHARS).collect(),
+        })
+    }
+}
+
+/// The pages of the last build that had push targets, which `deploy-index`
+/// sends without building again.
+pub fn records_file(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("search-records.json")
+}
+
+pub fn write_records(documents: &[SearchDocument], cache_dir: &Path) -> Result<()> {
+    fs::create_dir_all(cache_dir)?;
+    fs::write(records_file(cache_dir), serde_json::to_string(documents)?)?;
+    Ok(())
+}
+
+pub fn read_records(cache_dir: &Path) -> Result<Vec<SearchDocument>> {
+    let path = records_file(cache_dir);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}; build the site with [[search.push]] configured first", path.display()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Run Pagefind over `output_dir`, which writes its index and UI to `pagefind/`.
+pub fn run_pagefind(config: &PagefindConfig, output_dir: &Path) -> Result<()> {
+    let mut words = config.command.split_whitespace();
+    let program = words.next().ok_or_else(|| anyhow!("[search.pagefind] command is empty"))?;
+    let output = Command::new(program)
+        .args(words)
+        .arg("--site")
+        .arg(output_dir)
+        .args(&config.args)
+        .output()
+        .map_err(|e| anyhow!("Failed to run {}: {}; install Pagefind or set `command` in [search.pagefind]", program, e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Pagefind failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    info!("Pagefind index written to {}", output_dir.join("pagefind").display());
+    Ok(())
+}
+
+/// Write every document to `search-index.json` for client-side search.
+pub fn write_index_file(documents: &[SearchDocument], output_dir: &Path) -> Result<()> {
+    let path = output_dir.join("search-index.json");
+    fs::write(&path, serde_json::to_string(documents)?)?;
+    info!("Search index with {} pages written to {}", documents.len(), path.display());
+    Ok(())
+}
+
+/// Upsert documents that changed since the last successful push to each
+/// target and delete pages that no longer exist. Targets whose API key is
+/// not set are skipped and catch up on the next build that has one, unless
+/// `options` requires keys. With a remote cache, a fresh checkout picks up
+/// where the last push left off.
+pub fn push_changes(
+    targets: &[PushTarget],
+    documents: &[SearchDocument],
+    cache_dir: &Path,
+    remote_cache: Option<&RemoteCache>,
+    options: PushOptions,
+) -> Result<()> {
+    let mut current = Snapshot::new();
+    for doc in documents {
+        current.insert(doc.id.clone(), history::hash(serde_json::to_string(doc)?.as_bytes()));
+    }
+
+    for target in targets {
+        let state_file = target.state_file(cache_dir);
+        let remote_key = format!("search/{}", state_file.file_name().unwrap_or_default().to_string_lossy());
+        if let Some(remote) = remote_cache {
+            remote.fetch(&remote_key, &state_file);
+        }
+        let previous: Snapshot = fs::read_to_string(&state_file).ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        let changed: Vec<&SearchDocument> = documents.iter()
+            .filter(|doc| options.all || previous.get(&doc.id) != current.get(&doc.id))
+            .collect();
+        let removed: Vec<&str> = previous.keys()
+            .filter(|id| !current.contains_key(*id))
+            .map(String::as_str)
+            .collect();
+        if changed.is_empty() && removed.is_empty() {
+            info!("Search index '{}' is up to date", target.index);
+            continue;
+        }
+
+        let Ok(api_key) = env::var(target.api_key_env()) else {
+            if options.require_key {
+                return Err(anyhow!("{} is not set; it holds the API key for search index '{}'", target.api_key_env(), target.index));
+            }
+            warn!(
+                "{} is not set; not pushing {} changed pages to search index '{}'",
+                target.api_key_env(), changed.len() + removed.len(), target.index
+            );
+            continue;
+        };
+
+        for batch in changed.chunks(BATCH_SIZE) {
+            upsert(target, &api_key, batch)?;
+        }
+        for batch in removed.chunks(BATCH_SIZE) {
+            delete(target, &api_key, batch)?;
+        }
+        info!(
+            "Search index '{}': {} pages updated, {} removed",
+            target.index, changed.len(), removed.len()
+        );
+
+        fs::create_dir_all(cache_dir)?;
+        fs::write(&state_file, serde_json::to_string(&current)?)?;
+        if let Some(remote) = remote_cache {
+            remote.store(&remote_key, &state_file);
+        }
+    }
+    Ok(())
+}
+
+fn upsert(target: &PushTarget, api_key: &str, documents: &[&SearchDocument]) -> Result<()> {
+    match target.provider {
+        SearchProvider::Algolia => {
+            let requests: Vec<_> = documents.iter()
+                .map(|doc| {
+                    let mut body = serde_json::to_value(doc)?;
+                    body["objectID"] = json!(doc.id);
+                    Ok(json!({ "action": "updateObject", "body": body }))
+                })
+                .collect::<Result<_>>()?;
+            algolia_batch(target, api_key, requests)
+        },
+        SearchProvider::Meilisearch => {
+            let url = format!("{}?primaryKey=id", target.endpoint(target.host()?, &["indexes", "{index}", "documents"])?);
+            ureq::post(&url)
+                .set("Authorization", &format!("Bearer {}", api_key))
+                .send_json(documents)
+                .map_err(|e| anyhow!("Meilisearch upsert to '{}' failed: {}", target.index, e))?;
+            Ok(())
+        },
+        SearchProvider::Typesense => {
+            let url = format!("{}?action=upsert", target.endpoint(target.host()?, &["collections", "{index}", "documents", "import"])?);
+            let lines = documents.iter()
+                .map(serde_json::to_string)
+                .collect::<Result<Vec<_>, _>>()?
+                .join("\n");
+            let response = ureq::post(&url)
+                .set("X-TYPESENSE-API-KEY", api_key)
+                .set("Content-Type", "text/plain")
+                .send_string(&lines)
+                .map_err(|e| anyhow!("Typesense import to '{}' failed: {}", target.index, e))?
+                .into_string()?;
+            // The import reports each document's result on its own line
+            let failed = response.lines().filter(|line| line.contains("\"success\":false")).count();
+            if failed > 0 {
+                return Err(anyhow!("Typesense rejected {} documents for '{}': {}", failed, target.index, response));
+            }
+            Ok(())
+        },
+    }
+}
+
+fn delete(target: &PushTarget, api_key: &str, ids: &[&str]) -> Result<()> {
+    match target.provider {
+        SearchProvider::Algolia => {
+            let requests = ids.iter()
+                .map(|id| json!({ "action": "deleteObject", "body": { "objectID": id } }))
+                .collect();
+            algolia_batch(target, api_key, requests)
+        },
+        SearchProvider::Meilisearch => {
+            let url = target.endpoint(target.host()?, &["indexes", "{index}", "documents", "delete-batch"])?;
+            ureq::post(&url)
+                .set("Authorization", &format!("Bearer {}", api_key))
+                .send_json(ids)
+                .map_err(|e| anyhow!("Meilisearch delete from '{}' failed: {}", target.index, e))?;
+            Ok(())
+        },
+        SearchProvider::Typesense => {
+            let url = target.endpoint(target.host()?, &["collections", "{index}", "documents"])?;
+            ureq::delete(&url)
+                .set("X-TYPESENSE-API-KEY", api_key)
+                .query("filter_by", &format!("id:[{}]", ids.join(",")))
+                .call()
+                .map_err(|e| anyhow!("Typesense delete from '{}' failed: {}", target.index, e))?;
+            Ok(())
+        },
+    }
+}
+
+fn algolia_batch(target: &PushTarget, api_key: &str, requests: Vec<serde_json::Value>) -> Result<()> {
+    let app_id = target.app_id.as_deref()
+        .ok_or_else(|| anyhow!("[[search.push]] for Algolia index '{}' needs an app_id", target.index))?;
+    let url = target.endpoint(&format!("https://{}.algolia.net", app_id), &["1", "indexes", "{index}", "batch"])?;
+    ureq::post(&url)
+        .set("X-Algolia-Application-Id", app_id)
+        .set("X-Algolia-API-Key", api_key)
+        .send_json(json!({ "requests": requests }))
+        .map_err(|e| anyhow!("Algolia batch for '{}' failed: {}", target.index, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::thread::JoinHandle;
+
+    /// A server answering `requests` requests with `{}`, which returns the
+    /// line and body of each.
+    fn serve(requests: usize) -> (String, JoinHandle<Vec<(String, String)>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                received.push((request_line.trim().to_string(), String::from_utf8(body).unwrap()));
+                stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}").unwrap();
+            }
+            received
+        });
+        (host, server)
+    }
+
+    fn document(id: &str, content: &str) -> SearchDocument {
+        SearchDocument {
+            id: id.to_string(),
+            url: format!("/{}", id),
+            title: id.to_string(),
+            description: None,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_pushes_only_changes_to_an_encoded_index_path() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let (host, server) = serve(3);
+        std::env::set_var("ELDROID_TEST_MEILISEARCH_KEY", "secret");
+        let target: PushTarget = toml::from_str(&format!(
+            "provider = \"meilisearch\"\nindex = \"blog posts\"\nhost = \"{}/\"\napi_key_env = \"ELDROID_TEST_MEILISEARCH_KEY\"", host,
+        )).unwrap();
+        let push = |documents: &[SearchDocument]| push_changes(std::slice::from_ref(&target), documents, cache_dir.path(), None, PushOptions::default());
+
+        push(&[document("a", "one"), document("b", "two")]).unwrap();
+        push(&[document("a", "one"), document("b", "two")]).unwrap();
+        push(&[document("a", "changed")]).unwrap();
+        let received = server.join().unwrap();
+        let lines: Vec<&str> = received.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(lines, [
+            "POST /indexes/blog%20posts/documents?primaryKey=id HTTP/1.1",
+            "POST /indexes/blog%20posts/documents?primaryKey=id HTTP/1.1",
+            "POST /indexes/blog%20posts/documents/delete-batch HTTP/1.1",
+        ]);
+        let upserted: Vec<serde_json::Value> = serde_json::from_str(&received[1].1).unwrap();
+        assert_eq!(upserted.iter().map(|doc| doc["id"].as_str().unwrap()).collect::<Vec<_>>(), ["a"]);
+        assert_eq!(received[2].1, "[\"b\"]");
+    }
+
+    #[test]
+    fn test_state_files_stay_in_the_cache_dir() {
+        let cache_dir = Path::new("output/cache");
+        let target: PushTarget = toml::from_str("provider = \"algolia\"\nindex = \"../drafts/v1\"").unwrap();
+        let state_file = target.state_file(cache_dir);
+        assert_eq!(state_file.parent(), Some(cache_dir));
+        assert_eq!(state_file.file_name().unwrap(), "search-algolia-..%2Fdrafts%2Fv1.json");
+    }
+}
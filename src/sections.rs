@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, NaiveDate};
+use log::info;
+use serde::Deserialize;
+use walkdir::WalkDir;
+use yaml_front_matter::YamlFrontMatter;
+use crate::i18n::LocalizedPath;
+use crate::layout::{load_layout, render_layout};
+use crate::markdown::{markdown_to_html, BlogPost, BlogProcessor};
+use crate::seo::PageSEO;
+use crate::site::PageContext;
+use crate::taxonomy::{atom_feed, post_item_html};
+use crate::slug::Slugs;
+use crate::variables::DirectoryVariables;
+
+/// File in a content directory that makes it a section with a landing page.
+pub const SECTION_INDEX_FILE: &str = "_index.md";
+
+/// A `[[sections]]` entry in `eldroid.toml`: a content directory whose
+/// Markdown files, in subdirectories too, are posts with their own layout,
+/// URLs, feed, and listing page.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SectionConfig {
+    /// Directory under the content directory, like `notes`
+    pub path: PathBuf,
+    /// Heading of the listing page, by default the directory name capitalized
+    pub title: Option<String>,
+    /// Layout for the section's posts, instead of `blog_layout.html`
+    pub layout: Option<PathBuf>,
+    /// URL of each post, from `:section`, `:path` (the file's path in the
+    /// section), `:slug`, `:year`, `:month`, and `:day`; by default the
+    /// file's path in the content directory
+    pub permalink: Option<String>,
+    /// Write an Atom feed of the section's posts
+    #[serde(default = "crate::config::default_true")]
+    pub feed: bool,
+    /// Write a page listing the section's posts
+    #[serde(default = "crate::config::default_true")]
+    pub listing: bool,
+    /// Layout of the listing page with `@{title}` and `@{yield}` placeholders
+    pub listing_layout: Option<PathBuf>,
+    /// Posts the section's prev/next links step through, instead of the
+    /// site's `prev_next`
+    pub prev_next: Option<PrevNext>,
+}
+
+/// Posts that prev/next links step through, newest to oldest: `"all"`,
+/// `"section"`, or the name of a front matter field such as `"tags"` or
+/// `"series"`, whose first value posts must share.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum PrevNext {
+    /// Every post in the same language
+    #[default]
+    All,
+    /// Posts in the same section
+    Section,
+    /// Posts with the same first value of a front matter field
+    Field(String),
+}
+
+impl From<String> for PrevNext {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "all" => Self::All,
+            "section" => Self::Section,
+            "tag" => Self::Field("tags".to_string()),
+            _ => Self::Field(value),
+        }
+    }
+}
+
+/// Posts are read from `blog` unless a section of that path is configured.
+const BLOG_SECTION: &str = "blog";
+
+impl SectionConfig {
+    /// The `blog` directory as it is without configuration: the blog layout,
+    /// URLs from file paths, and no feed or listing besides `rss.xml`.
+    pub fn blog() -> Self {
+        Self {
+            path: PathBuf::from(BLOG_SECTION),
+            title: None,
+            layout: None,
+            permalink: None,
+            feed: false,
+            listing: false,
+            listing_layout: None,
+            prev_next: None,
+        }
+    }
+
+    /// The configured sections, and `blog` when none of them is it.
+    pub fn with_blog(sections: &[SectionConfig]) -> Vec<SectionConfig> {
+        let mut all = sections.to_vec();
+        if !all.iter().any(|section| section.path == Path::new(BLOG_SECTION)) {
+            all.push(Self::blog());
+        }
+        all
+    }
+
+    /// The section's path, with forward slashes and no surrounding ones.
+    pub fn name(&self) -> String {
+        self.path.to_string_lossy().replace('\\', "/").trim_matches('/').to_string()
+    }
+
+    pub fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| capitalized_name(&self.path))
+    }
+
+    /// URL path of the listing page.
+    pub fn url(&self) -> String {
+        format!("/{}/", self.name())
+    }
+
+    /// Whether `path`, relative to the content directory and without a
+    /// language directory, is in this section.
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.path) && path != self.path
+    }
+
+    /// URL of the post at `path`, relative to the content directory and
+    /// without a language directory, from the `permalink` pattern. `None`
+    /// when the section has no pattern.
+    pub fn permalink(&self, path: &Path, date: &str, slugs: Slugs) -> Result<Option<String>> {
+        let Some(pattern) = &self.permalink else { return Ok(None) };
+        let within = slugs.page_path(&path.strip_prefix(&self.path).unwrap_or(path).with_extension(""));
+        let slug = within.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let mut url = pattern
+            .replace(":section", &self.name())
+            .replace(":path", &within.to_string_lossy().replace('\\', "/"))
+            .replace(":slug", &slug);
+        if url.contains(":year") || url.contains(":month") || url.contains(":day") {
+            let date = DateTime::parse_from_rfc3339(date).map(|date| date.date_naive())
+                .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+                .map_err(|_| anyhow!("Date '{}' is needed for the permalink of {}", date, path.display()))?;
+            url = url
+                .replace(":year", &date.year().to_string())
+                .replace(":month", &format!("{:02}", date.month()))
+                .replace(":day", &format!("{:02}", date.day()));
+        }
+        Ok(Some(format!("/{}", url.trim_start_matches('/'))))
+    }
+}
+
+/// The last component of `dir` with its first letter capitalized.
+fn capitalized_name(dir: &Path) -> String {
+    let name = dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let mut chars = name.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Front matter of an `_index.md` file.
+#[derive(Debug, Default, Deserialize)]
+struct IndexFrontMatter {
+    title: Option<String>,
+    description: Option<String>,
+    layout: Option<PathBuf>,
+}
+
+/// A directory's `_index.md`: the title, description, and intro of its
+/// landing page, which pages beneath it also see.
+#[derive(Debug, Clone)]
+pub struct SectionIndex {
+    /// Directory relative to the content directory
+    pub dir: PathBuf,
+    /// URL path of the landing page
+    pub url: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// Layout of the landing page, relative to the components directory
+    pub layout: Option<PathBuf>,
+    /// HTML of the Markdown after the front matter
+    pub intro: String,
+}
+
+impl SectionIndex {
+    /// The `section` variable of pages beneath the directory.
+    fn variable(&self) -> toml::Value {
+        toml::Value::Table(toml::Table::from_iter([
+            ("title".to_string(), toml::Value::String(self.title.clone())),
+            ("description".to_string(), toml::Value::String(self.description.clone().unwrap_or_default())),
+            ("url".to_string(), toml::Value::String(self.url.clone())),
+        ]))
+    }
+}
+
+/// Every `_index.md` under the content directory.
+#[derive(Debug, Clone, Default)]
+pub struct SectionIndexes {
+    indexes: Vec<SectionIndex>,
+}
+
+impl SectionIndexes {
+    /// Read the `_index.md` files under `content_dir`. A section without a
+    /// title takes the one it is configured with, or its directory's name.
+    /// A file without front matter is all intro.
+    pub fn load(content_dir: &Path, sections: &[SectionConfig], slugs: Slugs) -> Result<Self> {
+        let mut indexes = Vec::new();
+        for entry in WalkDir::new(content_dir).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+            if entry.file_name() != SECTION_INDEX_FILE || !entry.file_type().is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(entry.path())?;
+            let (front_matter, intro) = if content.lines().next().map(str::trim_end) == Some("---") {
+                let document = YamlFrontMatter::parse::<IndexFrontMatter>(&content)
+                    .map_err(|e| anyhow!("Failed to parse front matter of {}: {}", entry.path().display(), e))?;
+                (document.metadata, document.content)
+            } else {
+                (IndexFrontMatter::default(), content)
+            };
+            let dir = entry.path().parent().and_then(|dir| dir.strip_prefix(content_dir).ok()).unwrap_or(Path::new("")).to_path_buf();
+            let configured = sections.iter().find(|section| section.path == dir).and_then(|section| section.title.clone());
+            indexes.push(SectionIndex {
+                url: dir_url(&slugs.page_path(&dir)),
+                title: front_matter.title.or(configured).unwrap_or_else(|| capitalized_name(&dir)),
+                description: front_matter.description,
+                layout: front_matter.layout,
+                intro: markdown_to_html(&intro),
+                dir,
+            });
+        }
+        Ok(Self { indexes })
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<&SectionIndex> {
+        self.indexes.iter().find(|index| index.dir == dir)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SectionIndex> {
+        self.indexes.iter()
+    }
+
+    /// Titles of the landing pages by URL path, for breadcrumbs.
+    pub fn titles(&self) -> BTreeMap<String, String> {
+        self.indexes.iter().map(|index| (index.url.clone(), index.title.clone())).collect()
+    }
+
+    /// Give pages beneath each section a `section` variable with its
+    /// `title`, `description`, and `url`. A `_vars.toml` in the same
+    /// directory can still set its own.
+    pub fn add_variables(&self, dir_vars: &mut DirectoryVariables) {
+        for index in &self.indexes {
+            dir_vars.set_default(&index.dir, "section", index.variable());
+        }
+    }
+}
+
+/// URL path of the landing page written to `dir` in the output directory.
+fn dir_url(dir: &Path) -> String {
+    format!("/{}/", dir.to_string_lossy().replace('\\', "/").trim_matches('/'))
+}
+
+/// Output file of a page at URL path `url`: `index.html` in a directory
+/// for URLs ending in `/`, and an `.html` file otherwise.
+pub fn output_path(url: &str) -> PathBuf {
+    let url = url.trim_start_matches('/');
+    if url.is_empty() || url.ends_with('/') {
+        Path::new(url).join("index.html")
+    } else {
+        PathBuf::from(format!("{}.html", url))
+    }
+}
+
+/// A section's posts in one language, newest first.
+pub struct SectionPosts<'a> {
+    pub section: &'a SectionConfig,
+    pub language: Option<String>,
+    pub posts: Vec<&'a BlogPost>,
+}
+
+/// Posts of each section with a listing or feed, by language. A post is
+/// listed in every section it is in, so nested sections' posts are also
+/// their parents'.
+pub fn group_posts(blog_processor: &BlogProcessor) -> Vec<SectionPosts<'_>> {
+    let paths: Vec<PathBuf> = blog_processor.posts().iter()
+        .map(|post| blog_processor.neutral_path(&post.file_path))
+        .collect();
+    let languages: BTreeSet<Option<String>> = blog_processor.posts().iter()
+        .map(|post| post.language.clone())
+        .collect();
+
+    let mut groups = Vec::new();
+    for section in blog_processor.sections().iter().filter(|section| section.listing || section.feed) {
+        for language in &languages {
+            let posts: Vec<&BlogPost> = blog_processor.posts().iter().zip(&paths)
+                .filter(|(post, path)| post.language == *language && section.contains(path))
+                .map(|(post, _)| post)
+                .collect();
+            if !posts.is_empty() {
+                groups.push(SectionPosts { section, language: language.clone(), posts });
+            }
+        }
+    }
+    groups
+}
+
+/// Write the listing page and Atom feed of each section that has them, and
+/// a landing page for each `_index.md`. An `_index.md` gives its section's
+/// listing a title, intro, and layout. A directory with its own `index.md`
+/// or `index.html` keeps it instead. The pages get SEO tags, asset names,
+/// and minification like content pages.
+pub(crate) fn generate_sections(ctx: &PageContext) -> Result<Vec<PathBuf>> {
+    let groups = group_posts(&ctx.blog_processor);
+    let indexes = &ctx.section_indexes;
+    let i18n = ctx.site_config.i18n.as_ref();
+    let content_dir = Path::new(&ctx.args.input_dir);
+    let output_dir = Path::new(&ctx.args.output_dir);
+    let slugs = ctx.site_config.slugs();
+    let base_url = ctx.seo_config.as_ref().and_then(|seo| seo.base_url.as_deref()).unwrap_or("");
+    let has_own_index = |dir: &Path| ["index.md", "index.html"].iter().any(|index| content_dir.join(dir).join(index).exists());
+    let mut written = Vec::new();
+    let mut listed = HashSet::new();
+    for group in &groups {
+        let section = group.section;
+        let dir = match (i18n, &group.language) {
+            (Some(i18n), Some(language)) => i18n.output_path(&LocalizedPath { language: language.clone(), path: section.path.clone() }),
+            _ => section.path.clone(),
+        };
+        let out_dir = slugs.page_path(&dir);
+        let url = dir_url(&out_dir);
+        let index = indexes.get(&dir);
+        let title = index.map_or_else(|| section.title(), |index| index.title.clone());
+        fs::create_dir_all(output_dir.join(&out_dir))?;
+
+        if section.listing && !has_own_index(&dir) {
+            let layout = index.and_then(|index| index.layout.as_deref()).or(section.listing_layout.as_deref());
+            written.push(write_listing(ctx, &dir, layout, &title, index, &group.posts)?);
+            listed.insert(dir.clone());
+        }
+        if section.feed {
+            fs::write(output_dir.join(&out_dir).join("atom.xml"), atom_feed(&title, &url, &group.posts, base_url))?;
+        }
+        info!("Generated section {} with {} posts", url, group.posts.len());
+    }
+
+    // Landing pages of directories that aren't listed as a section
+    for index in indexes.iter().filter(|index| !listed.contains(&index.dir) && !has_own_index(&index.dir)) {
+        let posts: Vec<&BlogPost> = ctx.blog_processor.posts().iter()
+            .filter(|post| post.file_path.strip_prefix(content_dir).is_ok_and(|path| path.starts_with(&index.dir)))
+            .collect();
+        fs::create_dir_all(output_dir.join(slugs.page_path(&index.dir)))?;
+        written.push(write_listing(ctx, &index.dir, index.layout.as_deref(), &index.title, Some(index), &posts)?);
+    }
+    Ok(written)
+}
+
+/// Write `<dir>/index.html` with a heading, the intro of the section's
+/// `_index.md`, and a list of its posts, if it has any.
+fn write_listing(
+    ctx: &PageContext,
+    dir: &Path,
+    layout: Option<&Path>,
+    title: &str,
+    index: Option<&SectionIndex>,
+    posts: &[&BlogPost],
+) -> Result<PathBuf> {
+    let mut body = format!("<h1>{}</h1>\n", html_escape::encode_text(title));
+    if let Some(index) = index {
+        body.push_str(&index.intro);
+    }
+    if !posts.is_empty() {
+        body.push_str("<ul class=\"posts\">\n");
+        for post in posts {
+            body.push_str(&post_item_html(post));
+        }
+        body.push_str("</ul>");
+    }
+    let description = index.and_then(|index| index.description.clone());
+    let html = render_layout(&load_layout(&ctx.components, layout), title, &body)
+        .replace("@{description}", &html_escape::encode_text(description.as_deref().unwrap_or_default()));
+    let out_dir = ctx.site_config.slugs().page_path(dir);
+    let seo = PageSEO {
+        title: title.to_string(),
+        description,
+        path: dir_url(&out_dir),
+        schema_type: Some("CollectionPage".to_string()),
+        section_titles: ctx.section_titles.clone(),
+        ..PageSEO::default()
+    };
+    let source = Path::new(&ctx.args.input_dir).join(dir).join(SECTION_INDEX_FILE);
+    let path = Path::new(&ctx.args.output_dir).join(out_dir).join("index.html");
+    let html = ctx.render_generated(&source, &path, html, seo)?;
+    fs::write(&path, html)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permalinks_and_output_paths() {
+        let notes: SectionConfig = toml::from_str("path = \"notes\"\npermalink = \"/:section/:year/:month/:slug/\"").unwrap();
+        assert_eq!(notes.title(), "Notes");
+        assert!(notes.contains(Path::new("notes/rust/borrowing.md")));
+        assert!(!notes.contains(Path::new("notesy/a.md")));
+        assert_eq!(
+            notes.permalink(Path::new("notes/rust/borrowing.md"), "2026-03-01T09:30:00Z", Slugs::default()).unwrap().as_deref(),
+            Some("/notes/2026/03/borrowing/"),
+        );
+        assert!(notes.permalink(Path::new("notes/a.md"), "someday", Slugs::default()).is_err());
+
+        let docs: SectionConfig = toml::from_str("path = \"docs\"\npermalink = \"guide/:path\"").unwrap();
+        assert_eq!(docs.permalink(Path::new("docs/cli/build.md"), "", Slugs::default()).unwrap().as_deref(), Some("/guide/cli/build"));
+        assert_eq!(SectionConfig::blog().permalink(Path::new("blog/a.md"), "", Slugs::default()).unwrap(), None);
+
+        assert_eq!(output_path("/notes/2026/03/borrowing/"), Path::new("notes/2026/03/borrowing/index.html"));
+        assert_eq!(output_path("/guide/cli/build"), Path::new("guide/cli/build.html"));
+    }
+}
@@ -17,6 +17,11 @@ pub struct SEOConfig {
     pub default_language: Option<String>,
     pub social_media: Option<SocialMedia>,
     pub structured_data: Option<StructuredData>,
+    /// Sitemap `<changefreq>` for pages that don't set their own
+    pub default_change_frequency: Option<String>,
+    /// Sitemap `<priority>` for pages that don't set their own
+    pub default_priority: Option<f32>,
+    pub robots: Option<RobotsConfig>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,6 +47,29 @@ pub struct StructuredData {
     pub same_as: Option<Vec<String>>,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct RobotsConfig {
+    /// Rule groups, one per user agent. Defaults to allowing everything.
+    #[serde(default)]
+    pub rules: Vec<RobotsRule>,
+    /// Emit a block disallowing known AI training crawlers
+    #[serde(default)]
+    pub block_ai_crawlers: bool,
+    /// Raw lines appended before the sitemap reference
+    #[serde(default)]
+    pub extra: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RobotsRule {
+    pub user_agent: String,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub disallow: Vec<String>,
+    pub crawl_delay: Option<u32>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ContactPoint {
     pub telephone: String,
@@ -73,7 +101,7 @@ pub fn load_seo_config(config_path: &Path) -> Option<SEOConfig> {
 pub fn parse_page_seo(content: &str) -> Option<PageSEO> {
     lazy_static! {
         static ref SEO_COMMENT: Regex = Regex::new(
-            r"<!--\s*SEO\s*\{(?P<json>.*?)\}\s*-->"
+            r"(?s)<!--\s*SEO\s*(?P<json>\{.*?\})\s*-->"
         ).unwrap();
     }
 
@@ -1,64 +1,96 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
 use chrono::{DateTime, Utc, FixedOffset};
-use crate::seo::SEOConfig;
-use crate::markdown::BlogFrontMatter;
-use yaml_front_matter::YamlFrontMatter;
+use crate::seo::{SEOConfig, PageSEO, RobotsConfig};
+use crate::markdown::BlogPost;
+use crate::taxonomy::cdata;
+use crate::plugins::{OutputGenerator, SiteInfo};
 
-pub fn generate_sitemap(processed_files: &[PathBuf], config: &SEOConfig, output_dir: &str) -> std::io::Result<()> {
+/// Values accepted by the sitemap protocol for `<changefreq>`.
+const CHANGE_FREQUENCIES: [&str; 7] = ["always", "hourly", "daily", "weekly", "monthly", "yearly", "never"];
+
+pub fn generate_sitemap(
+    processed_files: &[PathBuf],
+    page_seo: &HashMap<PathBuf, PageSEO>,
+    config: &SEOConfig,
+    output_dir: &str,
+) -> std::io::Result<()> {
     let mut sitemap = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
 <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
         xmlns:news="http://www.google.com/schemas/sitemap-news/0.9"
-        xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">"#);
+        xmlns:image="http://www.google.com/schemas/sitemap-image/1.1"
+        xmlns:xhtml="http://www.w3.org/1999/xhtml">"#);
 
     let base_url = config.base_url.as_deref().unwrap_or("");
 
     for file in processed_files {
-        if let Some(relative_path) = file.strip_prefix(output_dir).ok() {
-            if let Ok(content) = fs::read_to_string(file) {
-                // Try to parse frontmatter for markdown files
-                let front_matter = if file.extension().map_or(false, |ext| ext == "md") {
-                    YamlFrontMatter::parse::<BlogFrontMatter>(&content).ok()
-                } else {
-                    None
-                };
+        if let Ok(relative_path) = file.strip_prefix(output_dir) {
+            let seo = page_seo.get(file);
+            if seo.is_some_and(PageSEO::is_noindex) {
+                continue;
+            }
 
-                let url_path = relative_path
-                    .to_str()
-                    .unwrap()
-                    .replace("\\", "/")
-                    .trim_start_matches('/')
-                    .to_string();
+            let url_path = relative_path
+                .to_str()
+                .unwrap()
+                .replace("\\", "/")
+                .trim_start_matches('/')
+                .to_string();
 
-                let full_url = format!("{}/{}", base_url.trim_end_matches('/'), url_path);
+            let full_url = format!("{}/{}", base_url.trim_end_matches('/'), url_path);
 
-                sitemap.push_str("\n  <url>");
-                sitemap.push_str(&format!("\n    <loc>{}</loc>", full_url));
+            sitemap.push_str("\n  <url>");
+            sitemap.push_str(&format!("\n    <loc>{}</loc>", full_url));
 
-                // Add image if available in frontmatter
-                if let Some(yaml) = &front_matter {
-                    if let Some(image) = &yaml.metadata.image {
-                        sitemap.push_str(&format!(r#"
+            // Add image and translations if the page declares them
+            if let Some(seo) = seo {
+                for alternate in &seo.alternates {
+                    sitemap.push_str(&format!(
+                        "\n    <xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}\"/>",
+                        alternate.hreflang, alternate.href
+                    ));
+                }
+                if let Some(image) = &seo.image {
+                    sitemap.push_str(&format!(r#"
     <image:image>
       <image:loc>{}/{}</image:loc>
       <image:title>{}</image:title>
-    </image:image>"#, base_url.trim_end_matches('/'), image.trim_start_matches('/'), yaml.metadata.title));
-                    }
+    </image:image>"#, base_url.trim_end_matches('/'), image.trim_start_matches('/'), seo.title));
+                }
+            }
 
-                    sitemap.push_str(&format!("\n    <lastmod>{}</lastmod>", yaml.metadata.date));
+            // Prefer dates from page metadata, fall back to file modification time
+            let page_date = seo.and_then(|s| s.last_modified.or(s.published_date));
+            if let Some(date) = page_date {
+                sitemap.push_str(&format!("\n    <lastmod>{}</lastmod>", date.to_rfc3339()));
+            } else if let Ok(metadata) = fs::metadata(file) {
+                if let Ok(modified) = metadata.modified() {
+                    let datetime: DateTime<Utc> = modified.into();
+                    sitemap.push_str(&format!("\n    <lastmod>{}</lastmod>", 
+                        datetime.format("%Y-%m-%dT%H:%M:%SZ")));
+                }
+            }
+
+            let change_frequency = seo
+                .and_then(|s| s.change_frequency.as_deref())
+                .or(config.default_change_frequency.as_deref());
+            if let Some(freq) = change_frequency {
+                if CHANGE_FREQUENCIES.contains(&freq) {
+                    sitemap.push_str(&format!("\n    <changefreq>{}</changefreq>", freq));
                 } else {
-                    // Use file modification time for non-markdown files
-                    if let Ok(metadata) = fs::metadata(file) {
-                        if let Ok(modified) = metadata.modified() {
-                            let datetime: DateTime<Utc> = modified.into();
-                            sitemap.push_str(&format!("\n    <lastmod>{}</lastmod>", 
-                                datetime.format("%Y-%m-%dT%H:%M:%SZ")));
-                        }
-                    }
+                    log::warn!("Ignoring invalid change frequency '{}' for {}", freq, file.display());
                 }
+            }
 
-                sitemap.push_str("\n  </url>");
+            let priority = seo
+                .and_then(|s| s.priority)
+                .or(config.default_priority);
+            if let Some(priority) = priority {
+                sitemap.push_str(&format!("\n    <priority>{:.1}</priority>", priority.clamp(0.0, 1.0)));
             }
+
+            sitemap.push_str("\n  </url>");
         }
     }
 
@@ -67,7 +99,7 @@ pub fn generate_sitemap(processed_files: &[PathBuf], config: &SEOConfig, output_
     Ok(())
 }
 
-pub fn generate_rss(processed_files: &[PathBuf], config: &SEOConfig, output_dir: &str) -> std::io::Result<()> {
+pub fn generate_rss(posts: &[BlogPost], config: &SEOConfig, output_dir: &str) -> std::io::Result<()> {
     let base_url = config.base_url.as_deref().unwrap_or("");
     let mut rss = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
 <rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/"
@@ -87,54 +119,40 @@ pub fn generate_rss(processed_files: &[PathBuf], config: &SEOConfig, output_dir:
         base_url
     );
 
-    for file in processed_files {
-        if file.extension().map_or(false, |ext| ext == "md") {
-            if let Ok(content) = fs::read_to_string(file) {
-                if let Ok(yaml_content) = YamlFrontMatter::parse::<BlogFrontMatter>(&content) {
-                    let url_path = file
-                        .strip_prefix(output_dir)
-                        .unwrap()
-                        .with_extension("html")
-                        .to_str()
-                        .unwrap()
-                        .replace("\\", "/")
-                        .trim_start_matches('/')
-                        .to_string();
-
-                    rss.push_str(&format!(r#"
+    for post in posts.iter().filter(|post| !post.is_noindex()) {
+        let url_path = format!("{}.html", post.url.trim_start_matches('/'));
+
+        rss.push_str(&format!(r#"
         <item>
             <title>{}</title>
             <link>{}/{}</link>
-            <description><![CDATA[{}]]></description>
+            <description>{}</description>
             <pubDate>{}</pubDate>
             <guid isPermaLink="true">{}/{}</guid>"#,
-                        yaml_content.metadata.title,
-                        base_url.trim_end_matches('/'),
-                        url_path,
-                        yaml_content.metadata.description.unwrap_or_else(|| String::from("No description available")),
-                        DateTime::parse_from_rfc3339(&yaml_content.metadata.date)
-                            .unwrap_or_else(|_| DateTime::from_naive_utc_and_offset(
-                                Utc::now().naive_utc(),
-                                FixedOffset::east_opt(0).unwrap()
-                            ))
-                            .format("%a, %d %b %Y %H:%M:%S GMT"),
-                        base_url.trim_end_matches('/'),
-                        url_path
-                    ));
+            post.front_matter.title,
+            base_url.trim_end_matches('/'),
+            url_path,
+            cdata(post.description().unwrap_or("No description available")),
+            DateTime::parse_from_rfc3339(&post.front_matter.date)
+                .unwrap_or_else(|_| DateTime::from_naive_utc_and_offset(
+                    Utc::now().naive_utc(),
+                    FixedOffset::east_opt(0).unwrap()
+                ))
+                .format("%a, %d %b %Y %H:%M:%S GMT"),
+            base_url.trim_end_matches('/'),
+            url_path
+        ));
 
-                    // Add author if available
-                    if let Some(author) = yaml_content.metadata.author {
-                        rss.push_str(&format!("\n            <dc:creator>{}</dc:creator>", author));
-                    }
+        // A creator per author, lead author first
+        for author in post.author_names() {
+            rss.push_str(&format!("\n            <dc:creator>{}</dc:creator>", author));
+        }
 
-                    // Add content
-                    rss.push_str(&format!("\n            <content:encoded><![CDATA[{}]]></content:encoded>", 
-                        markdown_to_html(&yaml_content.content)));
+        // Add content
+        rss.push_str(&format!("\n            <content:encoded>{}</content:encoded>",
+            cdata(&post.html_content)));
 
-                    rss.push_str("\n        </item>");
-                }
-            }
-        }
+        rss.push_str("\n        </item>");
     }
 
     rss.push_str("\n    </channel>\n</rss>");
@@ -142,24 +160,112 @@ pub fn generate_rss(processed_files: &[PathBuf], config: &SEOConfig, output_dir:
     Ok(())
 }
 
+/// User agents of crawlers that collect training data for AI models.
+const AI_CRAWLERS: [&str; 12] = [
+    "GPTBot",
+    "ChatGPT-User",
+    "CCBot",
+    "Google-Extended",
+    "anthropic-ai",
+    "ClaudeBot",
+    "PerplexityBot",
+    "Bytespider",
+    "Amazonbot",
+    "Applebot-Extended",
+    "meta-externalagent",
+    "cohere-ai",
+];
+
 pub fn generate_robots_txt(config: &SEOConfig, output_dir: &str) -> std::io::Result<()> {
     let base_url = config.base_url.as_deref().unwrap_or("");
-    let robots = format!(r#"User-agent: *
-Allow: /
+    let default_robots = RobotsConfig::default();
+    let robots_config = config.robots.as_ref().unwrap_or(&default_robots);
 
-# Sitemaps
-Sitemap: {}/sitemap.xml"#,
-        base_url
-    );
+    let mut robots = String::new();
+    if robots_config.rules.is_empty() {
+        robots.push_str("User-agent: *\nAllow: /\n");
+    }
+
+    for rule in &robots_config.rules {
+        robots.push_str(&format!("User-agent: {}\n", rule.user_agent));
+        for path in &rule.allow {
+            robots.push_str(&format!("Allow: {}\n", path));
+        }
+        for path in &rule.disallow {
+            robots.push_str(&format!("Disallow: {}\n", path));
+        }
+        if let Some(delay) = rule.crawl_delay {
+            robots.push_str(&format!("Crawl-delay: {}\n", delay));
+        }
+        robots.push('\n');
+    }
+
+    if robots_config.block_ai_crawlers {
+        robots.push_str("\n# AI crawlers\n");
+        for agent in AI_CRAWLERS {
+            robots.push_str(&format!("User-agent: {}\n", agent));
+        }
+        robots.push_str("Disallow: /\n");
+    }
+
+    if !robots_config.extra.is_empty() {
+        robots.push('\n');
+        for line in &robots_config.extra {
+            robots.push_str(line);
+            robots.push('\n');
+        }
+    }
+
+    robots.push_str(&format!("\n# Sitemaps\nSitemap: {}/sitemap.xml", base_url.trim_end_matches('/')));
 
     fs::write(Path::new(output_dir).join("robots.txt"), robots)?;
     Ok(())
 }
 
-fn markdown_to_html(content: &str) -> String {
-    use pulldown_cmark::{Parser, html};
-    let parser = Parser::new(content);
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
-    html_output
-}
\ No newline at end of file
+/// `sitemap.xml` of the pages in the build.
+pub struct Sitemap;
+
+/// `rss.xml` of the blog posts.
+pub struct RssFeed;
+
+/// `robots.txt`, pointing crawlers at the sitemap.
+pub struct RobotsTxt;
+
+impl OutputGenerator for Sitemap {
+    fn name(&self) -> &str {
+        "sitemap"
+    }
+
+    fn generate(&self, site: &SiteInfo) -> anyhow::Result<()> {
+        if let Some(seo) = site.seo_config {
+            generate_sitemap(site.pages, site.page_seo, seo, &site.output_dir.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+impl OutputGenerator for RssFeed {
+    fn name(&self) -> &str {
+        "rss"
+    }
+
+    fn generate(&self, site: &SiteInfo) -> anyhow::Result<()> {
+        if let Some(seo) = site.seo_config {
+            generate_rss(site.posts, seo, &site.output_dir.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+impl OutputGenerator for RobotsTxt {
+    fn name(&self) -> &str {
+        "robots.txt"
+    }
+
+    fn generate(&self, site: &SiteInfo) -> anyhow::Result<()> {
+        if let Some(seo) = site.seo_config {
+            generate_robots_txt(seo, &site.output_dir.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
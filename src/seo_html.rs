@@ -1,77 +1,123 @@
+use html_escape::{encode_double_quoted_attribute as attr, encode_text};
+use lazy_static::lazy_static;
+use regex::Regex;
 use crate::seo::{SEOConfig, PageSEO};
 use crate::seo_types::JsonLd;
+use crate::plugins::{HtmlPostProcessor, PageInfo};
 
+lazy_static! {
+    static ref SEO_MACRO_REGEX: Regex = Regex::new(r"@\{\s*(og_tags|json_ld|seo_breadcrumbs|feed_links|analytics)\s*\(\s*\)\s*\}").unwrap();
+}
+
+/// Macros that place generated SEO markup where a layout wants it.
+pub const SEO_MACROS: &[&str] = &["og_tags", "json_ld", "seo_breadcrumbs", "feed_links", "analytics"];
+
+/// Schema.org types rendered with `og:type=article` and `article:*` tags.
+const ARTICLE_TYPES: &[&str] = &["Article", "BlogPosting", "NewsArticle", "TechArticle"];
+
+/// Complete set of SEO tags for a page: title, meta, canonical, hreflang
+/// alternates, Open Graph, Twitter card, article meta, and JSON-LD.
 pub fn generate_meta_tags(page: &PageSEO, config: &SEOConfig) -> String {
+    let mut meta = generate_head_tags(page, config);
+    meta.push_str(&generate_json_ld_script(page, config));
+    meta
+}
+
+/// Everything [`generate_meta_tags`] emits except the JSON-LD script.
+pub fn generate_head_tags(page: &PageSEO, config: &SEOConfig) -> String {
     let mut meta = String::new();
+    let description = page.description.as_deref().unwrap_or(&config.default_description);
 
     // Basic meta tags
+    let title = if page.title.is_empty() || page.title == config.site_name {
+        config.site_name.clone()
+    } else {
+        format!("{} | {}", page.title, config.site_name)
+    };
     meta.push_str(&format!(r#"<title>{}</title>
 <meta name="description" content="{}" />
-"#, 
-        page.title,
-        page.description.as_deref().unwrap_or(&config.default_description)
+"#,
+        encode_text(&title),
+        attr(description)
     ));
 
     if let Some(keywords) = &page.keywords {
         meta.push_str(&format!(r#"<meta name="keywords" content="{}" />
-"#, keywords.join(", ")));
+"#, attr(&keywords.join(", "))));
+    }
+
+    if let Some(robots) = &page.robots {
+        meta.push_str(&format!(r#"<meta name="robots" content="{}" />
+"#, attr(robots)));
     }
 
     // Canonical URL
-    let canonical = format!("{}/{}", 
-        config.base_url.as_deref().unwrap_or(""),
-        page.path.trim_start_matches('/')
-    );
-    meta.push_str(&format!(r#"<link rel="canonical" href="{}" />
-"#, canonical));
+    let canonical = page_url(page, config);
+    if let Some(canonical) = &canonical {
+        meta.push_str(&format!(r#"<link rel="canonical" href="{}" />
+"#, attr(canonical)));
+    }
+
+    // Translated variants
+    for alternate in &page.alternates {
+        meta.push_str(&format!(r#"<link rel="alternate" hreflang="{}" href="{}" />
+"#, attr(&alternate.hreflang), attr(&alternate.href)));
+    }
 
     // Open Graph tags
+    let is_article = is_article(page);
     meta.push_str(&format!(r#"<meta property="og:title" content="{}" />
-<meta property="og:type" content="article" />
-<meta property="og:url" content="{}" />
-"#, 
-        page.title,
-        canonical
+<meta property="og:type" content="{}" />
+<meta property="og:site_name" content="{}" />
+<meta property="og:description" content="{}" />
+"#,
+        attr(&page.title),
+        if is_article { "article" } else { "website" },
+        attr(&config.site_name),
+        attr(description)
     ));
 
-    if let Some(desc) = &page.description {
-        meta.push_str(&format!(r#"<meta property="og:description" content="{}" />
-"#, desc));
+    if let Some(url) = &canonical {
+        meta.push_str(&format!(r#"<meta property="og:url" content="{}" />
+"#, attr(url)));
     }
 
     if let Some(image) = &page.image {
         meta.push_str(&format!(r#"<meta property="og:image" content="{}" />
 <meta property="og:image:alt" content="{}" />
-"#, image, page.title));
+"#, attr(image), attr(&page.title)));
     }
 
     // Twitter Card tags
-    meta.push_str(r#"<meta name="twitter:card" content="summary_large_image" />"#);
+    let card = if page.image.is_some() { "summary_large_image" } else { "summary" };
+    meta.push_str(&format!(r#"<meta name="twitter:card" content="{}" />"#, card));
 
     if let Some(social) = &config.social_media {
         if let Some(site) = &social.twitter_site {
             meta.push_str(&format!(r#"
-<meta name="twitter:site" content="{}" />"#, site));
+<meta name="twitter:site" content="{}" />"#, attr(site)));
         }
         if let Some(creator) = &social.twitter_creator {
             meta.push_str(&format!(r#"
-<meta name="twitter:creator" content="{}" />"#, creator));
+<meta name="twitter:creator" content="{}" />"#, attr(creator)));
         }
     }
 
     meta.push_str(&format!(r#"
-<meta name="twitter:title" content="{}" />"#, page.title));
+<meta name="twitter:title" content="{}" />
+<meta name="twitter:description" content="{}" />"#, attr(&page.title), attr(description)));
 
-    if let Some(desc) = &page.description {
+    if let Some(image) = &page.image {
         meta.push_str(&format!(r#"
-<meta name="twitter:description" content="{}" />"#, desc));
+<meta name="twitter:image" content="{}" />"#, attr(image)));
     }
 
-    // Article meta tags for blog posts
-    if page.schema_type.as_deref() == Some("BlogPosting") {
-        if let Some(author) = &page.author {
+    // Article meta tags for blog posts and other articles
+    if is_article {
+        let co_authors = page.co_authors.iter().map(|author| &author.name);
+        for author in page.author.iter().chain(co_authors) {
             meta.push_str(&format!(r#"
-<meta property="article:author" content="{}" />"#, author));
+<meta property="article:author" content="{}" />"#, attr(author)));
         }
         if let Some(date) = &page.published_date {
             meta.push_str(&format!(r#"
@@ -83,24 +129,213 @@ pub fn generate_meta_tags(page: &PageSEO, config: &SEOConfig) -> String {
         }
         if let Some(section) = &page.category {
             meta.push_str(&format!(r#"
-<meta property="article:section" content="{}" />"#, section));
+<meta property="article:section" content="{}" />"#, attr(section)));
         }
         if let Some(tags) = &page.tags {
             for tag in tags {
                 meta.push_str(&format!(r#"
-<meta property="article:tag" content="{}" />"#, tag));
+<meta property="article:tag" content="{}" />"#, attr(tag)));
             }
         }
     }
 
-    // Add structured data
-    let jsonld = JsonLd::new_article(page, config);
-    meta.push_str(&format!(r#"
+    meta
+}
+
+/// JSON-LD script for the page, preferring structured data the page supplied itself.
+pub fn generate_json_ld_script(page: &PageSEO, config: &SEOConfig) -> String {
+    let json = match &page.structured_data {
+        Some(data) => serde_json::to_string_pretty(data).unwrap(),
+        None => serde_json::to_string_pretty(&JsonLd::new_article(page, config)).unwrap(),
+    };
+    // Keep the JSON from terminating the script element early
+    format!(r#"
 <script type="application/ld+json">
 {}
-</script>"#, serde_json::to_string_pretty(&jsonld).unwrap()));
+</script>"#, json.replace("</", "<\\/"))
+}
 
-    meta
+/// Google Analytics loader, if a measurement id is configured.
+pub fn analytics_script(config: &SEOConfig) -> String {
+    let Some(ga_id) = &config.google_site_verification else { return String::new() };
+    format!(
+        "\n<script async src=\"https://www.googletagmanager.com/gtag/js?id={}\"></script>\
+        <script>\
+        window.dataLayer = window.dataLayer || [];\
+        function gtag(){{dataLayer.push(arguments);}}\
+        gtag('js', new Date());\
+        gtag('config', '{}');\
+        </script>",
+        ga_id, ga_id
+    )
+}
+
+/// `<link>` tags advertising the RSS feed and sitemap.
+pub fn feed_link_tags(config: &SEOConfig) -> String {
+    let base_url = config.base_url.as_deref().unwrap_or("").trim_end_matches('/');
+    format!(
+        r#"<link rel="alternate" type="application/rss+xml" title="{}" href="{}/rss.xml" />
+<link rel="sitemap" type="application/xml" href="{}/sitemap.xml" />"#,
+        attr(&config.site_name), attr(base_url), attr(base_url)
+    )
+}
+
+/// Breadcrumb trail from the site root to the page, as a `<nav>` plus a
+/// `BreadcrumbList` JSON-LD block.
+pub fn breadcrumbs(page: &PageSEO, config: &SEOConfig) -> String {
+    let base_url = config.base_url.as_deref().unwrap_or("").trim_end_matches('/');
+    let segments: Vec<&str> = page.path.trim_matches('/')
+        .trim_end_matches(".html")
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != "index")
+        .collect();
+
+    let mut crumbs = vec![(config.site_name.clone(), format!("{}/", base_url))];
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        let path = format!("/{}/", segments[..=i].join("/"));
+        let name = if last && !page.title.is_empty() {
+            page.title.clone()
+        } else {
+            page.section_titles.get(&path).cloned().unwrap_or_else(|| title_case(segment))
+        };
+        let url = match page_url(page, config) {
+            Some(url) if last => url,
+            _ => format!("{}{}", base_url, path),
+        };
+        crumbs.push((name, url));
+    }
+
+    let items: String = crumbs.iter().enumerate()
+        .map(|(i, (name, url))| if i + 1 == crumbs.len() {
+            format!(r#"<li aria-current="page">{}</li>"#, encode_text(name))
+        } else {
+            format!(r#"<li><a href="{}">{}</a></li>"#, attr(url), encode_text(name))
+        })
+        .collect();
+    let list = serde_json::json!({
+        "@context": "https://schema.org",
+        "@type": "BreadcrumbList",
+        "itemListElement": crumbs.iter().enumerate()
+            .map(|(i, (name, url))| serde_json::json!({
+                "@type": "ListItem",
+                "position": i + 1,
+                "name": name,
+                "item": url,
+            }))
+            .collect::<Vec<_>>(),
+    });
+
+    format!(
+        r#"<nav class="breadcrumbs" aria-label="Breadcrumb"><ol>{}</ol></nav>
+<script type="application/ld+json">
+{}
+</script>"#,
+        items,
+        serde_json::to_string_pretty(&list).unwrap().replace("</", "<\\/")
+    )
+}
+
+/// Names of the SEO macros a template places itself.
+pub fn seo_macros_used(html: &str) -> Vec<String> {
+    SEO_MACRO_REGEX.captures_iter(html).map(|caps| caps[1].to_string()).collect()
+}
+
+/// Replace each SEO macro with its generated markup.
+pub fn expand_seo_macros(html: &str, page: &PageSEO, config: &SEOConfig) -> String {
+    SEO_MACRO_REGEX.replace_all(html, |caps: &regex::Captures| macro_markup(&caps[1], page, config)).to_string()
+}
+
+fn macro_markup(name: &str, page: &PageSEO, config: &SEOConfig) -> String {
+    match name {
+        "og_tags" => generate_head_tags(page, config),
+        "json_ld" => generate_json_ld_script(page, config),
+        "seo_breadcrumbs" => breadcrumbs(page, config),
+        "feed_links" => feed_link_tags(config),
+        _ => analytics_script(config),
+    }
+}
+
+/// Remove SEO macros from pages built without SEO.
+pub fn strip_seo_macros(html: &str) -> String {
+    SEO_MACRO_REGEX.replace_all(html, "").to_string()
+}
+
+/// Expands the SEO macros of a page from its SEO data, or strips them when
+/// SEO is off.
+pub struct SeoInjector<'a> {
+    config: Option<&'a SEOConfig>,
+}
+
+impl<'a> SeoInjector<'a> {
+    pub fn new(config: Option<&'a SEOConfig>) -> Self {
+        Self { config }
+    }
+}
+
+impl HtmlPostProcessor for SeoInjector<'_> {
+    fn name(&self) -> &str {
+        "seo"
+    }
+
+    fn process(&self, page: &PageInfo, html: String) -> anyhow::Result<String> {
+        Ok(match self.config {
+            Some(config) => expand_seo_macros(&html, page.seo.unwrap_or(&PageSEO::default()), config),
+            None => strip_seo_macros(&html),
+        })
+    }
+}
+
+/// Rewrite SEO macros as comments that survive HTML parsing anywhere in `<head>`.
+pub(crate) fn protect_seo_macros(html: &str) -> String {
+    SEO_MACRO_REGEX.replace_all(html, "<!--ssg:$1-->").to_string()
+}
+
+/// Expand the comments left by [`protect_seo_macros`].
+pub(crate) fn expand_protected_seo_macros(html: &str, page: &PageSEO, config: &SEOConfig) -> String {
+    let mut html = html.to_string();
+    for name in SEO_MACROS {
+        let placeholder = format!("<!--ssg:{}-->", name);
+        if html.contains(&placeholder) {
+            html = html.replace(&placeholder, &macro_markup(name, page, config));
+        }
+    }
+    html
+}
+
+fn title_case(segment: &str) -> String {
+    segment.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Absolute URL of the page, if it can be determined.
+fn page_url(page: &PageSEO, config: &SEOConfig) -> Option<String> {
+    if let Some(canonical) = &page.canonical_url {
+        return Some(canonical.clone());
+    }
+    if page.path.is_empty() {
+        return None;
+    }
+    Some(format!("{}/{}",
+        config.base_url.as_deref().unwrap_or("").trim_end_matches('/'),
+        page.path.trim_start_matches('/')
+    ))
+}
+
+fn is_article(page: &PageSEO) -> bool {
+    match page.schema_type.as_deref() {
+        Some(schema_type) => ARTICLE_TYPES.contains(&schema_type),
+        None => page.published_date.is_some(),
+    }
 }
 
 pub fn inject_meta_tags(html: &str, meta_tags: &str) -> String {
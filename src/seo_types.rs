@@ -1,7 +1,8 @@
+use std::collections::BTreeMap;
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, FixedOffset};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PageSEO {
     pub title: String,
     pub description: Option<String>,
@@ -11,6 +12,12 @@ pub struct PageSEO {
     pub path: String,
     pub image: Option<String>,
     pub author: Option<String>,
+    /// Author profile page and picture, from the authors file
+    pub author_url: Option<String>,
+    pub author_image: Option<String>,
+    /// Authors credited after `author`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub co_authors: Vec<Author>,
     pub published_date: Option<DateTime<FixedOffset>>,
     pub last_modified: Option<DateTime<FixedOffset>>,
     pub category: Option<String>,
@@ -19,6 +26,41 @@ pub struct PageSEO {
     pub structured_data: Option<serde_json::Value>,
     pub change_frequency: Option<String>,
     pub priority: Option<f32>,
+    #[serde(default)]
+    pub alternates: Vec<AlternateLink>,
+    /// Robots directives such as `noindex, nofollow`
+    pub robots: Option<String>,
+    /// Titles of section landing pages by URL path, for breadcrumbs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub section_titles: BTreeMap<String, String>,
+}
+
+impl PageSEO {
+    /// Whether the page asked to be kept out of search engines and feeds.
+    pub fn is_noindex(&self) -> bool {
+        self.robots.as_deref().is_some_and(robots_noindex)
+    }
+}
+
+pub fn robots_noindex(robots: &str) -> bool {
+    robots.split(',').any(|directive| {
+        matches!(directive.trim().to_ascii_lowercase().as_str(), "noindex" | "none")
+    })
+}
+
+/// A root-relative `path` as a URL under `base_url`; other values unchanged.
+pub fn absolute_url(base_url: &str, path: &str) -> String {
+    match path.starts_with('/') {
+        true => format!("{}{}", base_url.trim_end_matches('/'), path),
+        false => path.to_string(),
+    }
+}
+
+/// A translated variant of a page, emitted as `hreflang` alternates.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AlternateLink {
+    pub hreflang: String,
+    pub href: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,7 +74,7 @@ pub struct JsonLd {
     pub description: Option<String>,
     pub url: String,
     pub image: Option<Vec<String>>,
-    pub author: Option<Author>,
+    pub author: Option<Authorship>,
     pub publisher: Option<Organization>,
     pub date_published: Option<String>,
     pub date_modified: Option<String>,
@@ -41,12 +83,24 @@ pub struct JsonLd {
     pub article_section: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Author {
     #[serde(rename = "@type")]
     pub type_: String,
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+/// A single author as a Person, co-authors as a list of them.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Authorship {
+    One(Author),
+    Many(Vec<Author>),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,18 +123,32 @@ pub struct ImageObject {
 impl JsonLd {
     pub fn new_article(page: &PageSEO, config: &crate::seo::SEOConfig) -> Self {
         let base_url = config.base_url.as_deref().unwrap_or("");
-        let full_url = format!("{}/{}", base_url.trim_end_matches('/'), page.path);
+        let full_url = format!("{}/{}", base_url.trim_end_matches('/'), page.path.trim_start_matches('/'));
+        let absolute = |path: &String| absolute_url(base_url, path);
 
         Self {
             context: "https://schema.org".to_string(),
-            type_: "Article".to_string(),
+            type_: page.schema_type.clone().unwrap_or_else(|| "Article".to_string()),
             headline: page.title.clone(),
             description: page.description.clone(),
             url: full_url,
             image: page.image.as_ref().map(|img| vec![img.clone()]),
-            author: page.author.as_ref().map(|name| Author {
-                type_: "Person".to_string(),
-                name: name.clone(),
+            author: page.author.as_ref().map(|name| {
+                let lead = Author {
+                    type_: "Person".to_string(),
+                    name: name.clone(),
+                    url: page.author_url.as_ref().map(absolute),
+                    image: page.author_image.as_ref().map(absolute),
+                };
+                if page.co_authors.is_empty() {
+                    return Authorship::One(lead);
+                }
+                let co_authors = page.co_authors.iter().map(|author| Author {
+                    url: author.url.as_ref().map(absolute),
+                    image: author.image.as_ref().map(absolute),
+                    ..author.clone()
+                });
+                Authorship::Many(std::iter::once(lead).chain(co_authors).collect())
             }),
             publisher: config.organization.as_ref().map(|org| Organization {
                 type_: "Organization".to_string(),
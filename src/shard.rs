@@ -0,0 +1,256 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use crate::csp::ContentSecurityPolicy;
+use crate::history;
+use crate::redirects::Redirect;
+use crate::search::SearchDocument;
+use crate::seo::PageSEO;
+use crate::variants::VariantManifest;
+
+/// Output subdirectories that belong to one build and are not merged.
+const PER_BUILD_DIRS: &[&str] = &["cache", "performance"];
+
+/// One of `count` slices of the site's pages, `--shard 2/8` on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    /// 1-based
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s.split_once('/')
+            .ok_or_else(|| format!("Invalid shard '{}', expected N/M like 2/8", s))?;
+        let parse = |n: &str| n.trim().parse::<usize>()
+            .map_err(|_| format!("Invalid shard '{}', expected N/M like 2/8", s));
+        let (index, count) = (parse(index)?, parse(count)?);
+        if index == 0 || index > count {
+            return Err(format!("Invalid shard '{}', N must be between 1 and {}", s, count));
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// Whether this shard builds the content file at `relative_path` in the
+    /// input directory. Pages are assigned by a hash of their path, so every
+    /// job agrees without coordinating and a page keeps its shard as others
+    /// are added.
+    pub fn contains(&self, relative_path: &Path) -> bool {
+        let path = relative_path.to_string_lossy().replace('\\', "/");
+        let hash = u64::from_str_radix(&history::hash(path.as_bytes()), 16).unwrap_or(0);
+        hash % self.count as u64 == (self.index - 1) as u64
+    }
+
+    /// The shard that also generates pages not built from content files,
+    /// such as the API reference and release notes.
+    pub fn is_first(&self) -> bool {
+        self.index == 1
+    }
+}
+
+/// Per-page results that the site-wide files are generated from: sitemap,
+/// search index, redirects, `_headers`, and the A/B variants manifest.
+#[derive(Debug, Default)]
+pub struct SiteOutputs {
+    /// Listed pages, as paths in the output directory
+    pub pages: Vec<PathBuf>,
+    pub page_seo: HashMap<PathBuf, PageSEO>,
+    pub aliases: Vec<Redirect>,
+    pub search_documents: Vec<SearchDocument>,
+    pub csp: ContentSecurityPolicy,
+    pub variants: VariantManifest,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestPage {
+    /// Relative to the shard's output directory
+    path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seo: Option<PageSEO>,
+}
+
+/// What one shard built, written to its output directory for `merge`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShardManifest {
+    pub shard: Shard,
+    pages: Vec<ManifestPage>,
+    aliases: Vec<Redirect>,
+    search_documents: Vec<SearchDocument>,
+    csp: ContentSecurityPolicy,
+    variants: VariantManifest,
+}
+
+impl ShardManifest {
+    pub const FILE_NAME: &'static str = "shard-manifest.json";
+
+    pub fn new(shard: Shard, mut outputs: SiteOutputs, output_dir: &Path) -> Self {
+        let pages = outputs.pages.into_iter()
+            .map(|page| ManifestPage {
+                seo: outputs.page_seo.remove(&page),
+                path: page.strip_prefix(output_dir).map(Path::to_path_buf).unwrap_or(page),
+            })
+            .collect();
+        Self {
+            shard,
+            pages,
+            aliases: outputs.aliases,
+            search_documents: outputs.search_documents,
+            csp: outputs.csp,
+            variants: outputs.variants,
+        }
+    }
+
+    pub fn load(shard_dir: &Path) -> Result<Self> {
+        let path = shard_dir.join(Self::FILE_NAME);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn write(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(Self::FILE_NAME);
+        fs::write(&path, serde_json::to_string(self)?)?;
+        info!("Shard {} manifest with {} pages written to {}", self.shard, self.pages.len(), path.display());
+        Ok(())
+    }
+
+    /// Add this shard's results to `outputs`, with its pages moved to `output_dir`.
+    fn merge_into(self, outputs: &mut SiteOutputs, output_dir: &Path) {
+        for page in self.pages {
+            let path = output_dir.join(page.path);
+            if let Some(seo) = page.seo {
+                outputs.page_seo.insert(path.clone(), seo);
+            }
+            outputs.pages.push(path);
+        }
+        outputs.aliases.extend(self.aliases);
+        outputs.search_documents.extend(self.search_documents);
+        outputs.csp.merge(&self.csp);
+        outputs.variants.extend(self.variants);
+    }
+}
+
+/// Copy the output of every shard of one build into `output_dir` and combine
+/// their manifests. Fails unless `shard_dirs` holds each shard exactly once.
+pub fn merge_shards(shard_dirs: &[PathBuf], output_dir: &Path) -> Result<SiteOutputs> {
+    let manifests = shard_dirs.iter()
+        .map(|dir| ShardManifest::load(dir))
+        .collect::<Result<Vec<_>>>()?;
+    let count = manifests.first().map(|manifest| manifest.shard.count).unwrap_or(0);
+    let shards: BTreeSet<usize> = manifests.iter().map(|manifest| manifest.shard.index).collect();
+    if manifests.iter().any(|manifest| manifest.shard.count != count) {
+        return Err(anyhow!("Shard directories come from builds split different ways"));
+    }
+    if shards.len() != manifests.len() || shards.len() != count {
+        let missing: Vec<_> = (1..=count)
+            .filter(|index| !shards.contains(index))
+            .map(|index| Shard { index, count }.to_string())
+            .collect();
+        return Err(anyhow!(
+            "Expected each of {} shards once, got {} directories (missing: {})",
+            count, manifests.len(), if missing.is_empty() { "none".to_string() } else { missing.join(", ") }
+        ));
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let mut outputs = SiteOutputs::default();
+    for (dir, manifest) in shard_dirs.iter().zip(manifests) {
+        if dir.canonicalize()? == output_dir.canonicalize()? {
+            return Err(anyhow!("Cannot merge shard {} into its own directory", dir.display()));
+        }
+        let copied = copy_output(dir, output_dir)?;
+        info!("Merged shard {} from {} ({} files)", manifest.shard, dir.display(), copied);
+        manifest.merge_into(&mut outputs, output_dir);
+    }
+    Ok(outputs)
+}
+
+/// Copy a shard's files except its manifest, cache, and reports.
+fn copy_output(shard_dir: &Path, output_dir: &Path) -> Result<usize> {
+    let mut copied = 0;
+    let entries = WalkDir::new(shard_dir).min_depth(1).into_iter().filter_entry(|entry| {
+        let top_level = entry.depth() == 1;
+        let name = entry.file_name().to_string_lossy();
+        !(top_level && (name == ShardManifest::FILE_NAME || PER_BUILD_DIRS.contains(&name.as_ref())))
+    });
+    for entry in entries {
+        let entry = entry?;
+        let target = output_dir.join(entry.path().strip_prefix(shard_dir)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_shards_split_pages_once() {
+        assert_eq!("2/8".parse::<Shard>().unwrap(), Shard { index: 2, count: 8 });
+        assert!("0/8".parse::<Shard>().is_err());
+        assert!("9/8".parse::<Shard>().is_err());
+        assert!("2".parse::<Shard>().is_err());
+
+        let shards: Vec<Shard> = (1..=4).map(|index| Shard { index, count: 4 }).collect();
+        for i in 0..100 {
+            let page = PathBuf::from(format!("blog/post-{}.md", i));
+            assert_eq!(shards.iter().filter(|shard| shard.contains(&page)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_merge_combines_shard_outputs() {
+        let temp = tempdir().unwrap();
+        let output = temp.path().join("output");
+        let mut dirs = Vec::new();
+        for index in 1..=2 {
+            let dir = temp.path().join(format!("shard-{}", index));
+            fs::create_dir_all(dir.join("blog")).unwrap();
+            fs::create_dir_all(dir.join("cache")).unwrap();
+            fs::write(dir.join("cache/state.json"), "{}").unwrap();
+            let page = dir.join(format!("blog/p{}.html", index));
+            fs::write(&page, "<html></html>").unwrap();
+            let outputs = SiteOutputs {
+                pages: vec![page.clone()],
+                aliases: vec![Redirect::alias(&format!("old-{}", index), "/blog/")],
+                ..SiteOutputs::default()
+            };
+            ShardManifest::new(Shard { index, count: 2 }, outputs, &dir).write(&dir).unwrap();
+            dirs.push(dir);
+        }
+
+        assert!(merge_shards(&dirs[..1], &output).is_err());
+        let outputs = merge_shards(&dirs, &output).unwrap();
+        let mut pages = outputs.pages.clone();
+        pages.sort();
+        assert_eq!(pages, vec![output.join("blog/p1.html"), output.join("blog/p2.html")]);
+        assert_eq!(outputs.aliases.len(), 2);
+        assert!(output.join("blog/p2.html").exists());
+        assert!(!output.join(ShardManifest::FILE_NAME).exists());
+        assert!(!output.join("cache").exists());
+    }
+}
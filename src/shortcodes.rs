@@ -0,0 +1,403 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use html_escape::encode_double_quoted_attribute as attr;
+use image::{imageops, Rgba, RgbaImage};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use crate::history;
+use crate::remote_cache::RemoteCache;
+
+lazy_static! {
+    static ref SHORTCODE_REGEX: Regex = Regex::new(r"\{\{<\s*([a-z_]+)((?:\s+[^\s>]+)*)\s*>\}\}").unwrap();
+}
+
+/// Size of one slippy map tile in pixels.
+const TILE_SIZE: u32 = 256;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MapMode {
+    /// Image stitched from map tiles at build time
+    Static,
+    /// Placeholder that loads Leaflet once it scrolls into view
+    #[default]
+    Interactive,
+}
+
+/// `[maps]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MapsConfig {
+    pub mode: MapMode,
+    /// Slippy map tile URL with `{z}`, `{x}`, and `{y}` placeholders
+    pub tile_url: String,
+    pub attribution: String,
+    pub width: u32,
+    pub height: u32,
+    pub leaflet_js: String,
+    pub leaflet_css: String,
+}
+
+impl Default for MapsConfig {
+    fn default() -> Self {
+        Self {
+            mode: MapMode::default(),
+            tile_url: "https://tile.openstreetmap.org/{z}/{x}/{y}.png".to_string(),
+            attribution: "© OpenStreetMap contributors".to_string(),
+            width: 640,
+            height: 360,
+            leaflet_js: "https://unpkg.com/leaflet@1.9.4/dist/leaflet.js".to_string(),
+            leaflet_css: "https://unpkg.com/leaflet@1.9.4/dist/leaflet.css".to_string(),
+        }
+    }
+}
+
+/// A `{{< map lat lng zoom >}}` shortcode, optionally followed by `static`
+/// or `interactive` to override the configured mode.
+#[derive(Debug, Clone, PartialEq)]
+struct MapShortcode {
+    lat: f64,
+    lng: f64,
+    zoom: u8,
+    mode: Option<MapMode>,
+}
+
+impl MapShortcode {
+    fn parse(args: &[&str]) -> Result<Self> {
+        let number = |index: usize, name: &str| -> Result<f64> {
+            let value = args.get(index).ok_or_else(|| anyhow!("missing {}", name))?;
+            value.parse().map_err(|_| anyhow!("{} '{}' is not a number", name, value))
+        };
+        let lat = number(0, "latitude")?;
+        let lng = number(1, "longitude")?;
+        let zoom = match args.get(2) {
+            Some(zoom) => zoom.parse().map_err(|_| anyhow!("zoom '{}' is not a whole number", zoom))?,
+            None => 13,
+        };
+        if !(-85.0511..=85.0511).contains(&lat) || !(-180.0..=180.0).contains(&lng) || zoom > 19 {
+            return Err(anyhow!("{}, {} at zoom {} is outside the map", lat, lng, zoom));
+        }
+        let mode = match args.get(3).copied() {
+            Some("static") => Some(MapMode::Static),
+            Some("interactive") => Some(MapMode::Interactive),
+            Some(other) => return Err(anyhow!("unknown map mode '{}'", other)),
+            None => None,
+        };
+        Ok(Self { lat, lng, zoom, mode })
+    }
+
+    fn link(&self) -> String {
+        format!(
+            "https://www.openstreetmap.org/?mlat={lat}&mlon={lng}#map={zoom}/{lat}/{lng}",
+            lat = self.lat, lng = self.lng, zoom = self.zoom
+        )
+    }
+}
+
+/// Expands `{{< name args >}}` shortcodes in Markdown before it is rendered.
+pub struct Shortcodes {
+    maps: MapsConfig,
+    output_dir: PathBuf,
+    cache_dir: PathBuf,
+    remote_cache: Option<RemoteCache>,
+}
+
+impl Shortcodes {
+    pub fn new(maps: MapsConfig, output_dir: impl Into<PathBuf>) -> Self {
+        let output_dir = output_dir.into();
+        Self {
+            maps,
+            cache_dir: output_dir.join("cache").join("maps"),
+            output_dir,
+            remote_cache: None,
+        }
+    }
+
+    /// Share rendered map snapshots through a remote cache.
+    pub fn with_remote_cache(mut self, remote_cache: Option<RemoteCache>) -> Self {
+        self.remote_cache = remote_cache;
+        self
+    }
+
+    /// Replace every shortcode outside fenced code blocks. Shortcodes that
+    /// can't be expanded are left as written, with a warning.
+    pub fn expand(&self, markdown: &str, file_path: &Path) -> String {
+        if !markdown.contains("{{<") {
+            return markdown.to_string();
+        }
+        let mut in_fence = false;
+        let mut has_interactive_map = false;
+        let mut output = String::with_capacity(markdown.len());
+        for line in markdown.split_inclusive('\n') {
+            if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            if in_fence {
+                output.push_str(line);
+                continue;
+            }
+            let expanded = SHORTCODE_REGEX.replace_all(line, |caps: &Captures| {
+                let args: Vec<&str> = caps[2].split_whitespace().collect();
+                let result = match &caps[1] {
+                    "map" => MapShortcode::parse(&args).map(|map| {
+                        let (html, interactive) = self.map(&map);
+                        has_interactive_map |= interactive;
+                        html
+                    }),
+                    name => Err(anyhow!("unknown shortcode '{}'", name)),
+                };
+                result.unwrap_or_else(|e| {
+                    warn!("Leaving {} in {}: {}", &caps[0], file_path.display(), e);
+                    caps[0].to_string()
+                })
+            });
+            output.push_str(&expanded);
+        }
+        if has_interactive_map {
+            output.push_str("\n\n");
+            output.push_str(&self.map_loader());
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Markup for one map, and whether it needs the interactive loader.
+    fn map(&self, map: &MapShortcode) -> (String, bool) {
+        let config = &self.maps;
+        if map.mode.unwrap_or(config.mode) == MapMode::Static {
+            match self.snapshot(map) {
+                Ok(src) => {
+                    return (format!(
+                        r#"<figure class="map map-static"><a href="{link}"><img src="{src}" width="{w}" height="{h}" alt="Map of {lat}, {lng}" loading="lazy"></a><figcaption>{attribution}</figcaption></figure>"#,
+                        link = attr(&map.link()),
+                        src = attr(&src),
+                        w = config.width,
+                        h = config.height,
+                        lat = map.lat,
+                        lng = map.lng,
+                        attribution = html_escape::encode_text(&config.attribution),
+                    ), false);
+                },
+                Err(e) => warn!("Static map for {}, {} unavailable, using an interactive map: {}", map.lat, map.lng, e),
+            }
+        }
+        (format!(
+            r#"<div class="map map-interactive" data-lat="{lat}" data-lng="{lng}" data-zoom="{zoom}" style="aspect-ratio: {w} / {h}"><a href="{link}">View {lat}, {lng} on OpenStreetMap</a></div>"#,
+            lat = map.lat,
+            lng = map.lng,
+            zoom = map.zoom,
+            w = config.width,
+            h = config.height,
+            link = attr(&map.link()),
+        ), true)
+    }
+
+    /// Loads Leaflet the first time a map on the page scrolls into view.
+    fn map_loader(&self) -> String {
+        let config = &self.maps;
+        format!(
+            r#"<script>(function(){{var maps=document.querySelectorAll('.map-interactive');var loading;function load(){{return loading=loading||new Promise(function(resolve){{var css=document.createElement('link');css.rel='stylesheet';css.href={css};document.head.appendChild(css);var js=document.createElement('script');js.src={js};js.onload=resolve;document.head.appendChild(js);}});}}function show(el){{load().then(function(){{var at=[+el.dataset.lat,+el.dataset.lng];el.textContent='';var map=L.map(el).setView(at,+el.dataset.zoom);L.tileLayer({tiles},{{attribution:{attribution}}}).addTo(map);L.marker(at).addTo(map);}});}}var observer=new IntersectionObserver(function(entries){{entries.forEach(function(entry){{if(entry.isIntersecting){{observer.unobserve(entry.target);show(entry.target);}}}});}},{{rootMargin:'200px'}});maps.forEach(function(el){{observer.observe(el);}});}})();</script>"#,
+            css = js_string(&config.leaflet_css),
+            js = js_string(&config.leaflet_js),
+            tiles = js_string(&config.tile_url),
+            attribution = js_string(&config.attribution),
+        )
+    }
+
+    /// Stitch the tiles around the map's center into an image, reusing the
+    /// cached copy when the same map was rendered before. Returns its URL.
+    fn snapshot(&self, map: &MapShortcode) -> Result<String> {
+        let config = &self.maps;
+        let key = format!("{},{},{},{}x{},{}", map.lat, map.lng, map.zoom, config.width, config.height, config.tile_url);
+        let file_name = format!("{}.png", history::hash(key.as_bytes()));
+        let cached = self.cache_dir.join(&file_name);
+
+        let remote_key = format!("maps/{}", file_name);
+        let is_cached = match &self.remote_cache {
+            Some(remote) => remote.fetch(&remote_key, &cached),
+            None => cached.exists(),
+        };
+        if !is_cached {
+            info!("Fetching map tiles for {}, {} at zoom {}", map.lat, map.lng, map.zoom);
+            fs::create_dir_all(&self.cache_dir)?;
+            render_snapshot(map, config)?.save(&cached)?;
+            if let Some(remote) = &self.remote_cache {
+                remote.store(&remote_key, &cached);
+            }
+        }
+
+        let published = self.output_dir.join("maps").join(&file_name);
+        if !published.exists() {
+            fs::create_dir_all(self.output_dir.join("maps"))?;
+            fs::copy(&cached, &published)?;
+        }
+        Ok(format!("/maps/{}", file_name))
+    }
+}
+
+fn render_snapshot(map: &MapShortcode, config: &MapsConfig) -> Result<RgbaImage> {
+    let tiles = 1u32 << map.zoom;
+    let world = (tiles * TILE_SIZE) as f64;
+    let lat = map.lat.to_radians();
+    let center_x = (map.lng + 180.0) / 360.0 * world;
+    let center_y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / std::f64::consts::PI) / 2.0 * world;
+    let left = center_x - config.width as f64 / 2.0;
+    let top = center_y - config.height as f64 / 2.0;
+
+    let mut canvas = RgbaImage::from_pixel(config.width, config.height, Rgba([229, 227, 223, 255]));
+    let first_x = (left / TILE_SIZE as f64).floor() as i64;
+    let last_x = ((left + config.width as f64) / TILE_SIZE as f64).floor() as i64;
+    let first_y = (top / TILE_SIZE as f64).floor().max(0.0) as i64;
+    let last_y = ((top + config.height as f64) / TILE_SIZE as f64).floor().min(tiles as f64 - 1.0) as i64;
+
+    for tile_y in first_y..=last_y {
+        for tile_x in first_x..=last_x {
+            // Wrap around the antimeridian
+            let wrapped_x = tile_x.rem_euclid(tiles as i64);
+            let url = config.tile_url
+                .replace("{z}", &map.zoom.to_string())
+                .replace("{x}", &wrapped_x.to_string())
+                .replace("{y}", &tile_y.to_string());
+            let tile = fetch_tile(&url)?;
+            imageops::overlay(
+                &mut canvas,
+                &tile,
+                (tile_x * TILE_SIZE as i64) - left.round() as i64,
+                (tile_y * TILE_SIZE as i64) - top.round() as i64,
+            );
+        }
+    }
+
+    draw_marker(&mut canvas, config.width / 2, config.height / 2);
+    Ok(canvas)
+}
+
+fn fetch_tile(url: &str) -> Result<RgbaImage> {
+    let response = ureq::get(url)
+        .set("User-Agent", concat!("eldroid-ssg/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| anyhow!("failed to fetch {}: {}", url, e))?;
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(image::load_from_memory(&bytes)?.to_rgba8())
+}
+
+/// Red dot with a white outline at the map's center.
+fn draw_marker(canvas: &mut RgbaImage, cx: u32, cy: u32) {
+    const RADIUS: i64 = 8;
+    for dy in -RADIUS..=RADIUS {
+        for dx in -RADIUS..=RADIUS {
+            let distance = dx * dx + dy * dy;
+            let (x, y) = (cx as i64 + dx, cy as i64 + dy);
+            if distance > RADIUS * RADIUS || x < 0 || y < 0 || x >= canvas.width() as i64 || y >= canvas.height() as i64 {
+                continue;
+            }
+            let color = if distance > (RADIUS - 3) * (RADIUS - 3) {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([214, 40, 40, 255])
+            };
+            canvas.put_pixel(x as u32, y as u32, color);
+        }
+    }
+}
+
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default().replace("</", "<\\/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::thread::JoinHandle;
+    use tempfile::tempdir;
+
+    /// Serves a plain blue tile to `requests` connections and returns the
+    /// request lines it received.
+    fn serve_tiles(requests: usize) -> (String, JoinHandle<Vec<String>>) {
+        let mut tile = Vec::new();
+        RgbaImage::from_pixel(TILE_SIZE, TILE_SIZE, Rgba([0, 0, 255, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut tile), image::ImageFormat::Png)
+            .unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                }
+                received.push(request_line.trim().to_string());
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", tile.len()).unwrap();
+                stream.write_all(&tile).unwrap();
+            }
+            received
+        });
+        (host, server)
+    }
+
+    #[test]
+    fn test_interactive_maps_share_one_loader_and_skip_code() {
+        let temp = tempdir().unwrap();
+        let shortcodes = Shortcodes::new(MapsConfig::default(), temp.path());
+        let markdown = "{{< map 51.5 -0.12 >}}\n\n{{< map 48.85 2.35 10 >}}\n\n```\n{{< map 1 2 >}}\n```\n\n{{< map 95 0 >}}\n";
+
+        let html = shortcodes.expand(markdown, Path::new("post.md"));
+
+        assert!(html.contains(r#"data-lat="51.5" data-lng="-0.12" data-zoom="13""#));
+        assert!(html.contains(r#"data-lat="48.85" data-lng="2.35" data-zoom="10""#));
+        assert_eq!(html.matches("<script>").count(), 1);
+        assert!(html.contains("```\n{{< map 1 2 >}}\n```"));
+        assert!(html.contains("{{< map 95 0 >}}"));
+    }
+
+    #[test]
+    fn test_static_maps_are_stitched_once_and_published() {
+        let temp = tempdir().unwrap();
+        let (host, server) = serve_tiles(4);
+        let maps = MapsConfig {
+            mode: MapMode::Static,
+            tile_url: format!("{}/{{z}}/{{x}}/{{y}}.png", host),
+            width: 256,
+            height: 256,
+            ..MapsConfig::default()
+        };
+
+        let html = Shortcodes::new(maps.clone(), temp.path()).expand("{{< map 0 0 1 >}}\n", Path::new("post.md"));
+        let mut requested = server.join().unwrap();
+        requested.sort();
+        assert_eq!(requested, [
+            "GET /1/0/0.png HTTP/1.1",
+            "GET /1/0/1.png HTTP/1.1",
+            "GET /1/1/0.png HTTP/1.1",
+            "GET /1/1/1.png HTTP/1.1",
+        ]);
+
+        let src = Regex::new(r#"<img src="/maps/(\w+\.png)""#).unwrap()
+            .captures(&html).expect("static map markup")[1].to_string();
+        assert!(!html.contains("<script>"));
+        let published = image::open(temp.path().join("maps").join(&src)).unwrap().to_rgba8();
+        assert_eq!(published.dimensions(), (256, 256));
+        assert_eq!(published.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+        assert_eq!(published.get_pixel(128, 128), &Rgba([214, 40, 40, 255]));
+
+        // The tile server is gone, so a second build must come from the cache
+        fs::remove_dir_all(temp.path().join("maps")).unwrap();
+        let again = Shortcodes::new(maps, temp.path()).expand("{{< map 0 0 1 >}}\n", Path::new("post.md"));
+        assert_eq!(again, html);
+        assert!(temp.path().join("maps").join(&src).exists());
+    }
+}
@@ -0,0 +1,2109 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use log::{error, info, warn};
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+
+use crate::{
+    config::{CliArgs, BuildConfig, DuplicateUrls, SiteConfig, load_site_config},
+    defaults::FrontMatterDefaults,
+    apidoc::generate_api_docs,
+    redirects::{generate_redirects, Redirect},
+    releases::{generate_releases, load_releases},
+    taxonomy::generate_taxonomies,
+    sections::{self, generate_sections, SectionIndexes, SECTION_INDEX_FILE},
+    authors::{generate_author_pages, Authors},
+    git_dates::GitDates,
+    badges::generate_badges,
+    graph::{DependencyGraph, GraphInputs},
+    report::{write_report, Finding, Level, ReportFormat},
+    dashboard::{generate_dashboard, PageMetrics},
+    history::{self, snapshot, BuildLog, BuildRecord},
+    csp::{inject_csp_meta, write_headers_file, ContentSecurityPolicy},
+    caching::HeadersManifest,
+    host_headers::{HostFormat, HostHeaders},
+    assets::{AssetManifest, Assets, InlineAssets},
+    remote_cache::RemoteCache,
+    shard::{merge_shards, ShardManifest, SiteOutputs},
+    search::{self, PushOptions, SearchDocument},
+    webmentions,
+    hooks::{Hook, HookEnv},
+    plugins::{ContentTransformer, HtmlPostProcessor, OutputGenerator, PageInfo, Plugins, SiteInfo},
+    shortcodes::Shortcodes,
+    theme::ThemeColors,
+    themes::{ComponentDirs, Theme},
+    conditionals::Conditions,
+    variants::{Variant, VariantManifest},
+    seo::{load_seo_config, PageSEO, SEOConfig},
+    html::{update_page_seo, HtmlGenerator},
+    i18n::{alternate_link_tags, output_page_path, Translations, TranslationIndex},
+    seo_html::{inject_meta_tags, strip_seo_macros, SeoInjector},
+    seo_gen::{generate_rss, RobotsTxt, RssFeed, Sitemap},
+    minify::Minifier,
+    pretty::pretty_html,
+    analyzer::{Analyzer, LinkReport, PageMetadata, Pass, PassExclusions},
+    variables::{data_dir, load_variables, DirectoryVariables, Variables, DIR_VARS_FILE},
+    macros::{base_path, prefix_root_links, MacroProcessor},
+    loops::Collections,
+    watcher::{MemoryPages, PageFindings, Rebuilt},
+    error_handler::PageError,
+    markdown::{is_authored_by, BlogPost},
+    trace::PipelineTrace,
+    template_lint::{unresolved_placeholders, LintIssue, Severity, TemplateLinter},
+    BlogProcessor,
+};
+
+/// The generator as a library: the same pipeline as the command line, set up
+/// from the same arguments, with any stage's component replaceable.
+///
+/// ```no_run
+/// use clap::Parser;
+/// use eldroid_ssg::{CliArgs, Site};
+///
+/// let args = CliArgs::parse_from(["eldroid-ssg", "--input-dir", "content", "--output-dir", "public"]);
+/// let build = Site::new(args)?.build()?;
+/// for post in build.posts() {
+///     println!("{} -> {}", post.front_matter.title, post.url);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Site {
+    args: CliArgs,
+    config: BuildConfig,
+    html_gen: Arc<HtmlGenerator>,
+    minifier: Option<Minifier>,
+    analyzer: Option<Analyzer>,
+    seo_config: Option<SEOConfig>,
+    perf_dir: String,
+    /// Where each page's findings go as it is built, for the dev server
+    page_findings: Option<PageFindings>,
+    plugins: Plugins,
+}
+
+impl Site {
+    /// A site configured by `args` and the configuration files they name.
+    /// Fails when the variables or data files can't be loaded or the
+    /// `[minify]` section is invalid.
+    pub fn new(args: CliArgs) -> Result<Self> {
+        let config = BuildConfig::from(&args);
+        Ok(Self {
+            html_gen: load_html_generator(&args)?,
+            minifier: load_minifier(&args, &config)?,
+            analyzer: load_analyzer(&args, &config)?,
+            seo_config: load_seo(&args, &config),
+            perf_dir: format!("{}/performance", args.output_dir),
+            page_findings: None,
+            plugins: Plugins::default(),
+            config,
+            args,
+        })
+    }
+
+    /// Render with this generator, e.g. one with more variables or macros.
+    pub fn with_html_generator(mut self, html_gen: Arc<HtmlGenerator>) -> Self {
+        self.html_gen = html_gen;
+        self
+    }
+
+    pub fn with_minifier(mut self, minifier: Option<Minifier>) -> Self {
+        self.minifier = minifier;
+        self
+    }
+
+    pub fn with_analyzer(mut self, analyzer: Option<Analyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    pub fn with_seo_config(mut self, seo_config: Option<SEOConfig>) -> Self {
+        self.seo_config = seo_config;
+        self
+    }
+
+    pub fn with_page_findings(mut self, page_findings: Option<PageFindings>) -> Self {
+        self.page_findings = page_findings;
+        self
+    }
+
+    /// Add a stage that rewrites each page's source before it is rendered.
+    pub fn with_content_transformer(mut self, transformer: impl ContentTransformer + 'static) -> Self {
+        self.plugins.transformers.push(Arc::new(transformer));
+        self
+    }
+
+    /// Add a stage that rewrites each page's HTML before it is minified.
+    pub fn with_html_post_processor(mut self, processor: impl HtmlPostProcessor + 'static) -> Self {
+        self.plugins.post_processors.push(Arc::new(processor));
+        self
+    }
+
+    /// Add a generator of site-wide files, run after the sitemap and feeds.
+    pub fn with_output_generator(mut self, generator: impl OutputGenerator + 'static) -> Self {
+        self.plugins.generators.push(Arc::new(generator));
+        self
+    }
+
+    pub fn args(&self) -> &CliArgs {
+        &self.args
+    }
+
+    pub fn config(&self) -> &BuildConfig {
+        &self.config
+    }
+
+    fn pipeline(&self) -> Pipeline<'_> {
+        Pipeline {
+            args: &self.args,
+            config: &self.config,
+            html_gen: &self.html_gen,
+            minifier: &self.minifier,
+            analyzer: &self.analyzer,
+            seo_config: &self.seo_config,
+            perf_dir: &self.perf_dir,
+            plugins: &self.plugins,
+        }
+    }
+
+    /// Build every page and the site-wide files into the output directory,
+    /// and record the build in the build log.
+    pub fn build(&self) -> Result<Build> {
+        process_files(self.pipeline(), self.page_findings.as_ref(), &[])
+    }
+
+    /// Combine the outputs of `--shard` builds into the output directory and
+    /// generate the site-wide files.
+    pub fn merge(&self, shard_dirs: &[PathBuf]) -> Result<()> {
+        merge(shard_dirs, self.pipeline())
+    }
+
+    /// Run one content file through the pipeline without writing it,
+    /// recording each stage in `trace`. Paths relative to the input
+    /// directory are accepted too.
+    pub fn render_page(&self, file: &Path, trace: &mut PipelineTrace) -> Result<RenderedPage> {
+        let file_path = if file.starts_with(&self.args.input_dir) {
+            file.to_path_buf()
+        } else {
+            Path::new(&self.args.input_dir).join(file)
+        };
+        if !file_path.is_file() {
+            return Err(anyhow!("{} is not a content file", file_path.display()));
+        }
+        let content_files = content_files(Path::new(&self.args.input_dir));
+        let ctx = PageContext::new(self.pipeline(), &content_files)?;
+        ctx.render_page(&file_path, None, trace)
+    }
+}
+
+/// What a build produced.
+#[derive(Debug, Default)]
+pub struct Build {
+    /// Listed pages, as paths in the output directory
+    pages: Vec<PathBuf>,
+    page_seo: HashMap<PathBuf, PageSEO>,
+    posts: Vec<BlogPost>,
+    /// Analyzer errors and warnings
+    pub warnings: usize,
+}
+
+impl Build {
+    /// Output files of the pages in the sitemap, including generated ones
+    /// such as taxonomy and author pages.
+    pub fn pages(&self) -> impl Iterator<Item = &Path> {
+        self.pages.iter().map(PathBuf::as_path)
+    }
+
+    /// Blog posts in listing order, newest first.
+    pub fn posts(&self) -> impl Iterator<Item = &BlogPost> {
+        self.posts.iter()
+    }
+
+    /// SEO data of the page written to `out_path`.
+    pub fn seo(&self, out_path: &Path) -> Option<&PageSEO> {
+        self.page_seo.get(out_path)
+    }
+}
+
+/// Content files, `.html` and `.md`, anywhere under `dir`, besides the
+/// `_index.md` files of sections.
+pub fn content_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(content_files(&path));
+            } else if path.is_file() && path.extension().is_some_and(|ext| ext == "html" || ext == "md")
+                && path.file_name().is_some_and(|name| name != SECTION_INDEX_FILE) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+fn load_analyzer(args: &CliArgs, config: &BuildConfig) -> Result<Option<Analyzer>> {
+    let site_config = load_site_config(&args.site_config)?;
+    let wants_csp = site_config.csp.is_some();
+    if config.analyze_performance
+        || config.security_checks
+        || config.check_links
+        || config.enable_seo
+        || config.fix_mixed_content
+        || wants_csp
+    {
+        let base_url = load_seo_config(&args.seo_config)
+            .and_then(|cfg| cfg.base_url);
+        // Images are served from the asset directory and the content beside the pages
+        let input_dir = Path::new(&args.input_dir);
+        let site_root = input_dir.parent().unwrap_or(Path::new(""));
+        let assets_dir = site_config.assets.and_then(|assets| assets.dir);
+        let image_dirs = assets_dir.into_iter()
+            .chain([site_root.join("static"), input_dir.join("static"), input_dir.to_path_buf()])
+            .collect();
+        Ok(Some(Analyzer::new(base_url).with_image_dirs(image_dirs)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn load_minifier(args: &CliArgs, config: &BuildConfig) -> Result<Option<Minifier>> {
+    if !config.minify {
+        return Ok(None);
+    }
+    let minify_config = load_site_config(&args.site_config)?.minify;
+    let minifier = Minifier::from_config(&minify_config, Path::new(&args.output_dir))
+        .context("Invalid [minify] configuration")?;
+    Ok(Some(minifier))
+}
+
+fn load_seo(args: &CliArgs, config: &BuildConfig) -> Option<SEOConfig> {
+    if !config.enable_seo {
+        return None;
+    }
+    match load_seo_config(&args.seo_config) {
+        Some(config) => {
+            info!("SEO configuration loaded successfully");
+            Some(config)
+        },
+        None => {
+            error!("Failed to load SEO configuration");
+            None
+        }
+    }
+}
+
+/// The generator with the site's variables and data files. Any of them that
+/// fails to load fails the build, which would otherwise leave every
+/// reference to them unset.
+fn load_html_generator(args: &CliArgs) -> Result<Arc<HtmlGenerator>> {
+    let mut variables = load_variables(&args.variables_config)?;
+    info!("Variables configuration loaded successfully");
+
+    // JSON from `[[remote_data]]` URLs, read like data files
+    let site_config = load_site_config(&args.site_config)?;
+    let theme = site_config.load_theme(&args.site_config)?;
+    let includes = ComponentDirs::new(&args.components_dir, theme.as_ref());
+    let cache_dir = Path::new(&args.output_dir).join("cache");
+    let remote_cache = site_config.remote_cache.as_ref()
+        .filter(|_| !site_config.remote_data.is_empty())
+        .and_then(|cfg| match RemoteCache::from_config(cfg) {
+            Ok(remote_cache) => Some(remote_cache),
+            Err(e) => {
+                warn!("Remote cache disabled for remote data: {}", e);
+                None
+            },
+        });
+    for remote in &site_config.remote_data {
+        match remote.load(&cache_dir, remote_cache.as_ref()) {
+            Ok(value) => variables.insert_data(&remote.name, value),
+            Err(e) => error!("{:#}", e),
+        }
+    }
+
+    Ok(Arc::new(
+        HtmlGenerator::new()
+            .with_variables(variables)
+            .with_macros(MacroProcessor::new().with_includes(includes).with_base_path(site_base_path(args)))
+            .with_dev_mode(args.watch)
+    ))
+}
+
+/// Path the built site is served under, from the SEO `base_url`. The dev
+/// server serves the site at its root, so it's empty in watch mode.
+fn site_base_path(args: &CliArgs) -> String {
+    if args.watch {
+        return String::new();
+    }
+    base_path(load_seo_config(&args.seo_config).and_then(|seo| seo.base_url).as_deref())
+}
+
+/// Directory of the theme the site configuration selects, if any.
+pub fn theme_dir(args: &CliArgs) -> Result<Option<PathBuf>> {
+    let site_config = load_site_config(&args.site_config)?;
+    Ok(site_config.load_theme(&args.site_config)?.map(|theme| theme.dir))
+}
+
+/// Configuration files the build reads, including the environment-specific
+/// variables files next to the variables config.
+pub fn config_files(args: &CliArgs) -> Vec<PathBuf> {
+    let variables_dir = args.variables_config.parent().unwrap_or(Path::new(""));
+    vec![
+        args.variables_config.clone(),
+        variables_dir.join("variables.dev.toml"),
+        variables_dir.join("variables.prod.toml"),
+        args.site_config.clone(),
+        args.seo_config.clone(),
+    ]
+}
+
+/// Whether `a` and `b` name the same file, even if it no longer exists.
+fn same_file(a: &Path, b: &Path) -> bool {
+    let canonical = |path: &Path| {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        dir.canonicalize().ok().map(|dir| dir.join(path.file_name().unwrap_or_default()))
+    };
+    canonical(a).is_some_and(|a| canonical(b) == Some(a))
+}
+
+/// Public URL of an output file, as listed in the sitemap.
+fn page_url(out_path: &Path, output_dir: &Path, seo_config: &Option<SEOConfig>) -> String {
+    let base_url = seo_config.as_ref().and_then(|seo| seo.base_url.as_deref()).unwrap_or("");
+    let relative = out_path.strip_prefix(output_dir).unwrap_or(out_path);
+    format!("{}/{}", base_url.trim_end_matches('/'), relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Resolve internal links in the output directory against outputs and static assets.
+pub fn check_links(analyzer: &Analyzer, args: &CliArgs) -> Result<LinkReport> {
+    let site_root = Path::new(&args.input_dir).parent().unwrap_or(Path::new(""));
+    let static_dirs = [site_root.join("static"), Path::new(&args.input_dir).join("static")];
+    analyzer.check_links(Path::new(&args.output_dir), &static_dirs)
+}
+
+/// The components a build renders with, borrowed from a `Site` or from an
+/// `IncrementalBuild` and its current state.
+#[derive(Clone, Copy)]
+struct Pipeline<'a> {
+    args: &'a CliArgs,
+    config: &'a BuildConfig,
+    html_gen: &'a Arc<HtmlGenerator>,
+    minifier: &'a Option<Minifier>,
+    analyzer: &'a Option<Analyzer>,
+    seo_config: &'a Option<SEOConfig>,
+    perf_dir: &'a str,
+    plugins: &'a Plugins,
+}
+
+/// Shared state for rendering individual pages.
+pub(crate) struct PageContext<'a> {
+    pub(crate) args: &'a CliArgs,
+    config: &'a BuildConfig,
+    html_gen: &'a HtmlGenerator,
+    minifier: &'a Option<Minifier>,
+    analyzer: &'a Option<Analyzer>,
+    pub(crate) seo_config: &'a Option<SEOConfig>,
+    perf_dir: &'a str,
+    plugins: &'a Plugins,
+    pub(crate) site_config: SiteConfig,
+    /// Theme selected in the site configuration
+    site_theme: Option<Theme>,
+    /// The site's components directory, then the theme's
+    pub(crate) components: ComponentDirs,
+    /// Variables from `_vars.toml` files in content directories
+    dir_vars: DirectoryVariables,
+    /// Title, description, and intro of each directory with an `_index.md`
+    pub(crate) section_indexes: SectionIndexes,
+    /// Titles of the section landing pages by URL path, for breadcrumbs
+    pub(crate) section_titles: BTreeMap<String, String>,
+    /// Checks that fail the page on unresolved references, in strict builds
+    strict_linter: Option<TemplateLinter>,
+    pub(crate) blog_processor: BlogProcessor,
+    translations: Option<Translations>,
+    translation_index: Option<TranslationIndex>,
+    theme: Option<ThemeColors>,
+    remote_cache: Option<RemoteCache>,
+    conditions: Conditions,
+    /// Every post as an item of the `posts` collection for `@for` loops
+    posts: Vec<toml::Value>,
+    analysis_exclusions: PassExclusions,
+    /// Output names of static assets, from the last build unless this one processed them
+    asset_manifest: AssetManifest,
+    /// Small stylesheets and scripts put into the pages, in full builds only
+    inline_assets: InlineAssets,
+    /// Path the site is served under, for rewriting root-relative links
+    base_path: String,
+}
+
+/// A rendered page, ready to be written to `out_path`.
+pub struct RenderedPage {
+    pub out_path: PathBuf,
+    pub content: String,
+    pub seo: Option<PageSEO>,
+    pub aliases: Vec<Redirect>,
+    pub findings: Vec<Finding>,
+    /// Dashboard row, when security or performance analysis ran
+    pub metrics: Option<PageMetrics>,
+    /// Title, description, and canonical URL for the site-wide SEO audit
+    pub metadata: Option<PageMetadata>,
+    /// Policy covering the page's resources, when `[csp]` is configured
+    pub csp: Option<ContentSecurityPolicy>,
+    /// A/B arms declared by the page; the control render lists them all
+    pub variants: Vec<Variant>,
+}
+
+impl<'a> PageContext<'a> {
+    fn new(pipeline: Pipeline<'a>, content_files: &[PathBuf]) -> Result<Self> {
+        let Pipeline { args, config, html_gen, minifier, analyzer, seo_config, perf_dir, plugins } = pipeline;
+        let site_config = load_site_config(&args.site_config)?;
+        let site_theme = site_config.load_theme(&args.site_config)?;
+        let components = ComponentDirs::new(&args.components_dir, site_theme.as_ref());
+        let mut dir_vars = DirectoryVariables::load(Path::new(&args.input_dir))?;
+        let section_indexes = SectionIndexes::load(Path::new(&args.input_dir), &site_config.sections, site_config.slugs())?;
+        section_indexes.add_variables(&mut dir_vars);
+        let strict_linter = (config.strict || site_config.strict).then(|| {
+            TemplateLinter::new(&args.components_dir).with_theme(site_theme.as_ref())
+        });
+        let defaults = FrontMatterDefaults::from_config(&site_config.defaults)?;
+        let analysis_exclusions = PassExclusions::from_config(&site_config.analysis.exclude)?;
+        let remote_cache = site_config.remote_cache.as_ref().and_then(|cfg| match RemoteCache::from_config(cfg) {
+            Ok(remote_cache) => Some(remote_cache),
+            Err(e) => {
+                warn!("Remote cache disabled: {}", e);
+                None
+            },
+        });
+        let mut blog_processor = BlogProcessor::with_option_components(
+            Path::new(&args.input_dir).to_path_buf(),
+            html_gen.get_variables().clone()
+        )
+        .with_defaults(defaults)
+        .with_shortcodes(Shortcodes::new(site_config.maps.clone(), &args.output_dir).with_remote_cache(remote_cache.clone()))
+        .with_taxonomies(site_config.taxonomies.clone())
+        .with_sections(&site_config.sections)
+        .with_prev_next(site_config.prev_next.clone())
+        .with_excerpt_words(site_config.excerpts.words)
+        .with_authors(site_config.authors.as_ref().map(|authors| Authors::load(authors, site_config.slugs())).transpose()?)
+        .with_slugs(site_config.slugs())
+        .with_comments(site_config.comments.clone())
+        .with_plugins(plugins.clone())
+        .with_theme(site_theme.clone())
+        .with_components_dir(&args.components_dir)
+        .with_layout(site_config.blog_layout.clone())
+        .with_dir_vars(dir_vars.clone())
+        .with_macros(html_gen.get_macro_processor().clone())
+        .with_git_dates(site_config.dates.git.then(|| GitDates::load(Path::new(&args.input_dir))).flatten());
+
+        // Multilingual content: UI strings and the page translation index
+        let i18n = site_config.i18n.as_ref();
+        let translations = i18n.map(Translations::load).transpose()?;
+        let translation_index = i18n.map(|cfg| TranslationIndex::build(cfg, content_files, Path::new(&args.input_dir), site_config.slugs()));
+        if let Some(cfg) = i18n {
+            blog_processor = blog_processor.with_i18n(cfg.clone());
+        }
+
+        // Load posts for next/prev navigation
+        blog_processor.load_posts()?;
+        let posts = blog_processor.posts().iter().map(BlogPost::listing_entry).collect();
+
+        let theme = html_gen.get_variables().as_ref()
+            .map(ThemeColors::from_variables)
+            .transpose()?
+            .flatten();
+        let asset_manifest = match &site_config.assets {
+            Some(_) => AssetManifest::load(Path::new(&args.output_dir)).unwrap_or_default(),
+            None => AssetManifest::default(),
+        };
+
+        Ok(Self {
+            args,
+            config,
+            html_gen,
+            minifier,
+            analyzer,
+            seo_config,
+            perf_dir,
+            plugins,
+            site_config,
+            site_theme,
+            components,
+            dir_vars,
+            section_titles: section_indexes.titles(),
+            section_indexes,
+            strict_linter,
+            blog_processor,
+            translations,
+            translation_index,
+            theme,
+            remote_cache,
+            conditions: Conditions::from_env(args.watch),
+            posts,
+            analysis_exclusions,
+            asset_manifest,
+            inline_assets: InlineAssets::default(),
+            base_path: site_base_path(args),
+        })
+    }
+
+    /// Output path of a content file relative to the output directory: under
+    /// a language prefix for translated content, where its section's
+    /// permalink puts a post, with `.html` for Markdown, and at the slug of
+    /// its path for pages when `slug_paths` is on.
+    fn out_relative(&self, file_path: &Path) -> Result<PathBuf> {
+        let is_markdown = file_path.extension().is_some_and(|ext| ext == "md");
+        if is_markdown {
+            if let Some(url) = self.blog_processor.permalink_of_file(file_path)? {
+                return Ok(sections::output_path(&url));
+            }
+        }
+        let relative_path = file_path.strip_prefix(&self.args.input_dir)?;
+        let out_relative = match &self.site_config.i18n {
+            Some(cfg) => cfg.output_path(&cfg.localize(relative_path)),
+            None => relative_path.to_path_buf(),
+        };
+        Ok(output_page_path(&out_relative, self.site_config.slugs()))
+    }
+
+    /// Content files that would be written where one before them in path
+    /// order is. Any fail the build unless `duplicate_urls = "warn"`, which
+    /// skips them instead. Files whose output path can't be worked out are
+    /// left for rendering to report.
+    fn duplicate_outputs(&self, files: &[PathBuf]) -> Result<HashSet<PathBuf>> {
+        let mut files: Vec<&PathBuf> = files.iter().collect();
+        files.sort();
+        let mut owners: HashMap<PathBuf, &PathBuf> = HashMap::new();
+        let mut duplicates = HashSet::new();
+        let mut conflicts = Vec::new();
+        for file in files {
+            let Ok(out_relative) = self.out_relative(file) else { continue };
+            match owners.get(&out_relative) {
+                Some(owner) => {
+                    conflicts.push(format!("{} and {} both write {}", owner.display(), file.display(), out_relative.display()));
+                    duplicates.insert(file.clone());
+                },
+                None => {
+                    owners.insert(out_relative, file);
+                },
+            }
+        }
+        match self.site_config.duplicate_urls {
+            DuplicateUrls::Error if !conflicts.is_empty() => Err(anyhow!(
+                "{} content file(s) would overwrite another\n  {}",
+                conflicts.len(),
+                conflicts.join("\n  ")
+            )),
+            _ => {
+                for conflict in &conflicts {
+                    warn!("Skipping duplicate output: {}", conflict);
+                }
+                Ok(duplicates)
+            },
+        }
+    }
+
+    /// Run a single content file through the pipeline, recording each stage in `trace`.
+    /// With `variant`, render that A/B arm of the page instead of its control.
+    fn render_page(&self, file_path: &Path, variant: Option<&str>, trace: &mut PipelineTrace) -> Result<RenderedPage> {
+        let args = self.args;
+        let i18n = self.site_config.i18n.as_ref();
+
+        // Read content
+        let content = trace.stage("read", || fs::read_to_string(file_path))?;
+        trace.detail(format!("{} ({} bytes)", file_path.display(), content.len()));
+        let relative_path = file_path.strip_prefix(&args.input_dir)?;
+        let strict_source = self.strict_linter.is_some().then(|| content.clone());
+
+        let (localized, alternates) = trace.stage("i18n", || {
+            let localized = i18n.map(|cfg| cfg.localize(relative_path));
+            let alternates = match (&self.translation_index, &localized, self.seo_config) {
+                (Some(index), Some(localized), Some(seo)) => {
+                    index.alternates(localized, seo.base_url.as_deref().unwrap_or(""))
+                },
+                _ => Vec::new(),
+            };
+            (localized, alternates)
+        });
+        match &localized {
+            Some(localized) => trace.detail(format!("language: {}", localized.language)),
+            None => trace.detail("not configured"),
+        }
+        for alternate in &alternates {
+            trace.detail(format!("alternate {}: {}", alternate.hreflang, alternate.href));
+        }
+
+        // Process content based on file type
+        let mut aliases = Vec::new();
+        let mut supplied_violations = Vec::new();
+        let mut template_findings = Vec::new();
+        let mut variants = Vec::new();
+        let mut arm = None;
+        let mut page_fields = HashMap::new();
+        let is_markdown = file_path.extension().is_some_and(|ext| ext == "md");
+        let (mut processed_content, mut seo_data) = if is_markdown {
+            let mut post = trace.stage("front matter", || self.blog_processor.read_post(file_path))?;
+
+            // A/B variants: the first arm is served at the page's own URL
+            variants = Variant::parse_all(&post.front_matter.variants)?;
+            arm = match variant {
+                Some(name) => Some(variants.iter().find(|v| v.name == name).cloned()
+                    .ok_or_else(|| anyhow!("{} has no variant '{}'", file_path.display(), name))?),
+                None => variants.first().cloned(),
+            };
+            if let Some(arm) = &arm {
+                post = trace.stage("variant", || self.blog_processor.read_post_with(file_path, &arm.overrides))?;
+                trace.detail(format!("{} of {} arms", arm.name, variants.len()));
+            }
+
+            if trace.is_enabled() {
+                for pattern in self.blog_processor.defaults().matching(relative_path) {
+                    trace.detail(format!("defaults from \"{}\"", pattern));
+                }
+                trace.detail(format!("title: {}", post.front_matter.title));
+                trace.detail(format!("date: {}", post.front_matter.date));
+                trace.detail(format!("url: {}", post.url));
+            }
+            if variant.is_none() {
+                for alias in &post.front_matter.aliases {
+                    let redirect = Redirect::alias(alias, &post.url);
+                    redirect.validate().with_context(|| format!("Invalid alias in {}", file_path.display()))?;
+                    aliases.push(redirect);
+                }
+            }
+
+            // Structured data from front matter is checked on its own so violations name their source
+            if let (Some(analyzer), Some(data)) = (self.analyzer, &post.front_matter.structured_data) {
+                if self.config.enable_seo {
+                    supplied_violations = analyzer.validate_json_ld(data);
+                }
+            }
+
+            page_fields = post.loop_fields()?;
+            let rendered = trace.stage("layout", || self.blog_processor.process_post(&post))?;
+            let layout = self.blog_processor.layout_of(&post);
+            trace.detail(format!("layout: {}", layout.display()));
+            if !rendered.missing_fields.is_empty() {
+                let fields: Vec<String> = rendered.missing_fields.iter().map(|field| format!("page.{}", field)).collect();
+                trace.detail(format!("missing {}", fields.join(", ")));
+                template_findings.extend(fields.iter().map(|field| Finding::new("missing_field", Level::Warning, file_path,
+                    format!("{} is used by {} but not set", field, layout.display()))));
+            }
+            let mut html = rendered.html;
+            if let Some(arm) = &arm {
+                html = arm.substitute(&html);
+            }
+            if !alternates.is_empty() {
+                html = inject_meta_tags(&html, &alternate_link_tags(&alternates));
+            }
+            let mut seo = post.page_seo();
+            seo.section_titles = self.section_titles.clone();
+            (html, Some(seo))
+        } else {
+            // Plugins rewrite the source before it is rendered
+            let mut content = content;
+            for transformer in &self.plugins.transformers {
+                content = trace.stage(transformer.name(), || transformer.transform(file_path, &content))?;
+            }
+
+            if trace.is_enabled() {
+                trace.stage("variables", || ());
+                let vars = self.html_gen.get_variables();
+                for name in Variables::references(&content) {
+                    match vars.as_ref().and_then(|vars| vars.resolve(&name)) {
+                        Some((value, scope)) => trace.detail(format!("{} = {} ({})", name, value, scope)),
+                        None => trace.detail(format!("{} is undefined", name)),
+                    }
+                }
+            }
+
+            let dates = self.blog_processor.git_dates().and_then(|git| git.get(file_path));
+            let dir_vars = self.dir_vars.for_page(relative_path);
+            let page = trace.stage("render", || self.html_gen.generate_page(&content, file_path, &self.components, &dir_vars))
+                .map_err(|e| anyhow!("{} in {}", e, file_path.display()))?;
+            if let Some(layout) = &page.layout {
+                trace.detail(format!("layout: {}", self.components.resolve(layout).display()));
+            }
+            let mut seo_data = page.seo.map(|seo| PageSEO { section_titles: self.section_titles.clone(), ..seo });
+            let html = match self.seo_config {
+                Some(seo) => {
+                    trace.detail("SEO tags updated");
+                    update_page_seo(&page.html, seo_data.clone(), seo, &alternates, dates)
+                },
+                None => strip_seo_macros(&page.html),
+            };
+            if let Some(dates) = dates {
+                trace.detail(format!("modified {} (git)", dates.modified.to_rfc3339()));
+                dates.apply(seo_data.get_or_insert_with(PageSEO::default));
+            }
+            (html, seo_data)
+        };
+
+        let out_relative = self.out_relative(file_path)?;
+        let out_path = Path::new(&args.output_dir).join(&out_relative);
+        let out_path = match (variant, &arm) {
+            (Some(_), Some(arm)) => arm.output_path(&out_path),
+            _ => out_path,
+        };
+
+        // Posts place SEO tags with macros in the blog layout
+        if is_markdown {
+            let page = PageInfo { source: file_path, output: &out_path, seo: seo_data.as_ref() };
+            let seo = SeoInjector::new(self.seo_config.as_ref());
+            processed_content = seo.process(&page, processed_content)?;
+        }
+        if let Some(seo_data) = seo_data.as_mut() {
+            seo_data.alternates = alternates;
+        }
+
+        // Resolve @{t("key")} UI strings for the page language
+        if let (Some(translations), Some(localized)) = (&self.translations, &localized) {
+            processed_content = trace.stage("translations", || translations.substitute(&processed_content, &localized.language));
+        }
+
+        // Resolve @{color("name")} brand colors
+        if let Some(theme) = &self.theme {
+            processed_content = trace.stage("theme colors", || theme.substitute(&processed_content));
+        }
+
+        // Repeat @for(...) { } blocks over posts, the post's front matter lists, and list variables
+        let collections = Collections::new(&self.posts, self.html_gen.get_variables().as_ref()).with_page(&page_fields);
+        processed_content = trace.stage("loops", || collections.apply(&processed_content))
+            .map_err(|e| anyhow!("{} in {}", e, file_path.display()))?;
+
+        // Keep the @{if ...} branches that hold for this build's date and environment
+        processed_content = trace.stage("conditionals", || self.conditions.apply(&processed_content))
+            .map_err(|e| anyhow!("{} in {}", e, file_path.display()))?;
+        trace.detail(format!("environment: {}", self.conditions.environment()));
+
+        // Advertise where other sites send webmentions
+        if let Some(link) = self.site_config.webmentions.as_ref().and_then(|webmentions| webmentions.link_tag()) {
+            if processed_content.contains("</head>") {
+                processed_content = trace.stage("webmentions", || inject_meta_tags(&processed_content, &link));
+            }
+        }
+
+        // Upgrade http:// subresources so HTTPS pages don't load mixed content
+        if let (true, Some(analyzer)) = (self.config.fix_mixed_content, self.analyzer) {
+            let allowlist = &self.site_config.analysis.http_allowlist;
+            let (fixed, upgraded) = trace.stage("mixed content", || analyzer.fix_mixed_content(&processed_content, allowlist));
+            if !upgraded.is_empty() {
+                info!("Upgraded {} http:// URLs to https in {}", upgraded.len(), file_path.display());
+            }
+            for url in &upgraded {
+                trace.detail(format!("upgraded {}", url));
+            }
+            processed_content = fixed;
+        }
+
+        // Strict builds fail rather than publish placeholders
+        if let (Some(linter), Some(source)) = (&self.strict_linter, &strict_source) {
+            let issues = trace.stage("strict", || {
+                let mut issues: Vec<LintIssue> = linter.lint_template(file_path, source).into_iter()
+                    .filter(|issue| issue.severity == Severity::Error)
+                    .collect();
+                let reported: HashSet<usize> = issues.iter().map(|issue| issue.line).collect();
+                issues.extend(unresolved_placeholders(file_path, source, &out_path, &processed_content).into_iter()
+                    .filter(|issue| issue.file != file_path || !reported.contains(&issue.line)));
+                issues.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+                issues
+            });
+            if !issues.is_empty() {
+                let lines: Vec<String> = issues.iter().map(|issue| format!("  {}", issue)).collect();
+                return Err(anyhow!("Strict mode: {} unresolved reference(s) in {}\n{}", issues.len(), file_path.display(), lines.join("\n")));
+            }
+        }
+
+        // Point references to static assets at their output names and inline
+        // the small ones, so analysis sees the requests the page really makes
+        processed_content = self.place_assets(&out_relative, processed_content, trace);
+
+        // Run analysis if enabled
+        let mut findings = template_findings;
+        let mut metrics = None;
+        let mut metadata = None;
+        if let Some(analyzer) = self.analyzer {
+            let mut perf_measurements = None;
+            let skipped = self.analysis_exclusions.disabled(relative_path, &processed_content);
+            let runs = |pass: Pass| !skipped.contains(&pass);
+            trace.stage("analysis", || -> Result<()> {
+                if self.config.security_checks && runs(Pass::Security) {
+                    let security_report = analyzer.analyze_security(&processed_content, file_path);
+                    if !security_report.mixed_content.is_empty() {
+                        error!("Mixed content found in {}: {:?}", file_path.display(), security_report.mixed_content);
+                    }
+                    if !security_report.insecure_links.is_empty() {
+                        error!("Insecure links found in {}: {:?}", file_path.display(), security_report.insecure_links);
+                    }
+                    findings.extend(security_report.mixed_content.iter()
+                        .map(|url| Finding::new("mixed_content", Level::Error, file_path, format!("Mixed content: {}", url))));
+                    findings.extend(security_report.insecure_links.iter()
+                        .map(|url| Finding::new("insecure_links", Level::Error, file_path, format!("Insecure link: {}", url))));
+                }
+
+                if self.config.enable_seo && runs(Pass::Seo) {
+                    metadata = Some(analyzer.extract_metadata(&processed_content));
+                    for violation in &supplied_violations {
+                        warn!("Front matter structured_data in {}: {}", file_path.display(), violation);
+                        findings.push(Finding::new("structured_data", Level::Warning, file_path,
+                            format!("front matter structured_data: {}", violation)));
+                    }
+                    for violation in analyzer.analyze_structured_data(&processed_content).violations {
+                        if supplied_violations.contains(&violation) {
+                            continue;
+                        }
+                        warn!("Structured data in {}: {}", file_path.display(), violation);
+                        findings.push(Finding::new("structured_data", Level::Warning, file_path, violation));
+                    }
+                }
+
+                if self.config.analyze_performance && runs(Pass::Performance) {
+                    let mut perf_report = analyzer.analyze_performance(&processed_content, &out_relative);
+                    findings.extend(perf_report.recommendations.iter()
+                        .map(|rec| Finding::new("performance", Level::Note, file_path, rec.clone())));
+                    perf_measurements = Some((perf_report.blocking_scripts, perf_report.score));
+                    for finding in self.site_config.analysis.budgets.check(&perf_report, file_path) {
+                        perf_report.recommendations.push(finding.message.clone());
+                        findings.push(finding);
+                    }
+                    let no_js_issues = if runs(Pass::NoJs) { analyzer.analyze_no_js(&processed_content).issues() } else { Vec::new() };
+                    for issue in no_js_issues {
+                        warn!("{}: {}", file_path.display(), issue);
+                        findings.push(Finding::new("no_js_fallback", Level::Warning, file_path, issue.clone()));
+                        perf_report.recommendations.push(issue);
+                    }
+                    if self.config.report_format == ReportFormat::Text {
+                        let perf_file = Path::new(self.perf_dir)
+                            .join(file_path.file_name().unwrap())
+                            .with_extension("perf.txt");
+                        fs::write(&perf_file, format!(
+                            "Performance Analysis for {}\n\n{}\n\nRecommendations:\n{}",
+                            file_path.display(),
+                            perf_report.details,
+                            perf_report.recommendations.join("\n")
+                        ))?;
+                    }
+                }
+                Ok(())
+            })?;
+            trace.detail(format!("{} findings", findings.len()));
+            if !skipped.is_empty() {
+                let mut names: Vec<&str> = skipped.iter().map(Pass::as_str).collect();
+                names.sort();
+                trace.detail(format!("skipped {}", names.join(", ")));
+            }
+
+            if self.config.security_checks || self.config.analyze_performance {
+                let mut page_metrics = PageMetrics::new(file_path, processed_content.len(), &findings);
+                if let Some((blocking_scripts, score)) = perf_measurements {
+                    page_metrics.blocking_scripts = blocking_scripts;
+                    page_metrics.score = Some(score);
+                }
+                metrics = Some(page_metrics);
+            }
+        }
+
+        let page = PageInfo { source: file_path, output: &out_path, seo: seo_data.as_ref() };
+        let reparsed = !is_markdown && self.seo_config.is_some();
+        let mut final_content = self.finish_page(&page, processed_content, reparsed, trace)?;
+
+        // Content-Security-Policy for the resources the final page loads
+        let mut csp = None;
+        if let (Some(csp_config), Some(analyzer)) = (&self.site_config.csp, self.analyzer) {
+            let (html, policy) = trace.stage("csp", || {
+                let inventory = analyzer.resource_inventory(&final_content);
+                let policy = ContentSecurityPolicy::from_inventory(&inventory, csp_config);
+                let html = if csp_config.meta {
+                    inject_csp_meta(&final_content, &policy, file_path, &inventory)
+                } else {
+                    final_content.clone()
+                };
+                (html, policy)
+            });
+            trace.detail(policy.to_header_value());
+            final_content = html;
+            csp = Some(policy);
+        }
+
+        trace.stage("output", || ());
+        trace.detail(out_path.display().to_string());
+
+        Ok(RenderedPage {
+            out_path,
+            content: final_content,
+            seo: seo_data,
+            aliases,
+            findings,
+            metrics,
+            metadata,
+            csp,
+            variants: if variant.is_none() { variants } else { Vec::new() },
+        })
+    }
+
+    /// Point references to static assets in a page at `out_relative` at
+    /// their fingerprinted names, then put the small ones into the page.
+    fn place_assets(&self, out_relative: &Path, mut html: String, trace: &mut PipelineTrace) -> String {
+        let page_dir = out_relative.parent().unwrap_or(Path::new("")).to_string_lossy().replace('\\', "/");
+        if !self.asset_manifest.is_empty() {
+            html = trace.stage("assets", || self.asset_manifest.rewrite_html(&html, &page_dir));
+        }
+        if !self.inline_assets.is_empty() {
+            html = trace.stage("inline assets", || self.inline_assets.apply(&html, &page_dir));
+        }
+        html
+    }
+
+    /// Last stages of every page: root-relative links under the base path,
+    /// the post-processors registered on the Site, then minification, or
+    /// indentation for pages that were `reparsed` to update their SEO tags
+    /// and lost their own formatting.
+    fn finish_page(&self, page: &PageInfo, mut html: String, reparsed: bool, trace: &mut PipelineTrace) -> Result<String> {
+        // Keep root-relative links working when the site lives in a subdirectory
+        if self.site_config.rewrite_root_links {
+            html = trace.stage("base path", || prefix_root_links(&html, &self.base_path));
+            trace.detail(if self.base_path.is_empty() { "served at the root" } else { &self.base_path });
+        }
+
+        for processor in &self.plugins.post_processors {
+            html = trace.stage(processor.name(), || processor.process(page, html))?;
+        }
+        Ok(if let Some(minifier) = self.minifier {
+            let size = html.len();
+            let minified = trace.stage(minifier.name(), || minifier.process(page, html))?;
+            trace.detail(format!("{} -> {} bytes", size, minified.len()));
+            minified
+        } else if self.config.pretty_html && reparsed {
+            trace.stage("pretty", || pretty_html(&html))
+        } else {
+            html
+        })
+    }
+
+    /// Put a page the build generated itself, like a section listing, at
+    /// `out_path` through the stages of content pages after rendering: SEO
+    /// tags from `seo`, asset names, post-processors, and minification.
+    pub(crate) fn render_generated(&self, source: &Path, out_path: &Path, html: String, seo: PageSEO) -> Result<String> {
+        let trace = &mut PipelineTrace::disabled();
+        let html = match self.seo_config {
+            Some(site_seo) => update_page_seo(&html, Some(seo.clone()), site_seo, &[], None),
+            None => strip_seo_macros(&html),
+        };
+        let out_relative = out_path.strip_prefix(&self.args.output_dir).unwrap_or(out_path);
+        let html = self.place_assets(out_relative, html, trace);
+        let page = PageInfo { source, output: out_path, seo: Some(&seo) };
+        self.finish_page(&page, html, self.seo_config.is_some(), trace)
+    }
+}
+
+/// Combine `--shard` build outputs and generate the site-wide files, which
+/// need the content directory for feeds and badges.
+fn merge(shard_dirs: &[PathBuf], pipeline: Pipeline) -> Result<()> {
+    let args = pipeline.args;
+    let mut outputs = merge_shards(shard_dirs, Path::new(&args.output_dir))?;
+    let content_files = content_files(Path::new(&args.input_dir));
+    let ctx = PageContext::new(pipeline, &content_files)?;
+    info!("Merged {} pages from {} shards", outputs.pages.len(), shard_dirs.len());
+    write_site_files(&ctx, &mut outputs)?;
+    ctx.site_config.hooks.run(Hook::AfterBuild, &HookEnv::new(args))
+}
+
+/// Build the site between the `before_build` and `after_build` hooks, and
+/// record the outcome in the build log. `changed` are the files that started
+/// a watch-mode rebuild.
+fn process_files(pipeline: Pipeline, page_findings: Option<&PageFindings>, changed: &[PathBuf]) -> Result<Build> {
+    let Pipeline { args, config, .. } = pipeline;
+    let started = Instant::now();
+    let hook_env = HookEnv::new(args).with_changed_files(changed);
+    let result = load_site_config(&args.site_config).and_then(|site| {
+        site.hooks.run(Hook::BeforeBuild, &hook_env)
+            .and_then(|()| build_site(pipeline, page_findings, &hook_env))
+            .and_then(|build| site.hooks.run(Hook::AfterBuild, &hook_env).map(|()| build))
+    });
+
+    let cache_dir = format!("{}/cache", args.output_dir);
+    let summary = match &result {
+        Ok(build) => Ok(build.warnings),
+        Err(e) => Err(anyhow!("{}", e)),
+    };
+    let record = BuildRecord::new(config_hash(args, config), started.elapsed().as_millis(), &summary);
+    let inputs = snapshot(&[
+        Path::new(&args.input_dir),
+        Path::new(&args.components_dir),
+        &args.variables_config,
+        &data_dir(&args.variables_config),
+        &args.site_config,
+        &args.seo_config,
+    ], None);
+    let outputs = snapshot(&[Path::new(&args.output_dir)], Some(Path::new(&cache_dir)));
+    if let Err(e) = BuildLog::new(&cache_dir).append(record, inputs, outputs) {
+        warn!("Failed to record build in {}: {}", BuildLog::FILE_NAME, e);
+    }
+
+    result
+}
+
+/// Hash of the build flags and configuration files, to tell builds apart in the log.
+pub fn config_hash(args: &CliArgs, config: &BuildConfig) -> String {
+    let mut bytes = format!("{:?}", config).into_bytes();
+    for path in [&args.variables_config, &args.site_config, &args.seo_config] {
+        bytes.extend(fs::read(path).unwrap_or_default());
+    }
+    history::hash(&bytes)
+}
+
+/// Render every page and generate site-wide files; returns the pages and
+/// posts built, and the number of analyzer errors and warnings. Each page's
+/// findings also go to `page_findings`, for the dev server, and the
+/// `after_page` hooks run with `hook_env` as each page is written.
+fn build_site(pipeline: Pipeline, page_findings: Option<&PageFindings>, hook_env: &HookEnv) -> Result<Build> {
+    let Pipeline { args, config, minifier, analyzer, seo_config, perf_dir, .. } = pipeline;
+    let processed_files = Arc::new(Mutex::new(Vec::new()));
+    let page_seo = Arc::new(Mutex::new(HashMap::new()));
+    let aliases = Arc::new(Mutex::new(Vec::new()));
+    let findings = Arc::new(Mutex::new(Vec::new()));
+    let metrics = Arc::new(Mutex::new(Vec::new()));
+    let metadata = Arc::new(Mutex::new(Vec::new()));
+    let site_csp = Arc::new(Mutex::new(ContentSecurityPolicy::default()));
+    let search_documents = Arc::new(Mutex::new(Vec::new()));
+    let variant_manifest = Arc::new(Mutex::new(VariantManifest::default()));
+    let content_files = content_files(Path::new(&args.input_dir));
+    let mut ctx = PageContext::new(pipeline, &content_files)?;
+
+    // Name static assets before rendering the pages that reference them
+    let assets = ctx.site_config.assets.as_ref()
+        .map(|assets_config| Assets::process(assets_config, Path::new(&args.input_dir), ctx.site_theme.as_ref(), minifier.as_ref()))
+        .transpose()?;
+    if let Some(assets) = &assets {
+        for (stylesheet, reference) in &assets.unresolved {
+            warn!("{} references {}, which isn't a static asset", stylesheet, reference);
+        }
+        ctx.asset_manifest = assets.manifest.clone();
+        // The dev server rebuilds pages without the assets' content
+        let inline_limit = ctx.site_config.assets.as_ref().and_then(|config| config.inline_limit);
+        if let Some(limit) = inline_limit.filter(|_| !args.watch) {
+            ctx.inline_assets = assets.inlinable(limit)?;
+        }
+    }
+    let ctx = ctx;
+    let site_config = &ctx.site_config;
+    let blog_processor = &ctx.blog_processor;
+
+    // Strict builds also refuse templates with unknown macros or missing components
+    if let Some(linter) = &ctx.strict_linter {
+        let errors: Vec<String> = linter.lint_all()?.iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| format!("  {}", issue))
+            .collect();
+        if !errors.is_empty() {
+            return Err(anyhow!("Strict mode: {} error(s) in templates\n{}", errors.len(), errors.join("\n")));
+        }
+    }
+
+    // Contributor mode: only build the pages written by one author
+    let mut pages: Vec<&PathBuf> = match args.author.as_deref().filter(|_| args.watch) {
+        Some(author) => {
+            let pages: Vec<_> = content_files.iter()
+                .filter(|path| is_authored_by(path, author))
+                .collect();
+            info!("Building {} of {} pages by {}", pages.len(), content_files.len(), author);
+            pages
+        },
+        None => content_files.iter().collect(),
+    };
+
+    // Sharded CI builds: only this job's slice of the pages
+    if let Some(shard) = args.shard {
+        pages.retain(|path| shard.contains(path.strip_prefix(&args.input_dir).unwrap_or(path)));
+        info!("Building {} of {} pages as shard {}", pages.len(), content_files.len(), shard);
+    }
+
+    // Pages built in parallel must not overwrite each other
+    let duplicates = ctx.duplicate_outputs(&content_files)?;
+    pages.retain(|path| !duplicates.contains(*path));
+
+    // A/B arms other than the control are written but kept out of the
+    // sitemap, search index, and metadata audit
+    let write_page = |page: RenderedPage, file_path: &Path, listed: bool| -> Result<()> {
+        if let Some(parent) = page.out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if listed && site_config.search.is_some() && page.out_path.extension().is_some_and(|ext| ext == "html") {
+            let url = page_url(&page.out_path, Path::new(&args.output_dir), seo_config);
+            search_documents.lock().extend(SearchDocument::from_html(&url, &page.content));
+        }
+        fs::write(&page.out_path, page.content)?;
+        site_config.hooks.run(Hook::AfterPage, &hook_env.clone().with_page(file_path, &page.out_path))?;
+        if let Some(page_findings) = page_findings {
+            page_findings.set(page.out_path.clone(), page.findings.clone());
+        }
+        aliases.lock().extend(page.aliases);
+        findings.lock().extend(page.findings);
+        metrics.lock().extend(page.metrics);
+        if let Some(policy) = &page.csp {
+            site_csp.lock().merge(policy);
+        }
+        if !listed {
+            return Ok(());
+        }
+        if let Some(seo_data) = page.seo {
+            page_seo.lock().insert(page.out_path.clone(), seo_data);
+        }
+        if let Some(page_metadata) = page.metadata {
+            metadata.lock().push((file_path.to_path_buf(), page_metadata));
+        }
+        processed_files.lock().push(page.out_path);
+        Ok(())
+    };
+
+    let file_results: Vec<Result<PathBuf>> = pages
+        .par_iter()
+        .map(|file_path| -> Result<PathBuf> {
+            let mut page = ctx.render_page(file_path, None, &mut PipelineTrace::disabled())
+                .context(PageError(file_path.to_path_buf()))?;
+            let out_path = page.out_path.clone();
+            let variants = std::mem::take(&mut page.variants);
+            write_page(page, file_path, true)?;
+
+            if !variants.is_empty() {
+                for arm in variants.iter().skip(1) {
+                    let page = ctx.render_page(file_path, Some(&arm.name), &mut PipelineTrace::disabled())
+                        .context(PageError(file_path.to_path_buf()))?;
+                    write_page(page, file_path, false)?;
+                }
+                variant_manifest.lock().add(Path::new(&args.output_dir), &out_path, &variants);
+            }
+            Ok(out_path)
+        })
+        .collect();
+
+    // Check for errors
+    let mut built = Vec::new();
+    let mut errors = Vec::new();
+    for (file_path, result) in pages.iter().zip(file_results) {
+        match result {
+            Ok(out_path) => built.push(((*file_path).clone(), out_path)),
+            Err(e) => errors.push(e),
+        }
+    }
+    
+    if !errors.is_empty() {
+        let details: Vec<String> = errors.iter().map(|err| format!("  {:#}", err)).collect();
+        if !args.watch {
+            return Err(anyhow!("Some files failed to process:\n{}", details.join("\n")));
+        }
+        // The dev server keeps serving the last good output of these pages
+        // and builds the rest, so one broken file doesn't stop it
+        error!("Failed to process some files:\n{}", details.join("\n"));
+    }
+
+    let page_outputs: Vec<PathBuf> = built.iter().map(|(_, out_path)| out_path.clone()).collect();
+
+    // Layout fields that pages leave unset, one line per field
+    let mut unset: Vec<(String, Vec<String>)> = Vec::new();
+    for finding in findings.lock().iter().filter(|finding| finding.rule == "missing_field") {
+        let file = finding.file.display().to_string();
+        match unset.iter_mut().find(|(message, _)| *message == finding.message) {
+            Some((_, files)) => files.push(file),
+            None => unset.push((finding.message.clone(), vec![file])),
+        }
+    }
+    for (message, mut files) in unset {
+        files.sort();
+        warn!("{} in {} page(s): {}", message, files.len(), files.join(", "));
+    }
+
+    // Site-wide SEO audit across every page's metadata
+    if let Some(analyzer) = analyzer.as_ref().filter(|_| config.enable_seo) {
+        for (file, message) in analyzer.audit_metadata(&metadata.lock()) {
+            warn!("SEO metadata in {}: {}", file.display(), message);
+            findings.lock().push(Finding::new("seo_metadata", Level::Warning, &file, message));
+        }
+    }
+
+    // Pages that got slower or larger than in the baseline build
+    if let Some(regressions) = &site_config.analysis.regressions {
+        let regressed = regressions.check_and_update(&metrics.lock(), Path::new(perf_dir))?;
+        for finding in &regressed {
+            warn!("Performance regression in {}", finding);
+        }
+        findings.lock().extend(regressed);
+    }
+
+    // Structured analysis report for CI and code scanning
+    let mut findings = findings.lock();
+    findings.sort_by(|a, b| a.file.cmp(&b.file));
+    let warnings = findings.iter().filter(|f| f.level != Level::Note).count();
+    write_report(&findings, config.report_format, Path::new(perf_dir))?;
+    if config.security_checks || config.analyze_performance {
+        generate_dashboard(std::mem::take(&mut *metrics.lock()), Path::new(perf_dir))?;
+    }
+    let failures = site_config.analysis.failures(&findings);
+    if !failures.is_empty() {
+        for finding in &failures {
+            error!("Build-failing finding: {}", finding);
+        }
+        return Err(anyhow!("{} analyzer findings exceed the configured thresholds", failures.len()));
+    }
+
+    // Export the dependency graph of the pages just built
+    if let Some(graph_path) = &args.graph {
+        let pages = std::mem::take(&mut built);
+        let translations_dir = site_config.i18n.as_ref().map(|cfg| cfg.translations_dir.as_path());
+        let graph = DependencyGraph::build(&pages, &GraphInputs {
+            components: &ctx.components,
+            post_layout: &|path| blog_processor.layout_of_file(path),
+            variables_config: &args.variables_config,
+            translations_dir,
+        })?;
+        graph.write(graph_path)?;
+    }
+
+    // Pages not built from content files come from the first shard only
+    if args.shard.is_none_or(|shard| shard.is_first()) {
+        // Copy static assets under their output names
+        if let Some(assets) = &assets {
+            assets.write(Path::new(&args.output_dir), &page_outputs)?;
+            info!("Copied {} static assets", assets.manifest.files.len());
+        }
+
+        // Generate the API reference from rustdoc JSON if configured
+        if let Some(api_docs) = &site_config.api_docs {
+            let pages = generate_api_docs(api_docs, Path::new(&args.output_dir), &ctx.components)?;
+            processed_files.lock().extend(pages);
+        }
+
+        // Generate the releases section from git tags or a changelog
+        if let Some(releases_config) = &site_config.releases {
+            let releases = load_releases(releases_config)?;
+            let base_url = seo_config.as_ref().and_then(|seo| seo.base_url.as_deref()).unwrap_or("");
+            let pages = generate_releases(
+                releases_config,
+                &releases,
+                Path::new(&args.output_dir),
+                &ctx.components,
+                base_url,
+            )?;
+            processed_files.lock().extend(pages);
+        }
+
+        // Listing pages and feeds of content sections
+        let pages = generate_sections(&ctx)?;
+        processed_files.lock().extend(pages);
+
+        // Index and feed pages for each taxonomy
+        if !site_config.taxonomies.is_empty() {
+            let base_url = seo_config.as_ref().and_then(|seo| seo.base_url.as_deref()).unwrap_or("");
+            let pages = generate_taxonomies(
+                &site_config.taxonomies,
+                blog_processor.posts(),
+                Path::new(&args.output_dir),
+                &ctx.components,
+                base_url,
+                site_config.slugs(),
+            )?;
+            processed_files.lock().extend(pages);
+        }
+
+        // A page per author profile listing their posts
+        if let Some(authors) = blog_processor.authors() {
+            let pages = generate_author_pages(authors, blog_processor.posts(), Path::new(&args.output_dir), &ctx.components)?;
+            processed_files.lock().extend(pages);
+        }
+    }
+
+    // Brand colors as CSS custom properties
+    if let Some(theme) = &ctx.theme {
+        theme.write_css(Path::new(&args.output_dir))?;
+    }
+
+    let mut outputs = SiteOutputs {
+        pages: std::mem::take(&mut *processed_files.lock()),
+        page_seo: std::mem::take(&mut *page_seo.lock()),
+        aliases: std::mem::take(&mut *aliases.lock()),
+        search_documents: std::mem::take(&mut *search_documents.lock()),
+        csp: std::mem::take(&mut *site_csp.lock()),
+        variants: std::mem::take(&mut *variant_manifest.lock()),
+    };
+
+    // A shard only sees some pages; `merge` writes the site-wide files
+    if let Some(shard) = args.shard {
+        let pages = outputs.pages.clone();
+        ShardManifest::new(shard, outputs, Path::new(&args.output_dir)).write(Path::new(&args.output_dir))?;
+        return Ok(Build { pages, warnings, posts: ctx.blog_processor.into_posts(), ..Build::default() });
+    }
+    write_site_files(&ctx, &mut outputs)?;
+
+    // Report the first broken page now that everything else is in place
+    if let Some(err) = errors.into_iter().next() {
+        return Err(err);
+    }
+    Ok(Build {
+        pages: outputs.pages,
+        page_seo: outputs.page_seo,
+        posts: ctx.blog_processor.into_posts(),
+        warnings,
+    })
+}
+
+/// Generate the files that cover every page: variants manifest, `_headers`,
+/// search index, badges, redirects, sitemap and feeds, and caching and host headers.
+/// Checks internal links last, once every output is in place.
+fn write_site_files(ctx: &PageContext, outputs: &mut SiteOutputs) -> Result<()> {
+    let args = ctx.args;
+    let site_config = &ctx.site_config;
+    let output_dir = Path::new(&args.output_dir);
+
+    // Manifest and edge router for pages with A/B variants
+    if !outputs.variants.is_empty() {
+        outputs.variants.write(output_dir)?;
+    }
+
+    // Site-wide Content-Security-Policy for hosts that read `_headers`,
+    // unless it goes into the host headers written at the end
+    let host_netlify = site_config.host_headers.as_ref()
+        .is_some_and(|host| host.formats.contains(&HostFormat::Netlify));
+    if site_config.csp.as_ref().is_some_and(|csp| csp.headers_file) && !host_netlify {
+        write_headers_file(&outputs.csp, output_dir)?;
+    }
+
+    // Search index file, and hosted indexes updated with the pages that changed
+    if let Some(search_config) = &site_config.search {
+        let documents = &mut outputs.search_documents;
+        documents.sort_by(|a, b| a.url.cmp(&b.url));
+        if search_config.index_file {
+            search::write_index_file(documents, output_dir)?;
+        }
+        // Dev builds may cover only some pages, which would delete the rest
+        if !search_config.push.is_empty() && !args.watch {
+            let cache_dir = output_dir.join("cache");
+            search::write_records(documents, &cache_dir)?;
+            if search_config.push_on_build {
+                search::push_changes(&search_config.push, documents, &cache_dir, ctx.remote_cache.as_ref(), PushOptions::default())?;
+            }
+        }
+    }
+
+    // Outbound links of every post, for `send-webmentions` after deploying
+    if let Some(webmentions_config) = &site_config.webmentions {
+        match load_seo_config(&args.seo_config).and_then(|seo| seo.base_url) {
+            Some(base_url) if !args.watch => {
+                let outbound = webmentions::collect_outbound(ctx.blog_processor.posts(), &base_url, webmentions_config)?;
+                webmentions::write_outbound(&outbound, &output_dir.join("cache"))?;
+            },
+            Some(_) => {},
+            None => warn!("[webmentions] needs a base_url in {} to record outbound links", args.seo_config.display()),
+        }
+    }
+
+    // Generate badge endpoints with site statistics
+    if let Some(badges) = &site_config.badges {
+        generate_badges(badges, ctx.blog_processor.posts(), output_dir)?;
+    }
+
+    // Generate redirects from config rules and page aliases
+    let mut redirects = site_config.redirects.rules.clone();
+    redirects.append(&mut outputs.aliases);
+    generate_redirects(&redirects, &site_config.redirects.formats, output_dir)?;
+
+    // Sitemap, feed, and robots.txt when SEO is enabled, then the generators
+    // registered on the Site
+    let site = SiteInfo {
+        output_dir,
+        pages: &outputs.pages,
+        page_seo: &outputs.page_seo,
+        posts: ctx.blog_processor.posts(),
+        seo_config: ctx.seo_config.as_ref().filter(|_| ctx.config.enable_seo),
+    };
+    let built_in: [&dyn OutputGenerator; 3] = [&Sitemap, &RssFeed, &RobotsTxt];
+    for generator in built_in.into_iter().chain(ctx.plugins.generators.iter().map(|generator| generator.as_ref())) {
+        generator.generate(&site).with_context(|| format!("Failed to generate {}", generator.name()))?;
+    }
+
+    // Minified builds compact the XML and JSON written above too
+    if let Some(minifier) = ctx.minifier {
+        minifier.minify_generated(output_dir)?;
+    }
+
+    // Pagefind indexes the finished pages, and its files get caching headers too
+    if let Some(pagefind) = site_config.search.as_ref().and_then(|search| search.pagefind.as_ref()) {
+        if !args.watch {
+            search::run_pagefind(pagefind, output_dir)?;
+        }
+    }
+
+    // Small assets that every page now has inline aren't published
+    if !ctx.inline_assets.is_empty() {
+        let removed = ctx.inline_assets.remove_unreferenced(output_dir)?;
+        if !removed.is_empty() {
+            info!("Removed {} inlined asset(s) no longer referenced", removed.len());
+        }
+    }
+
+    // Recommended Cache-Control for every output, for deploy adapters and servers
+    let headers = HeadersManifest::build(&site_config.caching, output_dir)?;
+    headers.write(output_dir)?;
+
+    // Security headers and long caching for assets, in the host's own format
+    if let Some(host_config) = &site_config.host_headers {
+        let csp = site_config.csp.is_some().then_some(&outputs.csp);
+        HostHeaders::new(host_config, &site_config.caching, &headers, csp, output_dir)
+            .write(&host_config.formats, output_dir)?;
+    }
+
+    // Resolve internal links once every output is in place
+    if ctx.config.check_links {
+        if let Some(analyzer) = ctx.analyzer {
+            let report = check_links(analyzer, args)?;
+            for link in &report.broken {
+                error!("Broken link in {}", link);
+            }
+            if !report.broken.is_empty() {
+                return Err(anyhow!("{} broken internal links found", report.broken.len()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Blog posts' URLs and titles in listing order. Every post shows this list
+/// and links its neighbors in it.
+fn post_listing(blog_processor: &BlogProcessor) -> Vec<(String, String)> {
+    blog_processor.posts().iter()
+        .map(|post| (post.url.clone(), post.front_matter.title.clone()))
+        .collect()
+}
+
+/// Watch-mode rebuilds of just the pages a change affects.
+pub struct IncrementalBuild {
+    args: CliArgs,
+    config: BuildConfig,
+    minifier: Option<Minifier>,
+    perf_dir: String,
+    plugins: Plugins,
+    state: Mutex<BuildState>,
+    /// Where rebuilt pages go with `--in-memory`, instead of the output directory
+    memory_pages: Option<MemoryPages>,
+    /// Findings of every page built, shown in the dev overlay
+    page_findings: PageFindings,
+}
+
+/// Generator state read from the configuration files, and the post listing
+/// as of the last build.
+struct BuildState {
+    html_gen: Arc<HtmlGenerator>,
+    analyzer: Option<Analyzer>,
+    seo_config: Option<SEOConfig>,
+    listing: Vec<(String, String)>,
+}
+
+impl IncrementalBuild {
+    /// Rebuilds for `site`, whose first full build has already run.
+    pub fn new(site: Site) -> Result<Self> {
+        let Site { args, config, html_gen, minifier, analyzer, seo_config, perf_dir, page_findings, plugins } = site;
+        let state = BuildState { html_gen, analyzer, seo_config, listing: Vec::new() };
+        let memory_pages = args.in_memory.then(MemoryPages::default);
+        let page_findings = page_findings.unwrap_or_default();
+        let build = Self { args, config, minifier, perf_dir, plugins, state: Mutex::new(state), memory_pages, page_findings };
+        let mut state = build.state.lock();
+        let content_files = content_files(Path::new(&build.args.input_dir));
+        state.listing = post_listing(&build.context(&state, &content_files)?.blog_processor);
+        drop(state);
+        Ok(build)
+    }
+
+    pub fn memory_pages(&self) -> Option<MemoryPages> {
+        self.memory_pages.clone()
+    }
+
+    pub fn page_findings(&self) -> PageFindings {
+        self.page_findings.clone()
+    }
+
+    fn pipeline<'a>(&'a self, state: &'a BuildState) -> Pipeline<'a> {
+        Pipeline {
+            args: &self.args,
+            config: &self.config,
+            html_gen: &state.html_gen,
+            minifier: &self.minifier,
+            analyzer: &state.analyzer,
+            seo_config: &state.seo_config,
+            perf_dir: &self.perf_dir,
+            plugins: &self.plugins,
+        }
+    }
+
+    fn context<'a>(&'a self, state: &'a BuildState, content_files: &[PathBuf]) -> Result<PageContext<'a>> {
+        PageContext::new(self.pipeline(state), content_files)
+    }
+
+    /// Rebuild what `changed` affects:
+    /// - a configuration or theme file reloads the generator and rebuilds the whole site
+    /// - a static asset rebuilds the whole site, since its name may change
+    /// - a component or layout re-renders the pages that use it
+    /// - a content page is rendered again, plus every page in the blog
+    ///   directories when the post listing changed (a post was added, removed,
+    ///   retitled, or redated)
+    ///
+    /// Besides full rebuilds, site-wide files other than the RSS feed are left
+    /// for the next full build. With `--in-memory`, only full rebuilds write
+    /// pages; the others keep them in memory for the dev server, and skip the
+    /// `after_page` hooks. Every rebuild runs the build hooks with `changed`.
+    pub fn rebuild(&self, changed: &Path) -> Result<Rebuilt> {
+        let args = &self.args;
+        let mut state = self.state.lock();
+        // @{build_time} and @{now(...)} give this rebuild's time, not the first build's
+        state.html_gen = Arc::new(state.html_gen.as_ref().clone().at(Utc::now()));
+
+        let data_dir = data_dir(&args.variables_config).canonicalize().ok();
+        let theme_dir = theme_dir(args)?.and_then(|dir| dir.canonicalize().ok());
+        if config_files(args).iter().any(|file| same_file(file, changed))
+            || data_dir.is_some_and(|dir| changed.starts_with(dir))
+            || theme_dir.is_some_and(|dir| changed.starts_with(dir))
+        {
+            state.html_gen = load_html_generator(args)?;
+            state.analyzer = load_analyzer(args, &self.config)?;
+            state.seo_config = load_seo(args, &self.config);
+            // Pages that still build are written even when others fail
+            let built = process_files(self.pipeline(&state), Some(&self.page_findings), &[changed.to_path_buf()]);
+            if let Some(memory_pages) = &self.memory_pages {
+                memory_pages.clear();
+            }
+            let content_files = content_files(Path::new(&args.input_dir));
+            state.listing = post_listing(&self.context(&state, &content_files)?.blog_processor);
+            built?;
+            return Ok(Rebuilt::Site);
+        }
+
+        let content_files = content_files(Path::new(&args.input_dir));
+        let ctx = self.context(&state, &content_files)?;
+
+        // A changed asset gets a new name in every page that references it
+        let asset_dir = ctx.site_config.assets.as_ref()
+            .and_then(|assets| assets.dir(Path::new(&args.input_dir)).canonicalize().ok());
+        if asset_dir.is_some_and(|dir| changed.starts_with(dir)) {
+            drop(ctx);
+            let built = process_files(self.pipeline(&state), Some(&self.page_findings), &[changed.to_path_buf()]);
+            if let Some(memory_pages) = &self.memory_pages {
+                memory_pages.clear();
+            }
+            built?;
+            return Ok(Rebuilt::Site);
+        }
+
+        let hooks = &ctx.site_config.hooks;
+        let hook_env = HookEnv::new(args).with_changed_files(&[changed.to_path_buf()]);
+        hooks.run(Hook::BeforeBuild, &hook_env)?;
+
+        let listing = post_listing(&ctx.blog_processor);
+        let listing_changed = state.listing != listing;
+
+        let components_dir = Path::new(&args.components_dir);
+        let component = components_dir.canonicalize().ok()
+            .and_then(|dir| changed.strip_prefix(dir).ok().map(|relative| components_dir.join(relative)));
+        let pages: Vec<PathBuf> = match component {
+            Some(component) => {
+                let graph = DependencyGraph::from_sources(&content_files, &GraphInputs {
+                    components: &ctx.components,
+                    post_layout: &|path| ctx.blog_processor.layout_of_file(path),
+                    variables_config: &args.variables_config,
+                    translations_dir: ctx.site_config.i18n.as_ref().map(|cfg| cfg.translations_dir.as_path()),
+                })?;
+                graph.dependents(&component)
+            },
+            None => {
+                let blog_dirs: HashSet<&Path> = ctx.blog_processor.posts().iter()
+                    .filter_map(|post| post.file_path.parent())
+                    .collect();
+                let changed = changed.canonicalize().unwrap_or_else(|_| changed.to_path_buf());
+                // A _vars.toml or _index.md applies to every page beneath its directory
+                let vars_dir = changed.parent()
+                    .filter(|_| changed.file_name().is_some_and(|name| name == DIR_VARS_FILE || name == SECTION_INDEX_FILE));
+                content_files.iter()
+                    .filter(|path| {
+                        path.canonicalize().is_ok_and(|path| path == changed || vars_dir.is_some_and(|dir| path.starts_with(dir)))
+                            || (listing_changed && path.parent().is_some_and(|dir| blog_dirs.contains(dir)))
+                    })
+                    .cloned()
+                    .collect()
+            },
+        };
+        let pages: Vec<&PathBuf> = pages.iter()
+            .filter(|path| args.author.as_deref().is_none_or(|author| is_authored_by(path, author)))
+            .collect();
+
+        let write = |file_path: &Path, page: RenderedPage| -> Result<PathBuf> {
+            self.page_findings.set(page.out_path.clone(), page.findings);
+            if let Some(memory_pages) = &self.memory_pages {
+                memory_pages.insert(page.out_path.clone(), page.content);
+                return Ok(page.out_path);
+            }
+            if let Some(parent) = page.out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&page.out_path, page.content)?;
+            hooks.run(Hook::AfterPage, &hook_env.clone().with_page(file_path, &page.out_path))?;
+            Ok(page.out_path)
+        };
+        let written: Vec<Vec<PathBuf>> = pages
+            .par_iter()
+            .map(|file_path| -> Result<Vec<PathBuf>> {
+                let mut page = ctx.render_page(file_path, None, &mut PipelineTrace::disabled())
+                    .context(PageError(file_path.to_path_buf()))?;
+                let variants = std::mem::take(&mut page.variants);
+                let mut written = vec![write(file_path, page)?];
+                for arm in variants.iter().skip(1) {
+                    let page = ctx.render_page(file_path, Some(&arm.name), &mut PipelineTrace::disabled())
+                        .context(PageError(file_path.to_path_buf()))?;
+                    written.push(write(file_path, page)?);
+                }
+                Ok(written)
+            })
+            .collect::<Result<_>>()?;
+
+        if listing_changed && self.config.enable_seo {
+            if let Some(seo) = &state.seo_config {
+                generate_rss(ctx.blog_processor.posts(), seo, &args.output_dir)?;
+            }
+        }
+        hooks.run(Hook::AfterBuild, &hook_env)?;
+        drop(ctx);
+        state.listing = listing;
+
+        Ok(Rebuilt::Pages(written.into_iter().flatten().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// A home page and two posts under `root`, and the arguments to build them.
+    fn scaffold(root: &Path) -> CliArgs {
+        fs::create_dir_all(root.join("content/blog")).unwrap();
+        fs::create_dir_all(root.join("components")).unwrap();
+        fs::write(root.join("components/blog_layout.html"), "<html><body>@{yield}</body></html>").unwrap();
+        fs::write(root.join("content/index.html"), "<html><body><h1>Home</h1></body></html>").unwrap();
+        for (slug, date) in [("older", "2024-01-01T00:00:00Z"), ("newer", "2024-02-01T00:00:00Z")] {
+            fs::write(
+                root.join(format!("content/blog/{}.md", slug)),
+                format!("---\ntitle: {}\nauthor: A\ndate: {}\n---\n\nBody of {}.\n", slug, date, slug),
+            ).unwrap();
+        }
+        let path = |name: &str| root.join(name).to_string_lossy().into_owned();
+        CliArgs::parse_from([
+            "eldroid-ssg",
+            "--input-dir", &path("content"),
+            "--output-dir", &path("output"),
+            "--components-dir", &path("components"),
+            "--variables-config", &path("variables.toml"),
+            "--site-config", &path("eldroid.toml"),
+            "--seo-config", &path("seo_config.toml"),
+        ])
+    }
+
+    #[test]
+    fn test_build_lists_pages_and_posts() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+
+        let build = Site::new(scaffold(root)).unwrap().build().unwrap();
+        let titles: Vec<_> = build.posts().map(|post| post.front_matter.title.as_str()).collect();
+        assert_eq!(titles, ["newer", "older"]);
+        assert!(root.join("output/index.html").is_file());
+        for post in build.posts() {
+            assert!(post.html_content.contains(&format!("Body of {}.", post.front_matter.title)));
+        }
+    }
+
+    #[test]
+    fn test_posts_build_with_the_bundled_blog_layout() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::write(root.join("components/blog_layout.html"), include_str!("../components/blog_layout.html")).unwrap();
+        fs::write(
+            root.join("content/blog/tagged.md"),
+            "---\ntitle: Tagged\ndate: 2024-03-01T00:00:00Z\ntags: [rust, ssg]\n---\n\nBody of tagged.\n",
+        ).unwrap();
+
+        let build = Site::new(args).unwrap().build().unwrap();
+        assert_eq!(build.posts().count(), 3);
+        let html = fs::read_to_string(root.join("output/blog/tagged.html")).unwrap();
+        assert!(html.contains(r#"<a href="/tags/rust" class="tag">#rust</a>"#));
+        assert!(html.contains(r#"<a href="/tags/ssg" class="tag">#ssg</a>"#));
+        assert!(!html.contains("@for("));
+        let untagged = fs::read_to_string(root.join("output/blog/older.html")).unwrap();
+        assert!(!untagged.contains("class=\"tag\""));
+    }
+
+    #[test]
+    fn test_bad_data_file_fails_the_build_instead_of_dropping_variables() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::write(root.join("variables.toml"), "site_name = \"Mine\"").unwrap();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("data/team.json"), "[{\"name\": \"Ann\"},").unwrap();
+
+        let err = format!("{:#}", Site::new(args.clone()).err().unwrap());
+        assert!(err.contains("Invalid data file") && err.contains("team.json"), "{}", err);
+
+        fs::write(root.join("data/team.json"), "[{\"name\": \"Ann\"}]").unwrap();
+        fs::write(root.join("variables.toml"), "site_name = ").unwrap();
+        let err = format!("{:#}", Site::new(args.clone()).err().unwrap());
+        assert!(err.contains("Invalid variables file") && err.contains("variables.toml"), "{}", err);
+
+        fs::write(root.join("variables.toml"), "site_name = \"Mine\"").unwrap();
+        fs::write(root.join("content/index.html"), "<p>@{var(\"site_name\")} @{data.team.0.name}</p>").unwrap();
+        Site::new(args).unwrap().build().unwrap();
+        assert!(fs::read_to_string(root.join("output/index.html")).unwrap().contains("<p>Mine Ann</p>"));
+    }
+
+    #[test]
+    fn test_html_front_matter_sets_layout_variables_and_seo() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::write(root.join("variables.toml"), "greeting = 1\nsite = 2\n").unwrap();
+        fs::write(root.join("components/page.html"), "<html><head><title>@{title}</title></head><body>@{yield}</body></html>").unwrap();
+        fs::write(
+            root.join("content/about.html"),
+            "---\ntitle: About us\ndescription: Who we are\nlayout: page.html\nvariables:\n  greeting: 3\n---\n<p>@{var(\"greeting\")} @{var(\"site\")}</p>\n",
+        ).unwrap();
+
+        let build = Site::new(args).unwrap().build().unwrap();
+        let about = fs::read_to_string(root.join("output/about.html")).unwrap();
+        assert_eq!(about, "<html><head><title>About us</title></head><body><p>3 2</p>\n</body></html>");
+        let seo = build.seo(&root.join("output/about.html")).unwrap();
+        assert_eq!((seo.title.as_str(), seo.description.as_deref()), ("About us", Some("Who we are")));
+    }
+
+    #[test]
+    fn test_sections_have_their_own_layouts_urls_and_listings() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::write(root.join("eldroid.toml"), "[[sections]]\npath = \"notes\"\nlayout = \"note.html\"\npermalink = \"/:section/:year/:slug/\"\n").unwrap();
+        fs::write(root.join("components/note.html"), "<html><body class=\"note\">@{yield}</body></html>").unwrap();
+        fs::create_dir_all(root.join("content/notes/rust")).unwrap();
+        fs::write(root.join("content/notes/rust/borrowing.md"), "---\ntitle: Borrowing\ndate: 2025-06-01T00:00:00Z\n---\n\nShared or mutable.\n").unwrap();
+
+        let build = Site::new(args).unwrap().build().unwrap();
+        let titles: Vec<_> = build.posts().map(|post| post.front_matter.title.as_str()).collect();
+        assert_eq!(titles, ["Borrowing", "newer", "older"]);
+
+        let note = fs::read_to_string(root.join("output/notes/2025/borrowing/index.html")).unwrap();
+        assert!(note.starts_with("<html><body class=\"note\">"));
+        assert!(fs::read_to_string(root.join("output/blog/newer.html")).unwrap().starts_with("<html><body><p>"));
+
+        let listing = fs::read_to_string(root.join("output/notes/index.html")).unwrap();
+        assert!(listing.contains("<a href=\"/notes/2025/borrowing/\">Borrowing</a>"));
+        assert!(!listing.contains("newer"));
+        assert!(fs::read_to_string(root.join("output/notes/atom.xml")).unwrap().contains("<title>Borrowing</title>"));
+        assert!(!root.join("output/blog/index.html").exists());
+    }
+
+    #[test]
+    fn test_page_paths_are_slugged_only_with_slug_paths() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::create_dir_all(root.join("content/Été 2024")).unwrap();
+        fs::write(root.join("content/blog/Mon Café.md"), "---\ntitle: Café\ndate: 2024-03-01T00:00:00Z\n---\n\nCrème.\n").unwrap();
+        fs::write(root.join("content/Été 2024/Notes Page.html"), "<html><body>Notes</body></html>").unwrap();
+
+        let build = Site::new(args.clone()).unwrap().build().unwrap();
+        assert_eq!(build.posts().next().unwrap().url, "/blog/Mon Café");
+        assert!(root.join("output/blog/Mon Café.html").exists());
+        assert!(root.join("output/Été 2024/Notes Page.html").exists());
+
+        fs::remove_dir_all(root.join("output")).unwrap();
+        fs::write(root.join("eldroid.toml"), "slug_paths = true\n").unwrap();
+        let build = Site::new(args).unwrap().build().unwrap();
+        assert_eq!(build.posts().next().unwrap().url, "/blog/mon-cafe");
+        assert!(root.join("output/blog/mon-cafe.html").exists());
+        assert!(root.join("output/ete-2024/notes-page.html").exists());
+        assert!(!root.join("output/blog/Mon Café.html").exists());
+    }
+
+    #[test]
+    fn test_section_index_files_make_landing_pages_and_section_variables() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let mut args = scaffold(root);
+        args.enable_seo = true;
+        fs::write(root.join("eldroid.toml"), "[[sections]]\npath = \"notes\"\nlayout = \"note.html\"\nfeed = false\n").unwrap();
+        fs::write(root.join("seo_config.toml"), "site_name = \"Site\"\nbase_url = \"https://ex.com\"\ndefault_description = \"\"\ndefault_keywords = []\n").unwrap();
+        fs::write(root.join("components/note.html"), "<html><head>@{seo_breadcrumbs()}</head><body>@{var(\"section.title\")}: @{yield}</body></html>").unwrap();
+        fs::create_dir_all(root.join("content/notes")).unwrap();
+        fs::write(root.join("content/notes/_index.md"), "---\ntitle: Field Notes\ndescription: Short and unpolished\n---\n\nThings I learned.\n").unwrap();
+        fs::write(root.join("content/notes/borrowing.md"), "---\ntitle: Borrowing\ndate: 2025-06-01T00:00:00Z\n---\n\nShared or mutable.\n").unwrap();
+        fs::create_dir_all(root.join("content/docs")).unwrap();
+        fs::write(root.join("content/docs/_index.md"), "---\ntitle: Guides\n---\n\nStart here.\n").unwrap();
+        fs::write(root.join("content/docs/setup.html"), "<p>@{var(\"section.title\")} @{var(\"section.url\")}</p>").unwrap();
+        fs::create_dir_all(root.join("content/faq")).unwrap();
+        fs::write(root.join("content/faq/_index.md"), "Ask away.\n").unwrap();
+
+        Site::new(args).unwrap().build().unwrap();
+        let notes = fs::read_to_string(root.join("output/notes/index.html")).unwrap();
+        assert!(notes.contains("<h1>Field Notes</h1>\n      <p>Things I learned.</p>"), "{}", notes);
+        assert!(notes.contains("<a href=\"/notes/borrowing\">Borrowing</a>"));
+        // Landing pages get the SEO tags of content pages
+        assert!(notes.contains("<link href=\"https://ex.com/notes/\" rel=\"canonical\">"), "{}", notes);
+        assert!(notes.contains("<meta content=\"Short and unpolished\" name=\"description\">"), "{}", notes);
+        assert!(notes.contains("\"@type\": \"CollectionPage\""), "{}", notes);
+        let docs = fs::read_to_string(root.join("output/docs/index.html")).unwrap();
+        assert!(docs.contains("<h1>Guides</h1>\n      <p>Start here.</p>") && !docs.contains("class=\"posts\""), "{}", docs);
+        assert!(!root.join("output/notes/_index.html").exists());
+        // Without front matter, the whole file is the intro
+        let faq = fs::read_to_string(root.join("output/faq/index.html")).unwrap();
+        assert!(faq.contains("<h1>Faq</h1>\n      <p>Ask away.</p>"), "{}", faq);
+
+        let note = fs::read_to_string(root.join("output/notes/borrowing.html")).unwrap();
+        assert!(note.contains("<li><a href=\"https://ex.com/notes/\">Field Notes</a></li>"));
+        assert!(note.contains("<body>Field Notes: "));
+        assert!(fs::read_to_string(root.join("output/docs/setup.html")).unwrap().contains("<p>Guides /docs/</p>"));
+    }
+
+    #[test]
+    fn test_content_files_with_the_same_output_fail_or_warn() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::write(root.join("content/about.html"), "<p>HTML</p>").unwrap();
+        fs::write(root.join("content/about.md"), "---\ntitle: About\ndate: 2024-01-01T00:00:00Z\n---\n\nMarkdown\n").unwrap();
+        fs::create_dir_all(root.join("content/notes/a")).unwrap();
+        fs::create_dir_all(root.join("content/notes/b")).unwrap();
+        for dir in ["a", "b"] {
+            fs::write(root.join(format!("content/notes/{}/intro.md", dir)), "---\ntitle: Intro\ndate: 2024-01-01T00:00:00Z\n---\n\nHi\n").unwrap();
+        }
+        fs::write(root.join("eldroid.toml"), "[[sections]]\npath = \"notes\"\npermalink = \"/notes/:slug/\"\n").unwrap();
+
+        let err = Site::new(args.clone()).unwrap().build().unwrap_err().to_string();
+        assert!(err.contains("2 content file(s) would overwrite another"), "{}", err);
+        assert!(err.contains(&format!("{} and {} both write about.html",
+            root.join("content/about.html").display(), root.join("content/about.md").display())), "{}", err);
+        assert!(err.contains("intro.md both write notes/intro/index.html"), "{}", err);
+
+        fs::write(root.join("eldroid.toml"), "duplicate_urls = \"warn\"\n[[sections]]\npath = \"notes\"\npermalink = \"/notes/:slug/\"\n").unwrap();
+        let build = Site::new(args).unwrap().build().unwrap();
+        assert_eq!(fs::read_to_string(root.join("output/about.html")).unwrap(), "<p>HTML</p>");
+        assert!(build.pages.contains(&root.join("output/notes/intro/index.html")));
+    }
+
+    #[test]
+    fn test_small_assets_are_inlined_and_their_files_dropped() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        for (path, content) in [
+            ("css/small.css", "body { background: url(../img/bg.png) }".to_string()),
+            ("css/big.css", format!(".a {{ color: red; }}{}", " ".repeat(100))),
+            ("js/tiny.js", "console.log(1)".to_string()),
+            ("js/lazy.js", "console.log(2)".to_string()),
+            ("js/cached.js", "console.log(3)".to_string()),
+            ("js/unused.js", "console.log(4)".to_string()),
+            ("sw.js", "const PRECACHE = [\"/js/cached.js\"];".to_string()),
+            ("img/bg.png", "png".to_string()),
+        ] {
+            fs::create_dir_all(root.join("static").join(path).parent().unwrap()).unwrap();
+            fs::write(root.join("static").join(path), content).unwrap();
+        }
+        fs::write(root.join("content/index.html"), "<html><head><link rel=\"stylesheet\" href=\"/css/small.css\">\
+            <link rel=\"stylesheet\" href=\"/css/big.css\"><script src=\"/js/tiny.js\"></script>\
+            <script src=\"/js/cached.js\"></script><script defer src=\"/js/lazy.js\"></script></head>\
+            <body><h1>Home</h1><p>Built with tiny.js</p></body></html>").unwrap();
+        fs::write(root.join("eldroid.toml"), "[assets]\nfingerprint = false\ninline_limit = 64\n").unwrap();
+        let mut args = args;
+        args.analyze_performance = true;
+        fs::create_dir_all(root.join("output/performance")).unwrap();
+        Site::new(args).unwrap().build().unwrap();
+
+        let index = fs::read_to_string(root.join("output/index.html")).unwrap();
+        assert!(index.contains("<style>body { background: url(/img/bg.png) }</style>"), "{}", index);
+        assert!(index.contains("<script>console.log(1)</script>"), "{}", index);
+        assert!(index.contains("href=\"/css/big.css\"") && index.contains("<script defer src=\"/js/lazy.js\">"), "{}", index);
+        assert!(!root.join("output/css/small.css").exists() && !root.join("output/js/tiny.js").exists());
+        assert!(root.join("output/css/big.css").exists() && root.join("output/js/lazy.js").exists());
+        assert!(root.join("output/img/bg.png").exists());
+        // Still listed by the service worker, and never inlined
+        assert!(root.join("output/js/cached.js").exists() && root.join("output/js/unused.js").exists());
+
+        // Analysis sees the page with its small assets inlined
+        let perf = fs::read_to_string(root.join("output/performance/index.perf.txt")).unwrap();
+        assert!(perf.contains("CSS files: 1\n"), "{}", perf);
+    }
+
+    #[test]
+    fn test_inlined_fingerprinted_assets_are_dropped_by_output_name() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::create_dir_all(root.join("static/css")).unwrap();
+        fs::write(root.join("static/css/small.css"), "h1 { color: red }").unwrap();
+        fs::write(root.join("static/css/print.css"), "h1 { color: black }").unwrap();
+        fs::write(root.join("content/index.html"), "<html><head><link rel=\"stylesheet\" href=\"/css/small.css\">\
+            <link rel=\"stylesheet\" href=\"/css/print.css\"></head><body><h1>Home</h1></body></html>").unwrap();
+        fs::write(root.join("content/print.html"), "<html><head><link rel=\"preload\" as=\"style\" href=\"/css/print.css\">\
+            </head><body><h1>Print</h1></body></html>").unwrap();
+        fs::write(root.join("eldroid.toml"), "[assets]\ninline_limit = 64\n").unwrap();
+        Site::new(args).unwrap().build().unwrap();
+
+        let manifest = AssetManifest::load(&root.join("output")).unwrap();
+        let small = &manifest.files["css/small.css"];
+        let print = &manifest.files["css/print.css"];
+        assert_ne!(small, "css/small.css");
+        let index = fs::read_to_string(root.join("output/index.html")).unwrap();
+        assert!(index.contains("<style>h1 { color: red }</style><style>h1 { color: black }</style>"), "{}", index);
+        assert!(!root.join("output").join(small).exists());
+        // Preloaded by a page that doesn't inline it
+        assert!(root.join("output").join(print).exists());
+    }
+
+    #[test]
+    fn test_pages_rebuilt_for_seo_are_pretty_unless_asked_not_to_be() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let mut args = scaffold(root);
+        args.enable_seo = true;
+        fs::write(root.join("seo_config.toml"), "site_name = \"Site\"\ndefault_description = \"\"\ndefault_keywords = []\n").unwrap();
+        fs::write(root.join("content/index.html"), "<html><head></head><body><main><h1>Home</h1><p>Hi <b>there</b></p></main></body></html>").unwrap();
+
+        Site::new(args.clone()).unwrap().build().unwrap();
+        let index = fs::read_to_string(root.join("output/index.html")).unwrap();
+        assert!(index.contains("\n  <body>\n    <main>\n      <h1>Home</h1>\n      <p>Hi <b>there</b></p>\n    </main>"), "{}", index);
+
+        args.pretty_html = Some(false);
+        Site::new(args).unwrap().build().unwrap();
+        let index = fs::read_to_string(root.join("output/index.html")).unwrap();
+        assert!(index.contains("<body><main><h1>Home</h1>"), "{}", index);
+    }
+
+    #[test]
+    fn test_strict_mode_fails_on_unresolved_references() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::write(root.join("variables.toml"), "name = \"Site\"").unwrap();
+        fs::write(root.join("content/about.html"), "<html><body>\n<p>@{var(\"name\")}</p>\n<p>@{var(\"missing\")}</p>\n</body></html>").unwrap();
+        Site::new(args.clone()).unwrap().build().unwrap();
+
+        fs::write(root.join("eldroid.toml"), "strict = true").unwrap();
+        let err = Site::new(args.clone()).unwrap().build().unwrap_err();
+        let page = Site::new(args.clone()).unwrap().render_page(Path::new("about.html"), &mut PipelineTrace::new()).err().unwrap();
+        assert!(page.to_string().ends_with(&format!("{}:3: error: Unresolved @{{var(\"missing\")}}", root.join("content/about.html").display())), "{}", page);
+        let err = err.to_string();
+        assert!(err.contains("about.html") && err.contains("Unresolved @{var(\"missing\")}"), "{}", err);
+
+        fs::write(root.join("content/about.html"), "<html><body><p>@{var(\"name\")}</p></body></html>").unwrap();
+        fs::write(root.join("components/blog_layout.html"), "<html><body>@{yield}@{shout()}</body></html>").unwrap();
+        let err = Site::new(args).unwrap().build().unwrap_err();
+        assert!(err.to_string().contains("Unknown macro 'shout'"), "{}", err);
+    }
+
+    struct Shout;
+
+    impl ContentTransformer for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn transform(&self, _source: &Path, content: &str) -> Result<String> {
+            Ok(content.replace("Body", "BODY").replace("Home", "HOME"))
+        }
+    }
+
+    struct Stamp;
+
+    impl HtmlPostProcessor for Stamp {
+        fn name(&self) -> &str {
+            "stamp"
+        }
+
+        fn process(&self, page: &PageInfo, html: String) -> Result<String> {
+            let name = page.output.file_name().unwrap().to_string_lossy();
+            Ok(html.replace("</body>", &format!("<footer>{}</footer></body>", name)))
+        }
+    }
+
+    struct PageList;
+
+    impl OutputGenerator for PageList {
+        fn name(&self) -> &str {
+            "page list"
+        }
+
+        fn generate(&self, site: &SiteInfo) -> Result<()> {
+            let titles: Vec<&str> = site.posts.iter().map(|post| post.front_matter.title.as_str()).collect();
+            fs::write(site.output_dir.join("posts.txt"), titles.join("\n"))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plugins_run_at_their_stages() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+
+        let site = Site::new(scaffold(root)).unwrap()
+            .with_content_transformer(Shout)
+            .with_html_post_processor(Stamp)
+            .with_output_generator(PageList);
+        site.build().unwrap();
+        let home = fs::read_to_string(root.join("output/index.html")).unwrap();
+        assert!(home.contains("<h1>HOME</h1>"), "{}", home);
+        assert!(home.contains("<footer>index.html</footer>"), "{}", home);
+        let post = fs::read_to_string(root.join("output/blog/newer.html")).unwrap();
+        assert!(post.contains("BODY of newer."), "{}", post);
+        assert!(post.contains("<footer>newer.html</footer>"), "{}", post);
+        assert_eq!(fs::read_to_string(root.join("output/posts.txt")).unwrap(), "newer\nolder");
+
+        let page = site.render_page(Path::new("index.html"), &mut PipelineTrace::new()).unwrap();
+        assert!(page.content.contains("<footer>index.html</footer>"));
+    }
+
+    #[test]
+    fn test_invalid_minify_config_fails_the_build() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut args = scaffold(temp.path());
+        args.minify = true;
+        fs::write(temp.path().join("eldroid.toml"), "[minify]\npreserve_comments = [\"(license\"]\n").unwrap();
+
+        let err = Site::new(args).err().unwrap();
+        assert!(format!("{:#}", err).contains("Invalid [minify] configuration"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_unparsable_site_config_fails_the_build() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut args = scaffold(temp.path());
+        args.minify = true;
+        fs::write(temp.path().join("eldroid.toml"), "[minify\nexclude = [\"legacy/**\"]\n").unwrap();
+
+        let err = Site::new(args).err().unwrap();
+        assert!(format!("{:#}", err).contains("TOML parse error"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_rebuilds_refresh_the_build_time() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let args = scaffold(root);
+        fs::write(root.join("content/index.html"), "<p>@{now(\"%Y\")}</p>").unwrap();
+        let first = chrono::DateTime::parse_from_rfc3339("2001-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let site = Site::new(args).unwrap();
+        let html_gen = Arc::new(site.html_gen.as_ref().clone().at(first));
+        let site = site.with_html_generator(html_gen);
+
+        site.build().unwrap();
+        assert!(fs::read_to_string(root.join("output/index.html")).unwrap().contains("<p>2001</p>"));
+        let build = IncrementalBuild::new(site).unwrap();
+        build.rebuild(&root.join("content/index.html").canonicalize().unwrap()).unwrap();
+        let year = Utc::now().format("%Y").to_string();
+        assert!(fs::read_to_string(root.join("output/index.html")).unwrap().contains(&format!("<p>{}</p>", year)));
+    }
+}
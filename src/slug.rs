@@ -0,0 +1,84 @@
+use std::path::{Component, Path, PathBuf};
+
+/// How names and paths become slugs, from `unicode_slugs` and `slug_paths`
+/// in `eldroid.toml`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Slugs {
+    /// Keep non-ASCII letters instead of transliterating them
+    pub unicode: bool,
+    /// Publish pages at slugs of their file paths instead of the paths as they are
+    pub paths: bool,
+}
+
+impl Slugs {
+    /// [`slugify_as`] with this setting's handling of non-ASCII letters.
+    pub fn slugify(&self, value: &str) -> String {
+        slugify_as(value, self.unicode)
+    }
+
+    /// Where a page at `path` is published: the path as it is, or with
+    /// `paths` on, the slug of it with the extension kept.
+    pub fn page_path(&self, path: &Path) -> PathBuf {
+        if !self.paths {
+            return path.to_path_buf();
+        }
+        let slug = slugify_path(&path.with_extension(""), self.unicode);
+        match path.extension() {
+            Some(extension) => slug.with_extension(extension),
+            None => slug,
+        }
+    }
+}
+
+/// Lowercase letters and digits joined by single hyphens, transliterated to
+/// ASCII: `Café Crème` becomes `cafe-creme`.
+pub fn slugify(value: &str) -> String {
+    slugify_as(value, false)
+}
+
+/// [`slugify`], keeping non-ASCII letters when `unicode` is set.
+pub fn slugify_as(value: &str, unicode: bool) -> String {
+    let value = if unicode { value.to_string() } else { deunicode::deunicode(value) };
+    value.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// `path`, without an extension, with each component slugified.
+pub fn slugify_path(path: &Path, unicode: bool) -> PathBuf {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => {
+                let slug = slugify_as(&name.to_string_lossy(), unicode);
+                // A name of only punctuation keeps its own
+                if slug.is_empty() { name.to_os_string() } else { slug.into() }
+            },
+            other => other.as_os_str().to_os_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugs_transliterate_unless_unicode_is_kept() {
+        assert_eq!(slugify_as("Café Crème", false), "cafe-creme");
+        assert_eq!(slugify_as("Über_uns: Teil 2", false), "uber-uns-teil-2");
+        assert_eq!(slugify_as("日本語", false), "ri-ben-yu");
+        assert_eq!(slugify_as("Café Crème", true), "café-crème");
+        assert_eq!(slugify_as("日本語 Guide", true), "日本語-guide");
+        assert_eq!(slugify_path(Path::new("blog/2024/My First Post"), false), Path::new("blog/2024/my-first-post"));
+    }
+
+    #[test]
+    fn test_page_paths_are_slugged_only_when_enabled() {
+        let page = Path::new("blog/Été 2024/Mon Café.html");
+        assert_eq!(Slugs::default().page_path(page), page);
+        assert_eq!(Slugs { unicode: false, paths: true }.page_path(page), Path::new("blog/ete-2024/mon-cafe.html"));
+        assert_eq!(Slugs { unicode: true, paths: true }.page_path(page), Path::new("blog/été-2024/mon-café.html"));
+    }
+}
@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use html_escape::{encode_double_quoted_attribute as attr, encode_text};
+use scraper::{Html, Selector};
+
+/// Roughly where X/Twitter cuts titles and descriptions off.
+const TWITTER_TITLE_LIMIT: usize = 70;
+const TWITTER_DESCRIPTION_LIMIT: usize = 200;
+/// Roughly where Facebook and LinkedIn cut titles and descriptions off.
+const OG_TITLE_LIMIT: usize = 88;
+const OG_DESCRIPTION_LIMIT: usize = 160;
+
+/// Social metadata read from a built page's `<head>`.
+#[derive(Debug, Default)]
+pub struct SocialCard {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+    pub url: Option<String>,
+    pub twitter_card: Option<String>,
+    pub twitter_title: Option<String>,
+    pub twitter_description: Option<String>,
+    pub twitter_image: Option<String>,
+}
+
+impl SocialCard {
+    pub fn from_html(html: &str) -> Self {
+        let document = Html::parse_document(html);
+        let meta = |attribute: &str, name: &str| {
+            let selector = Selector::parse(&format!("meta[{}='{}']", attribute, name)).ok()?;
+            document.select(&selector).next()
+                .and_then(|el| el.value().attr("content"))
+                .map(|content| content.trim().to_string())
+                .filter(|content| !content.is_empty())
+        };
+        let title_tag = document.select(&Selector::parse("title").unwrap()).next()
+            .map(|el| el.text().collect::<String>().trim().to_string())
+            .filter(|title| !title.is_empty());
+
+        Self {
+            title: meta("property", "og:title").or(title_tag),
+            description: meta("property", "og:description").or_else(|| meta("name", "description")),
+            image: meta("property", "og:image"),
+            site_name: meta("property", "og:site_name"),
+            url: meta("property", "og:url"),
+            twitter_card: meta("name", "twitter:card"),
+            twitter_title: meta("name", "twitter:title"),
+            twitter_description: meta("name", "twitter:description"),
+            twitter_image: meta("name", "twitter:image"),
+        }
+    }
+
+    /// Problems a social network validator would point out.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.title.is_none() {
+            warnings.push("No og:title or <title>".to_string());
+        }
+        if self.description.is_none() {
+            warnings.push("No og:description or meta description".to_string());
+        }
+        if self.image.is_none() && self.twitter_image.is_none() {
+            warnings.push("No og:image; cards will render without a picture".to_string());
+        }
+        if self.twitter_card.is_none() {
+            warnings.push("No twitter:card; X falls back to a plain summary".to_string());
+        }
+        for (label, value, limit) in [
+            ("Title", self.twitter_title.as_ref().or(self.title.as_ref()), TWITTER_TITLE_LIMIT),
+            ("Description", self.twitter_description.as_ref().or(self.description.as_ref()), TWITTER_DESCRIPTION_LIMIT),
+        ] {
+            if let Some(value) = value {
+                let length = value.chars().count();
+                if length > limit {
+                    warnings.push(format!("{} is {} characters; X truncates after about {}", label, length, limit));
+                }
+            }
+        }
+        warnings
+    }
+}
+
+/// Built file served for a URL path, allowing directory indexes and clean URLs.
+pub fn resolve_page(output_dir: &Path, url_path: &str) -> Option<PathBuf> {
+    page_candidates(output_dir, url_path).into_iter().find(|candidate| candidate.is_file())
+}
+
+/// Output files that could serve `url_path`, in the order static hosts try
+/// them: the exact file, the directory's `index.html`, then `.html` added.
+pub fn page_candidates(output_dir: &Path, url_path: &str) -> Vec<PathBuf> {
+    let relative = url_path.split(['?', '#']).next().unwrap_or_default().trim_start_matches('/');
+    if relative.split('/').any(|segment| segment == "..") {
+        return Vec::new();
+    }
+    let trimmed = relative.trim_end_matches('/');
+    vec![
+        output_dir.join(relative),
+        output_dir.join(trimmed).join("index.html"),
+        output_dir.join(format!("{}.html", trimmed)),
+    ]
+}
+
+/// Dev server page showing how `url_path` would look when shared.
+pub fn render_preview(output_dir: &Path, url_path: &str) -> String {
+    let body = match resolve_page(output_dir, url_path).map(fs::read_to_string) {
+        Some(Ok(html)) => render_cards(&SocialCard::from_html(&html)),
+        Some(Err(e)) => format!("<p class=\"warning\">Failed to read page: {}</p>", encode_text(&e.to_string())),
+        None => "<p class=\"warning\">No built page found for this path.</p>".to_string(),
+    };
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Social preview: {path}</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; max-width: 720px; margin: 2rem auto; color: #0f1419; }}
+        form {{ margin-bottom: 2rem; }}
+        input {{ width: 70%; padding: 0.4rem; }}
+        h2 {{ font-size: 1rem; color: #536471; margin-top: 2rem; }}
+        .card {{ border: 1px solid #cfd9de; border-radius: 16px; overflow: hidden; }}
+        .card.og {{ border-radius: 0; background: #f0f2f5; }}
+        .image {{ aspect-ratio: 1.91 / 1; background: #e1e8ed center / cover no-repeat; }}
+        .text {{ padding: 0.75rem; }}
+        .domain {{ color: #536471; font-size: 0.85rem; text-transform: uppercase; }}
+        .title {{ font-weight: 600; margin: 0.2rem 0; }}
+        .description {{ color: #536471; font-size: 0.9rem; }}
+        .summary {{ display: flex; }}
+        .summary .image {{ width: 130px; aspect-ratio: 1; flex-shrink: 0; }}
+        .warning {{ color: #b3261e; }}
+    </style>
+</head>
+<body>
+    <form method="get" action="/__ssg/preview">
+        <input name="path" value="{path}"> <button>Preview</button>
+    </form>
+    {body}
+</body>
+</html>
+"#, path = attr(url_path), body = body)
+}
+
+fn render_cards(card: &SocialCard) -> String {
+    let domain = card.url.as_deref()
+        .and_then(|url| url::Url::parse(url).ok())
+        .and_then(|url| url.host_str().map(str::to_string))
+        .or_else(|| card.site_name.clone())
+        .unwrap_or_default();
+    let image_style = |image: Option<&String>| image
+        .map(|src| format!(" style=\"background-image: url('{}')\"", attr(src)))
+        .unwrap_or_default();
+
+    let twitter_title = card.twitter_title.as_ref().or(card.title.as_ref());
+    let twitter_description = card.twitter_description.as_ref().or(card.description.as_ref());
+    let twitter_image = card.twitter_image.as_ref().or(card.image.as_ref());
+    let large = card.twitter_card.as_deref() == Some("summary_large_image");
+
+    let mut html = String::from("<h2>X / Twitter</h2>");
+    html.push_str(&format!(
+        r#"<div class="card{}"><div class="image"{}></div><div class="text"><div class="domain">{}</div><div class="title">{}</div><div class="description">{}</div></div></div>"#,
+        if large { "" } else { " summary" },
+        image_style(twitter_image),
+        encode_text(&domain),
+        encode_text(&truncate(twitter_title, TWITTER_TITLE_LIMIT)),
+        encode_text(&truncate(twitter_description, TWITTER_DESCRIPTION_LIMIT)),
+    ));
+
+    html.push_str("<h2>Facebook / LinkedIn (Open Graph)</h2>");
+    html.push_str(&format!(
+        r#"<div class="card og"><div class="image"{}></div><div class="text"><div class="domain">{}</div><div class="title">{}</div><div class="description">{}</div></div></div>"#,
+        image_style(card.image.as_ref()),
+        encode_text(&domain),
+        encode_text(&truncate(card.title.as_ref(), OG_TITLE_LIMIT)),
+        encode_text(&truncate(card.description.as_ref(), OG_DESCRIPTION_LIMIT)),
+    ));
+
+    let warnings = card.warnings();
+    if !warnings.is_empty() {
+        html.push_str("<h2>Warnings</h2><ul>");
+        for warning in warnings {
+            html.push_str(&format!("<li class=\"warning\">{}</li>", encode_text(&warning)));
+        }
+        html.push_str("</ul>");
+    }
+    html
+}
+
+fn truncate(value: Option<&String>, limit: usize) -> String {
+    let Some(value) = value else { return String::new() };
+    if value.chars().count() <= limit {
+        return value.clone();
+    }
+    let cut: String = value.chars().take(limit.saturating_sub(1)).collect();
+    format!("{}…", cut.trim_end())
+}
@@ -0,0 +1,300 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::Deserialize;
+use serde_yaml::Value;
+use crate::layout::{load_layout, render_layout};
+use crate::themes::ComponentDirs;
+use crate::markdown::BlogPost;
+use crate::slug::Slugs;
+
+/// A `[[taxonomies]]` entry in `eldroid.toml`: a front matter field whose
+/// values group posts, like `tags`, `series`, or `difficulty`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaxonomyConfig {
+    /// Front matter key holding one value or a list of them
+    pub name: String,
+    /// Heading of the index page, by default the name capitalized
+    pub title: Option<String>,
+    /// Output subdirectory, by default the name
+    pub path: Option<String>,
+    /// Write an Atom feed for each term
+    #[serde(default = "default_feed")]
+    pub feed: bool,
+    /// Optional layout containing `@{title}` and `@{yield}` placeholders;
+    /// `@{taxonomy}` and `@{term}` are also filled in
+    pub layout: Option<PathBuf>,
+}
+
+fn default_feed() -> bool {
+    true
+}
+
+/// One value of a taxonomy and the posts that have it, newest first.
+#[derive(Debug)]
+pub struct Term<'a> {
+    /// The value as first written in front matter
+    pub name: String,
+    pub slug: String,
+    pub posts: Vec<&'a BlogPost>,
+}
+
+impl TaxonomyConfig {
+    pub fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| {
+            let mut chars = self.name.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        })
+    }
+
+    /// Output subdirectory and URL prefix of the taxonomy's pages.
+    pub fn section(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name).trim_matches('/')
+    }
+
+    pub fn term_url(&self, term: &str, slugs: Slugs) -> String {
+        format!("/{}/{}/", self.section(), slugs.slugify(term))
+    }
+
+    /// Values of this taxonomy in a post's front matter.
+    pub fn terms_of(&self, post: &BlogPost, slugs: Slugs) -> Vec<String> {
+        match post.metadata.get(self.name.as_str()) {
+            Some(Value::Sequence(values)) => values.iter().filter_map(scalar).collect(),
+            Some(value) => scalar(value).into_iter().collect(),
+            None => Vec::new(),
+        }
+        .into_iter()
+        .filter(|term| !slugs.slugify(term).is_empty())
+        .collect()
+    }
+
+    /// `@{<name>}` on a post page: links to the post's term pages.
+    pub fn links_html(&self, post: &BlogPost, slugs: Slugs) -> String {
+        let terms = self.terms_of(post, slugs);
+        if terms.is_empty() {
+            return String::new();
+        }
+        let mut html = format!("<ul class=\"taxonomy taxonomy-{}\">", slugs.slugify(&self.name));
+        for term in terms {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>",
+                self.term_url(&term, slugs),
+                html_escape::encode_text(&term)
+            ));
+        }
+        html.push_str("</ul>");
+        html
+    }
+
+    /// Every term used by `posts`, sorted by name. Values that differ only in
+    /// case or punctuation are the same term.
+    pub fn terms<'a>(&self, posts: &'a [BlogPost], slugs: Slugs) -> Vec<Term<'a>> {
+        let mut terms: Vec<Term<'a>> = Vec::new();
+        for post in posts {
+            for name in self.terms_of(post, slugs) {
+                let slug = slugs.slugify(&name);
+                match terms.iter_mut().find(|term| term.slug == slug) {
+                    Some(term) if term.posts.last().is_some_and(|last| last.url == post.url) => {},
+                    Some(term) => term.posts.push(post),
+                    None => terms.push(Term { name, slug, posts: vec![post] }),
+                }
+            }
+        }
+        terms.sort_by_key(|term| term.name.to_lowercase());
+        terms
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Write each taxonomy's index of terms and a page (plus Atom feed) per term.
+pub fn generate_taxonomies(
+    configs: &[TaxonomyConfig],
+    posts: &[BlogPost],
+    output_dir: &Path,
+    components: &ComponentDirs,
+    base_url: &str,
+    slugs: Slugs,
+) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for config in configs {
+        let section = config.section();
+        let root = output_dir.join(section);
+        let layout = load_layout(components, config.layout.as_deref());
+        let title = config.title();
+        let terms = config.terms(posts, slugs);
+
+        let write_page = |path: PathBuf, page_title: &str, term: &str, body: &str| -> Result<PathBuf> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let html = render_layout(&layout, page_title, body)
+                .replace("@{taxonomy}", &html_escape::encode_text(&title))
+                .replace("@{term}", &html_escape::encode_text(term));
+            fs::write(&path, html)?;
+            Ok(path)
+        };
+
+        // One page per term
+        for term in &terms {
+            let mut body = format!("<h1>{}: {}</h1>\n<ul class=\"posts\">\n", html_escape::encode_text(&title), html_escape::encode_text(&term.name));
+            for post in &term.posts {
+                body.push_str(&post_item_html(post));
+            }
+            body.push_str(&format!("</ul>\n<p><a href=\"/{}/\">&larr; All {}</a></p>", section, html_escape::encode_text(&title.to_lowercase())));
+            let dir = root.join(&term.slug);
+            written.push(write_page(dir.join("index.html"), &format!("{} - {}", term.name, title), &term.name, &body)?);
+            if config.feed {
+                fs::write(dir.join("atom.xml"), term_atom(config, term, base_url))?;
+            }
+        }
+
+        // Index of every term
+        let mut body = format!("<h1>{}</h1>\n<ul class=\"terms\">\n", html_escape::encode_text(&title));
+        for term in &terms {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> <span class=\"count\">({})</span></li>\n",
+                config.term_url(&term.name, slugs),
+                html_escape::encode_text(&term.name),
+                term.posts.len()
+            ));
+        }
+        body.push_str("</ul>");
+        written.push(write_page(root.join("index.html"), &title, "", &body)?);
+
+        info!("Generated {} {} pages", terms.len(), config.name);
+    }
+    Ok(written)
+}
+
+pub(crate) fn post_item_html(post: &BlogPost) -> String {
+    let date = DateTime::parse_from_rfc3339(&post.front_matter.date)
+        .map(|d| format!(" <time datetime=\"{}\">{}</time>", d.to_rfc3339(), d.format("%B %-d, %Y")))
+        .unwrap_or_default();
+    let description = post.description()
+        .map(|d| format!("<p>{}</p>", html_escape::encode_text(d)))
+        .unwrap_or_default();
+    format!(
+        "<li><a href=\"{}\">{}</a>{}{}</li>\n",
+        post.url,
+        html_escape::encode_text(&post.front_matter.title),
+        date,
+        description
+    )
+}
+
+fn term_atom(config: &TaxonomyConfig, term: &Term, base_url: &str) -> String {
+    let path = format!("/{}/{}/", config.section(), term.slug);
+    atom_feed(&format!("{}: {}", config.title(), term.name), &path, &term.posts, base_url)
+}
+
+/// `text` as a CDATA section, split where it contains `]]>`, which would
+/// end the section early.
+pub(crate) fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Atom feed of `posts`, published as `atom.xml` in the listing page at `path`.
+pub(crate) fn atom_feed(title: &str, path: &str, posts: &[&BlogPost], base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    let url = format!("{}{}", base, path);
+    let date = |post: &BlogPost| DateTime::parse_from_rfc3339(&post.front_matter.date).ok().map(|d| d.to_rfc3339());
+    let updated = posts.iter()
+        .filter_map(|post| date(post))
+        .max()
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+    let mut feed = format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+    <title>{}</title>
+    <link href="{}atom.xml" rel="self"/>
+    <link href="{}"/>
+    <id>{}</id>
+    <updated>{}</updated>"#,
+        html_escape::encode_text(title),
+        url,
+        url,
+        url,
+        updated
+    );
+
+    for post in posts.iter().filter(|post| !post.is_noindex()) {
+        let link = format!("{}{}", base, post.url);
+        feed.push_str(&format!(r#"
+    <entry>
+        <title>{}</title>
+        <link href="{}"/>
+        <id>{}</id>
+        <updated>{}</updated>
+        <content type="html">{}</content>
+    </entry>"#,
+            html_escape::encode_text(&post.front_matter.title),
+            link,
+            link,
+            date(post).unwrap_or_else(|| updated.clone()),
+            cdata(&post.html_content)
+        ));
+    }
+
+    feed.push_str("\n</feed>\n");
+    feed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(url: &str, front_matter: &str) -> BlogPost {
+        let metadata: serde_yaml::Mapping = serde_yaml::from_str(front_matter).unwrap();
+        BlogPost {
+            front_matter: serde_yaml::from_value(Value::Mapping(metadata.clone())).unwrap(),
+            content: String::new(),
+            html_content: String::new(),
+            url: url.to_string(),
+            file_path: PathBuf::from(url),
+            language: None,
+            authors: Vec::new(),
+            metadata,
+            excerpt: String::new(),
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn test_groups_posts_by_term() {
+        let posts = vec![
+            post("/blog/b", "title: B\ndate: 2024-02-01T00:00:00Z\nseries: Rust Basics\nstack: [rust, wasm]"),
+            post("/blog/a", "title: A\ndate: 2024-01-01T00:00:00Z\nseries: rust basics\nstack: rust"),
+            post("/blog/c", "title: C\ndate: 2023-01-01T00:00:00Z"),
+        ];
+        let series: TaxonomyConfig = toml::from_str("name = \"series\"").unwrap();
+        let terms = series.terms(&posts, Slugs::default());
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].name, "Rust Basics");
+        assert_eq!(terms[0].slug, "rust-basics");
+        assert_eq!(terms[0].posts.iter().map(|p| p.url.as_str()).collect::<Vec<_>>(), ["/blog/b", "/blog/a"]);
+
+        let stack: TaxonomyConfig = toml::from_str("name = \"stack\"\npath = \"/tech/\"").unwrap();
+        assert_eq!(stack.terms(&posts, Slugs::default()).iter().map(|t| t.posts.len()).collect::<Vec<_>>(), [2, 1]);
+        assert_eq!(stack.title(), "Stack");
+        assert!(stack.links_html(&posts[0], Slugs::default()).contains("<a href=\"/tech/wasm/\">wasm</a>"));
+        assert_eq!(stack.links_html(&posts[2], Slugs::default()), "");
+    }
+
+    #[test]
+    fn test_feed_content_keeps_cdata_terminators_inside() {
+        let mut entry = post("/blog/a", "title: A\ndate: 2024-01-01T00:00:00Z");
+        entry.html_content = "<pre>a[b[0]]></pre>".to_string();
+        let feed = atom_feed("Series: A", "/series/a/", &[&entry], "https://example.com");
+        assert!(feed.contains("<content type=\"html\"><![CDATA[<pre>a[b[0]]]]><![CDATA[></pre>]]></content>"), "{}", feed);
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use crate::conditionals::Conditions;
+use crate::filters::Filter;
+use crate::i18n::Translations;
+use crate::seo_html::SEO_MACROS;
+use crate::themes::Theme;
+use crate::variables::{mask_escapes, Variables};
+
+lazy_static! {
+    static ref MACRO_CALL_REGEX: Regex = Regex::new(r"@\{\s*([A-Za-z_]\w*)\s*\(").unwrap();
+    static ref VAR_REGEX: Regex = Regex::new(r#"@\{var\(["']([^"']+)["']\)([^}\n]*)\}"#).unwrap();
+    static ref T_REGEX: Regex = Regex::new(r#"@\{t\(["']([^"']+)["']\)\}"#).unwrap();
+    static ref BLOCK_REGEX: Regex = Regex::new(r"@(if|for|macro)\b[^{\n]*\{").unwrap();
+    static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"@\{[^}\n]*\}").unwrap();
+    static ref UNCLOSED_PLACEHOLDER_REGEX: Regex = Regex::new(r"@\{[^}\n]*(\n|$)").unwrap();
+    static ref COMPONENT_TAG_REGEX: Regex = Regex::new(r"<el-component\b[^>]*?(/?)>|</el-component>").unwrap();
+    static ref COMPONENT_NAME_REGEX: Regex = Regex::new(r#"\bc_name=["']([^"']+)["']"#).unwrap();
+}
+
+/// Macros the build actually expands; anything else is left verbatim in the output.
+const KNOWN_MACROS: &[&str] = &["var", "env", "include", "url", "now", "t", "color", "variant"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    pub file: PathBuf,
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}:{}: {}: {}", self.file.display(), self.line, severity, self.message)
+    }
+}
+
+/// Static checks over components and layouts, run without building any content.
+pub struct TemplateLinter {
+    components_dir: PathBuf,
+    /// Components the theme provides when the site has no file of its own
+    theme_components_dir: Option<PathBuf>,
+    variables: Option<Variables>,
+    translations: Option<(Translations, String)>,
+}
+
+impl TemplateLinter {
+    pub fn new(components_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            components_dir: components_dir.into(),
+            theme_components_dir: None,
+            variables: None,
+            translations: None,
+        }
+    }
+
+    /// Accept components from the `theme` too.
+    pub fn with_theme(mut self, theme: Option<&Theme>) -> Self {
+        self.theme_components_dir = theme.map(Theme::components_dir);
+        self
+    }
+
+    pub fn with_variables(mut self, vars: Variables) -> Self {
+        self.variables = Some(vars);
+        self
+    }
+
+    /// Check `@{t("key")}` lookups against the default language's strings.
+    pub fn with_translations(mut self, translations: Translations, language: &str) -> Self {
+        self.translations = Some((translations, language.to_string()));
+        self
+    }
+
+    /// Lint every `.html` template under the components directory.
+    pub fn lint_all(&self) -> Result<Vec<LintIssue>> {
+        let mut issues = Vec::new();
+        let mut templates: Vec<_> = walkdir::WalkDir::new(&self.components_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "html"))
+            .map(|e| e.into_path())
+            .collect();
+        templates.sort();
+
+        for path in templates {
+            let content = fs::read_to_string(&path)?;
+            issues.extend(self.lint_template(&path, &content));
+        }
+        Ok(issues)
+    }
+
+    pub fn lint_template(&self, path: &Path, content: &str) -> Vec<LintIssue> {
+        // Escaped placeholders are text, not references
+        let content = &mask_escapes(content);
+        let mut issues = Vec::new();
+        let mut report = |offset: usize, severity: Severity, message: String| {
+            issues.push(LintIssue {
+                file: path.to_path_buf(),
+                line: line_of(content, offset),
+                severity,
+                message,
+            });
+        };
+
+        for caps in MACRO_CALL_REGEX.captures_iter(content) {
+            let name = &caps[1];
+            if !KNOWN_MACROS.contains(&name) && !SEO_MACROS.contains(&name) {
+                report(caps.get(0).unwrap().start(), Severity::Error, format!("Unknown macro '{}'", name));
+            }
+        }
+
+        for caps in VAR_REGEX.captures_iter(content) {
+            let name = &caps[1];
+            let defined = self.variables.as_ref().is_some_and(|vars| vars.get(name).is_some());
+            if !defined {
+                // Pages can still supply it through front matter variables
+                report(caps.get(0).unwrap().start(), Severity::Warning, format!("Variable '{}' is not defined in the variables config", name));
+            }
+            if let Err(e) = Filter::parse_chain(&caps[2]) {
+                report(caps.get(0).unwrap().start(), Severity::Error, format!("{} on variable '{}'", e, name));
+            }
+        }
+
+        if let Some((translations, language)) = &self.translations {
+            for caps in T_REGEX.captures_iter(content) {
+                let key = &caps[1];
+                if translations.get(language, key).is_none() {
+                    report(caps.get(0).unwrap().start(), Severity::Warning, format!("Missing '{}' translation for '{}'", language, key));
+                }
+            }
+        }
+
+        for m in UNCLOSED_PLACEHOLDER_REGEX.find_iter(content) {
+            report(m.start(), Severity::Error, "Unclosed '@{' placeholder".to_string());
+        }
+
+        for caps in BLOCK_REGEX.captures_iter(content) {
+            let open = caps.get(0).unwrap();
+            if !block_closes(&content[open.end()..]) {
+                report(open.start(), Severity::Error, format!("Unclosed @{} block", &caps[1]));
+            }
+        }
+
+        // Structure and condition syntax of @{if ...} blocks; the environment doesn't matter here
+        if let Err(e) = Conditions::new("prod").apply(content) {
+            report(e.offset, Severity::Error, e.message);
+        }
+
+        let mut open_components = Vec::new();
+        for caps in COMPONENT_TAG_REGEX.captures_iter(content) {
+            let tag = caps.get(0).unwrap();
+            if tag.as_str().starts_with("</") {
+                if open_components.pop().is_none() {
+                    report(tag.start(), Severity::Error, "Closing </el-component> without a matching opening tag".to_string());
+                }
+                continue;
+            }
+
+            if caps.get(1).is_some_and(|m| m.as_str().is_empty()) {
+                open_components.push(tag.start());
+            }
+
+            match COMPONENT_NAME_REGEX.captures(tag.as_str()) {
+                Some(name) => {
+                    let name = &name[1];
+                    // Dynamic names are only known at render time
+                    let file = format!("{}.html", name);
+                    let exists = self.components_dir.join(&file).exists()
+                        || self.theme_components_dir.as_ref().is_some_and(|dir| dir.join(&file).exists());
+                    if !name.contains("@{") && !exists {
+                        report(tag.start(), Severity::Error, format!("Component '{}' not found in {}", name, self.components_dir.display()));
+                    }
+                },
+                None => report(tag.start(), Severity::Error, "<el-component> is missing a c_name attribute".to_string()),
+            }
+        }
+        for start in open_components {
+            report(start, Severity::Error, "Unclosed <el-component>".to_string());
+        }
+
+        issues.sort_by_key(|issue| issue.line);
+        issues
+    }
+}
+
+/// `@{...}` placeholders left in a page's rendered `output`, at their line in
+/// the `source` file, or at their line of the output when a layout or
+/// component put them there.
+pub fn unresolved_placeholders(source: &Path, source_content: &str, output_path: &Path, output: &str) -> Vec<LintIssue> {
+    let source_content = &mask_escapes(source_content);
+    PLACEHOLDER_REGEX.find_iter(output)
+        .map(|m| {
+            let (file, line) = match source_content.find(m.as_str()) {
+                Some(offset) => (source, line_of(source_content, offset)),
+                None => (output_path, line_of(output, m.start())),
+            };
+            LintIssue {
+                file: file.to_path_buf(),
+                line,
+                severity: Severity::Error,
+                message: format!("Unresolved {}", m.as_str()),
+            }
+        })
+        .collect()
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+/// Whether the brace opened just before `rest` is closed again.
+fn block_closes(rest: &str) -> bool {
+    let mut depth = 1;
+    for c in rest.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return true;
+                }
+            },
+            _ => {},
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reports_template_mistakes() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("header.html"), "<header></header>").unwrap();
+        let linter = TemplateLinter::new(temp.path());
+
+        let template = "<el-component c_name=\"header\" />\n\
+                        <el-component c_name=\"footer\" />\n\
+                        @{var(\"site_name\")}\n\
+                        @{current_year()}\n\
+                        @if(author) {\n  <p>@{author}</p>\n\
+                        @{if env(\"prod\")}<p>Live</p>\n";
+        let issues = linter.lint_template(Path::new("layout.html"), template);
+        let summary: Vec<_> = issues.iter().map(|i| (i.line, i.severity)).collect();
+
+        assert_eq!(summary, vec![
+            (2, Severity::Error),
+            (3, Severity::Warning),
+            (4, Severity::Error),
+            (5, Severity::Error),
+            (7, Severity::Error),
+        ]);
+    }
+
+    #[test]
+    fn test_clean_template() {
+        let temp = tempdir().unwrap();
+        let linter = TemplateLinter::new(temp.path());
+        let template = "<style>body { margin: 0; }</style>\n\
+                        @if(tags) {\n  @for(tag in tags) {\n    <a>@{tag}</a>\n  }\n}\n\
+                        @{if after(\"2025-01-01\")}<p>New</p>@{else}<p>Old</p>@{endif}\n\
+                        <code>@@{shout()}</code>\n@{raw}<el-component c_name=\"demo\" />\n@{raw}@{endraw}\n";
+
+        assert!(linter.lint_template(Path::new("list.html"), template).is_empty());
+    }
+}
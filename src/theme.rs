@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use crate::variables::Variables;
+
+lazy_static! {
+    static ref COLOR_REGEX: Regex = Regex::new(r#"@\{color\(["']([^"']+)["']\)\}"#).unwrap();
+}
+
+/// Brand colors from `[theme.colors]` in `variables.toml`, keyed by name.
+/// Nested tables such as `[theme.colors.dark]` give dotted names (`dark.bg`).
+#[derive(Debug, Clone, Default)]
+pub struct ThemeColors {
+    colors: BTreeMap<String, String>,
+}
+
+impl ThemeColors {
+    /// The theme colors defined in `variables`, if there are any.
+    pub fn from_variables(variables: &Variables) -> Result<Option<Self>> {
+        let Some(colors) = variables.get("theme").and_then(|theme| theme.get("colors")) else {
+            return Ok(None);
+        };
+        let table = colors.as_table()
+            .ok_or_else(|| anyhow!("theme.colors must be a table of color names to CSS colors"))?;
+        let mut theme = Self::default();
+        flatten_colors("", table, &mut theme.colors)?;
+        Ok(Some(theme))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.colors.get(name).map(String::as_str)
+    }
+
+    /// CSS custom property for a color, e.g. `--color-dark-bg` for `dark.bg`.
+    pub fn property(name: &str) -> String {
+        format!("--color-{}", name.replace('.', "-"))
+    }
+
+    /// Stylesheet defining every color as a custom property on `:root`.
+    pub fn to_css(&self) -> String {
+        let mut css = String::from("/* Generated from [theme.colors] in variables.toml */\n:root {\n");
+        for (name, value) in &self.colors {
+            css.push_str(&format!("  {}: {};\n", Self::property(name), value));
+        }
+        css.push_str("}\n");
+        css
+    }
+
+    /// Write the stylesheet to `css/theme.css` in the output directory.
+    pub fn write_css(&self, output_dir: &Path) -> Result<PathBuf> {
+        let path = output_dir.join("css").join("theme.css");
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, self.to_css())?;
+        info!("Theme colors written to {}", path.display());
+        Ok(path)
+    }
+
+    /// Resolve `@{color("name")}` references to the color's value.
+    pub fn substitute(&self, content: &str) -> String {
+        COLOR_REGEX.replace_all(content, |caps: &regex::Captures| {
+            let name = &caps[1];
+            match self.get(name) {
+                Some(value) => value.to_string(),
+                None => {
+                    log::warn!("Theme color '{}' not found", name);
+                    caps[0].to_string()
+                }
+            }
+        }).to_string()
+    }
+}
+
+fn flatten_colors(prefix: &str, table: &toml::Table, out: &mut BTreeMap<String, String>) -> Result<()> {
+    for (key, value) in table {
+        if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow!("Theme color name '{}' can't be used in a CSS custom property", key));
+        }
+        let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(nested) => flatten_colors(&name, nested, out)?,
+            toml::Value::String(color) if !color.contains([';', '{', '}']) => {
+                out.insert(name, color.trim().to_string());
+            },
+            _ => return Err(anyhow!("Theme color '{}' must be a CSS color string", name)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+/// A shared design in `themes/<name>/` next to `eldroid.toml`, selected with
+/// `theme = "<name>"`. Its `components/` directory holds components and
+/// layouts, and its `static/` directory assets. A site overrides any of them
+/// with its own file at the same path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl Theme {
+    /// The theme `name` for the site configured by `site_config`.
+    pub fn load(name: &str, site_config: &Path) -> Result<Self> {
+        let dir = site_config.parent().unwrap_or(Path::new("")).join("themes").join(name);
+        if !dir.is_dir() {
+            return Err(anyhow!("Theme '{}' not found; expected a directory at {}", name, dir.display()));
+        }
+        Ok(Self { name: name.to_string(), dir })
+    }
+
+    pub fn components_dir(&self) -> PathBuf {
+        self.dir.join("components")
+    }
+
+    pub fn static_dir(&self) -> PathBuf {
+        self.dir.join("static")
+    }
+}
+
+/// Where components and layouts are looked up: the site's own directory,
+/// then the theme's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentDirs {
+    dirs: Vec<PathBuf>,
+}
+
+impl ComponentDirs {
+    pub fn new(site_dir: impl Into<PathBuf>, theme: Option<&Theme>) -> Self {
+        let mut dirs = vec![site_dir.into()];
+        dirs.extend(theme.map(Theme::components_dir));
+        Self { dirs }
+    }
+
+    /// The first file at `relative` in the site and then the theme, or the
+    /// site's path when neither has one.
+    pub fn resolve(&self, relative: impl AsRef<Path>) -> PathBuf {
+        let relative = relative.as_ref();
+        self.dirs.iter()
+            .map(|dir| dir.join(relative))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| self.dirs[0].join(relative))
+    }
+
+    /// The site's own components directory.
+    pub fn site_dir(&self) -> &Path {
+        &self.dirs[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_site_files_override_the_theme() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("themes/paper/components")).unwrap();
+        fs::create_dir_all(root.join("components")).unwrap();
+        fs::write(root.join("themes/paper/components/blog_layout.html"), "theme").unwrap();
+        fs::write(root.join("themes/paper/components/header.html"), "theme").unwrap();
+        fs::write(root.join("components/header.html"), "site").unwrap();
+
+        let theme = Theme::load("paper", &root.join("eldroid.toml")).unwrap();
+        let dirs = ComponentDirs::new(root.join("components"), Some(&theme));
+        assert_eq!(dirs.resolve("header.html"), root.join("components/header.html"));
+        assert_eq!(dirs.resolve("blog_layout.html"), root.join("themes/paper/components/blog_layout.html"));
+        assert_eq!(dirs.resolve("footer.html"), root.join("components/footer.html"));
+        assert!(Theme::load("missing", &root.join("eldroid.toml")).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::net::IpAddr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use log::info;
+use crate::config::user_cache_dir;
+use crate::history;
+
+/// PEM-encoded certificate chain and private key for serving the development
+/// server over HTTPS.
+#[derive(Clone)]
+pub struct TlsIdentity {
+    pub cert: Vec<u8>,
+    pub key: Vec<u8>,
+}
+
+impl TlsIdentity {
+    /// A certificate and key from files, e.g. made with mkcert.
+    pub fn load(cert_path: &Path, key_path: &Path) -> Result<Self> {
+        let read = |path: &Path| fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e));
+        Ok(Self { cert: read(cert_path)?, key: read(key_path)? })
+    }
+
+    /// Where self-signed certificates are kept: outside the output directory,
+    /// which the dev server publishes, so the private key is never served.
+    pub fn default_dir() -> PathBuf {
+        Self::dir_in(&user_cache_dir())
+    }
+
+    /// The certificate directory under the cache directory `cache_root`.
+    pub fn dir_in(cache_root: &Path) -> PathBuf {
+        cache_root.join("tls")
+    }
+
+    /// A self-signed certificate for localhost and `host`, kept in `cache_dir`
+    /// so a browser exception for it survives restarts.
+    pub fn self_signed(cache_dir: &Path, host: IpAddr) -> Result<Self> {
+        let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()];
+        if !host.is_unspecified() && !host.is_loopback() {
+            names.push(host.to_string());
+        }
+
+        // A new address needs a new certificate
+        let id = &history::hash(names.join(",").as_bytes())[..8];
+        let cert_path = cache_dir.join(format!("dev-cert-{}.pem", id));
+        let key_path = cache_dir.join(format!("dev-key-{}.pem", id));
+        if cert_path.exists() && key_path.exists() {
+            return Self::load(&cert_path, &key_path);
+        }
+
+        let generated = rcgen::generate_simple_self_signed(names.clone())?;
+        let identity = Self {
+            cert: generated.cert.pem().into_bytes(),
+            key: generated.key_pair.serialize_pem().into_bytes(),
+        };
+        create_private_dir(cache_dir)?;
+        fs::write(&cert_path, &identity.cert)?;
+        write_private(&key_path, &identity.key)?;
+        info!("Generated a self-signed certificate for {} at {}", names.join(", "), cert_path.display());
+        Ok(identity)
+    }
+}
+
+/// `dir` and its parents, with `dir` readable only by its owner.
+fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Write `bytes` to a file only its owner can read.
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn test_dev_key_is_kept_out_of_the_served_directory() {
+        let cache_root = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let dir = TlsIdentity::dir_in(cache_root.path());
+        assert!(dir.starts_with(cache_root.path()));
+        let identity = TlsIdentity::self_signed(&dir, IpAddr::V4(Ipv4Addr::UNSPECIFIED)).unwrap();
+        let key = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.file_name().unwrap().to_string_lossy().starts_with("dev-key-"))
+            .unwrap();
+        assert_eq!(fs::read(&key).unwrap(), identity.key);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&key).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        // Nothing the dev server publishes holds the key
+        assert_eq!(fs::read_dir(output.path()).unwrap().count(), 0);
+        let name = key.file_name().unwrap().to_string_lossy().into_owned();
+        let relative = key.strip_prefix(cache_root.path()).unwrap().to_string_lossy().replace('\\', "/");
+        let served = warp::fs::dir(output.path().to_path_buf());
+        for path in [format!("/{}", name), format!("/{}", relative), format!("/.cache/eldroid-ssg/{}", relative)] {
+            let response = warp::test::request().path(&path).reply(&served).await;
+            assert_eq!(response.status(), 404, "{} is served", path);
+        }
+    }
+}
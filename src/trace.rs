@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+
+/// One pipeline stage applied to a page.
+#[derive(Debug)]
+pub struct TraceStage {
+    pub name: String,
+    pub details: Vec<String>,
+    pub duration: Duration,
+}
+
+/// Records the stages a page goes through for `eldroid-ssg explain`.
+///
+/// A disabled trace only runs the stages, so the regular build can pass one
+/// through the same code path at no cost.
+#[derive(Debug, Default)]
+pub struct PipelineTrace {
+    enabled: bool,
+    stages: Vec<TraceStage>,
+}
+
+impl PipelineTrace {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            stages: Vec::new(),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Run `f` as the stage `name`, recording how long it took.
+    pub fn stage<T>(&mut self, name: &str, f: impl FnOnce() -> T) -> T {
+        if !self.enabled {
+            return f();
+        }
+        let start = Instant::now();
+        let result = f();
+        self.stages.push(TraceStage {
+            name: name.to_string(),
+            details: Vec::new(),
+            duration: start.elapsed(),
+        });
+        result
+    }
+
+    /// Attach a detail line to the most recent stage.
+    pub fn detail(&mut self, line: impl Into<String>) {
+        if let Some(stage) = self.stages.last_mut() {
+            stage.details.push(line.into());
+        }
+    }
+
+    pub fn stages(&self) -> &[TraceStage] {
+        &self.stages
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.duration).sum()
+    }
+
+    /// Human-readable listing of every stage with its details and timing.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        for (i, stage) in self.stages.iter().enumerate() {
+            out.push_str(&format!("{:>2}. {:<14} {:>10.3?}\n", i + 1, stage.name, stage.duration));
+            for detail in &stage.details {
+                out.push_str(&format!("      {}\n", detail));
+            }
+        }
+        out.push_str(&format!("    {:<14} {:>10.3?}\n", "total", self.total()));
+        out
+    }
+}
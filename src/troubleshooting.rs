@@ -2,7 +2,7 @@ use std::path::Path;
 use anyhow::{Result, anyhow};
 use log::{info, warn};
 use std::fs;
-use image::GenericImageView;
+use crate::images::{kilobytes, ImageFile};
 
 pub struct Troubleshooter {
     cache_dir: String,
@@ -72,20 +72,37 @@ impl Troubleshooter {
     pub fn verify_assets(&self, input_dir: &str) -> Result<()> {
         info!("Verifying static assets...");
         
-        let static_dir = Path::new(input_dir).join("static");
-        if !static_dir.exists() {
-            return Err(anyhow!("Static directory not found at {}", static_dir.display()));
+        let input_dir = Path::new(input_dir);
+        let site_root = input_dir.parent().unwrap_or(Path::new(""));
+        let static_dirs: Vec<_> = [site_root.join("static"), input_dir.join("static")]
+            .into_iter()
+            .filter(|dir| dir.is_dir())
+            .collect();
+        if static_dirs.is_empty() {
+            return Err(anyhow!("Static directory not found at {}", input_dir.join("static").display()));
         }
 
         let mut issues = Vec::new();
+        let mut image_bytes = 0;
+        let mut image_savings = 0;
         
-        // Walk through static directory
-        for entry in walkdir::WalkDir::new(&static_dir)
-            .into_iter()
+        // Walk through static directories
+        for entry in static_dirs.iter()
+            .flat_map(walkdir::WalkDir::new)
             .filter_map(|e| e.ok()) {
                 
             let path = entry.path();
             if path.is_file() {
+                // Weigh images the way page analysis does, without knowing how big they are shown
+                let is_image = path.extension().and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "jpg" | "jpeg" | "png" | "webp"));
+                if let Some(image) = is_image.then(|| ImageFile::inspect(path)).flatten() {
+                    let advice = image.advise(None, image.webp_copy().is_some());
+                    image_bytes += image.bytes;
+                    image_savings += advice.savings;
+                    issues.extend(advice.issues);
+                }
+
                 // Check file size
                 if let Ok(metadata) = path.metadata() {
                     let size = metadata.len();
@@ -94,22 +111,12 @@ impl Troubleshooter {
                             path.display(), size as f64 / 1_000_000.0));
                     }
                 }
-                
-                // Check image dimensions for common formats
-                if let Some(ext) = path.extension() {
-                    if matches!(ext.to_str(), Some("jpg" | "jpeg" | "png" | "webp")) {
-                        if let Ok(img) = image::open(path) {
-                            let dims = img.dimensions();
-                            if dims.0 > 2000 || dims.1 > 2000 {
-                                issues.push(format!("Large image dimensions: {} ({}x{})", 
-                                    path.display(), dims.0, dims.1));
-                            }
-                        }
-                    }
-                }
             }
         }
 
+        if image_bytes > 0 {
+            info!("Images: {}, about {} could be saved", kilobytes(image_bytes as f64), kilobytes(image_savings as f64));
+        }
         if issues.is_empty() {
             info!("No asset issues found");
         } else {
@@ -144,7 +151,7 @@ impl Troubleshooter {
         }
         
         // Sort bundles by size
-        bundles.sort_by(|a, b| b.1.cmp(&a.1));
+        bundles.sort_by_key(|b| std::cmp::Reverse(b.1));
         
         info!("Bundle Analysis:");
         info!("  Total bundle size: {:.1}MB", total_size as f64 / 1_000_000.0);
@@ -1,14 +1,28 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 use serde::Deserialize;
 use toml;
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde_json::{Map, Value};
+use walkdir::WalkDir;
+use crate::data::{load_data_dir, lookup, render};
+use crate::filters::{self, Filter};
 
 lazy_static! {
-    static ref VAR_REGEX: Regex = Regex::new(r#"@\{var\(["']([^"']+)["']\)\}"#).unwrap();
+    static ref VAR_REGEX: Regex = Regex::new(
+        r#"@\{var\(["']([^"']+)["']\)((?:\s*\|\s*\w+(?:\((?:"[^"]*"|'[^']*'|[^)"'])*\))?)*)\s*\}"#
+    ).unwrap();
+    static ref DATA_REGEX: Regex = Regex::new(r"@\{data\.([\w-]+(?:\.[\w-]+)*)\}").unwrap();
+    static ref ENV_MACRO_REGEX: Regex = Regex::new(
+        r#"@\{env\(["']([^"']+)["'](?:\s*,\s*["']([^"']*)["'])?\)\}"#
+    ).unwrap();
+    static ref ESCAPE_REGEX: Regex = Regex::new(
+        r#"(?s)@\{raw\}(.*?)@\{endraw\}|@(@\{(?:"[^"]*"|'[^']*'|[^}"'\n])*\})"#
+    ).unwrap();
+    static ref ENV_REF_REGEX: Regex = Regex::new(r"\$\$|\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap();
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -19,6 +33,9 @@ pub struct Variables {
     env_vars: Option<HashMap<String, toml::Value>>,
     #[serde(skip)]
     page_vars: Option<HashMap<String, toml::Value>>,
+    /// Files from the data directory, as `@{data.<file>.<key>}`
+    #[serde(skip)]
+    data: Map<String, Value>,
 }
 
 impl Variables {
@@ -33,8 +50,8 @@ impl Variables {
             base_dir.join("variables.prod.toml")
         };
         
-        if let Ok(env_vars) = Self::load_file(&env_file) {
-            vars.env_vars = Some(env_vars.vars);
+        if env_file.exists() {
+            vars.env_vars = Some(Self::load_file(&env_file)?.vars);
         }
         
         Ok(vars)
@@ -42,8 +59,14 @@ impl Variables {
 
     fn load_file(path: &Path) -> Result<Self> {
         if path.exists() {
-            let content = fs::read_to_string(path)?;
-            Ok(toml::from_str(&content)?)
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read variables file {}", path.display()))?;
+            let mut vars: Self = toml::from_str(&content)
+                .with_context(|| format!("Invalid variables file {}", path.display()))?;
+            for value in vars.vars.values_mut() {
+                interpolate_env(value);
+            }
+            Ok(vars)
         } else {
             Ok(Self::default())
         }
@@ -54,35 +77,328 @@ impl Variables {
     }
 
     pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        self.resolve(key).map(|(value, _)| value)
+    }
+
+    /// Look up a variable along with the scope it was found in
+    /// (`"page"`, `"env"`, or `"global"`). `key` may be a dotted path into
+    /// tables and arrays, like `site.title` or `nav.links[0].url`.
+    pub fn resolve(&self, key: &str) -> Option<(&toml::Value, &'static str)> {
         // Check in order: page vars -> env vars -> global vars
-        if let Some(page_vars) = &self.page_vars {
-            if let Some(value) = page_vars.get(key) {
-                return Some(value);
-            }
+        if let Some(value) = self.page_vars.as_ref().and_then(|vars| lookup_path(vars, key)) {
+            return Some((value, "page"));
         }
 
-        if let Some(env_vars) = &self.env_vars {
-            if let Some(value) = env_vars.get(key) {
-                return Some(value);
-            }
+        if let Some(value) = self.env_vars.as_ref().and_then(|vars| lookup_path(vars, key)) {
+            return Some((value, "env"));
         }
 
-        self.vars.get(key)
+        lookup_path(&self.vars, key).map(|value| (value, "global"))
+    }
+
+    /// Add data under `name`, replacing any data file of that name.
+    pub fn insert_data(&mut self, name: &str, value: Value) {
+        self.data.insert(name.to_string(), value);
+    }
+
+    pub fn data(&self) -> &Map<String, Value> {
+        &self.data
+    }
+
+    /// Names of all `@{var("...")}` references in `content`, in order.
+    pub fn references(content: &str) -> Vec<String> {
+        VAR_REGEX.captures_iter(content).map(|caps| caps[1].to_string()).collect()
     }
 
+    /// Replace `@{var("...")}`, `@{data....}`, and `@{env("...")}` references.
+    /// Escaped placeholders are written out literally; see [`resolve_escapes`].
     pub fn substitute(&self, content: &str) -> String {
-        VAR_REGEX.replace_all(content, |caps: &regex::Captures| {
+        let content = self.substitute_vars(content);
+        let content = DATA_REGEX.replace_all(&content, |caps: &regex::Captures| {
+            match lookup(&self.data, &caps[1]).and_then(render) {
+                Some(value) => value,
+                None => {
+                    log::warn!("Data '{}' not found", &caps[1]);
+                    caps[0].to_string()
+                },
+            }
+        });
+        // @{env("NAME")} reads the build's environment, with an optional default
+        ENV_MACRO_REGEX.replace_all(&content, |caps: &regex::Captures| {
+            match (std::env::var(&caps[1]), caps.get(2)) {
+                (Ok(value), _) => value,
+                (Err(_), Some(default)) => default.as_str().to_string(),
+                (Err(_), None) => {
+                    log::warn!("Environment variable '{}' is not set", &caps[1]);
+                    caps[0].to_string()
+                },
+            }
+        }).to_string()
+    }
+
+    /// Replace `@{var("...")}` references, passing each value through its
+    /// filters. References that can't be resolved are left as they are, and
+    /// escaped ones are written out literally.
+    pub fn substitute_vars(&self, content: &str) -> String {
+        let content = resolve_escapes(content);
+        VAR_REGEX.replace_all(&content, |caps: &regex::Captures| {
             let var_name = &caps[1];
-            if let Some(value) = self.get(var_name) {
-                value.to_string()
-            } else {
+            let Some(value) = self.get(var_name) else {
                 log::warn!("Variable '{}' not found", var_name);
-                format!("@{{var(\"{var_name}\")}}")
+                return caps[0].to_string();
+            };
+            let filtered = Filter::parse_chain(&caps[2])
+                .and_then(|filters| filters.iter().try_fold(value.clone(), |value, filter| filter.apply(value)));
+            match filtered.map(|value| filters::render(&value)) {
+                Ok(Some(text)) => text,
+                Ok(None) => {
+                    log::warn!("Variable '{}' is a table and has no text", var_name);
+                    caps[0].to_string()
+                },
+                Err(e) => {
+                    log::warn!("Variable '{}': {}", var_name, e);
+                    caps[0].to_string()
+                },
             }
         }).to_string()
     }
 }
 
+/// Write escaped placeholders out literally: `@@{...}` becomes `@{...}`, and
+/// everything between `@{raw}` and `@{endraw}` is kept as it is, without the
+/// markers. The `@` of each literal placeholder is written as `&#64;`, which
+/// browsers show as `@`, so no later pass mistakes it for a reference.
+pub fn resolve_escapes(content: &str) -> String {
+    if !content.contains("@@{") && !content.contains("@{raw}") {
+        return content.to_string();
+    }
+    ESCAPE_REGEX.replace_all(content, |caps: &regex::Captures| {
+        match caps.get(1) {
+            Some(raw) => raw.as_str().replace("@{", "&#64;{"),
+            None => format!("&#64;{}", &caps[2][1..]),
+        }
+    }).to_string()
+}
+
+/// `content` with its escaped placeholders blanked out, keeping every other
+/// byte where it was, so checks can skip text that's meant literally.
+pub fn mask_escapes(content: &str) -> String {
+    ESCAPE_REGEX.replace_all(content, |caps: &regex::Captures| {
+        caps[0].chars().map(|c| if c == '\n' { "\n".to_string() } else { " ".repeat(c.len_utf8()) }).collect::<String>()
+    }).to_string()
+}
+
+/// The value at `key` in `vars`, either a key of its own or a dotted path
+/// whose numbers, bare or in brackets, index into arrays.
+pub(crate) fn lookup_path<'a>(vars: &'a HashMap<String, toml::Value>, key: &str) -> Option<&'a toml::Value> {
+    if let Some(value) = vars.get(key) {
+        return Some(value);
+    }
+    let path = key.replace('[', ".").replace(']', "");
+    let mut parts = path.split('.');
+    let mut value = vars.get(parts.next()?)?;
+    for part in parts {
+        value = match value {
+            toml::Value::Table(table) => table.get(part)?,
+            toml::Value::Array(items) => items.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// File in a content directory whose variables apply to every page beneath it.
+pub const DIR_VARS_FILE: &str = "_vars.toml";
+
+/// The `_vars.toml` files under the content directory, by their directory
+/// relative to it.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryVariables {
+    dirs: HashMap<PathBuf, HashMap<String, toml::Value>>,
+}
+
+impl DirectoryVariables {
+    pub fn load(content_dir: &Path) -> Result<Self> {
+        let mut dirs = HashMap::new();
+        for entry in WalkDir::new(content_dir).into_iter().filter_map(|e| e.ok()) {
+            if entry.file_name() != DIR_VARS_FILE || !entry.file_type().is_file() {
+                continue;
+            }
+            let vars = Variables::load_file(entry.path())
+                .map_err(|e| anyhow!("Failed to load {}: {}", entry.path().display(), e))?;
+            let dir = entry.path().parent().and_then(|dir| dir.strip_prefix(content_dir).ok()).unwrap_or(Path::new(""));
+            dirs.insert(dir.to_path_buf(), vars.vars);
+        }
+        Ok(Self { dirs })
+    }
+
+    /// Give the pages beneath `dir` a variable unless its `_vars.toml` sets one
+    /// of that name.
+    pub fn set_default(&mut self, dir: &Path, name: &str, value: toml::Value) {
+        self.dirs.entry(dir.to_path_buf()).or_default().entry(name.to_string()).or_insert(value);
+    }
+
+    /// Variables for the page at `relative_path` in the content directory.
+    /// A directory's values override those of the directories above it, with
+    /// tables merged key by key.
+    pub fn for_page(&self, relative_path: &Path) -> HashMap<String, toml::Value> {
+        let mut vars = HashMap::new();
+        let mut dirs: Vec<&Path> = relative_path.ancestors().skip(1).collect();
+        dirs.reverse();
+        for dir in dirs {
+            if let Some(dir_vars) = self.dirs.get(dir) {
+                merge_vars(&mut vars, dir_vars.clone());
+            }
+        }
+        vars
+    }
+}
+
+/// Lay `over` on top of `base`, merging tables key by key.
+pub fn merge_vars(base: &mut HashMap<String, toml::Value>, over: HashMap<String, toml::Value>) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => merge_table(existing, table),
+            (_, value) => {
+                base.insert(key, value);
+            },
+        }
+    }
+}
+
+fn merge_table(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => merge_table(existing, table),
+            (_, value) => {
+                base.insert(key, value);
+            },
+        }
+    }
+}
+
+/// Expand `${NAME}` and `${NAME:-default}` in the strings of `value` from
+/// the environment; `$$` is a literal `$`. Unset variables without a
+/// default expand to nothing.
+fn interpolate_env(value: &mut toml::Value) {
+    match value {
+        toml::Value::String(s) if s.contains('$') => {
+            *s = ENV_REF_REGEX.replace_all(s, |caps: &regex::Captures| {
+                let Some(name) = caps.get(1) else { return "$".to_string() };
+                std::env::var(name.as_str()).unwrap_or_else(|_| match caps.get(2) {
+                    Some(default) => default.as_str().to_string(),
+                    None => {
+                        log::warn!("Environment variable '{}' is not set", name.as_str());
+                        String::new()
+                    },
+                })
+            }).to_string();
+        },
+        toml::Value::Array(items) => items.iter_mut().for_each(interpolate_env),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, value)| interpolate_env(value)),
+        _ => {},
+    }
+}
+
+/// The `data/` directory next to the variables config.
+pub fn data_dir(config_path: &Path) -> PathBuf {
+    config_path.parent().unwrap_or(Path::new("")).join("data")
+}
+
+/// Variables from `config_path`, with the files of the `data/` directory
+/// beside it. A file that can't be read or parsed is an error naming it,
+/// rather than a site built without any variables.
 pub fn load_variables(config_path: &Path) -> Result<Variables> {
-    Variables::load(config_path)
-}
\ No newline at end of file
+    let mut vars = Variables::load(config_path)?;
+    vars.data = load_data_dir(&data_dir(config_path))?;
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_paths_reach_nested_tables_and_arrays() {
+        let mut vars: Variables = toml::from_str(
+            "title = \"flat\"\n\"a.b\" = \"dotted key\"\n\
+             [site]\ntitle = \"Site\"\nlinks = [{ url = \"/one\" }, { url = \"/two\" }]",
+        ).unwrap();
+        vars.env_vars = Some(toml::from_str("[site]\ntitle = \"Staging\"").unwrap());
+
+        assert_eq!(vars.resolve("site.title"), Some((&toml::Value::from("Staging"), "env")));
+        assert_eq!(vars.get("site.links[1].url"), Some(&toml::Value::from("/two")));
+        assert_eq!(vars.get("site.links.0.url"), Some(&toml::Value::from("/one")));
+        assert_eq!(vars.get("a.b"), Some(&toml::Value::from("dotted key")));
+        assert_eq!(vars.get("site.links.2.url"), None);
+        assert_eq!(vars.get("title.length"), None);
+
+        vars.set_page_vars(HashMap::from([("site".to_string(), toml::from_str("title = \"Page\"").unwrap())]));
+        assert_eq!(vars.resolve("site.title"), Some((&toml::Value::from("Page"), "page")));
+        assert_eq!(vars.resolve("site.links.0.url").map(|(_, scope)| scope), Some("global"));
+    }
+
+    #[test]
+    fn test_environment_interpolation_and_macro() {
+        std::env::set_var("ELDROID_TEST_ANALYTICS_ID", "G-123");
+        std::env::remove_var("ELDROID_TEST_UNSET");
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("variables.toml");
+        fs::write(&path, concat!(
+            "analytics = \"${ELDROID_TEST_ANALYTICS_ID}\"\n",
+            "api = \"${ELDROID_TEST_UNSET:-https://api.example.com}/v1\"\n",
+            "price = \"$$5 ${ELDROID_TEST_UNSET}\"\n",
+            "[nested]\nlist = [\"${ELDROID_TEST_ANALYTICS_ID}\"]\n",
+        )).unwrap();
+
+        let vars = Variables::load(&path).unwrap();
+        assert_eq!(vars.get("analytics"), Some(&toml::Value::from("G-123")));
+        assert_eq!(vars.get("api"), Some(&toml::Value::from("https://api.example.com/v1")));
+        assert_eq!(vars.get("price"), Some(&toml::Value::from("$5 ")));
+        assert_eq!(vars.get("nested.list.0"), Some(&toml::Value::from("G-123")));
+        assert_eq!(
+            vars.substitute(r#"@{env("ELDROID_TEST_ANALYTICS_ID")} @{env("ELDROID_TEST_UNSET", "none")} @{env("ELDROID_TEST_UNSET")}"#),
+            r#"G-123 none @{env("ELDROID_TEST_UNSET")}"#,
+        );
+    }
+
+    #[test]
+    fn test_directory_variables_cascade() {
+        let temp = tempfile::tempdir().unwrap();
+        let content = temp.path();
+        fs::create_dir_all(content.join("docs/guide")).unwrap();
+        fs::write(content.join(DIR_VARS_FILE), "sidebar = \"main\"\n[nav]\nhome = \"/\"").unwrap();
+        fs::write(content.join("docs").join(DIR_VARS_FILE), "layout = \"docs.html\"\nsidebar = \"docs\"\n[nav]\nup = \"/docs\"").unwrap();
+        fs::write(content.join("docs/guide").join(DIR_VARS_FILE), "sidebar = \"guide\"").unwrap();
+
+        let dirs = DirectoryVariables::load(content).unwrap();
+        let guide = dirs.for_page(Path::new("docs/guide/install.html"));
+        assert_eq!(guide["sidebar"].as_str(), Some("guide"));
+        assert_eq!(guide["layout"].as_str(), Some("docs.html"));
+        assert_eq!(guide["nav"], toml::from_str::<toml::Value>("home = \"/\"\nup = \"/docs\"").unwrap());
+        let home = dirs.for_page(Path::new("index.html"));
+        assert_eq!(home["sidebar"].as_str(), Some("main"));
+        assert!(!home.contains_key("layout"));
+    }
+
+    #[test]
+    fn test_substitute_renders_values_through_filters() {
+        let vars: Variables = toml::from_str("name = \"Ada\"\nprice = 4.5\ntags = [\"a\", \"b\"]\n[site]\ntitle = \"x\"").unwrap();
+        let content = r#"@{var("name")} @{var("price") | format("$%.2f")} @{var("tags")} @{var("site")} @{var("name") | nope}"#;
+        assert_eq!(
+            vars.substitute(content),
+            r#"Ada $4.50 a, b @{var("site")} @{var("name") | nope}"#,
+        );
+    }
+
+    #[test]
+    fn test_escaped_placeholders_are_written_literally() {
+        let vars: Variables = toml::from_str("name = \"Ada\"").unwrap();
+        let content = "@{var(\"name\")} @@{var(\"name\") | format(\"{}\")} @{raw}<code>@{var(\"name\")}\n@{env(\"HOME\")}</code>@{endraw} @{var(\"name\")}";
+        let expected = "Ada &#64;{var(\"name\") | format(\"{}\")} <code>&#64;{var(\"name\")}\n&#64;{env(\"HOME\")}</code> Ada";
+        assert_eq!(vars.substitute(content), expected);
+        assert_eq!(crate::macros::MacroProcessor::new().with_variables(vars).process(content), expected);
+        assert_eq!(crate::macros::MacroProcessor::new().process("@@{title}"), "&#64;{title}");
+        assert_eq!(mask_escapes("a @@{x}\n@{raw}é@{endraw}").len(), "a @@{x}\n@{raw}é@{endraw}".len());
+    }
+}
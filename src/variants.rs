@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use crate::history;
+
+lazy_static! {
+    // Quotes may already be escaped when the reference is in Markdown text
+    static ref VARIANT_REGEX: Regex = Regex::new(r#"@\{variant\((?:"|'|&quot;)([\w.-]+)(?:"|'|&quot;)\)\}"#).unwrap();
+}
+
+/// Cookie lifetime for a visitor's variant assignment, in seconds.
+const ASSIGNMENT_MAX_AGE: u32 = 30 * 24 * 60 * 60;
+
+/// One arm of an A/B test declared in front matter `variants:`.
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: String,
+    /// Relative share of visitors, 1 unless the arm sets `weight`
+    pub weight: u32,
+    /// Front matter this arm replaces, e.g. `title`
+    pub overrides: Mapping,
+}
+
+impl Variant {
+    /// Arms in the order they are declared. The first is served at the
+    /// page's own URL.
+    pub fn parse_all(variants: &Mapping) -> Result<Vec<Self>> {
+        variants.iter()
+            .map(|(name, arm)| {
+                let name = name.as_str()
+                    .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+                    .ok_or_else(|| anyhow!("Variant names must be letters, digits, '-' or '_', got {:?}", name))?
+                    .to_string();
+                let mut overrides = match arm {
+                    Value::Mapping(mapping) => mapping.clone(),
+                    Value::Null => Mapping::new(),
+                    _ => return Err(anyhow!("Variant '{}' must be a mapping of front matter overrides", name)),
+                };
+                let weight = match overrides.remove("weight") {
+                    Some(weight) => weight.as_u64()
+                        .and_then(|weight| u32::try_from(weight).ok())
+                        .ok_or_else(|| anyhow!("Variant '{}' weight must be a whole number", name))?,
+                    None => 1,
+                };
+                Ok(Self { name, weight, overrides })
+            })
+            .collect()
+    }
+
+    /// Output path of a non-control arm, e.g. `post.variant-b.html`.
+    pub fn output_path(&self, control: &Path) -> PathBuf {
+        let stem = control.file_stem().unwrap_or_default().to_string_lossy();
+        control.with_file_name(format!("{}.variant-{}.html", stem, self.name))
+    }
+
+    /// Resolve `@{variant("key")}` to this arm's value for `key`.
+    pub fn substitute(&self, content: &str) -> String {
+        VARIANT_REGEX.replace_all(content, |caps: &regex::Captures| {
+            let key = &caps[1];
+            match self.overrides.get(key) {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Number(value)) => value.to_string(),
+                Some(Value::Bool(value)) => value.to_string(),
+                _ => {
+                    warn!("Variant '{}' has no value for '{}'", self.name, key);
+                    String::new()
+                },
+            }
+        }).to_string()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestArm {
+    path: String,
+    weight: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestPage {
+    /// Cookie remembering which arm a visitor was assigned
+    cookie: String,
+    /// Arm served at the page's own URL
+    control: String,
+    variants: BTreeMap<String, ManifestArm>,
+}
+
+/// Every page with variants, keyed by its URL path, for `variants.json`
+/// and the edge router.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VariantManifest {
+    pages: BTreeMap<String, ManifestPage>,
+}
+
+impl VariantManifest {
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Record the arms of the page written to `control` (an output path).
+    pub fn add(&mut self, output_dir: &Path, control: &Path, variants: &[Variant]) {
+        let url = |path: &Path| format!("/{}", path.strip_prefix(output_dir).unwrap_or(path).to_string_lossy().replace('\\', "/"));
+        let page_url = url(control);
+        let arms = variants.iter().enumerate()
+            .map(|(i, variant)| {
+                let path = if i == 0 { control.to_path_buf() } else { variant.output_path(control) };
+                (variant.name.clone(), ManifestArm { path: url(&path), weight: variant.weight })
+            })
+            .collect();
+        self.pages.insert(page_url.clone(), ManifestPage {
+            cookie: format!("ab_{}", &history::hash(page_url.as_bytes())[..8]),
+            control: variants.first().map(|variant| variant.name.clone()).unwrap_or_default(),
+            variants: arms,
+        });
+    }
+
+    /// Add the pages recorded by another build, e.g. a shard of this one.
+    pub fn extend(&mut self, other: VariantManifest) {
+        self.pages.extend(other.pages);
+    }
+
+    /// Write `variants.json` and `ab-router.js`, an edge worker that assigns
+    /// each visitor a sticky arm and serves it from the page's own URL.
+    pub fn write(&self, output_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(output_dir.join("variants.json"), &json)?;
+        fs::write(output_dir.join("ab-router.js"), ROUTER_TEMPLATE
+            .replace("__MANIFEST__", &json)
+            .replace("__MAX_AGE__", &ASSIGNMENT_MAX_AGE.to_string()))?;
+        info!("A/B variants for {} pages written to variants.json and ab-router.js", self.pages.len());
+        Ok(())
+    }
+}
+
+const ROUTER_TEMPLATE: &str = r#"// Generated by eldroid-ssg from variants.json.
+// Assigns each visitor a sticky variant per page and serves it from the page's
+// own URL. Deploy as a Cloudflare Worker (with static assets bound to ASSETS),
+// or call pickVariant() from another edge runtime's request handler.
+const MANIFEST = __MANIFEST__;
+
+export function pickVariant(request) {
+  const path = new URL(request.url).pathname;
+  const page = MANIFEST[path] || MANIFEST[path + '.html'] || MANIFEST[path.replace(/\/?$/, '/') + 'index.html'];
+  if (!page) return null;
+  const names = Object.keys(page.variants);
+  const cookies = request.headers.get('Cookie') || '';
+  const match = cookies.match(new RegExp('(?:^|;\\s*)' + page.cookie + '=([^;]+)'));
+  if (match && page.variants[match[1]]) {
+    return { name: match[1], path: page.variants[match[1]].path, setCookie: null };
+  }
+  const total = names.reduce((sum, name) => sum + page.variants[name].weight, 0);
+  let roll = Math.random() * total;
+  const name = names.find((name) => (roll -= page.variants[name].weight) < 0) || names[0];
+  return {
+    name,
+    path: page.variants[name].path,
+    setCookie: `${page.cookie}=${name}; Path=/; Max-Age=__MAX_AGE__; SameSite=Lax`,
+  };
+}
+
+export default {
+  async fetch(request, env) {
+    const origin = (req) => (env && env.ASSETS ? env.ASSETS.fetch(req) : fetch(req));
+    const variant = pickVariant(request);
+    if (!variant) return origin(request);
+    const url = new URL(request.url);
+    url.pathname = variant.path;
+    const response = await origin(new Request(url, request));
+    const headers = new Headers(response.headers);
+    headers.set('Cache-Control', 'private, no-cache');
+    headers.append('Vary', 'Cookie');
+    if (variant.setCookie) headers.append('Set-Cookie', variant.setCookie);
+    return new Response(response.body, { status: response.status, headers });
+  },
+};
+"#;
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+lazy_static! {
+    // Stamped with the time of the build, or in sitemaps with the time the
+    // page was written when it has no date of its own
+    static ref BUILD_TIME_REGEX: Regex = Regex::new(r"<(lastBuildDate|lastmod)>[^<]*</(lastBuildDate|lastmod)>").unwrap();
+}
+
+/// Output that differs between builds of the same sources: the build cache
+/// and log, and the analysis dashboard.
+const VOLATILE: &[&str] = &["cache/**", "performance/**"];
+
+/// Extensions of text output normalized before hashing.
+const TEXT: &[&str] = &["html", "htm", "xml", "json", "txt", "css", "js", "svg", "webmanifest"];
+
+/// Output verification settings, `[verify]` in `eldroid.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VerifyConfig {
+    /// Globs relative to the output directory, in which `*` also matches `/`,
+    /// of files left out of output manifests besides the cache and dashboard.
+    /// They are recorded in the manifest, which verification takes them from.
+    #[serde(default)]
+    pub volatile: Vec<String>,
+}
+
+/// The built-in volatile globs and `volatile`.
+fn globs(volatile: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in VOLATILE.iter().copied().chain(volatile.iter().map(String::as_str)) {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid volatile pattern '{}'", pattern))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// SHA-256 of every normalized output file keyed by its path relative to the
+/// output directory, written by `--output-manifest` and checked by `--verify-output`.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputManifest {
+    pub files: BTreeMap<String, String>,
+    /// Globs of the files left out besides the cache and dashboard
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volatile: Vec<String>,
+}
+
+/// Where a build's output differs from a manifest.
+#[derive(Debug, Default, PartialEq)]
+pub struct OutputDiff {
+    pub missing: Vec<String>,
+    pub unexpected: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl OutputDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl OutputManifest {
+    /// Hash the files in `output_dir` except those matching `volatile` and
+    /// `skip`, usually the manifest itself.
+    pub fn from_output(output_dir: &Path, volatile: &[String], skip: Option<&Path>) -> Result<Self> {
+        let globs = globs(volatile)?;
+        let output_dir = output_dir.canonicalize()
+            .with_context(|| format!("Failed to read {}", output_dir.display()))?;
+        let skip = skip.map(canonical);
+        let mut files = BTreeMap::new();
+        for entry in WalkDir::new(&output_dir).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !entry.file_type().is_file() || skip.as_deref() == Some(path) {
+                continue;
+            }
+            let relative = path.strip_prefix(&output_dir)?.to_string_lossy().replace('\\', "/");
+            if globs.is_match(&relative) {
+                continue;
+            }
+            let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            files.insert(relative, hex(&Sha256::digest(normalize(path, bytes))));
+        }
+        Ok(Self { files, volatile: volatile.to_vec() })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read output manifest {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid output manifest {}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Compare `actual` against this expected manifest, ignoring expected
+    /// files that `actual` left out as volatile.
+    pub fn diff(&self, actual: &OutputManifest) -> Result<OutputDiff> {
+        let volatile = globs(&actual.volatile)?;
+        let mut diff = OutputDiff::default();
+        for (path, hash) in self.files.iter().filter(|(path, _)| !volatile.is_match(path.as_str())) {
+            match actual.files.get(path) {
+                None => diff.missing.push(path.clone()),
+                Some(actual_hash) if actual_hash != hash => diff.changed.push(path.clone()),
+                Some(_) => {},
+            }
+        }
+        diff.unexpected = actual.files.keys()
+            .filter(|path| !self.files.contains_key(*path))
+            .cloned()
+            .collect();
+        Ok(diff)
+    }
+}
+
+/// Remove differences that don't change what a file serves: Windows line
+/// endings and build timestamps in text output.
+pub fn normalize(path: &Path, bytes: Vec<u8>) -> Vec<u8> {
+    let is_text = path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| TEXT.contains(&ext.to_lowercase().as_str()));
+    if !is_text {
+        return bytes;
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => BUILD_TIME_REGEX
+            .replace_all(&text.replace("\r\n", "\n"), "<$1></$2>")
+            .into_owned()
+            .into_bytes(),
+        Err(e) => e.into_bytes(),
+    }
+}
+
+/// `path` made absolute with symlinks resolved, also when the file doesn't
+/// exist yet, to compare with the paths of walked files.
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Lowercase hexadecimal of `bytes`, as digests are written.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verifies_normalized_output() {
+        assert_eq!(normalize(Path::new("sitemap.xml"), b"<lastmod>2024-01-02</lastmod>".to_vec()), b"<lastmod></lastmod>");
+
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path();
+        fs::create_dir_all(out.join("cache")).unwrap();
+        fs::write(out.join("index.html"), "<p>Hi</p>\n").unwrap();
+        fs::write(out.join("rss.xml"), "<lastBuildDate>Mon, 01 Jan 2024</lastBuildDate>").unwrap();
+        fs::write(out.join("cache/builds.log.jsonl"), "{}").unwrap();
+        let config: VerifyConfig = toml::from_str("volatile = [\"stats/*.json\"]").unwrap();
+        let expected = OutputManifest::from_output(out, &config.volatile, None).unwrap();
+        assert_eq!(expected.files.keys().collect::<Vec<_>>(), ["index.html", "rss.xml"]);
+        assert_eq!(expected.volatile, ["stats/*.json"]);
+
+        fs::write(out.join("index.html"), "<p>Hi</p>\r\n").unwrap();
+        fs::write(out.join("rss.xml"), "<lastBuildDate>Tue, 02 Jan 2024</lastBuildDate>").unwrap();
+        fs::create_dir_all(out.join("stats/daily")).unwrap();
+        fs::write(out.join("stats/daily/views.json"), "[]").unwrap();
+        let actual = OutputManifest::from_output(out, &config.volatile, None).unwrap();
+        assert!(expected.diff(&actual).unwrap().is_empty());
+
+        fs::write(out.join("index.html"), "<p>Hi!</p>").unwrap();
+        fs::write(out.join("extra.js"), "").unwrap();
+        fs::remove_file(out.join("rss.xml")).unwrap();
+        let actual = OutputManifest::from_output(out, &config.volatile, None).unwrap();
+        assert_eq!(expected.diff(&actual).unwrap(), OutputDiff {
+            missing: vec!["rss.xml".to_string()],
+            unexpected: vec!["extra.js".to_string()],
+            changed: vec!["index.html".to_string()],
+        });
+    }
+
+    #[test]
+    fn test_verification_takes_volatile_files_from_the_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path();
+        fs::create_dir_all(out.join("stats")).unwrap();
+        fs::write(out.join("index.html"), "<p>Hi</p>").unwrap();
+        let expected = OutputManifest::from_output(out, &["stats/**".to_string()], None).unwrap();
+        let manifest = out.join("manifest.json");
+        expected.write(&manifest).unwrap();
+
+        // The manifest is left out however its path is spelled
+        fs::write(out.join("stats/views.json"), "[]").unwrap();
+        let expected = OutputManifest::load(&manifest).unwrap();
+        let actual = OutputManifest::from_output(out, &expected.volatile, Some(&out.join("stats/../manifest.json"))).unwrap();
+        assert_eq!(actual.files.keys().collect::<Vec<_>>(), ["index.html"]);
+        assert!(expected.diff(&actual).unwrap().is_empty());
+
+        // Without the manifest's globs the file is unexpected
+        let actual = OutputManifest::from_output(out, &[], Some(&manifest)).unwrap();
+        assert_eq!(expected.diff(&actual).unwrap().unexpected, ["stats/views.json"]);
+    }
+}
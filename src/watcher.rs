@@ -1,18 +1,35 @@
-use std::path::{PathBuf};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::broadcast;
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use warp::Filter;
+use notify::{Config, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+use futures::future::{self, BoxFuture};
 use futures::StreamExt;
 use futures::SinkExt;
-use log::{info, error};
+use log::{info, warn, error};
 use portpicker::pick_unused_port;
 use parking_lot::RwLock;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
+use std::sync::mpsc;
+use std::thread;
 use std::fs;
 use std::io;
 use thiserror::Error;
+use crate::config::BuildConfig;
+use crate::markdown::is_authored_by;
+use crate::social_preview::{page_candidates, render_preview, resolve_page};
+use crate::tls::TlsIdentity;
+use crate::variables::DIR_VARS_FILE;
+use crate::proxy::{self, ProxyRule};
+use crate::error_handler::{BuildError, ErrorHandler};
+use crate::presence::{same_origin, ClientMessage, Ping, Presence, Reviewer};
+use crate::report::Finding;
 
 #[derive(Error, Debug)]
 pub enum DevServerError {
@@ -20,6 +37,16 @@ pub enum DevServerError {
     DirectoryCreation(#[from] io::Error),
     #[error("Watcher error: {0}")]
     Watcher(#[from] notify::Error),
+    #[error("Invalid watch ignore pattern: {0}")]
+    IgnorePattern(#[from] globset::Error),
+    #[error("Failed to start server: {0}")]
+    Bind(#[from] warp::Error),
+    #[error("Port {port} is already in use{}; stop it, pick another --port, or pass --port-fallback", .holder.as_ref().map(|h| format!(" by {}", h)).unwrap_or_default())]
+    PortInUse { port: u16, holder: Option<String> },
+    #[error("Failed to listen on port {port}: {source}")]
+    Listen { port: u16, source: io::Error },
+    #[error("No free port available for the development server")]
+    NoFreePort,
 }
 
 #[derive(Debug, Clone)]
@@ -34,16 +61,178 @@ pub enum ChangeType {
     Modify,
     Delete,
     CssChange,  // Special handling for CSS files
-    Error(String),  // For tracking build/processing errors
+    Error(BuildError),  // For tracking build/processing errors
+    Rebuilt(Vec<String>),  // URL paths of the pages written by an incremental rebuild
+    Presence(Vec<Reviewer>),  // Everyone with the site open, after one comes or goes
+    Ping(Ping),  // A reviewer pointing the others to a section
+}
+
+/// What a rebuild wrote.
+#[derive(Debug, Clone)]
+pub enum Rebuilt {
+    /// Just these output files
+    Pages(Vec<PathBuf>),
+    /// The whole site, e.g. after a configuration change
+    Site,
+}
+
+/// Pages rebuilt in watch mode, kept in memory and served ahead of the
+/// output directory instead of being written to it.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPages {
+    /// Page content by output path, e.g. `output/blog/post.html`
+    pages: Arc<RwLock<HashMap<PathBuf, String>>>,
+}
+
+impl MemoryPages {
+    pub fn insert(&self, out_path: PathBuf, content: String) {
+        self.pages.write().insert(out_path, content);
+    }
+
+    /// Drop every page, e.g. once a full build has written them all to disk.
+    pub fn clear(&self) {
+        self.pages.write().clear();
+    }
+
+    /// The page served at `url_path`, trying the same clean URLs as the disk.
+    fn get(&self, output_dir: &Path, url_path: &str) -> Option<String> {
+        let pages = self.pages.read();
+        page_candidates(output_dir, url_path).iter().find_map(|candidate| pages.get(candidate).cloned())
+    }
+}
+
+/// Analyzer findings of each page as of its last build, shown in the dev
+/// overlay of browsers that have the page open.
+#[derive(Debug, Clone, Default)]
+pub struct PageFindings {
+    /// Findings by output path, e.g. `output/blog/post.html`
+    findings: Arc<RwLock<HashMap<PathBuf, Vec<Finding>>>>,
+}
+
+impl PageFindings {
+    pub fn set(&self, out_path: PathBuf, findings: Vec<Finding>) {
+        self.findings.write().insert(out_path, findings);
+    }
+
+    /// Findings of the page served at `url_path`.
+    fn get(&self, output_dir: &Path, url_path: &str) -> Vec<Finding> {
+        let findings = self.findings.read();
+        page_candidates(output_dir, url_path).iter()
+            .find_map(|candidate| findings.get(candidate).cloned())
+            .unwrap_or_default()
+    }
+}
+
+/// Rebuilds what a changed content, component, or configuration file affects.
+pub type RebuildHook = Arc<dyn Fn(&Path) -> anyhow::Result<Rebuilt> + Send + Sync>;
+
+/// What each live reload connection shares: change events, the error to show
+/// on connect, reviewers, and page findings with the output directory.
+type WsState = (broadcast::Sender<FileChange>, Option<BuildError>, Presence, Option<(PageFindings, PathBuf)>);
+
+/// Paths the watcher skips by default: editor swap and backup files and VCS metadata.
+const DEFAULT_WATCH_IGNORE: &[&str] = &["*.swp", "*.swx", "*~", "*.tmp", "**/.#*", "**/.DS_Store", "**/.git/**"];
+
+/// How the dev server notices file changes.
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Quiet period after the last event before changes are handled
+    pub debounce: Duration,
+    /// Extra globs to ignore, matched against paths relative to the working directory
+    pub ignore: Vec<String>,
+    /// Poll at this interval instead of using native file events, for
+    /// network filesystems and container volumes that don't deliver them
+    pub poll_interval: Option<Duration>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(100),
+            ignore: Vec::new(),
+            poll_interval: None,
+        }
+    }
+}
+
+impl From<&BuildConfig> for WatchOptions {
+    fn from(config: &BuildConfig) -> Self {
+        Self {
+            debounce: Duration::from_millis(config.watch_debounce_ms),
+            ignore: config.watch_ignore.clone(),
+            poll_interval: config.watch_poll.then(|| Duration::from_millis(config.watch_poll_interval_ms)),
+        }
+    }
+}
+
+impl WatchOptions {
+    fn ignore_set(&self, output_dir: &Path) -> Result<IgnoreSet, DevServerError> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in DEFAULT_WATCH_IGNORE.iter().copied().chain(self.ignore.iter().map(String::as_str)) {
+            builder.add(Glob::new(pattern)?);
+        }
+        Ok(IgnoreSet {
+            globs: builder.build()?,
+            root: std::env::current_dir()?.canonicalize()?,
+            output_dir: output_dir.canonicalize().unwrap_or_else(|_| output_dir.to_path_buf()),
+        })
+    }
+}
+
+/// Paths whose changes the watcher drops before debouncing.
+struct IgnoreSet {
+    globs: GlobSet,
+    /// Globs match paths relative to this directory
+    root: PathBuf,
+    /// Builds write here, so its changes would trigger endless rebuilds
+    output_dir: PathBuf,
+}
+
+impl IgnoreSet {
+    fn is_ignored(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        path.starts_with(&self.output_dir) || self.globs.is_match(relative)
+    }
 }
 
 pub struct DevServer {
     input_dir: PathBuf,
     output_dir: PathBuf,
     components_dir: PathBuf,
-    port: u16,
-    ws_port: u16,
+    /// Port to listen on; a free one is picked when unset
+    port: Option<u16>,
+    /// Use the next free port when `port` is taken
+    port_fallback: bool,
+    /// Address to listen on; `0.0.0.0` reaches other devices on the network
+    host: IpAddr,
+    /// Open the site in the default browser once the server is listening
+    open_browser: bool,
+    /// Serve over HTTPS with this certificate
+    tls: Option<TlsIdentity>,
+    /// Routes forwarded to backend servers
+    proxy: Vec<ProxyRule>,
     changed_files: Arc<RwLock<HashSet<PathBuf>>>,
+    /// Only react to content pages by this author
+    author: Option<String>,
+    /// List the contents of directories that have no index.html
+    directory_listing: bool,
+    /// Rebuilds affected pages when a content file changes
+    rebuild: Option<RebuildHook>,
+    /// Configuration files whose changes trigger a rebuild
+    config_files: Vec<PathBuf>,
+    /// Static asset directory whose changes trigger a rebuild
+    asset_dir: Option<PathBuf>,
+    /// Template data directory whose changes trigger a rebuild
+    data_dir: Option<PathBuf>,
+    /// Theme directory whose changes trigger a full rebuild
+    theme_dir: Option<PathBuf>,
+    watch_options: WatchOptions,
+    /// Error from the build before the server started
+    build_error: Option<BuildError>,
+    /// Rebuilt pages served from memory ahead of the output directory
+    memory_pages: Option<MemoryPages>,
+    /// Findings sent to each browser for the page it has open
+    page_findings: Option<PageFindings>,
 }
 
 impl DevServer {
@@ -52,18 +241,127 @@ impl DevServer {
         output_dir: impl Into<PathBuf>,
         components_dir: impl Into<PathBuf>,
         port: Option<u16>,
-        ws_port: Option<u16>,
     ) -> Self {
         Self {
             input_dir: input_dir.into(),
             output_dir: output_dir.into(),
             components_dir: components_dir.into(),
-            port: port.unwrap_or_else(|| pick_unused_port().expect("No ports available")),
-            ws_port: ws_port.unwrap_or_else(|| pick_unused_port().expect("No ports available")),
+            port,
+            port_fallback: false,
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            open_browser: false,
+            tls: None,
+            proxy: Vec::new(),
             changed_files: Arc::new(RwLock::new(HashSet::new())),
+            author: None,
+            directory_listing: true,
+            rebuild: None,
+            config_files: Vec::new(),
+            asset_dir: None,
+            data_dir: None,
+            theme_dir: None,
+            watch_options: WatchOptions::default(),
+            build_error: None,
+            memory_pages: None,
+            page_findings: None,
         }
     }
+
+    /// Ignore changes to content pages whose front matter names a different author.
+    pub fn with_author(mut self, author: Option<String>) -> Self {
+        self.author = author;
+        self
+    }
+
+    pub fn with_host(mut self, host: IpAddr) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// When the requested port is taken, listen on the next free one instead.
+    pub fn with_port_fallback(mut self, enabled: bool) -> Self {
+        self.port_fallback = enabled;
+        self
+    }
+
+    pub fn with_open_browser(mut self, enabled: bool) -> Self {
+        self.open_browser = enabled;
+        self
+    }
+
+    /// Serve over HTTPS, so pages can use APIs that need a secure context.
+    pub fn with_tls(mut self, identity: Option<TlsIdentity>) -> Self {
+        self.tls = identity;
+        self
+    }
+
+    /// Forward requests under these paths to other servers, such as an API
+    /// backend, so pages can call it without CORS. Earlier rules win.
+    pub fn with_proxy(mut self, rules: Vec<ProxyRule>) -> Self {
+        self.proxy = rules;
+        self
+    }
+
+    pub fn with_directory_listing(mut self, enabled: bool) -> Self {
+        self.directory_listing = enabled;
+        self
+    }
+
+    /// Rebuild pages as content files change and reload only the browsers
+    /// showing them. Without a hook every change reloads every page.
+    pub fn with_rebuild(mut self, rebuild: RebuildHook) -> Self {
+        self.rebuild = Some(rebuild);
+        self
+    }
+
+    pub fn with_watch_options(mut self, options: WatchOptions) -> Self {
+        self.watch_options = options;
+        self
+    }
+
+    /// Serve these in-memory pages ahead of the output directory. The rebuild
+    /// hook fills them in place of writing files.
+    pub fn with_memory_pages(mut self, pages: Option<MemoryPages>) -> Self {
+        self.memory_pages = pages;
+        self
+    }
+
+    /// Send browsers the findings of the page they show, as builds record them.
+    pub fn with_page_findings(mut self, findings: Option<PageFindings>) -> Self {
+        self.page_findings = findings;
+        self
+    }
+
+    /// Show this error from the initial build until a rebuild succeeds.
+    pub fn with_build_error(mut self, error: Option<BuildError>) -> Self {
+        self.build_error = error;
+        self
+    }
+
+    /// Also watch these configuration files, which need not exist yet.
+    pub fn with_config_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.config_files = files;
+        self
+    }
     
+    /// Also watch the static asset directory, when it exists.
+    pub fn with_asset_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.asset_dir = dir.filter(|dir| dir.is_dir());
+        self
+    }
+
+    /// Also watch the template data directory, when it exists.
+    pub fn with_data_dir(mut self, dir: PathBuf) -> Self {
+        self.data_dir = Some(dir).filter(|dir| dir.is_dir());
+        self
+    }
+
+    /// Also watch the site's theme, whose files rebuild the whole site.
+    pub fn with_theme_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.theme_dir = dir.filter(|dir| dir.is_dir());
+        self
+    }
+
     fn ensure_directory(&self, path: &PathBuf) -> Result<(), DevServerError> {
         if !path.exists() {
             fs::create_dir_all(path)?;
@@ -93,30 +391,113 @@ impl DevServer {
         // Set up file watcher
         let (tx, _) = broadcast::channel(100);
         let tx_clone = tx.clone();
+        let error_handler = Arc::new(ErrorHandler::new(tx.clone()).with_last_error(self.build_error.clone()));
+        let ws_errors = error_handler.clone();
         
-        let mut watcher = self.setup_watcher(tx)?;
+        let mut watcher = self.setup_watcher(tx, error_handler)?;
         
-        // Watch input and components directories
-        watcher.watch(&self.input_dir, RecursiveMode::Recursive)?;
-        watcher.watch(&self.components_dir, RecursiveMode::Recursive)?;
+        // Watch input and components directories. Absolute paths make every
+        // watcher backend report absolute paths too.
+        watcher.watch(&self.input_dir.canonicalize()?, RecursiveMode::Recursive)?;
+        watcher.watch(&self.components_dir.canonicalize()?, RecursiveMode::Recursive)?;
+        for dir in self.asset_dir.iter().chain(&self.data_dir).chain(&self.theme_dir) {
+            watcher.watch(&dir.canonicalize()?, RecursiveMode::Recursive)?;
+        }
+
+        // Watch configuration files through their directories, since editors
+        // often replace a file rather than write to it
+        let config_dirs: HashSet<PathBuf> = self.config_files.iter()
+            .map(|file| {
+                let dir = config_dir(file);
+                dir.canonicalize().unwrap_or(dir)
+            })
+            .collect();
+        for dir in config_dirs {
+            watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        }
 
-        // Set up WebSocket for live reload
+        // Set up WebSocket for live reload. Browsers also say who is reviewing
+        // which page and ping each other to look at a section.
+        // Each page also gets its analyzer findings when it says hello, which
+        // it does again after reloading for a rebuild.
+        let presence = Presence::default();
+        let findings = self.page_findings.clone().map(|findings| (findings, self.output_dir.clone()));
         let ws_route = warp::path("ws")
             .and(warp::ws())
-            .and(warp::any().map(move || tx_clone.subscribe()))
-            .map(|ws: warp::ws::Ws, mut rx: broadcast::Receiver<FileChange>| {
+            .and(warp::header::optional::<String>("origin"))
+            .and(warp::header::optional::<String>("host"))
+            .and(warp::any().map(move || (tx_clone.clone(), ws_errors.last_error(), presence.clone(), findings.clone())))
+            .map(|ws: warp::ws::Ws, origin: Option<String>, host: Option<String>, (changes, last_error, presence, findings): WsState| {
+                // Other sites open in the browser mustn't join as reviewers
+                if !same_origin(origin.as_deref(), host.as_deref()) {
+                    warn!("Refused a live reload connection from {}", origin.as_deref().unwrap_or_default());
+                    return warp::reply::with_status("Cross-origin connections are refused", StatusCode::FORBIDDEN).into_response();
+                }
                 ws.on_upgrade(move |socket| async move {
-                    let (mut tx, _) = socket.split();
-                    while let Ok(change) = rx.recv().await {
+                    let mut rx = changes.subscribe();
+                    let id = presence.connect();
+                    let (mut tx, mut incoming) = socket.split();
+                    // Pages opened while the site has an error show it right away
+                    if let Some(err) = last_error {
+                        let msg = serde_json::json!({ "type": "error", "error": err }).to_string();
+                        if tx.send(warp::ws::Message::text(msg)).await.is_err() {
+                            return;
+                        }
+                    }
+                    let announce = |event_type: ChangeType| {
+                        let _ = changes.send(FileChange { path: PathBuf::new(), event_type });
+                    };
+                    loop {
+                        let change = tokio::select! {
+                            change = rx.recv() => match change {
+                                Ok(change) => change,
+                                Err(_) => break,
+                            },
+                            message = incoming.next() => {
+                                let Some(Ok(message)) = message else { break };
+                                match message.to_str().ok().and_then(|text| serde_json::from_str(text).ok()) {
+                                    Some(ClientMessage::Hello { name, path }) => {
+                                        if !presence.hello(id, name, path.clone()) {
+                                            continue;
+                                        }
+                                        if let Some((findings, output_dir)) = &findings {
+                                            let page_findings = findings.get(output_dir, &path);
+                                            let msg = serde_json::json!({ "type": "findings", "findings": page_findings }).to_string();
+                                            if tx.send(warp::ws::Message::text(msg)).await.is_err() {
+                                                break;
+                                            }
+                                        }
+                                        announce(ChangeType::Presence(presence.reviewers()));
+                                    },
+                                    Some(ClientMessage::Ping { path, section, label }) => {
+                                        if let Some(ping) = presence.ping(id, path, section, label) {
+                                            announce(ChangeType::Ping(ping));
+                                        }
+                                    },
+                                    None => {},
+                                }
+                                continue;
+                            },
+                        };
                         let msg = match change.event_type {
                             ChangeType::CssChange => {
                                 // For CSS changes, send a special message to reload only CSS
-                                format!("{{\"type\":\"css\",\"path\":\"{}\"}}", 
-                                    change.path.display())
+                                serde_json::json!({ "type": "css", "path": change.path.display().to_string() }).to_string()
                             },
                             ChangeType::Error(err) => {
                                 // For errors, send error details to show in overlay
-                                format!("{{\"type\":\"error\",\"message\":\"{}\"}}", err)
+                                serde_json::json!({ "type": "error", "error": err }).to_string()
+                            },
+                            ChangeType::Rebuilt(paths) => {
+                                // Only pages showing one of these paths reload
+                                serde_json::json!({ "type": "pages", "paths": paths }).to_string()
+                            },
+                            ChangeType::Presence(reviewers) => {
+                                serde_json::json!({ "type": "presence", "you": id, "reviewers": reviewers }).to_string()
+                            },
+                            ChangeType::Ping(ping) if ping.from == id => continue,
+                            ChangeType::Ping(ping) => {
+                                serde_json::json!({ "type": "ping", "ping": ping }).to_string()
                             },
                             _ => {
                                 // For other changes, do a full page reload
@@ -129,72 +510,248 @@ impl DevServer {
                             break;
                         }
                     }
-                })
+                    if presence.disconnect(id) {
+                        announce(ChangeType::Presence(presence.reviewers()));
+                    }
+                }).into_response()
+            });
+
+        // Social card preview for a built page, e.g. /__ssg/preview?path=/blog/post/
+        let preview_dir = self.output_dir.clone();
+        let preview_route = warp::path!("__ssg" / "preview")
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |query: HashMap<String, String>| {
+                let path = query.get("path").map(String::as_str).unwrap_or("/");
+                warp::reply::html(render_preview(&preview_dir, path))
             });
 
-        // Set up static file server
+        // Static files share the port with /ws, so the page reaches live reload
+        // through whatever host and proxy served it. Anything warp can't map to
+        // a file falls through to clean URLs, listings, and the 404 page.
         let static_route = warp::fs::dir(self.output_dir.clone());
-        let routes = ws_route.clone().or(static_route);
+        let fallback_dir = self.output_dir.clone();
+        let directory_listing = self.directory_listing;
+        let fallback_route = warp::get()
+            .and(warp::path::full())
+            .map(move |path: warp::path::FullPath| fallback_response(&fallback_dir, path.as_str(), directory_listing));
+        for rule in &self.proxy {
+            info!("Proxying {} to {}", rule.path, rule.target);
+        }
+        let proxy_route = proxy::route(self.proxy.clone(), self.tls.is_some());
 
-        // Start the servers
-        let server_handle = tokio::spawn(warp::serve(routes).run(([127, 0, 0, 1], self.port)));
-        let ws_handle = tokio::spawn(warp::serve(ws_route).run(([127, 0, 0, 1], self.ws_port)));
+        // Pages rebuilt in memory shadow their older copies on disk
+        let memory_pages = self.memory_pages.clone();
+        let memory_dir = self.output_dir.clone();
+        let memory_route = warp::get()
+            .and(warp::path::full())
+            .and_then(move |path: warp::path::FullPath| {
+                let page = memory_pages.as_ref().and_then(|pages| pages.get(&memory_dir, path.as_str()));
+                async move { page.map(warp::reply::html).ok_or_else(warp::reject::not_found) }
+            });
+        if self.memory_pages.is_some() {
+            info!("Serving rebuilt pages from memory; the output directory is only written by full builds");
+        }
 
-        info!("Development server running at http://localhost:{}", self.port);
-        info!("WebSocket server running at ws://localhost:{}", self.ws_port);
+        let routes = ws_route.or(preview_route).or(proxy_route).or(memory_route).or(static_route).or(fallback_route);
 
-        // Keep the server running
-        tokio::select! {
-            _ = server_handle => {},
-            _ = ws_handle => {},
+        let bind_addr = SocketAddr::new(self.host, self.listen_port()?);
+        let (addr, server): (SocketAddr, BoxFuture<'static, ()>) = match &self.tls {
+            Some(identity) => {
+                let (addr, server) = warp::serve(routes).tls()
+                    .cert(&identity.cert)
+                    .key(&identity.key)
+                    .try_bind_with_graceful_shutdown(bind_addr, shutdown_signal())?;
+                (addr, Box::pin(server))
+            },
+            None => {
+                let (addr, server) = warp::serve(routes).try_bind_with_graceful_shutdown(bind_addr, shutdown_signal())?;
+                (addr, Box::pin(server))
+            },
+        };
+        let (scheme, ws_scheme) = if self.tls.is_some() { ("https", "wss") } else { ("http", "ws") };
+        // Browsers can't open the unspecified address, so point them at this machine
+        let url_host = match addr.ip() {
+            ip if ip.is_unspecified() || ip.is_loopback() => "localhost".to_string(),
+            IpAddr::V6(ip) => format!("[{}]", ip),
+            ip => ip.to_string(),
+        };
+        let url = format!("{}://{}:{}/", scheme, url_host, addr.port());
+        info!("Development server running at {}", url);
+        if addr.ip().is_unspecified() {
+            info!("Listening on all interfaces; other devices can use this machine's network address on port {}", addr.port());
+        }
+        info!("Live reload at {}://{}:{}/ws", ws_scheme, url_host, addr.port());
+        if self.open_browser {
+            if let Err(e) = open_in_browser(&url) {
+                error!("Failed to open {} in a browser: {}", url, e);
+            }
         }
+        server.await;
 
+        // Stop watching before the rebuild thread winds down with it
+        drop(watcher);
+        info!("Development server stopped");
         Ok(())
     }
 
-    fn setup_watcher(&self, tx: broadcast::Sender<FileChange>) -> Result<RecommendedWatcher, DevServerError> {
+    /// The port to listen on: the requested one if it's free, otherwise the
+    /// next free one with `port_fallback`, or any free port when none was asked for.
+    fn listen_port(&self) -> Result<u16, DevServerError> {
+        let Some(port) = self.port else {
+            return pick_unused_port().ok_or(DevServerError::NoFreePort);
+        };
+        let bind = |port: u16| std::net::TcpListener::bind(SocketAddr::new(self.host, port)).map(drop);
+        match bind(port) {
+            Ok(()) => return Ok(port),
+            Err(e) if e.kind() != io::ErrorKind::AddrInUse => return Err(DevServerError::Listen { port, source: e }),
+            Err(_) => {},
+        }
+        let holder = port_holder(port);
+        if !self.port_fallback {
+            return Err(DevServerError::PortInUse { port, holder });
+        }
+        let fallback = (port.saturating_add(1)..=port.saturating_add(PORT_FALLBACK_ATTEMPTS))
+            .find(|&candidate| bind(candidate).is_ok())
+            .ok_or(DevServerError::NoFreePort)?;
+        warn!(
+            "Port {} is in use{}, using {} instead",
+            port,
+            holder.map(|holder| format!(" by {}", holder)).unwrap_or_default(),
+            fallback
+        );
+        Ok(fallback)
+    }
+
+    fn setup_watcher(&self, tx: broadcast::Sender<FileChange>, error_handler: Arc<ErrorHandler>) -> Result<Box<dyn Watcher + Send>, DevServerError> {
         let changed_files = self.changed_files.clone();
-        let mut last_event = std::time::Instant::now();
-        let debounce_duration = Duration::from_millis(100);
-        
-        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
-            if let Ok(event) = res {
-                let now = std::time::Instant::now();
-                if now.duration_since(last_event) < debounce_duration {
+        let author = self.author.clone();
+        let rebuild = self.rebuild.clone();
+        let input_dir = self.input_dir.canonicalize().unwrap_or_else(|_| self.input_dir.clone());
+        let components_dir = self.components_dir.canonicalize().unwrap_or_else(|_| self.components_dir.clone());
+        let config_files: Vec<PathBuf> = self.config_files.iter()
+            .map(|file| {
+                let dir = config_dir(file);
+                dir.canonicalize().unwrap_or(dir).join(file.file_name().unwrap_or_default())
+            })
+            .collect();
+        let asset_dir = self.asset_dir.as_ref().map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.clone()));
+        let data_dir = self.data_dir.as_ref().map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.clone()));
+        let theme_dir = self.theme_dir.as_ref().map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.clone()));
+        let output_dir = self.output_dir.clone();
+        let ignore = self.watch_options.ignore_set(&self.output_dir)?;
+
+        // Events are collected until none arrive for the debounce duration,
+        // then each changed path is handled once
+        let (event_tx, event_rx) = mpsc::channel::<(PathBuf, ChangeType)>();
+        let handler = move |res: notify::Result<notify::Event>| {
+            match res {
+                Ok(event) => {
+                    let change_type = match event.kind {
+                        notify::EventKind::Create(_) => ChangeType::Create,
+                        notify::EventKind::Modify(_) => {
+                            // Special handling for CSS changes
+                            if event.paths.iter().any(|p| p.extension().is_some_and(|ext| ext == "css")) {
+                                ChangeType::CssChange
+                            } else {
+                                ChangeType::Modify
+                            }
+                        },
+                        notify::EventKind::Remove(_) => ChangeType::Delete,
+                        _ => return,
+                    };
+                    for path in event.paths {
+                        if !ignore.is_ignored(&path) {
+                            let _ = event_tx.send((path, change_type.clone()));
+                        }
+                    }
+                },
+                Err(e) => error!("File watcher error: {}", e),
+            }
+        };
+
+        let debounce = self.watch_options.debounce;
+        thread::spawn(move || {
+            let handle = |path: PathBuf, change_type: ChangeType| {
+                let is_html = path.extension().is_some_and(|ext| ext == "html");
+                let is_dir_vars = path.file_name().is_some_and(|name| name == DIR_VARS_FILE);
+                let is_content = (is_html || is_dir_vars || path.extension().is_some_and(|ext| ext == "md"))
+                    && path.starts_with(&input_dir)
+                    && !path.starts_with(&components_dir);
+                let is_component = is_html && path.starts_with(&components_dir);
+                let is_config = config_files.contains(&path)
+                    || data_dir.as_ref().is_some_and(|dir| path.starts_with(dir))
+                    || theme_dir.as_ref().is_some_and(|dir| path.starts_with(dir));
+                let is_asset = asset_dir.as_ref().is_some_and(|dir| path.starts_with(dir));
+                if !is_config && !is_asset && !path.starts_with(&input_dir) && !path.starts_with(&components_dir) {
+                    // Other files next to the configuration
                     return;
                 }
-
-                let change_type = match event.kind {
-                    notify::EventKind::Create(_) => ChangeType::Create,
-                    notify::EventKind::Modify(_) => {
-                        // Special handling for CSS changes
-                        if event.paths.iter().any(|p| p.extension().map_or(false, |ext| ext == "css")) {
-                            ChangeType::CssChange
-                        } else {
+                if let Some(author) = &author {
+                    if is_content && !is_dir_vars && path.exists() && !is_authored_by(&path, author) {
+                        return;
+                    }
+                }
+                changed_files.write().insert(path.clone());
+                let event_type = match rebuild.as_ref().filter(|_| is_content || is_component || is_config || is_asset) {
+                    Some(rebuild) => match rebuild(&path) {
+                        Ok(Rebuilt::Pages(outputs)) => {
+                            error_handler.clear();
+                            info!("Rebuilt {} page(s) for {}", outputs.len(), path.display());
+                            ChangeType::Rebuilt(outputs.iter().map(|output| served_path(&output_dir, output)).collect())
+                        },
+                        Ok(Rebuilt::Site) => {
+                            error_handler.clear();
+                            info!("Rebuilt the site for {}", path.display());
                             ChangeType::Modify
-                        }
+                        },
+                        Err(e) => {
+                            // Stays on screen until a rebuild succeeds
+                            let _ = error_handler.handle_error(e, Some(&path));
+                            return;
+                        },
                     },
-                    notify::EventKind::Remove(_) => ChangeType::Delete,
-                    _ => return,
+                    None => change_type,
+                };
+                let change = FileChange {
+                    path,
+                    event_type,
                 };
 
-                for path in event.paths {
-                    changed_files.write().insert(path.clone());
-                    let change = FileChange {
-                        path,
-                        event_type: change_type.clone(),
-                    };
-                    
-                    if tx.send(change).is_err() {
-                        error!("Failed to send file change event");
-                    }
+                if tx.send(change).is_err() {
+                    error!("Failed to send file change event");
+                }
+            };
+
+            let mut pending: Vec<(PathBuf, ChangeType)> = Vec::new();
+            loop {
+                let received = if pending.is_empty() {
+                    event_rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected)
+                } else {
+                    event_rx.recv_timeout(debounce)
+                };
+                match received {
+                    Ok((path, change_type)) => {
+                        // The latest event for a path wins
+                        pending.retain(|(pending_path, _)| *pending_path != path);
+                        pending.push((path, change_type));
+                    },
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        for (path, change_type) in pending.drain(..) {
+                            handle(path, change_type);
+                        }
+                    },
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
                 }
-                last_event = now;
-            } else if let Err(e) = res {
-                error!("File watcher error: {}", e);
             }
-        })?;
+        });
 
+        let watcher: Box<dyn Watcher + Send> = match self.watch_options.poll_interval {
+            Some(interval) => {
+                info!("Polling for file changes every {} ms", interval.as_millis());
+                Box::new(PollWatcher::new(handler, Config::default().with_poll_interval(interval))?)
+            },
+            None => Box::new(RecommendedWatcher::new(handler, Config::default())?),
+        };
         Ok(watcher)
     }
 
@@ -207,11 +764,158 @@ impl DevServer {
     }
 }
 
+/// How many ports after the requested one `--port-fallback` tries.
+const PORT_FALLBACK_ATTEMPTS: u16 = 20;
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix, to stop the server cleanly.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", e);
+            future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => { signal.recv().await; },
+            Err(_) => future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutting down the development server");
+}
+
+/// The process listening on `port`, like `node (pid 1234)`, when the
+/// platform's tools can tell.
+fn port_holder(port: u16) -> Option<String> {
+    let output = |program: &str, args: &[&str]| -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    if cfg!(target_os = "windows") {
+        // `  TCP    0.0.0.0:8080    0.0.0.0:0    LISTENING    1234`
+        let suffix = format!(":{}", port);
+        let netstat = output("netstat", &["-ano", "-p", "TCP"])?;
+        return netstat.lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .find(|fields| fields.len() == 5 && fields[1].ends_with(&suffix) && fields[3] == "LISTENING")
+            .map(|fields| format!("pid {}", fields[4]));
+    }
+    // lsof prints `p<pid>` and `c<command>` lines with -F
+    if let Some(lsof) = output("lsof", &["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-Fpc"]) {
+        let field = |prefix: char| lsof.lines().find_map(|line| line.strip_prefix(prefix)).map(str::to_string);
+        if let (Some(pid), Some(command)) = (field('p'), field('c')) {
+            return Some(format!("{} (pid {})", command, pid));
+        }
+    }
+    // `users:(("node",pid=1234,fd=20))`
+    let ss = output("ss", &["-Hltnp", &format!("sport = :{}", port)])?;
+    let users = ss.split("users:((\"").nth(1)?;
+    let (command, rest) = users.split_once('"')?;
+    let pid = rest.split("pid=").nth(1)?.split([',', ')']).next()?;
+    Some(format!("{} (pid {})", command, pid))
+}
+
+/// Open `url` with the platform's default browser.
+fn open_in_browser(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(url).spawn().map(|_| ())
+}
+
+/// Directory a configuration file lives in, `.` for bare file names.
+fn config_dir(file: &Path) -> PathBuf {
+    match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// URL path an output file is served at, e.g. `/blog/post.html`.
+fn served_path(output_dir: &Path, output: &Path) -> String {
+    let relative = output.strip_prefix(output_dir).unwrap_or(output);
+    format!("/{}", relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Response for a path with no exact file: `/about` serves `about.html`,
+/// directories without an index are listed, and anything else gets the
+/// site's `404.html`, the way static hosts behave.
+fn fallback_response(output_dir: &Path, url_path: &str, directory_listing: bool) -> warp::reply::Response {
+    if let Some(page) = resolve_page(output_dir, url_path) {
+        if let Ok(html) = fs::read_to_string(page) {
+            return warp::reply::html(html).into_response();
+        }
+    }
+
+    let relative = url_path.trim_start_matches('/');
+    let dir = output_dir.join(relative);
+    if directory_listing && !relative.split('/').any(|segment| segment == "..") && dir.is_dir() {
+        if let Ok(listing) = render_listing(&dir, url_path) {
+            return warp::reply::html(listing).into_response();
+        }
+    }
+
+    let not_found = fs::read_to_string(output_dir.join("404.html"))
+        .unwrap_or_else(|_| format!("<h1>404 Not Found</h1><p>{}</p>", encode_text(url_path)));
+    warp::reply::with_status(warp::reply::html(not_found), StatusCode::NOT_FOUND).into_response()
+}
+
+fn render_listing(dir: &Path, url_path: &str) -> io::Result<String> {
+    let mut entries: Vec<(String, bool)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path().is_dir()))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let base = format!("{}/", url_path.trim_end_matches('/'));
+    let items: String = entries.iter()
+        .map(|(name, is_dir)| {
+            let name = if *is_dir { format!("{}/", name) } else { name.clone() };
+            format!("<li><a href=\"{}{}\">{}</a></li>", encode_double_quoted_attribute(&base), encode_double_quoted_attribute(&name), encode_text(&name))
+        })
+        .collect();
+    Ok(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"UTF-8\"><title>Index of {path}</title></head><body><h1>Index of {path}</h1><ul>{items}</ul></body></html>",
+        path = encode_text(&base),
+        items = items,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_listen_port_reports_only_a_taken_port_as_in_use() {
+        let temp = tempdir().unwrap();
+        let taken = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let server = DevServer::new(temp.path().join("input"), temp.path().join("output"), temp.path().join("components"), Some(port));
+
+        assert!(matches!(server.listen_port(), Err(DevServerError::PortInUse { port: p, .. }) if p == port));
+        let fallback = server.with_port_fallback(true).listen_port().unwrap();
+        assert_ne!(fallback, port);
+
+        // An address this machine doesn't have fails for another reason
+        let server = DevServer::new(temp.path().join("input"), temp.path().join("output"), temp.path().join("components"), Some(port))
+            .with_host(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        assert!(matches!(server.listen_port(), Err(DevServerError::Listen { .. })));
+    }
+
     #[test]
     fn test_ensure_directory() {
         let temp = tempdir().unwrap();
@@ -221,7 +925,6 @@ mod tests {
             temp.path().join("output"),
             temp.path().join("components"),
             Some(8080),
-            Some(8081),
         );
 
         // Test directory creation
@@ -246,7 +949,6 @@ mod tests {
             output_dir.clone(),
             components_dir.clone(),
             Some(8080),
-            Some(8081),
         );
 
         // Test initial state
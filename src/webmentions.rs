@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result, anyhow};
+use log::info;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use url::Url;
+use crate::markdown::BlogPost;
+use crate::remote_cache::RemoteCache;
+
+/// `[webmentions]` section of `eldroid.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebmentionsConfig {
+    /// Where other sites send mentions of this one, e.g. a webmention.io
+    /// endpoint; every page advertises it with `<link rel="webmention">`
+    pub endpoint: Option<String>,
+    /// Hosts `send-webmentions` never notifies
+    #[serde(default)]
+    pub exclude_hosts: Vec<String>,
+}
+
+impl WebmentionsConfig {
+    /// The `<link>` tag advertising the endpoint, if there is one.
+    pub fn link_tag(&self) -> Option<String> {
+        self.endpoint.as_ref().map(|endpoint| format!(
+            "<link rel=\"webmention\" href=\"{}\">",
+            html_escape::encode_double_quoted_attribute(endpoint)
+        ))
+    }
+}
+
+/// Absolute post URLs and the external pages each links to.
+pub type Outbound = BTreeMap<String, BTreeSet<String>>;
+
+/// Outbound links of the last build, which `send-webmentions` notifies.
+pub fn outbound_file(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("webmentions-outbound.json")
+}
+
+/// Links already notified, so each is sent once.
+fn sent_file(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("webmentions-sent.json")
+}
+
+/// The external links in the content of each post, by the post's URL under `base_url`.
+pub fn collect_outbound(posts: &[BlogPost], base_url: &str, config: &WebmentionsConfig) -> Result<Outbound> {
+    let mut outbound = Outbound::new();
+    for post in posts {
+        let source = format!("{}{}", base_url.trim_end_matches('/'), post.url);
+        let source_url = Url::parse(&source).with_context(|| format!("Invalid post URL {}", source))?;
+        let targets = outbound_links(&post.html_content, &source_url, &config.exclude_hosts);
+        if !targets.is_empty() {
+            outbound.insert(source, targets);
+        }
+    }
+    Ok(outbound)
+}
+
+/// http(s) links in `html` to hosts other than `source`'s, without fragments.
+fn outbound_links(html: &str, source: &Url, exclude_hosts: &[String]) -> BTreeSet<String> {
+    let document = Html::parse_fragment(html);
+    let selector = Selector::parse("a[href]").unwrap();
+    document.select(&selector)
+        .filter_map(|a| source.join(a.value().attr("href")?.trim()).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .filter(|url| url.host_str().is_some_and(|host| {
+            Some(host) != source.host_str() && !exclude_hosts.iter().any(|excluded| excluded.eq_ignore_ascii_case(host))
+        }))
+        .map(|mut url| {
+            url.set_fragment(None);
+            url.to_string()
+        })
+        .collect()
+}
+
+pub fn write_outbound(outbound: &Outbound, cache_dir: &Path) -> Result<()> {
+    fs::create_dir_all(cache_dir)?;
+    fs::write(outbound_file(cache_dir), serde_json::to_string_pretty(outbound)?)?;
+    Ok(())
+}
+
+pub fn read_outbound(cache_dir: &Path) -> Result<Outbound> {
+    let path = outbound_file(cache_dir);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}; build the site with [webmentions] configured first", path.display()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// What became of one source/target pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The target's endpoint accepted the mention
+    Sent,
+    /// The target advertises no endpoint; not tried again
+    Unsupported,
+    /// Tried again on the next run
+    Failed(String),
+    /// Would be sent, with `dry_run`
+    Pending,
+}
+
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub source: String,
+    pub target: String,
+    pub outcome: Outcome,
+}
+
+/// Notify every target linked since the last run, and every target a post
+/// stopped linking to so it can drop the mention. Pairs that were handled
+/// are recorded in the sent-log in `cache_dir`, which a remote cache carries
+/// over to fresh checkouts.
+pub fn send_webmentions(
+    outbound: &Outbound,
+    cache_dir: &Path,
+    remote_cache: Option<&RemoteCache>,
+    dry_run: bool,
+) -> Result<Vec<Delivery>> {
+    let log_file = sent_file(cache_dir);
+    let remote_key = format!("webmentions/{}", log_file.file_name().unwrap_or_default().to_string_lossy());
+    if let Some(remote) = remote_cache {
+        remote.fetch(&remote_key, &log_file);
+    }
+    let mut sent: Outbound = fs::read_to_string(&log_file).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let mut deliveries = Vec::new();
+    for (source, target, linked) in pending(outbound, &sent) {
+        let outcome = if dry_run {
+            Outcome::Pending
+        } else {
+            match notify(&source, &target) {
+                Ok(true) => Outcome::Sent,
+                Ok(false) => Outcome::Unsupported,
+                Err(e) => Outcome::Failed(format!("{:#}", e)),
+            }
+        };
+        if matches!(outcome, Outcome::Sent | Outcome::Unsupported) {
+            let targets = sent.entry(source.clone()).or_default();
+            if linked {
+                targets.insert(target.clone());
+            } else {
+                targets.remove(&target);
+            }
+        }
+        deliveries.push(Delivery { source, target, outcome });
+    }
+
+    if !dry_run {
+        sent.retain(|_, targets| !targets.is_empty());
+        fs::create_dir_all(cache_dir)?;
+        fs::write(&log_file, serde_json::to_string_pretty(&sent)?)?;
+        if let Some(remote) = remote_cache {
+            remote.store(&remote_key, &log_file);
+        }
+    }
+    Ok(deliveries)
+}
+
+/// Pairs that differ between `outbound` and the sent-log, flagged with
+/// whether the source still links to the target.
+fn pending(outbound: &Outbound, sent: &Outbound) -> Vec<(String, String, bool)> {
+    let pairs = |map: &Outbound| -> BTreeSet<(String, String)> {
+        map.iter()
+            .flat_map(|(source, targets)| targets.iter().map(move |target| (source.clone(), target.clone())))
+            .collect()
+    };
+    let (linked, notified) = (pairs(outbound), pairs(sent));
+    let added = linked.difference(&notified).map(|(source, target)| (source.clone(), target.clone(), true));
+    let removed = notified.difference(&linked).map(|(source, target)| (source.clone(), target.clone(), false));
+    added.chain(removed).collect()
+}
+
+/// Send one mention. Returns false when the target has no endpoint.
+fn notify(source: &str, target: &str) -> Result<bool> {
+    let Some(endpoint) = discover_endpoint(target)? else {
+        return Ok(false);
+    };
+    ureq::post(endpoint.as_str())
+        .send_form(&[("source", source), ("target", target)])
+        .map_err(|e| anyhow!("{} rejected the mention: {}", endpoint, e))?;
+    info!("Sent webmention for {} to {}", target, endpoint);
+    Ok(true)
+}
+
+/// The endpoint a page advertises in its `Link` header, or else in its first
+/// `<link>` or `<a>` with `rel="webmention"`.
+fn discover_endpoint(target: &str) -> Result<Option<Url>> {
+    let response = ureq::get(target).call()
+        .map_err(|e| anyhow!("Failed to fetch {}: {}", target, e))?;
+    // Relative endpoints resolve against the page after redirects
+    let base = Url::parse(response.get_url())?;
+    if let Some(endpoint) = response.all("Link").iter().find_map(|header| header_endpoint(header, &base)) {
+        return Ok(Some(endpoint));
+    }
+    if !response.content_type().contains("html") {
+        return Ok(None);
+    }
+    Ok(html_endpoint(&response.into_string()?, &base))
+}
+
+fn is_webmention_rel(rel: &str) -> bool {
+    rel.split_whitespace().any(|rel| rel.eq_ignore_ascii_case("webmention"))
+}
+
+/// The endpoint in a header like `<https://example.com/wm>; rel="webmention"`.
+fn header_endpoint(header: &str, base: &Url) -> Option<Url> {
+    header.split(',').find_map(|link| {
+        let (target, params) = link.trim().strip_prefix('<')?.split_once('>')?;
+        let is_webmention = params.split(';').any(|param| {
+            param.trim().strip_prefix("rel=").is_some_and(|rel| is_webmention_rel(rel.trim_matches('"')))
+        });
+        if is_webmention { base.join(target).ok() } else { None }
+    })
+}
+
+fn html_endpoint(html: &str, base: &Url) -> Option<Url> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[rel][href], a[rel][href]").unwrap();
+    document.select(&selector)
+        .find(|el| el.value().attr("rel").is_some_and(is_webmention_rel))
+        .and_then(|el| base.join(el.value().attr("href")?).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collects_links_and_finds_endpoints() {
+        let source = Url::parse("https://me.example/blog/post").unwrap();
+        let html = r##"<a href="https://other.example/a#top">a</a> <a href="/about">me</a>
+            <a href="http://skip.example/">skip</a> <a href="mailto:x@y.z">mail</a> <a href="#note">note</a>"##;
+        let links = outbound_links(html, &source, &["skip.example".to_string()]);
+        assert_eq!(links.into_iter().collect::<Vec<_>>(), ["https://other.example/a"]);
+
+        let base = Url::parse("https://other.example/a").unwrap();
+        assert_eq!(
+            header_endpoint(r#"<https://other.example/>; rel="me", </wm>; rel="webmention""#, &base).unwrap().as_str(),
+            "https://other.example/wm"
+        );
+        let html = r#"<head><link rel="stylesheet" href="/s.css"><link rel="webmention pingback" href="wm?x=1"></head>"#;
+        assert_eq!(html_endpoint(html, &base).unwrap().as_str(), "https://other.example/wm?x=1");
+
+        let outbound: Outbound = serde_json::from_str(r#"{"s": ["a", "b"]}"#).unwrap();
+        let sent: Outbound = serde_json::from_str(r#"{"s": ["b", "c"]}"#).unwrap();
+        assert_eq!(pending(&outbound, &sent), [
+            ("s".to_string(), "a".to_string(), true),
+            ("s".to_string(), "c".to_string(), false),
+        ]);
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::{Captures, Regex};
+use roxmltree::{Document, Node, ParsingOptions};
+use serde_yaml::Value;
+use crate::import::{agent, child_text, children, markdown_document, parse_date, text, unique_path, FeedEntry, ImportOptions, ImportReport};
+use crate::slug::Slugs;
+
+lazy_static! {
+    // Block editor markers like <!-- wp:paragraph --> and <!-- /wp:paragraph -->
+    static ref BLOCK_COMMENT_REGEX: Regex = Regex::new(r"<!--\s*/?wp:.*?-->").unwrap();
+    // [caption id="..."]<img ...> Text[/caption] keeps its contents
+    static ref CAPTION_REGEX: Regex = Regex::new(r"\[/?caption[^\]]*\]").unwrap();
+    // Files in the uploads directory, on the old site or root-relative
+    static ref UPLOAD_URL_REGEX: Regex = Regex::new(r#"(https?://[^/\s"'<>]+)?/wp-content/uploads/([^\s"'<>()?#]+)"#).unwrap();
+    static ref BLOCK_TAG_REGEX: Regex = Regex::new(r"(?i)^<(p|div|h[1-6]|ul|ol|blockquote|pre|table|figure|hr|img|iframe|section)\b").unwrap();
+    static ref BLANK_LINE_REGEX: Regex = Regex::new(r"\n\s*\n").unwrap();
+}
+
+/// Settings for `import wordpress` besides the ones shared with feeds.
+#[derive(Debug, Clone)]
+pub struct WordPressOptions {
+    /// Directory pages are written to; posts go to the import directory
+    pub pages_dir: PathBuf,
+    /// Download media under `uploads/` in this directory and link to the
+    /// copies; `None` keeps links to the old site
+    pub media_dir: Option<PathBuf>,
+}
+
+/// A published post or page of a WordPress export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordPressItem {
+    pub entry: FeedEntry,
+    pub slug: String,
+    pub is_page: bool,
+    pub categories: Vec<String>,
+    /// URL of the featured image
+    pub image: Option<String>,
+}
+
+/// The contents of a WXR file.
+#[derive(Debug, Clone, Default)]
+pub struct WordPressExport {
+    /// Address of the old site, for root-relative media links
+    pub site_url: Option<String>,
+    pub items: Vec<WordPressItem>,
+    /// Drafts, private posts, and trashed posts, which aren't imported
+    pub unpublished: usize,
+}
+
+/// Published posts and pages of a WordPress export file, in export order,
+/// with slugs made by `slugs`.
+pub fn parse_export(xml: &str, slugs: Slugs) -> Result<WordPressExport> {
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(xml, options).context("Failed to parse the WordPress export")?;
+    let channel = children(document.root_element(), "channel").next()
+        .filter(|channel| children(*channel, "wxr_version").next().is_some())
+        .ok_or_else(|| anyhow!("Not a WordPress export: it has no <wp:wxr_version>"))?;
+
+    // Posts name their author by login, and their featured image by attachment ID
+    let authors: HashMap<String, String> = children(channel, "author")
+        .filter_map(|author| Some((child_text(author, "author_login")?, child_text(author, "author_display_name")?)))
+        .collect();
+    let items: Vec<Node> = children(channel, "item").collect();
+    let attachments: HashMap<String, String> = items.iter()
+        .filter(|item| child_text(**item, "post_type").as_deref() == Some("attachment"))
+        .filter_map(|item| Some((child_text(*item, "post_id")?, child_text(*item, "attachment_url")?)))
+        .collect();
+
+    let mut export = WordPressExport {
+        site_url: child_text(channel, "base_site_url").or_else(|| child_text(channel, "link")),
+        ..WordPressExport::default()
+    };
+    for item in items {
+        let is_page = match child_text(item, "post_type").as_deref() {
+            Some("post") => false,
+            Some("page") => true,
+            _ => continue,
+        };
+        if child_text(item, "status").as_deref() != Some("publish") {
+            export.unpublished += 1;
+            continue;
+        }
+        export.items.push(wordpress_item(item, is_page, &authors, &attachments, slugs));
+    }
+    Ok(export)
+}
+
+fn wordpress_item(
+    item: Node,
+    is_page: bool,
+    authors: &HashMap<String, String>,
+    attachments: &HashMap<String, String>,
+    slugs: Slugs,
+) -> WordPressItem {
+    // content:encoded and excerpt:encoded differ only by namespace
+    let encoded = |namespace: &str| children(item, "encoded")
+        .find(|node| node.tag_name().namespace().is_some_and(|ns| ns.contains(namespace)))
+        .map(text)
+        .filter(|text| !text.is_empty());
+    let terms = |domain: &str| children(item, "category")
+        .filter(|category| category.attribute("domain") == Some(domain))
+        .map(text)
+        .filter(|term| !term.is_empty())
+        .collect::<Vec<_>>();
+    let date = ["post_date_gmt", "post_date"].iter()
+        .filter_map(|name| children(item, name).next().map(text))
+        .find_map(|date| parse_wordpress_date(&date))
+        .or_else(|| child_text(item, "pubDate").and_then(|date| parse_date(&date)));
+    let image = children(item, "postmeta")
+        .find(|meta| child_text(*meta, "meta_key").as_deref() == Some("_thumbnail_id"))
+        .and_then(|meta| child_text(meta, "meta_value"))
+        .and_then(|id| attachments.get(&id).cloned());
+
+    let entry = FeedEntry {
+        title: child_text(item, "title").unwrap_or_default(),
+        link: child_text(item, "link"),
+        date,
+        author: child_text(item, "creator").map(|login| authors.get(&login).cloned().unwrap_or(login)),
+        tags: terms("post_tag"),
+        summary: encoded("/excerpt/"),
+        content: encoded("/content/"),
+    };
+    // Non-ASCII slugs are stored percent-encoded
+    let slug = child_text(item, "post_name")
+        .filter(|name| !name.contains('%'))
+        .map(|name| slugs.slugify(&name))
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| entry.slug(slugs));
+    WordPressItem { entry, slug, is_page, categories: terms("category"), image }
+}
+
+/// `2024-01-02 10:00:00` in UTC; unpublished posts have all zeros.
+fn parse_wordpress_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    NaiveDateTime::parse_from_str(date.trim(), "%Y-%m-%d %H:%M:%S").ok()
+        .filter(|_| !date.starts_with("0000"))
+        .map(|date| date.and_utc().fixed_offset())
+}
+
+/// Post HTML without block editor comments and caption shortcodes, with
+/// paragraphs for the blank lines the classic editor separates them by.
+fn clean_content(html: &str) -> String {
+    let html = BLOCK_COMMENT_REGEX.replace_all(html, "");
+    let html = CAPTION_REGEX.replace_all(&html, "");
+    if html.contains("<p") {
+        return html.trim().to_string();
+    }
+    BLANK_LINE_REGEX.split(html.trim())
+        .map(str::trim)
+        .filter(|block| !block.is_empty())
+        .map(|block| match BLOCK_TAG_REGEX.is_match(block) {
+            true => block.to_string(),
+            false => format!("<p>{}</p>", block.replace('\n', "<br>\n")),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Point uploads links at `/uploads/...` and note where each file is
+/// downloaded from, by its path under `uploads/`.
+fn localize_media(html: &str, site_url: Option<&str>, downloads: &mut BTreeMap<String, String>) -> String {
+    UPLOAD_URL_REGEX.replace_all(html, |caps: &Captures| {
+        let Some(origin) = caps.get(1).map(|m| m.as_str()).or(site_url) else {
+            return caps[0].to_string();
+        };
+        // The export decides where downloads are written, so it mustn't climb out of uploads/
+        if !is_plain_relative(&caps[2]) {
+            warn!("Not downloading {}, which isn't a plain path under uploads/", &caps[0]);
+            return caps[0].to_string();
+        }
+        downloads.entry(caps[2].to_string())
+            .or_insert_with(|| format!("{}/wp-content/uploads/{}", origin.trim_end_matches('/'), &caps[2]));
+        format!("/uploads/{}", &caps[2])
+    }).into_owned()
+}
+
+/// Whether `path` only names directories and a file below where it's joined,
+/// without `..`, a root, or a drive.
+fn is_plain_relative(path: &str) -> bool {
+    !path.contains('\\') && Path::new(path).components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Write the published posts and pages of a WordPress export as Markdown,
+/// and download the media they use.
+pub fn import_wordpress(export_file: &Path, options: &ImportOptions, wordpress: &WordPressOptions) -> Result<ImportReport> {
+    let xml = fs::read_to_string(export_file)
+        .with_context(|| format!("Failed to read {}", export_file.display()))?;
+    let mut export = parse_export(&xml, options.slugs)?;
+    if let Some(limit) = options.limit {
+        export.items.truncate(limit);
+    }
+    info!(
+        "Importing {} posts and pages from {}, leaving out {} unpublished",
+        export.items.len(), export_file.display(), export.unpublished
+    );
+
+    let mut report = ImportReport::default();
+    let mut downloads = BTreeMap::new();
+    for item in &export.items {
+        let dir = if item.is_page { &wordpress.pages_dir } else { &options.dir };
+        fs::create_dir_all(dir)?;
+        let path = unique_path(dir, &item.slug, &report.written);
+        if path.exists() && !options.overwrite {
+            report.skipped.push(path);
+            continue;
+        }
+
+        let mut body = clean_content(item.entry.content.as_deref().unwrap_or_default());
+        let mut image = item.image.clone();
+        if wordpress.media_dir.is_some() {
+            body = localize_media(&body, export.site_url.as_deref(), &mut downloads);
+            image = image.map(|image| localize_media(&image, export.site_url.as_deref(), &mut downloads));
+        }
+        let mut front_matter = item.entry.front_matter();
+        if !item.categories.is_empty() {
+            front_matter.insert("categories".into(), item.categories.iter().cloned().map(Value::from).collect::<Vec<_>>().into());
+        }
+        if let Some(image) = image {
+            front_matter.insert("image".into(), image.into());
+        }
+        fs::write(&path, markdown_document(&front_matter, &item.entry.title, &body)?)?;
+        report.written.push(path);
+    }
+
+    if let Some(media_dir) = &wordpress.media_dir {
+        let agent = agent();
+        for (file, url) in downloads {
+            let path = media_dir.join("uploads").join(&file);
+            if !is_plain_relative(&file) || path.exists() && !options.overwrite {
+                continue;
+            }
+            match download(&agent, &url) {
+                Ok(bytes) => {
+                    fs::create_dir_all(path.parent().unwrap_or(media_dir))?;
+                    fs::write(&path, bytes)?;
+                    report.media.push(path);
+                },
+                Err(e) => warn!("{:#}; /uploads/{} will be missing", e, file),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn download(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    agent.get(url)
+        .call()
+        .map_err(|e| anyhow!("Failed to download {}: {}", url, e))?
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to read {}", url))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/" xmlns:content="http://purl.org/rss/1.0/modules/content/"
+     xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+  <link>https://old.example.com</link>
+  <wp:wxr_version>1.2</wp:wxr_version>
+  <wp:base_site_url>https://old.example.com</wp:base_site_url>
+  <wp:author><wp:author_login><![CDATA[jane]]></wp:author_login><wp:author_display_name><![CDATA[Jane Doe]]></wp:author_display_name></wp:author>
+  <item>
+    <title>Hello World</title>
+    <link>https://old.example.com/2024/01/hello-world/</link>
+    <dc:creator><![CDATA[jane]]></dc:creator>
+    <content:encoded><![CDATA[<!-- wp:paragraph -->
+<p>First <strong>post</strong>.</p>
+<!-- /wp:paragraph -->
+[caption id="attachment_7"]<img src="https://old.example.com/wp-content/uploads/2024/01/cat-300x200.jpg" alt="Cat"> A cat[/caption]]]></content:encoded>
+    <excerpt:encoded><![CDATA[A short hello.]]></excerpt:encoded>
+    <wp:post_id>5</wp:post_id>
+    <wp:post_date_gmt><![CDATA[2024-01-02 10:00:00]]></wp:post_date_gmt>
+    <wp:post_name><![CDATA[hello-world]]></wp:post_name>
+    <wp:status><![CDATA[publish]]></wp:status>
+    <wp:post_type><![CDATA[post]]></wp:post_type>
+    <category domain="category" nicename="news"><![CDATA[News]]></category>
+    <category domain="post_tag" nicename="intro"><![CDATA[intro]]></category>
+    <wp:postmeta><wp:meta_key><![CDATA[_thumbnail_id]]></wp:meta_key><wp:meta_value><![CDATA[7]]></wp:meta_value></wp:postmeta>
+  </item>
+  <item>
+    <title>Cat</title>
+    <wp:post_id>7</wp:post_id>
+    <wp:status><![CDATA[inherit]]></wp:status>
+    <wp:post_type><![CDATA[attachment]]></wp:post_type>
+    <wp:attachment_url><![CDATA[https://old.example.com/wp-content/uploads/2024/01/cat.jpg]]></wp:attachment_url>
+  </item>
+  <item>
+    <title>About</title>
+    <link>https://old.example.com/about/</link>
+    <content:encoded><![CDATA[Line one
+line two
+
+Second paragraph]]></content:encoded>
+    <wp:post_date_gmt><![CDATA[2023-05-01 08:00:00]]></wp:post_date_gmt>
+    <wp:post_name><![CDATA[about]]></wp:post_name>
+    <wp:status><![CDATA[publish]]></wp:status>
+    <wp:post_type><![CDATA[page]]></wp:post_type>
+  </item>
+  <item>
+    <title>Unfinished</title>
+    <wp:post_date_gmt><![CDATA[0000-00-00 00:00:00]]></wp:post_date_gmt>
+    <wp:status><![CDATA[draft]]></wp:status>
+    <wp:post_type><![CDATA[post]]></wp:post_type>
+  </item>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn test_imports_posts_pages_and_media_links() {
+        let export = parse_export(EXPORT, Slugs::default()).unwrap();
+        assert_eq!(export.unpublished, 1);
+        assert_eq!(export.items.len(), 2);
+        let post = &export.items[0];
+        assert_eq!((post.slug.as_str(), post.is_page), ("hello-world", false));
+        assert_eq!(post.entry.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(post.entry.tags, ["intro"]);
+        assert_eq!(post.categories, ["News"]);
+        assert_eq!(post.image.as_deref(), Some("https://old.example.com/wp-content/uploads/2024/01/cat.jpg"));
+
+        let mut downloads = BTreeMap::new();
+        let body = localize_media(&clean_content(post.entry.content.as_deref().unwrap()), None, &mut downloads);
+        assert_eq!(body, "<p>First <strong>post</strong>.</p>\n\n<img src=\"/uploads/2024/01/cat-300x200.jpg\" alt=\"Cat\"> A cat");
+        assert_eq!(downloads["2024/01/cat-300x200.jpg"], "https://old.example.com/wp-content/uploads/2024/01/cat-300x200.jpg");
+        assert_eq!(clean_content(export.items[1].entry.content.as_deref().unwrap()), "<p>Line one<br>\nline two</p>\n<p>Second paragraph</p>");
+
+        let site = tempfile::tempdir().unwrap();
+        let export_file = site.path().join("export.xml");
+        fs::write(&export_file, EXPORT).unwrap();
+        let options = ImportOptions { dir: site.path().join("content/blog"), full_content: false, overwrite: false, limit: None, slugs: Slugs::default() };
+        let wordpress = WordPressOptions { pages_dir: site.path().join("content"), media_dir: None };
+        let report = import_wordpress(&export_file, &options, &wordpress).unwrap();
+        assert_eq!(report.written, [site.path().join("content/blog/hello-world.md"), site.path().join("content/about.md")]);
+        let markdown = fs::read_to_string(&report.written[0]).unwrap();
+        assert!(markdown.starts_with("---\ntitle: Hello World\ndate: 2024-01-02T10:00:00+00:00\nauthor: Jane Doe\n"));
+        assert!(markdown.contains("description: A short hello.\naliases:\n- /2024/01/hello-world/\ncategories:\n- News\nimage: https://old.example.com/"));
+        assert!(markdown.contains("First **post**."));
+    }
+
+    #[test]
+    fn test_media_paths_cannot_leave_the_uploads_directory() {
+        let mut downloads = BTreeMap::new();
+        let html = "<img src=\"https://old.example.com/wp-content/uploads/../../../.bashrc\">\
+                    <img src=\"/wp-content/uploads/2024/../../etc/cron.d/job\">\
+                    <img src=\"/wp-content/uploads/2024/..\\..\\x.jpg\">\
+                    <img src=\"/wp-content/uploads/2024/./ok.jpg\">";
+        let localized = localize_media(html, Some("https://old.example.com"), &mut downloads);
+        assert!(localized.contains("https://old.example.com/wp-content/uploads/../../../.bashrc"));
+        assert!(localized.contains("\"/wp-content/uploads/2024/../../etc/cron.d/job\""));
+        assert!(localized.contains("/uploads/2024/./ok.jpg"));
+        assert_eq!(downloads.keys().collect::<Vec<_>>(), ["2024/./ok.jpg"]);
+        assert!(!is_plain_relative("/etc/passwd"));
+        assert!(is_plain_relative("2024/01/cat.jpg"));
+    }
+}